@@ -0,0 +1,24 @@
+//! Feeds arbitrary strings to the address parsers. Any panic is a bug.
+//! Run with `cargo +nightly fuzz run parse_address`
+#![no_main]
+
+use std::str::FromStr;
+
+use fisa::parse::{
+    usb::{RawUsbAddress, UsbAddress, UsbPattern},
+    ParseOptions, VisaAddress,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|addr: &str| {
+    let lenient = ParseOptions::pyvisa_compat()
+        .strip_wrappers(true)
+        .strip_trailing_slash(true);
+
+    let _ = UsbAddress::from_str(addr);
+    let _ = UsbAddress::parse_with(addr, &lenient);
+    let _ = UsbPattern::from_str(addr);
+    let _ = RawUsbAddress::from_str(addr);
+    // Picks the parser of every interface, including GPIB, ASRL, VXI, and PXI, by prefix.
+    let _ = VisaAddress::from_str(addr);
+});
@@ -16,6 +16,18 @@
 
 pub mod parse;
 
+pub mod rm;
+
+pub mod scpi;
+
+pub mod session;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(test)]
+mod testing;
+
 mod sealed {
     //! Just for sealing traits so no one can be sneaky
 
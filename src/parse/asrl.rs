@@ -0,0 +1,319 @@
+//! Module for serial port (ASRL) VISA addresses.
+//! Includes primarily the main struct and the errors.
+use std::{fmt::Display, num::ParseIntError, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{strip_keyword, ResourceClass};
+
+/// Represents a serial port VISA address, `ASRL[board][::INSTR]`.
+///
+/// PyVISA-py also names ports by their device, such as `ASRL/dev/ttyUSB0::INSTR`
+/// on Unix or `ASRLCOM3::INSTR` on Windows, so those are accepted as well.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::asrl::AsrlAddress;
+/// let addr: AsrlAddress = "ASRL/dev/ttyUSB0::INSTR".parse()?;
+/// assert_eq!(addr.path(), Some("/dev/ttyUSB0"));
+/// assert_eq!(addr.board(), None);
+///
+/// let addr: AsrlAddress = "ASRL1::instr".parse()?;
+/// assert_eq!(addr.board(), Some(1));
+/// assert_eq!(addr.to_string(), "ASRL1::INSTR");
+/// # Ok::<(), fisa::parse::asrl::AsrlParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct AsrlAddress {
+    /// Which port, if given.
+    port: Option<AsrlPort>,
+    /// The resource class, if given. Only INSTR is parsed.
+    class: Option<ResourceClass>,
+}
+
+/// How a serial port is named in an address.
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum AsrlPort {
+    /// A board number, as in the VISA specification.
+    Board(u32),
+    /// A device path, such as "/dev/ttyUSB0" or "COM3".
+    Path(String),
+}
+
+impl AsrlAddress {
+    /// Failably creates a new AsrlAddress from an address.
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, AsrlParseError> {
+        AsrlAddress::from_str(addr)
+    }
+
+    /// The board number, if the port is given by one.
+    pub fn board(&self) -> Option<u32> {
+        match self.port {
+            Some(AsrlPort::Board(board)) => Some(board),
+            _ => None,
+        }
+    }
+
+    /// The same address, but on the given board. This replaces a device path.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.port = Some(AsrlPort::Board(board));
+        self
+    }
+
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    pub fn class(&self) -> Option<ResourceClass> {
+        self.class
+    }
+
+    /// The device path, if the port is given by one.
+    pub fn path(&self) -> Option<&str> {
+        match &self.port {
+            Some(AsrlPort::Path(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The port, if the address has one.
+    pub fn port(&self) -> Option<&AsrlPort> {
+        self.port.as_ref()
+    }
+}
+
+/// Errors that can return from serial port address parsing.
+#[derive(Error, Debug)]
+pub enum AsrlParseError {
+    /// When the given address does not have the ASRL prefix.
+    #[error("Expected \"ASRL\" at address start, found {0:?}")]
+    NotAsrl(String),
+
+    /// When the port is neither a board number nor a device path.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When there is more address after the final field.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the final field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+}
+
+impl AsrlParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        use AsrlParseError::*;
+
+        match self {
+            NumParseError { start, end, .. } | TrailingData { start, end, .. } => {
+                Some(*start..*end)
+            }
+            NotAsrl(_) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use AsrlParseError::*;
+
+        match self {
+            NotAsrl(..) => "NotAsrl",
+            NumParseError { .. } => "NumParseError",
+            TrailingData { .. } => "TrailingData",
+        }
+    }
+}
+
+impl FromStr for AsrlAddress {
+    type Err = AsrlParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use AsrlParseError::*;
+
+        // ASRL[board][::INSTR]
+        let Some(rest) = strip_keyword(address, "ASRL") else {
+            return Err(NotAsrl(address.chars().take(4).collect()));
+        };
+        let start = "ASRL".len();
+
+        // ASRL[board][::INSTR]
+        //     ↑-----↑
+        let port_len = rest.find("::").unwrap_or(rest.len());
+        let port = match &rest[..port_len] {
+            "" => None,
+            path if is_device_path(path) => Some(AsrlPort::Path(path.to_string())),
+            digits => {
+                // u32 parsing accepts a leading '+', which is never written in an address.
+                // Parsing just the sign fails with an invalid digit, which is the error for it.
+                let parsed = match digits.starts_with('+') {
+                    true => "+".parse(),
+                    false => digits.parse(),
+                };
+                match parsed {
+                    Ok(board) => Some(AsrlPort::Board(board)),
+                    Err(err) => {
+                        return Err(NumParseError {
+                            found: digits.to_string(),
+                            addr: address.to_string(),
+                            start,
+                            end: start + port_len,
+                            source: err,
+                        })
+                    }
+                }
+            }
+        };
+
+        // ASRL[board][::INSTR]
+        //              ↑---↑
+        let index = start + port_len;
+        let instr = match &address[index..] {
+            "" => false,
+            tail if tail.len() == "::INSTR".len() && tail[2..].eq_ignore_ascii_case("INSTR") => {
+                true
+            }
+            tail => {
+                return Err(TrailingData {
+                    found: tail.to_string(),
+                    addr: address.to_string(),
+                    start: index,
+                    end: address.len(),
+                })
+            }
+        };
+
+        Ok(AsrlAddress {
+            port,
+            class: instr.then_some(ResourceClass::Instr),
+        })
+    }
+}
+
+/// Checks if a port is a Unix device path, or a Windows COM port.
+fn is_device_path(port: &str) -> bool {
+    let is_com = port
+        .get(..3)
+        .is_some_and(|com| com.eq_ignore_ascii_case("COM"))
+        && port.len() > 3
+        && port[3..].bytes().all(|byte| byte.is_ascii_digit());
+
+    is_com || (port.starts_with('/') && !port.contains(char::is_whitespace))
+}
+
+impl Display for AsrlAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // ASRL[board][::INSTR]
+
+        write!(f, "ASRL")?;
+        match &self.port {
+            Some(AsrlPort::Board(board)) => write!(f, "{}", board)?,
+            Some(AsrlPort::Path(path)) => write!(f, "{}", path)?,
+            None => {}
+        }
+        if let Some(class) = self.class {
+            write!(f, "::{}", class)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of serial port addresses to parse.
+    use super::*;
+    use crate::testing::check_never_panics;
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), AsrlParseError> {
+                const ADDR: &str = $addr;
+                match AsrlAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_eq!(address.to_string(), ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(asrl_parse_board, "ASRL1::INSTR");
+    test_parse!(asrl_parse_no_board, "ASRL::INSTR");
+    test_parse!(asrl_parse_no_instr, "ASRL12");
+    test_parse!(asrl_parse_unix_path, "ASRL/dev/ttyUSB0::INSTR");
+    test_parse!(asrl_parse_windows_port, "ASRLCOM3::INSTR");
+
+    #[test]
+    fn asrl_ports() -> Result<(), AsrlParseError> {
+        let addr = AsrlAddress::from_str("ASRL/dev/tty.usbserial-A10::instr")?;
+        assert_eq!(addr.path(), Some("/dev/tty.usbserial-A10"));
+        assert_eq!(addr.board(), None);
+        assert_eq!(addr.to_string(), "ASRL/dev/tty.usbserial-A10::INSTR");
+
+        let addr = addr.with_board(4);
+        assert_eq!(addr.port(), Some(&AsrlPort::Board(4)));
+        assert_eq!(addr.path(), None);
+        assert_eq!(addr.to_string(), "ASRL4::INSTR");
+
+        assert_eq!(AsrlAddress::from_str("ASRL::INSTR")?.port(), None);
+        assert_eq!(
+            AsrlAddress::from_str("asrlcom3::instr")?.to_string(),
+            "ASRLcom3::INSTR"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn asrl_invalid() {
+        for (addr, expected) in [
+            ("ASRLx::INSTR", "Found \"x\" instead of a number at position 4 to 5 of \n\"ASRLx::INSTR\""),
+            ("ASRL+1::INSTR", "Found \"+1\" instead of a number at position 4 to 6 of \n\"ASRL+1::INSTR\""),
+            ("ASRLCOM::INSTR", "Found \"COM\" instead of a number at position 4 to 7 of \n\"ASRLCOM::INSTR\""),
+            ("ASRL1:INSTR", "Found \"1:INSTR\" instead of a number at position 4 to 11 of \n\"ASRL1:INSTR\""),
+            ("ASRL1::INSTR::2", "Found \"::INSTR::2\" after the end of the address at 5 to 15 of\n \"ASRL1::INSTR::2\""),
+            ("ASRL1::SOCKET", "Found \"::SOCKET\" after the end of the address at 5 to 13 of\n \"ASRL1::SOCKET\""),
+            ("ASRL1::", "Found \"::\" after the end of the address at 5 to 7 of\n \"ASRL1::\""),
+            ("GPIB0::1::INSTR", "Expected \"ASRL\" at address start, found \"GPIB\""),
+        ] {
+            match AsrlAddress::from_str(addr) {
+                Ok(asrl) => panic!("Accepted invalid address {addr:?} as {asrl}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+    }
+
+    #[test]
+    fn asrl_never_panics() {
+        let seeds = ["ASRL/dev/ttyUSB0::INSTR", "ASRL1::INSTR", "ASRLCOM3"];
+        check_never_panics("ASRL", &seeds, |input| {
+            AsrlAddress::from_str(input)
+                .err()
+                .and_then(|err| err.span())
+        });
+    }
+}
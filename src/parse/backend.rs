@@ -0,0 +1,21 @@
+//! Module for the VISA implementations that addresses may be handed to.
+
+/// A VISA implementation, for when an address must be written how that implementation prefers.
+///
+/// All of them accept canonical addresses in theory, but the form they list resources in
+/// is the form that they are sure to accept.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum VisaBackend {
+    /// National Instruments NI-VISA.
+    /// Lists an explicit board and class, with four digit hex codes.
+    NiVisa,
+    /// Keysight IO Libraries VISA.
+    /// Same as NI-VISA, but also lists the USB interface number.
+    KeysightVisa,
+    /// The pure Python PyVISA-py backend.
+    /// Same as Keysight, as it matches resources on the interface number as well.
+    PyVisaPy,
+    /// Rohde & Schwarz VISA.
+    /// Same as NI-VISA.
+    RohdeSchwarz,
+}
@@ -0,0 +1,50 @@
+//! Module for board numbers as used in resource discovery.
+use std::fmt::Display;
+
+/// Which boards to look on, for resource discovery.
+///
+/// An address without a board number may be on any board, which is clearer to
+/// match on than a `None`.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::BoardSpec;
+/// assert!(BoardSpec::Any.matches(3));
+/// assert!(!BoardSpec::Specific(1).matches(3));
+/// assert_eq!(BoardSpec::Specific(1).to_string(), "1");
+/// assert_eq!(BoardSpec::Any.to_string(), "?*");
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum BoardSpec {
+    /// Only this board.
+    Specific(u32),
+    /// Every board.
+    Any,
+}
+
+impl BoardSpec {
+    /// Whether a resource on the board is included.
+    pub fn matches(&self, board: u32) -> bool {
+        match self {
+            BoardSpec::Specific(specific) => *specific == board,
+            BoardSpec::Any => true,
+        }
+    }
+}
+
+impl From<Option<u32>> for BoardSpec {
+    fn from(board: Option<u32>) -> Self {
+        board.map_or(BoardSpec::Any, BoardSpec::Specific)
+    }
+}
+
+impl Display for BoardSpec {
+    /// Writes the board as in a VISA search expression.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardSpec::Specific(board) => write!(f, "{board}"),
+            BoardSpec::Any => f.write_str("?*"),
+        }
+    }
+}
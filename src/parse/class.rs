@@ -0,0 +1,111 @@
+//! Module for the resource classes that end VISA addresses.
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+/// The resource class of a VISA address, such as the "INSTR" in `USB::0x1A34::0x5678::A22-5::INSTR`.
+///
+/// This is every class in the VISA specification. Each interface only allows some of them.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::ResourceClass;
+/// assert_eq!("instr".parse::<ResourceClass>()?, ResourceClass::Instr);
+/// assert_eq!(ResourceClass::Backplane.to_string(), "BACKPLANE");
+/// # Ok::<(), fisa::parse::UnknownResourceClass>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum ResourceClass {
+    /// A device.
+    Instr,
+    /// An interface board, such as a GPIB controller.
+    Intfc,
+    /// A raw TCP socket.
+    Socket,
+    /// Raw USB.
+    Raw,
+    /// Memory access, for VXI and PXI.
+    Memacc,
+    /// A VXI or PXI chassis backplane.
+    Backplane,
+    /// The controller acting as a device.
+    Servant,
+}
+
+/// When a resource class is not one in the VISA specification.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+#[error("{0:?} is not a resource class")]
+pub struct UnknownResourceClass(pub String);
+
+impl ResourceClass {
+    /// Every resource class.
+    pub const ALL: [ResourceClass; 7] = [
+        ResourceClass::Instr,
+        ResourceClass::Intfc,
+        ResourceClass::Socket,
+        ResourceClass::Raw,
+        ResourceClass::Memacc,
+        ResourceClass::Backplane,
+        ResourceClass::Servant,
+    ];
+
+    /// The class as written in an address.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceClass::Instr => "INSTR",
+            ResourceClass::Intfc => "INTFC",
+            ResourceClass::Socket => "SOCKET",
+            ResourceClass::Raw => "RAW",
+            ResourceClass::Memacc => "MEMACC",
+            ResourceClass::Backplane => "BACKPLANE",
+            ResourceClass::Servant => "SERVANT",
+        }
+    }
+}
+
+impl FromStr for ResourceClass {
+    type Err = UnknownResourceClass;
+
+    /// Case-insensitively parses a resource class.
+    fn from_str(class: &str) -> Result<Self, Self::Err> {
+        ResourceClass::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(class))
+            .ok_or_else(|| UnknownResourceClass(class.to_string()))
+    }
+}
+
+impl Display for ResourceClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Resource class tokens.
+    use super::*;
+
+    #[test]
+    fn class_round_trip() -> Result<(), UnknownResourceClass> {
+        for class in ResourceClass::ALL {
+            let token = class.to_string();
+
+            assert_eq!(ResourceClass::from_str(&token)?, class);
+            assert_eq!(ResourceClass::from_str(&token.to_lowercase())?, class);
+        }
+        assert_eq!(ResourceClass::from_str("Socket")?, ResourceClass::Socket);
+        Ok(())
+    }
+
+    #[test]
+    fn class_unknown() {
+        for token in ["", "INST", "INSTRUMENT", "SOCKETS", " RAW", "SERVANT "] {
+            assert_eq!(
+                ResourceClass::from_str(token),
+                Err(UnknownResourceClass(token.to_string()))
+            );
+        }
+    }
+}
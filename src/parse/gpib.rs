@@ -0,0 +1,397 @@
+//! Module for GPIB VISA addresses.
+//! Includes primarily the main struct and the errors.
+use std::{fmt::Display, num::ParseIntError, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{strip_keyword, ResourceClass};
+
+/// Represents a GPIB VISA address
+///
+/// This is the INSTR form, `GPIB[board]::primary address[::secondary address][::INSTR]`.
+///
+/// Both addresses are 0 to 30. IEEE 488 puts secondary addresses on the bus as 96 to 126,
+/// and some tools write them that way, so those are accepted too and stored as 0 to 30.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::gpib::GpibAddress;
+/// let addr: GpibAddress = "GPIB0::22::96::INSTR".parse()?;
+/// assert_eq!(addr.primary(), 22);
+/// assert_eq!(addr.secondary(), Some(0));
+/// assert_eq!(addr.to_string(), "GPIB0::22::0::INSTR");
+/// # Ok::<(), fisa::parse::gpib::GpibParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct GpibAddress {
+    /// Board number, if any.
+    board: Option<u32>,
+    /// Primary address, 0 to 30.
+    primary: u8,
+    /// Secondary address, 0 to 30, if any.
+    secondary: Option<u8>,
+    /// The resource class, if given. Only INSTR is parsed.
+    class: Option<ResourceClass>,
+}
+
+impl GpibAddress {
+    /// Failably creates a new GpibAddress from an address.
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, GpibParseError> {
+        GpibAddress::from_str(addr)
+    }
+
+    /// The board number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
+
+    /// The same address, but on the given board.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    pub fn class(&self) -> Option<ResourceClass> {
+        self.class
+    }
+
+    /// The primary address, 0 to 30.
+    pub fn primary(&self) -> u8 {
+        self.primary
+    }
+
+    /// The secondary address, 0 to 30, if the address has one.
+    pub fn secondary(&self) -> Option<u8> {
+        self.secondary
+    }
+}
+
+/// Errors that can return from GPIB address parsing.
+#[derive(Error, Debug)]
+pub enum GpibParseError {
+    /// When the given address does not have the GPIB prefix.
+    #[error("Expected \"GPIB\" at address start, found {0:?}")]
+    NotGpib(String),
+
+    /// When parsing a number fails.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When a primary or secondary address is not a valid GPIB address.
+    #[error("The {field} must be {allowed}, found {found} at position {start:?} to {end:?} of\n {addr:?}")]
+    OutOfRange {
+        /// Which field was out of range
+        field: &'static str,
+        /// The values that are allowed
+        allowed: &'static str,
+        /// The number that was found
+        found: u32,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the number
+        start: usize,
+        /// End of the span containing the number
+        end: usize,
+    },
+
+    /// When an address is detected to not be complete.
+    #[error("{0:?} is an incomplete address missing: {1}")]
+    IncompleteAddress(String, String),
+
+    /// When the end of a token in the address is detect but is malformed.
+    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
+    InvalidSeperator {
+        /// What was found instead of "::"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "::"
+        start: usize,
+        /// End fo the span containing the invalid "::"
+        end: usize,
+    },
+
+    /// When there is more address after the final field.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the final field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+}
+
+impl GpibParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        use GpibParseError::*;
+
+        match self {
+            NumParseError { start, end, .. }
+            | OutOfRange { start, end, .. }
+            | InvalidSeperator { start, end, .. }
+            | TrailingData { start, end, .. } => Some(*start..*end),
+            NotGpib(_) | IncompleteAddress(..) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use GpibParseError::*;
+
+        match self {
+            NotGpib(..) => "NotGpib",
+            NumParseError { .. } => "NumParseError",
+            OutOfRange { .. } => "OutOfRange",
+            IncompleteAddress(..) => "IncompleteAddress",
+            InvalidSeperator { .. } => "InvalidSeperator",
+            TrailingData { .. } => "TrailingData",
+        }
+    }
+}
+
+impl FromStr for GpibAddress {
+    type Err = GpibParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use GpibParseError::*;
+
+        // GPIB[board]::primary address[::secondary address][::INSTR]
+        let Some(rest) = strip_keyword(address, "GPIB") else {
+            return Err(NotGpib(address.chars().take(4).collect()));
+        };
+        let mut index = "GPIB".len();
+
+        // GPIB[board]::primary address[::secondary address][::INSTR]
+        //     ↑-----↑
+        let board_len = rest.find(':').unwrap_or(rest.len());
+        let board = match &rest[..board_len] {
+            "" => None,
+            digits => Some(number(address, index, digits)?),
+        };
+        index += board_len;
+
+        // GPIB[board]::primary address[::secondary address][::INSTR]
+        //               ↑-------------↑
+        let rest = separator(address, index)?
+            .ok_or_else(|| IncompleteAddress(address.to_string(), "Primary Address".to_string()))?;
+        index += 2;
+        let primary_len = rest.find(':').unwrap_or(rest.len());
+        let found = number(address, index, &rest[..primary_len])?;
+        let primary = match u8::try_from(found) {
+            Ok(primary @ 0..=30) => primary,
+            _ => {
+                return Err(OutOfRange {
+                    field: "primary address",
+                    allowed: "0 to 30",
+                    found,
+                    addr: address.to_string(),
+                    start: index,
+                    end: index + primary_len,
+                })
+            }
+        };
+        index += primary_len;
+
+        // GPIB[board]::primary address[::secondary address][::INSTR]
+        //                                ↑---------------↑    ↑---↑
+        let mut secondary = None;
+        let mut instr = false;
+        while let Some(rest) = separator(address, index)? {
+            index += 2;
+            let field_len = rest.find(':').unwrap_or(rest.len());
+            let field = &rest[..field_len];
+
+            if instr
+                || field.is_empty()
+                || (secondary.is_some() && !field.eq_ignore_ascii_case("INSTR"))
+            {
+                return Err(TrailingData {
+                    found: address[index - 2..].to_string(),
+                    addr: address.to_string(),
+                    start: index - 2,
+                    end: address.len(),
+                });
+            } else if field.eq_ignore_ascii_case("INSTR") {
+                instr = true;
+            } else {
+                let found = number(address, index, field)?;
+                secondary = match u8::try_from(found) {
+                    Ok(secondary @ 0..=30) => Some(secondary),
+                    Ok(secondary @ 96..=126) => Some(secondary - 96),
+                    _ => {
+                        return Err(OutOfRange {
+                            field: "secondary address",
+                            allowed: "0 to 30, or 96 to 126",
+                            found,
+                            addr: address.to_string(),
+                            start: index,
+                            end: index + field_len,
+                        })
+                    }
+                };
+            }
+            index += field_len;
+        }
+
+        Ok(GpibAddress {
+            board,
+            primary,
+            secondary,
+            class: instr.then_some(ResourceClass::Instr),
+        })
+    }
+}
+
+/// Parses a number at the index of the address.
+fn number(address: &str, index: usize, digits: &str) -> Result<u32, GpibParseError> {
+    // u32 parsing accepts a leading '+', which is never written in an address.
+    // Parsing just the sign fails with an invalid digit, which is the error for it.
+    let parsed = match digits.starts_with('+') {
+        true => "+".parse(),
+        false => digits.parse(),
+    };
+    parsed.map_err(|err| GpibParseError::NumParseError {
+        found: digits.to_string(),
+        addr: address.to_string(),
+        start: index,
+        end: index + digits.len(),
+        source: err,
+    })
+}
+
+/// Checks for a "::" at the index, returning the rest of the address after it.
+/// Returns None at the end of the address.
+fn separator(address: &str, index: usize) -> Result<Option<&str>, GpibParseError> {
+    let rest = &address[index..];
+
+    if rest.is_empty() {
+        Ok(None)
+    } else if let Some(rest) = rest.strip_prefix("::") {
+        Ok(Some(rest))
+    } else {
+        Err(GpibParseError::InvalidSeperator {
+            found: rest.chars().take(2).collect(),
+            addr: address.to_string(),
+            start: index,
+            end: (index + 2).min(address.len()),
+        })
+    }
+}
+
+impl Display for GpibAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // GPIB[board]::primary address[::secondary address][::INSTR]
+
+        write!(f, "GPIB")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+        write!(f, "::{}", self.primary)?;
+        if let Some(secondary) = self.secondary {
+            write!(f, "::{}", secondary)?
+        }
+        if let Some(class) = self.class {
+            write!(f, "::{}", class)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of GPIB addresses to parse.
+    use super::*;
+    use crate::testing::check_never_panics;
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), GpibParseError> {
+                const ADDR: &str = $addr;
+                match GpibAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_eq!(address.to_string(), ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(gpib_parse_primary, "GPIB::1");
+    test_parse!(gpib_parse_instr, "GPIB0::22::INSTR");
+    test_parse!(gpib_parse_secondary, "GPIB::1::0::INSTR");
+    test_parse!(gpib_parse_board, "GPIB2::30::30");
+
+    #[test]
+    fn gpib_fields() -> Result<(), GpibParseError> {
+        let addr = GpibAddress::from_str("GPIB1::5::110::instr")?;
+        assert_eq!(addr.board(), Some(1));
+        assert_eq!(addr.primary(), 5);
+        assert_eq!(addr.secondary(), Some(14));
+        assert_eq!(addr.to_string(), "GPIB1::5::14::INSTR");
+        assert_eq!(addr.with_board(3).to_string(), "GPIB3::5::14::INSTR");
+        assert_eq!(
+            GpibAddress::from_str("gpib1::5::instr")?.to_string(),
+            "GPIB1::5::INSTR"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gpib_invalid() {
+        for (addr, expected) in [
+            ("GPIB::31::INSTR", "The primary address must be 0 to 30, found 31 at position 6 to 8 of\n \"GPIB::31::INSTR\""),
+            ("GPIB::1::31", "The secondary address must be 0 to 30, or 96 to 126, found 31 at position 9 to 11 of\n \"GPIB::1::31\""),
+            ("GPIB::1::127::INSTR", "The secondary address must be 0 to 30, or 96 to 126, found 127 at position 9 to 12 of\n \"GPIB::1::127::INSTR\""),
+            ("GPIB::+1", "Found \"+1\" instead of a number at position 6 to 8 of \n\"GPIB::+1\""),
+            ("GPIB-VXI::9::INSTR", "Found \"-VXI\" instead of a number at position 4 to 8 of \n\"GPIB-VXI::9::INSTR\""),
+            ("GPIB0", "\"GPIB0\" is an incomplete address missing: Primary Address"),
+            ("GPIB0:1", "Double colons must seperate address portions. Found \":1\" in:\n \"GPIB0:1\"."),
+            ("GPIB::1::2::3", "Found \"::3\" after the end of the address at 10 to 13 of\n \"GPIB::1::2::3\""),
+            ("GPIB::1::INSTR::2", "Found \"::2\" after the end of the address at 14 to 17 of\n \"GPIB::1::INSTR::2\""),
+            ("GPIB::1::", "Found \"::\" after the end of the address at 7 to 9 of\n \"GPIB::1::\""),
+            ("USB::0x1A34::0x5678::A22-5", "Expected \"GPIB\" at address start, found \"USB:\""),
+        ] {
+            match GpibAddress::from_str(addr) {
+                Ok(gpib) => panic!("Accepted invalid address {addr:?} as {gpib}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+    }
+
+    #[test]
+    fn gpib_never_panics() {
+        let seeds = ["GPIB0::22::96::INSTR", "GPIB::1"];
+        check_never_panics("GPIB", &seeds, |input| {
+            GpibAddress::from_str(input)
+                .err()
+                .and_then(|err| err.span())
+        });
+    }
+}
@@ -0,0 +1,211 @@
+//! Module for options that change how leniently addresses are parsed.
+
+/// Options for parsing addresses.
+///
+/// The default accepts any well-formed address, in any spelling the VISA specification allows.
+/// Each option loosens, or tightens, the parser in one specific way. For the common
+/// combinations there are the [`ParseOptions::strict`] and [`ParseOptions::lenient`] presets.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::{usb::UsbAddress, ParseOptions};
+/// let opts = ParseOptions::default().strip_wrappers(true);
+/// let addr = UsbAddress::parse_with("<USB::0x1A34::0x5678::A22-5>", &opts)?;
+/// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ParseOptions {
+    /// Strip a single matched pair of quotes or brackets around the address.
+    pub(crate) strip_wrappers: bool,
+    /// Treat four hex digits without a "0x" as hex.
+    pub(crate) infer_hex_prefix: bool,
+    /// Strip a single trailing '/'.
+    pub(crate) strip_trailing_slash: bool,
+    /// Trim surrounding whitespace.
+    pub(crate) trim_whitespace: bool,
+    /// Reject addresses that are not written exactly as they are displayed.
+    pub(crate) require_canonical: bool,
+    /// Reject control characters, such as tabs, anywhere in the address.
+    pub(crate) reject_control_chars: bool,
+    /// Trim whitespace padding from the serial number.
+    pub(crate) trim_serial: bool,
+    /// Accept the interface number after the resource class.
+    pub(crate) tolerate_reordered_tail: bool,
+}
+
+impl ParseOptions {
+    /// Options that only accept addresses written in their canonical form, such as for
+    /// validating a configuration file that is compared as text elsewhere.
+    ///
+    /// This enables [`ParseOptions::require_canonical`], and nothing else.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, ParseOptions};
+    /// let strict = ParseOptions::strict();
+    /// assert!(UsbAddress::parse_with("USB::0x1A34::0x5678::A22-5::INSTR", &strict).is_ok());
+    /// assert!(UsbAddress::parse_with("USB::0x1a34::0x5678::A22-5::instr", &strict).is_err());
+    /// ```
+    #[must_use]
+    pub fn strict() -> Self {
+        ParseOptions::default().require_canonical(true)
+    }
+
+    /// Options that accept anything that can reasonably be read as an address,
+    /// such as from user input or a copy-paste.
+    ///
+    /// This enables:
+    /// * [`ParseOptions::trim_whitespace`]
+    /// * [`ParseOptions::strip_wrappers`]
+    /// * [`ParseOptions::strip_trailing_slash`]
+    /// * [`ParseOptions::infer_hex_prefix`]
+    /// * [`ParseOptions::reject_control_chars`]
+    /// * [`ParseOptions::tolerate_reordered_tail`]
+    ///
+    /// The whole address is always case-insensitive, so needs no option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, ParseOptions};
+    /// let addr = UsbAddress::parse_with(" \"usb::1A34::0x5678::A22-5/\" ", &ParseOptions::lenient())?;
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[must_use]
+    pub fn lenient() -> Self {
+        ParseOptions::default()
+            .trim_whitespace(true)
+            .strip_wrappers(true)
+            .strip_trailing_slash(true)
+            .infer_hex_prefix(true)
+            .reject_control_chars(true)
+            .tolerate_reordered_tail(true)
+    }
+
+    /// Options matching what PyVISA accepts, for those migrating from it.
+    ///
+    /// This enables [`ParseOptions::infer_hex_prefix`], as PyVISA does not check that codes start with "0x".
+    ///
+    /// An omitted board and a lowercase address, such as `usb::0x1a34::0x5678::A22-5::instr`,
+    /// are always accepted, so need no option.
+    #[must_use]
+    pub fn pyvisa_compat() -> Self {
+        ParseOptions::default().infer_hex_prefix(true)
+    }
+
+    /// Strips a single matched pair of `"`, `'`, `` ` ``, or `<>` from around the address before parsing.
+    /// These show up when addresses are copy-pasted out of documentation or a shell.
+    #[must_use]
+    pub fn strip_wrappers(mut self, enable: bool) -> Self {
+        self.strip_wrappers = enable;
+        self
+    }
+
+    /// Accepts a manufacturer ID or model code that is missing its "0x" prefix, as long as it is
+    /// exactly four hex digits, such as `1A34`. Anything else still must start with "0x".
+    /// A warning is recorded for each code that the prefix was inferred for.
+    #[must_use]
+    pub fn infer_hex_prefix(mut self, enable: bool) -> Self {
+        self.infer_hex_prefix = enable;
+        self
+    }
+
+    /// Strips a single trailing `/` from the address before parsing.
+    /// Web tooling likes to append these when an address is pasted from a URL.
+    #[must_use]
+    pub fn strip_trailing_slash(mut self, enable: bool) -> Self {
+        self.strip_trailing_slash = enable;
+        self
+    }
+
+    /// Accepts the interface prefix in any case, such as `usb` or `Usb`.
+    ///
+    /// This does nothing, as the prefix is always accepted in any case. The VISA specification
+    /// makes addresses case-insensitive. Use [`ParseOptions::require_canonical`] to reject
+    /// anything but uppercase.
+    #[must_use]
+    #[deprecated(
+        since = "0.1.0",
+        note = "the interface prefix is always case-insensitive, this does nothing"
+    )]
+    pub fn ignore_prefix_case(self, _enable: bool) -> Self {
+        self
+    }
+
+    /// Trims whitespace from both ends of the address before parsing.
+    /// This is done before any other option is applied.
+    #[must_use]
+    pub fn trim_whitespace(mut self, enable: bool) -> Self {
+        self.trim_whitespace = enable;
+        self
+    }
+
+    /// Rejects any address that is not written exactly how it is displayed,
+    /// such as one with lowercase hex digits or a lowercase "instr".
+    /// This is checked after the other options are applied.
+    #[must_use]
+    pub fn require_canonical(mut self, enable: bool) -> Self {
+        self.require_canonical = enable;
+        self
+    }
+
+    /// Rejects a control character anywhere in the address, such as a tab or carriage return,
+    /// rather than taking it as part of the serial number.
+    /// Spreadsheets separate cells with these, so they show up when a column is copy-pasted.
+    /// Surrounding whitespace is not rejected if [`ParseOptions::trim_whitespace`] is also enabled.
+    #[must_use]
+    pub fn reject_control_chars(mut self, enable: bool) -> Self {
+        self.reject_control_chars = enable;
+        self
+    }
+
+    /// Trims whitespace padding from both ends of the serial number, as some instruments pad
+    /// their serial in their USB descriptor. See [`UsbAddress::with_trimmed_serial`](crate::parse::usb::UsbAddress::with_trimmed_serial).
+    #[must_use]
+    pub fn trim_serial(mut self, enable: bool) -> Self {
+        self.trim_serial = enable;
+        self
+    }
+
+    /// Accepts the interface number after the resource class, such as `USB::0x1A34::0x5678::A22-5::INSTR::5`,
+    /// as some tools write them in that order. It is moved before the class, where it belongs.
+    /// A warning is recorded when this happens.
+    #[must_use]
+    pub fn tolerate_reordered_tail(mut self, enable: bool) -> Self {
+        self.tolerate_reordered_tail = enable;
+        self
+    }
+
+    /// Applies the options that operate on the whole address before it is tokenized.
+    ///
+    /// Spans in errors are relative to the address returned here.
+    pub(crate) fn preprocess<'a>(&self, addr: &'a str) -> &'a str {
+        let mut addr = addr;
+
+        if self.trim_whitespace {
+            addr = addr.trim();
+        }
+
+        if self.strip_wrappers {
+            for (open, close) in [('"', '"'), ('\'', '\''), ('`', '`'), ('<', '>')] {
+                if let Some(inner) = addr
+                    .strip_prefix(open)
+                    .and_then(|rest| rest.strip_suffix(close))
+                {
+                    addr = inner;
+                    break;
+                }
+            }
+        }
+
+        if self.strip_trailing_slash {
+            addr = addr.strip_suffix('/').unwrap_or(addr);
+        }
+
+        addr
+    }
+}
@@ -0,0 +1,574 @@
+//! Module for PXI VISA addresses.
+//! Includes primarily the main struct and the errors.
+use std::{fmt::Display, num::ParseIntError, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{strip_keyword, ResourceClass};
+
+/// Represents a PXI VISA address
+///
+/// This is the INSTR form, either by PCI location, `PXI[bus]::device[::function][::INSTR]`,
+/// or by where the module is in the chassis,
+/// `PXI[interface]::CHASSISchassis::SLOTslot[::FUNCfunction][::INSTR]`.
+///
+/// Devices are 0 to 31 and functions are 0 to 7, as in PCI. The
+/// `PXI[interface]::bus-device[.function]` form, such as `PXI0::3-18.2::INSTR`, is not
+/// supported, and is an [`Unsupported`](PxiParseError::Unsupported) error.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::pxi::{PxiAddress, PxiLocation};
+/// let addr: PxiAddress = "PXI0::CHASSIS1::SLOT4::instr".parse()?;
+/// assert_eq!(
+///     addr.location(),
+///     &PxiLocation::Slot { chassis: 1, slot: 4, function: None }
+/// );
+/// assert_eq!(addr.to_string(), "PXI0::CHASSIS1::SLOT4::INSTR");
+///
+/// let addr: PxiAddress = "PXI1::15::2".parse()?;
+/// assert_eq!(addr.location(), &PxiLocation::Device { device: 15, function: Some(2) });
+/// # Ok::<(), fisa::parse::pxi::PxiParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct PxiAddress {
+    /// Bus or interface number, if any.
+    board: Option<u32>,
+    /// Where the module is.
+    location: PxiLocation,
+    /// The resource class, if given. Only INSTR is parsed.
+    class: Option<ResourceClass>,
+}
+
+/// Where a PXI module is, as given in an address.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum PxiLocation {
+    /// By PCI device and function on the bus.
+    Device {
+        /// PCI device, 0 to 31.
+        device: u8,
+        /// PCI function, 0 to 7, if any.
+        function: Option<u8>,
+    },
+    /// By chassis and slot.
+    Slot {
+        /// Chassis number.
+        chassis: u32,
+        /// Slot number in the chassis.
+        slot: u32,
+        /// PCI function, 0 to 7, if any.
+        function: Option<u8>,
+    },
+}
+
+impl PxiAddress {
+    /// Failably creates a new PxiAddress from an address.
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, PxiParseError> {
+        PxiAddress::from_str(addr)
+    }
+
+    /// The bus or interface number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
+
+    /// The same address, but on the given bus or interface.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    pub fn class(&self) -> Option<ResourceClass> {
+        self.class
+    }
+
+    /// Where the module is.
+    pub fn location(&self) -> &PxiLocation {
+        &self.location
+    }
+}
+
+/// Errors that can return from PXI address parsing.
+#[derive(Error, Debug)]
+pub enum PxiParseError {
+    /// When the given address does not have the PXI prefix.
+    #[error("Expected \"PXI\" at address start, found {0:?}")]
+    NotPxi(String),
+
+    /// When parsing a number fails.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When a device or function is not a valid PCI one.
+    #[error("The {field} must be {allowed}, found {found} at position {start:?} to {end:?} of\n {addr:?}")]
+    OutOfRange {
+        /// Which field was out of range
+        field: &'static str,
+        /// The values that are allowed
+        allowed: &'static str,
+        /// The number that was found
+        found: u32,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the number
+        start: usize,
+        /// End of the span containing the number
+        end: usize,
+    },
+
+    /// When the device is given in the `bus-device[.function]` form, which is not supported.
+    #[error("The bus-device form is not supported, found {found:?} at position {start:?} to {end:?} of\n {addr:?}")]
+    Unsupported {
+        /// The bus-device field that was found
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the field
+        start: usize,
+        /// End of the field
+        end: usize,
+    },
+
+    /// When a field of the chassis form does not start with its keyword, such as "SLOT".
+    #[error(
+        "Expected {expected:?} at position {start:?} to {end:?}, found {found:?} in\n {addr:?}"
+    )]
+    MissingKeyword {
+        /// The keyword that the field must start with
+        expected: &'static str,
+        /// The field that was found
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the field
+        start: usize,
+        /// End of the field
+        end: usize,
+    },
+
+    /// When an address is detected to not be complete.
+    #[error("{0:?} is an incomplete address missing: {1}")]
+    IncompleteAddress(String, String),
+
+    /// When the end of a token in the address is detect but is malformed.
+    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
+    InvalidSeperator {
+        /// What was found instead of "::"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "::"
+        start: usize,
+        /// End fo the span containing the invalid "::"
+        end: usize,
+    },
+
+    /// When there is more address after the final field.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the final field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+}
+
+impl PxiParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        use PxiParseError::*;
+
+        match self {
+            NumParseError { start, end, .. }
+            | OutOfRange { start, end, .. }
+            | Unsupported { start, end, .. }
+            | MissingKeyword { start, end, .. }
+            | InvalidSeperator { start, end, .. }
+            | TrailingData { start, end, .. } => Some(*start..*end),
+            NotPxi(_) | IncompleteAddress(..) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use PxiParseError::*;
+
+        match self {
+            NotPxi(..) => "NotPxi",
+            NumParseError { .. } => "NumParseError",
+            OutOfRange { .. } => "OutOfRange",
+            Unsupported { .. } => "Unsupported",
+            MissingKeyword { .. } => "MissingKeyword",
+            IncompleteAddress(..) => "IncompleteAddress",
+            InvalidSeperator { .. } => "InvalidSeperator",
+            TrailingData { .. } => "TrailingData",
+        }
+    }
+}
+
+/// The field that the parser expects next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+    /// The PCI device, or "CHASSIS" for the chassis form.
+    DeviceOrChassis,
+    /// The optional PCI function, or INSTR.
+    Function,
+    /// The "SLOT" of the chassis form.
+    Slot,
+    /// The optional "FUNC" of the chassis form, or INSTR.
+    Func,
+    /// Only INSTR is left.
+    Instr,
+    /// Nothing is left.
+    End,
+}
+
+impl FromStr for PxiAddress {
+    type Err = PxiParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use PxiParseError::*;
+
+        // PXI[bus]::device[::function][::INSTR]
+        // PXI[interface]::CHASSISn::SLOTm[::FUNCf][::INSTR]
+        let Some(rest) = strip_keyword(address, "PXI") else {
+            return Err(NotPxi(address.chars().take(3).collect()));
+        };
+        let mut index = "PXI".len();
+
+        // PXI[bus]::device[::function][::INSTR]
+        //    ↑---↑
+        let board_len = rest.find(':').unwrap_or(rest.len());
+        let board = match &rest[..board_len] {
+            "" => None,
+            digits => Some(number(address, index, digits)?),
+        };
+        index += board_len;
+
+        let mut state = State::DeviceOrChassis;
+        let mut location = PxiLocation::Device {
+            device: 0,
+            function: None,
+        };
+        let mut instr = false;
+
+        // PXI[bus]::device[::function][::INSTR]
+        //            ↑----↑  ↑------↑    ↑---↑
+        while let Some(rest) = separator(address, index)? {
+            index += 2;
+            let field_len = rest.find(':').unwrap_or(rest.len());
+            let field = &rest[..field_len];
+            let is_instr = field.eq_ignore_ascii_case("INSTR");
+
+            state = match state {
+                State::DeviceOrChassis => match strip_keyword(field, "CHASSIS") {
+                    Some(digits) => {
+                        let chassis = number(address, index + "CHASSIS".len(), digits)?;
+                        location = PxiLocation::Slot {
+                            chassis,
+                            slot: 0,
+                            function: None,
+                        };
+                        State::Slot
+                    }
+                    None if is_bus_device(field) => {
+                        return Err(Unsupported {
+                            found: field.to_string(),
+                            addr: address.to_string(),
+                            start: index,
+                            end: index + field_len,
+                        })
+                    }
+                    None => {
+                        let device = ranged(address, index, field, "device", "0 to 31", 31)?;
+                        location = PxiLocation::Device {
+                            device,
+                            function: None,
+                        };
+                        State::Function
+                    }
+                },
+                State::Slot => match (strip_keyword(field, "SLOT"), &mut location) {
+                    (Some(digits), PxiLocation::Slot { slot, .. }) => {
+                        *slot = number(address, index + "SLOT".len(), digits)?;
+                        State::Func
+                    }
+                    _ => {
+                        return Err(MissingKeyword {
+                            expected: "SLOT",
+                            found: field.to_string(),
+                            addr: address.to_string(),
+                            start: index,
+                            end: index + field_len,
+                        })
+                    }
+                },
+                State::Function | State::Func | State::Instr if is_instr => {
+                    instr = true;
+                    State::End
+                }
+                State::Function if !field.is_empty() => {
+                    let found = ranged(address, index, field, "function", "0 to 7", 7)?;
+                    if let PxiLocation::Device { function, .. } = &mut location {
+                        *function = Some(found);
+                    }
+                    State::Instr
+                }
+                State::Func if strip_keyword(field, "FUNC").is_some() => {
+                    let digits = &field["FUNC".len()..];
+                    let found = ranged(
+                        address,
+                        index + "FUNC".len(),
+                        digits,
+                        "function",
+                        "0 to 7",
+                        7,
+                    )?;
+                    if let PxiLocation::Slot { function, .. } = &mut location {
+                        *function = Some(found);
+                    }
+                    State::Instr
+                }
+                State::Function | State::Func | State::Instr | State::End => {
+                    return Err(TrailingData {
+                        found: address[index - 2..].to_string(),
+                        addr: address.to_string(),
+                        start: index - 2,
+                        end: address.len(),
+                    })
+                }
+            };
+            index += field_len;
+        }
+
+        match state {
+            State::DeviceOrChassis => {
+                Err(IncompleteAddress(address.to_string(), "Device".to_string()))
+            }
+            State::Slot => Err(IncompleteAddress(address.to_string(), "Slot".to_string())),
+            _ => Ok(PxiAddress {
+                board,
+                location,
+                class: instr.then_some(ResourceClass::Instr),
+            }),
+        }
+    }
+}
+
+/// Whether the field is in the `bus-device[.function]` form, such as `3-18.2`.
+fn is_bus_device(field: &str) -> bool {
+    field
+        .split_once('-')
+        .is_some_and(|(bus, _)| !bus.is_empty() && bus.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// Parses a number at the index of the address that must be at most `max`.
+fn ranged(
+    address: &str,
+    index: usize,
+    digits: &str,
+    field: &'static str,
+    allowed: &'static str,
+    max: u8,
+) -> Result<u8, PxiParseError> {
+    let found = number(address, index, digits)?;
+    match u8::try_from(found) {
+        Ok(value) if value <= max => Ok(value),
+        _ => Err(PxiParseError::OutOfRange {
+            field,
+            allowed,
+            found,
+            addr: address.to_string(),
+            start: index,
+            end: index + digits.len(),
+        }),
+    }
+}
+
+/// Parses a number at the index of the address.
+fn number(address: &str, index: usize, digits: &str) -> Result<u32, PxiParseError> {
+    // u32 parsing accepts a leading '+', which is never written in an address.
+    // Parsing just the sign fails with an invalid digit, which is the error for it.
+    let parsed = match digits.starts_with('+') {
+        true => "+".parse(),
+        false => digits.parse(),
+    };
+    parsed.map_err(|err| PxiParseError::NumParseError {
+        found: digits.to_string(),
+        addr: address.to_string(),
+        start: index,
+        end: index + digits.len(),
+        source: err,
+    })
+}
+
+/// Checks for a "::" at the index, returning the rest of the address after it.
+/// Returns None at the end of the address.
+fn separator(address: &str, index: usize) -> Result<Option<&str>, PxiParseError> {
+    let rest = &address[index..];
+
+    if rest.is_empty() {
+        Ok(None)
+    } else if let Some(rest) = rest.strip_prefix("::") {
+        Ok(Some(rest))
+    } else {
+        Err(PxiParseError::InvalidSeperator {
+            found: rest.chars().take(2).collect(),
+            addr: address.to_string(),
+            start: index,
+            end: (index + 2).min(address.len()),
+        })
+    }
+}
+
+impl Display for PxiAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // PXI[bus]::device[::function][::INSTR]
+        // PXI[interface]::CHASSISn::SLOTm[::FUNCf][::INSTR]
+
+        write!(f, "PXI")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+        match self.location {
+            PxiLocation::Device { device, function } => {
+                write!(f, "::{}", device)?;
+                if let Some(function) = function {
+                    write!(f, "::{}", function)?
+                }
+            }
+            PxiLocation::Slot {
+                chassis,
+                slot,
+                function,
+            } => {
+                write!(f, "::CHASSIS{}::SLOT{}", chassis, slot)?;
+                if let Some(function) = function {
+                    write!(f, "::FUNC{}", function)?
+                }
+            }
+        }
+        if let Some(class) = self.class {
+            write!(f, "::{}", class)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of PXI addresses to parse.
+    use super::*;
+    use crate::testing::check_never_panics;
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), PxiParseError> {
+                const ADDR: &str = $addr;
+                match PxiAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_eq!(address.to_string(), ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(pxi_parse_device, "PXI::15");
+    test_parse!(pxi_parse_function, "PXI1::15::3::INSTR");
+    test_parse!(pxi_parse_chassis, "PXI0::CHASSIS1::SLOT4");
+    test_parse!(
+        pxi_parse_chassis_func,
+        "PXI::CHASSIS2::SLOT10::FUNC1::INSTR"
+    );
+
+    #[test]
+    fn pxi_fields() -> Result<(), PxiParseError> {
+        let addr = PxiAddress::from_str("pxi2::chassis3::slot7::func1::instr")?;
+        assert_eq!(addr.board(), Some(2));
+        assert_eq!(
+            addr.location(),
+            &PxiLocation::Slot {
+                chassis: 3,
+                slot: 7,
+                function: Some(1)
+            }
+        );
+        assert_eq!(addr.to_string(), "PXI2::CHASSIS3::SLOT7::FUNC1::INSTR");
+        assert_eq!(
+            addr.with_board(0).to_string(),
+            "PXI0::CHASSIS3::SLOT7::FUNC1::INSTR"
+        );
+
+        let addr = PxiAddress::from_str("PXI::31::INSTR")?;
+        assert_eq!(
+            addr.location(),
+            &PxiLocation::Device {
+                device: 31,
+                function: None
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pxi_invalid() {
+        for (addr, expected) in [
+            ("PXI::32::INSTR", "The device must be 0 to 31, found 32 at position 5 to 7 of\n \"PXI::32::INSTR\""),
+            ("PXI::1::8", "The function must be 0 to 7, found 8 at position 8 to 9 of\n \"PXI::1::8\""),
+            ("PXI::CHASSIS1::SLOT2::FUNC8", "The function must be 0 to 7, found 8 at position 26 to 27 of\n \"PXI::CHASSIS1::SLOT2::FUNC8\""),
+            ("PXI::CHASSISx::SLOT2", "Found \"x\" instead of a number at position 12 to 13 of \n\"PXI::CHASSISx::SLOT2\""),
+            ("PXI::CHASSIS1::2", "Expected \"SLOT\" at position 15 to 16, found \"2\" in\n \"PXI::CHASSIS1::2\""),
+            ("PXI::CHASSIS1", "\"PXI::CHASSIS1\" is an incomplete address missing: Slot"),
+            ("PXI0", "\"PXI0\" is an incomplete address missing: Device"),
+            ("PXI::+1", "Found \"+1\" instead of a number at position 5 to 7 of \n\"PXI::+1\""),
+            ("PXI0:1", "Double colons must seperate address portions. Found \":1\" in:\n \"PXI0:1\"."),
+            ("PXI::1::2::3", "Found \"::3\" after the end of the address at 9 to 12 of\n \"PXI::1::2::3\""),
+            ("PXI::1::INSTR::2", "Found \"::2\" after the end of the address at 13 to 16 of\n \"PXI::1::INSTR::2\""),
+            ("PXI::CHASSIS1::SLOT2::3", "Found \"::3\" after the end of the address at 20 to 23 of\n \"PXI::CHASSIS1::SLOT2::3\""),
+            ("PXI::1::", "Found \"::\" after the end of the address at 6 to 8 of\n \"PXI::1::\""),
+            ("PXI0::3-18::INSTR", "The bus-device form is not supported, found \"3-18\" at position 6 to 10 of\n \"PXI0::3-18::INSTR\""),
+            ("PXI0::3-18.2::INSTR", "The bus-device form is not supported, found \"3-18.2\" at position 6 to 12 of\n \"PXI0::3-18.2::INSTR\""),
+            ("VXI::1", "Expected \"PXI\" at address start, found \"VXI\""),
+        ] {
+            match PxiAddress::from_str(addr) {
+                Ok(pxi) => panic!("Accepted invalid address {addr:?} as {pxi}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+    }
+
+    #[test]
+    fn pxi_never_panics() {
+        let seeds = ["PXI1::15::3::INSTR", "PXI0::CHASSIS1::SLOT4::FUNC1::INSTR"];
+        check_never_panics("PXI", &seeds, |input| {
+            PxiAddress::from_str(input).err().and_then(|err| err.span())
+        });
+    }
+}
@@ -0,0 +1,117 @@
+//! Module for serializing addresses with serde.
+//!
+//! Addresses are serialized as their canonical string, and deserialized by parsing,
+//! so a bad address in a config file is reported by serde with the parse error.
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{
+    asrl::AsrlAddress,
+    gpib::GpibAddress,
+    pxi::PxiAddress,
+    tcpip::{TcpipAddress, TcpipSocketAddress},
+    usb::UsbAddress,
+    vxi::VxiAddress,
+    ResourceClass, VisaAddress,
+};
+
+/// Parses a string into `T`, turning parse errors into serde errors.
+struct ParseVisitor<T> {
+    /// What is being parsed, for serde's error messages.
+    expecting: &'static str,
+    /// The type parsed into.
+    parsed: PhantomData<T>,
+}
+
+impl<'de, T> Visitor<'de> for ParseVisitor<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// Implements `Serialize` with `Display`, and `Deserialize` with `FromStr`.
+macro_rules! serde_with_str {
+    ($($ty:ty => $expecting:literal),* $(,)?) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(ParseVisitor {
+                    expecting: $expecting,
+                    parsed: PhantomData,
+                })
+            }
+        }
+    )*};
+}
+
+serde_with_str! {
+    UsbAddress => "a USB address",
+    GpibAddress => "a GPIB address",
+    AsrlAddress => "a serial address",
+    TcpipAddress => "a TCPIP address",
+    TcpipSocketAddress => "a TCPIP socket address",
+    VxiAddress => "a VXI address",
+    PxiAddress => "a PXI address",
+    VisaAddress => "a VISA address",
+    ResourceClass => "a resource class",
+}
+
+#[cfg(test)]
+mod test {
+    //! Addresses in and out of JSON.
+    use super::*;
+
+    #[test]
+    fn serde_round_trip() -> Result<(), serde_json::Error> {
+        let addr = UsbAddress::try_new("usb::0x1a34::0x5678::A22-5::instr").unwrap();
+        let json = serde_json::to_string(&addr)?;
+        assert_eq!(json, r#""USB::0x1A34::0x5678::A22-5::INSTR""#);
+        assert_eq!(serde_json::from_str::<UsbAddress>(&json)?, addr);
+
+        let addrs: Vec<VisaAddress> = serde_json::from_str(
+            r#"["GPIB0::1::INSTR", "ASRL1::INSTR", "TCPIP::1.2.3.4::5025::SOCKET", "PXI0::CHASSIS1::SLOT4::INSTR"]"#,
+        )?;
+        assert_eq!(addrs.len(), 4);
+        assert_eq!(
+            serde_json::to_string(&addrs)?,
+            r#"["GPIB0::1::INSTR","ASRL1::INSTR","TCPIP::1.2.3.4::5025::SOCKET","PXI0::CHASSIS1::SLOT4::INSTR"]"#
+        );
+
+        assert_eq!(
+            serde_json::from_str::<ResourceClass>(r#""backplane""#)?,
+            ResourceClass::Backplane
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn serde_errors() {
+        let err = serde_json::from_str::<UsbAddress>(r#""GPIB0::1::INSTR""#).unwrap_err();
+        assert!(err.to_string().contains("USB"), "{err}");
+
+        let err = serde_json::from_str::<VxiAddress>(r#""VXI0::256::INSTR""#).unwrap_err();
+        assert!(err.is_data(), "{err}");
+
+        let err = serde_json::from_str::<VisaAddress>("5").unwrap_err();
+        assert!(err.to_string().contains("a VISA address"), "{err}");
+    }
+}
@@ -0,0 +1,675 @@
+//! Module for TCPIP VISA addresses.
+//! Includes primarily the main struct and the errors.
+use std::{fmt::Display, num::ParseIntError, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{strip_keyword, ResourceClass};
+
+/// Represents a TCPIP VISA address
+///
+/// This is the INSTR form, `TCPIP[board]::host address[::LAN device name][::INSTR]`.
+/// See [`TcpipSocketAddress`] for the SOCKET form.
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct TcpipAddress {
+    /// Board number, if any.
+    board: Option<u32>,
+    /// Host name or IP address. IPv6 addresses keep their brackets.
+    host: String,
+    /// LAN device name, such as "inst0" or "hislip0". If None, then "inst0" is used.
+    device_name: Option<String>,
+    /// The resource class, if given. Only INSTR is parsed.
+    class: Option<ResourceClass>,
+}
+
+/// Represents a raw socket TCPIP VISA address, `TCPIP[board]::host address::port::SOCKET`.
+///
+/// Many instruments only expose SCPI over a raw socket, usually on port 5025.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::tcpip::TcpipSocketAddress;
+/// let addr: TcpipSocketAddress = "TCPIP0::192.168.0.5::5025::socket".parse()?;
+/// assert_eq!(addr.port(), 5025);
+/// assert_eq!(addr.to_string(), "TCPIP0::192.168.0.5::5025::SOCKET");
+/// # Ok::<(), fisa::parse::tcpip::TcpipParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct TcpipSocketAddress {
+    /// Board number, if any.
+    board: Option<u32>,
+    /// Host name or IP address. IPv6 addresses keep their brackets.
+    host: String,
+    /// TCP port.
+    port: u16,
+}
+
+/// The protocol used to talk to a LAN instrument, as chosen by the LAN device name.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum LanProtocol {
+    /// VXI-11 core channel, such as "inst0". Also the default.
+    Instr,
+    /// HiSLIP, such as "hislip0".
+    HiSlip,
+    /// VXI-11 to GPIB gateway, such as "gpib0,2".
+    VxiGpib,
+}
+
+impl LanProtocol {
+    /// Classifies a LAN device name. Anything that is not HiSLIP or a GPIB gateway is VXI-11.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::tcpip::LanProtocol;
+    /// assert_eq!(LanProtocol::from_device_name("hislip0"), LanProtocol::HiSlip);
+    /// assert_eq!(LanProtocol::from_device_name("inst0"), LanProtocol::Instr);
+    /// ```
+    pub fn from_device_name(name: &str) -> LanProtocol {
+        /// Case-insensitively checks for a prefix.
+        fn starts_with(name: &str, prefix: &str) -> bool {
+            name.get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        }
+
+        if starts_with(name, "hislip") {
+            LanProtocol::HiSlip
+        } else if starts_with(name, "gpib") {
+            LanProtocol::VxiGpib
+        } else {
+            LanProtocol::Instr
+        }
+    }
+}
+
+impl TcpipAddress {
+    /// Failably creates a new TcpipAddress from an address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::tcpip::{TcpipAddress, TcpipParseError};
+    /// let addr = "TCPIP0::192.168.0.5::hislip0::INSTR";
+    /// assert_eq!(TcpipAddress::try_new(addr)?.to_string(), addr);
+    /// # Ok::<(), TcpipParseError>(())
+    /// ```
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, TcpipParseError> {
+        TcpipAddress::from_str(addr)
+    }
+
+    /// The board number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
+
+    /// The same address, but on the given board.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    pub fn class(&self) -> Option<ResourceClass> {
+        self.class
+    }
+
+    /// Host name or IP address. IPv6 addresses keep their brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The LAN device name, such as "inst0" or "hislip0", if the address has one.
+    /// VISA takes a missing name to be "inst0".
+    pub fn device_name(&self) -> Option<&str> {
+        self.device_name.as_deref()
+    }
+
+    /// The protocol to use for this address, from its LAN device name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::tcpip::{LanProtocol, TcpipAddress};
+    /// let addr = TcpipAddress::try_new("TCPIP::devicename.company.com::INSTR")?;
+    /// assert_eq!(addr.lan_protocol(), LanProtocol::Instr);
+    /// # Ok::<(), fisa::parse::tcpip::TcpipParseError>(())
+    /// ```
+    pub fn lan_protocol(&self) -> LanProtocol {
+        self.device_name
+            .as_deref()
+            .map_or(LanProtocol::Instr, LanProtocol::from_device_name)
+    }
+}
+
+impl TcpipSocketAddress {
+    /// Failably creates a new TcpipSocketAddress from an address.
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, TcpipParseError> {
+        TcpipSocketAddress::from_str(addr)
+    }
+
+    /// The board number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
+
+    /// The same address, but on the given board.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// The resource class, which is always SOCKET.
+    pub fn class(&self) -> ResourceClass {
+        ResourceClass::Socket
+    }
+
+    /// Host name or IP address. IPv6 addresses keep their brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The TCP port.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Errors that can return from TCPIP address parsing.
+#[derive(Error, Debug)]
+pub enum TcpipParseError {
+    /// When the given address does not have the TCPIP prefix.
+    #[error("Expected \"TCPIP\" at address start, found {0:?}")]
+    NotTcpip(String),
+
+    /// When parsing the board number fails.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When an address is detected to not be complete.
+    #[error("{0:?} is an incomplete address missing: {1}")]
+    IncompleteAddress(String, String),
+
+    /// When the end of a token in the address is detect but is malformed.
+    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
+    InvalidSeperator {
+        /// What was found instead of "::"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "::"
+        start: usize,
+        /// End fo the span containing the invalid "::"
+        end: usize,
+    },
+
+    /// When the host address is not closed by a "]", or has whitespace in it.
+    #[error("Found {found:?} instead of a host address at {start:?} to {end:?} of\n {addr:?}")]
+    InvalidHost {
+        /// What was found instead of a host address
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the host address
+        start: usize,
+        /// End of the span containing the host address
+        end: usize,
+    },
+
+    /// When a SOCKET address is parsed as an INSTR address, or the other way around.
+    #[error(
+        "Expected the {expected} class, found {found:?} at {start:?} to {end:?} of\n {addr:?}"
+    )]
+    WrongClass {
+        /// The resource class that was expected
+        expected: &'static str,
+        /// What was found instead
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the class
+        start: usize,
+        /// End of the span containing the class
+        end: usize,
+    },
+
+    /// When there is more address after the final field.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the final field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+}
+
+impl TcpipParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        use TcpipParseError::*;
+
+        match self {
+            NumParseError { start, end, .. }
+            | InvalidSeperator { start, end, .. }
+            | InvalidHost { start, end, .. }
+            | WrongClass { start, end, .. }
+            | TrailingData { start, end, .. } => Some(*start..*end),
+            NotTcpip(_) | IncompleteAddress(..) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use TcpipParseError::*;
+
+        match self {
+            NotTcpip(..) => "NotTcpip",
+            NumParseError { .. } => "NumParseError",
+            IncompleteAddress(..) => "IncompleteAddress",
+            InvalidSeperator { .. } => "InvalidSeperator",
+            InvalidHost { .. } => "InvalidHost",
+            WrongClass { .. } => "WrongClass",
+            TrailingData { .. } => "TrailingData",
+        }
+    }
+}
+
+impl FromStr for TcpipAddress {
+    type Err = TcpipParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use TcpipParseError::*;
+
+        let (board, host, mut index) = board_and_host(address)?;
+
+        // TCPIP[board]::host address[::LAN device name][::INSTR]
+        //                              ↑-------------↑    ↑---↑
+        let mut device_name = None;
+        let mut instr = false;
+        while let Some(rest) = separator(address, index)? {
+            index += 2;
+            let field_len = rest.find(':').unwrap_or(rest.len());
+            let field = &rest[..field_len];
+
+            if instr || field.is_empty() {
+                return Err(TrailingData {
+                    found: address[index - 2..].to_string(),
+                    addr: address.to_string(),
+                    start: index - 2,
+                    end: address.len(),
+                });
+            } else if field.eq_ignore_ascii_case("INSTR") {
+                instr = true;
+            } else if field.eq_ignore_ascii_case("SOCKET") {
+                return Err(WrongClass {
+                    expected: "INSTR",
+                    found: field.to_string(),
+                    addr: address.to_string(),
+                    start: index,
+                    end: index + field_len,
+                });
+            } else if device_name.is_none() {
+                device_name = Some(field.to_string());
+            } else {
+                return Err(TrailingData {
+                    found: address[index - 2..].to_string(),
+                    addr: address.to_string(),
+                    start: index - 2,
+                    end: address.len(),
+                });
+            }
+            index += field_len;
+        }
+
+        Ok(TcpipAddress {
+            board,
+            host,
+            device_name,
+            class: instr.then_some(ResourceClass::Instr),
+        })
+    }
+}
+
+impl FromStr for TcpipSocketAddress {
+    type Err = TcpipParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use TcpipParseError::*;
+
+        let (board, host, mut index) = board_and_host(address)?;
+
+        // TCPIP[board]::host address::port::SOCKET
+        //                             ↑--↑
+        let rest = separator(address, index)?
+            .ok_or_else(|| IncompleteAddress(address.to_string(), "Port, SOCKET".to_string()))?;
+        index += 2;
+        let port_len = rest.find(':').unwrap_or(rest.len());
+        let digits = &rest[..port_len];
+        if digits.eq_ignore_ascii_case("INSTR") {
+            return Err(WrongClass {
+                expected: "SOCKET",
+                found: digits.to_string(),
+                addr: address.to_string(),
+                start: index,
+                end: index + port_len,
+            });
+        }
+        let port = number(address, index, digits)?;
+        index += port_len;
+
+        // TCPIP[board]::host address::port::SOCKET
+        //                                   ↑----↑
+        let rest = separator(address, index)?
+            .ok_or_else(|| IncompleteAddress(address.to_string(), "SOCKET".to_string()))?;
+        index += 2;
+        if !rest.eq_ignore_ascii_case("SOCKET") {
+            let class_len = rest.find(':').unwrap_or(rest.len());
+            if rest[..class_len].eq_ignore_ascii_case("SOCKET") {
+                return Err(TrailingData {
+                    found: rest[class_len..].to_string(),
+                    addr: address.to_string(),
+                    start: index + class_len,
+                    end: address.len(),
+                });
+            }
+            return Err(WrongClass {
+                expected: "SOCKET",
+                found: rest[..class_len].to_string(),
+                addr: address.to_string(),
+                start: index,
+                end: index + class_len,
+            });
+        }
+
+        Ok(TcpipSocketAddress { board, host, port })
+    }
+}
+
+/// Parses the prefix, board, and host that start every TCPIP address.
+/// Returns them with the index just after the host.
+fn board_and_host(address: &str) -> Result<(Option<u32>, String, usize), TcpipParseError> {
+    use TcpipParseError::*;
+
+    // TCPIP[board]::host address...
+    let Some(rest) = strip_keyword(address, "TCPIP") else {
+        return Err(NotTcpip(address.chars().take(5).collect()));
+    };
+    let mut index = "TCPIP".len();
+
+    // TCPIP[board]::host address...
+    //      ↑-----↑
+    let board_len = rest.find(':').unwrap_or(rest.len());
+    let board = match &rest[..board_len] {
+        "" => None,
+        digits => Some(number(address, index, digits)?),
+    };
+    index += board_len;
+
+    // TCPIP[board]::host address...
+    //        ↑----------↑
+    let rest = separator(address, index)?
+        .ok_or_else(|| IncompleteAddress(address.to_string(), "Host Address".to_string()))?;
+    index += 2;
+
+    // IPv6 addresses have colons in them, so they are bracketed.
+    let host_len = if rest.starts_with('[') {
+        match rest.find(']') {
+            Some(close) => close + 1,
+            None => {
+                return Err(InvalidHost {
+                    found: rest.to_string(),
+                    addr: address.to_string(),
+                    start: index,
+                    end: address.len(),
+                })
+            }
+        }
+    } else {
+        rest.find(':').unwrap_or(rest.len())
+    };
+    if host_len == 0 {
+        return Err(IncompleteAddress(
+            address.to_string(),
+            "Host Address".to_string(),
+        ));
+    }
+    let host = &rest[..host_len];
+    if host.contains(char::is_whitespace) {
+        return Err(InvalidHost {
+            found: host.to_string(),
+            addr: address.to_string(),
+            start: index,
+            end: index + host_len,
+        });
+    }
+    index += host_len;
+
+    Ok((board, host.to_string(), index))
+}
+
+/// Parses a number at the index of the address.
+fn number<T: FromStr<Err = ParseIntError>>(
+    address: &str,
+    index: usize,
+    digits: &str,
+) -> Result<T, TcpipParseError> {
+    // Integer parsing accepts a leading '+', which is never written in an address.
+    // Parsing just the sign fails with an invalid digit, which is the error for it.
+    let parsed = match digits.starts_with('+') {
+        true => "+".parse(),
+        false => digits.parse(),
+    };
+    parsed.map_err(|err| TcpipParseError::NumParseError {
+        found: digits.to_string(),
+        addr: address.to_string(),
+        start: index,
+        end: index + digits.len(),
+        source: err,
+    })
+}
+
+/// Checks for a "::" at the index, returning the rest of the address after it.
+/// Returns None at the end of the address.
+fn separator(address: &str, index: usize) -> Result<Option<&str>, TcpipParseError> {
+    let rest = &address[index..];
+
+    if rest.is_empty() {
+        Ok(None)
+    } else if let Some(rest) = rest.strip_prefix("::") {
+        Ok(Some(rest))
+    } else {
+        Err(TcpipParseError::InvalidSeperator {
+            found: rest.chars().take(2).collect(),
+            addr: address.to_string(),
+            start: index,
+            end: (index + 2).min(address.len()),
+        })
+    }
+}
+
+impl Display for TcpipAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // TCPIP[board]::host address[::LAN device name][::INSTR]
+
+        write!(f, "TCPIP")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+        write!(f, "::{}", self.host)?;
+        if let Some(name) = &self.device_name {
+            write!(f, "::{}", name)?
+        }
+        if let Some(class) = self.class {
+            write!(f, "::{}", class)?
+        }
+        Ok(())
+    }
+}
+
+impl Display for TcpipSocketAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TCPIP")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+        write!(f, "::{}::{}::SOCKET", self.host, self.port)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of TCPIP addresses to parse.
+    use super::*;
+    use crate::testing::check_never_panics;
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), TcpipParseError> {
+                const ADDR: &str = $addr;
+                match TcpipAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_eq!(address.to_string(), ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(tcpip_parse_host, "TCPIP::devicename.company.com::INSTR");
+    test_parse!(tcpip_parse_vxi, "TCPIP::1.2.3.4::inst0::INSTR");
+    test_parse!(tcpip_parse_board, "TCPIP3::1.2.3.4::inst0");
+    test_parse!(tcpip_parse_ipv6_hislip, "TCPIP::[fe80::1]::hislip0::INSTR");
+    test_parse!(tcpip_parse_gpib, "TCPIP0::1.2.3.4::gpib0,2::INSTR");
+
+    #[test]
+    fn tcpip_socket_round_trip() -> Result<(), TcpipParseError> {
+        for addr in [
+            "TCPIP0::192.168.1.5::5025::SOCKET",
+            "TCPIP::devicename.company.com::5025::SOCKET",
+            "TCPIP::[fe80::1]::5025::SOCKET",
+            "TCPIP3::1.2.3.4::0::SOCKET",
+            "TCPIP::1.2.3.4::65535::SOCKET",
+        ] {
+            assert_eq!(TcpipSocketAddress::from_str(addr)?.to_string(), addr);
+        }
+
+        let addr = TcpipSocketAddress::from_str("tcpip::1.2.3.4::05025::socket")?;
+        assert_eq!(addr.port(), 5025);
+        assert_eq!(addr.host(), "1.2.3.4");
+        assert_eq!(addr.board(), None);
+        assert_eq!(addr.to_string(), "TCPIP::1.2.3.4::5025::SOCKET");
+        Ok(())
+    }
+
+    #[test]
+    fn tcpip_socket_invalid() {
+        for (addr, expected) in [
+            ("TCPIP::1.2.3.4::65536::SOCKET", "Found \"65536\" instead of a number at position 16 to 21 of \n\"TCPIP::1.2.3.4::65536::SOCKET\""),
+            ("TCPIP::1.2.3.4::+5025::SOCKET", "Found \"+5025\" instead of a number at position 16 to 21 of \n\"TCPIP::1.2.3.4::+5025::SOCKET\""),
+            ("TCPIP::1.2.3.4::::SOCKET", "Found \"\" instead of a number at position 16 to 16 of \n\"TCPIP::1.2.3.4::::SOCKET\""),
+            ("TCPIP::1.2.3.4::5025", "\"TCPIP::1.2.3.4::5025\" is an incomplete address missing: SOCKET"),
+            ("TCPIP::1.2.3.4", "\"TCPIP::1.2.3.4\" is an incomplete address missing: Port, SOCKET"),
+            ("TCPIP::1.2.3.4::INSTR", "Expected the SOCKET class, found \"INSTR\" at 16 to 21 of\n \"TCPIP::1.2.3.4::INSTR\""),
+            ("TCPIP::1.2.3.4::5025::INSTR", "Expected the SOCKET class, found \"INSTR\" at 22 to 27 of\n \"TCPIP::1.2.3.4::5025::INSTR\""),
+            ("TCPIP::1.2.3.4::5025::SOCKET::1", "Found \"::1\" after the end of the address at 28 to 31 of\n \"TCPIP::1.2.3.4::5025::SOCKET::1\""),
+        ] {
+            match TcpipSocketAddress::from_str(addr) {
+                Ok(socket) => panic!("Accepted invalid address {addr:?} as {socket}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+
+        // And the other way around
+        let err = TcpipAddress::from_str("TCPIP::1.2.3.4::5025::SOCKET").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected the INSTR class, found \"SOCKET\" at 22 to 28 of\n \"TCPIP::1.2.3.4::5025::SOCKET\""
+        );
+    }
+
+    #[test]
+    fn tcpip_lan_protocol() -> Result<(), TcpipParseError> {
+        for (addr, protocol) in [
+            ("TCPIP::1.2.3.4::hislip0::INSTR", LanProtocol::HiSlip),
+            ("TCPIP::1.2.3.4::HiSLIP1", LanProtocol::HiSlip),
+            ("TCPIP::1.2.3.4::inst0::INSTR", LanProtocol::Instr),
+            ("TCPIP::1.2.3.4::INSTR", LanProtocol::Instr),
+            ("TCPIP::1.2.3.4", LanProtocol::Instr),
+            ("TCPIP::1.2.3.4::gpib0,2::INSTR", LanProtocol::VxiGpib),
+        ] {
+            assert_eq!(TcpipAddress::from_str(addr)?.lan_protocol(), protocol);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tcpip_never_panics() {
+        let seeds = [
+            "TCPIP::[fe80::1]::hislip0::INSTR",
+            "TCPIP0::1.2.3.4::gpib0,2::INSTR",
+        ];
+        check_never_panics("TCPIP", &seeds, |input| {
+            TcpipAddress::from_str(input)
+                .err()
+                .and_then(|err| err.span())
+        });
+    }
+
+    #[test]
+    fn tcpip_invalid() {
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5",
+            "TCPIP",
+            "TCPIP::",
+            "TCPIPx::1.2.3.4",
+            "TCPIP:1.2.3.4",
+            "TCPIP::1.2.3.4::INSTR::inst0",
+            "TCPIP::1.2.3.4::inst0::inst1",
+            "TCPIP::1.2.3.4::",
+            "TCPIP::[fe80::1]:inst0",
+            "TCPIP::[fe80::1",
+            "TCPIP+1::1.2.3.4::INSTR",
+            "TCPIP::a b::INSTR",
+        ] {
+            assert!(TcpipAddress::from_str(addr).is_err(), "{addr}");
+        }
+
+        for (addr, kind) in [
+            ("TCPIP::[fe80::1", "InvalidHost"),
+            ("TCPIP+1::1.2.3.4::INSTR", "NumParseError"),
+            ("TCPIP::a b::INSTR", "InvalidHost"),
+        ] {
+            assert_eq!(
+                TcpipAddress::from_str(addr).unwrap_err().kind(),
+                kind,
+                "{addr}"
+            );
+        }
+    }
+}
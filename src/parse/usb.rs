@@ -1,51 +1,102 @@
 //! Module for USB VISA addresses.
 //! Includes primarily the main struct and the errors.
 use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::OsStr,
     fmt::{Display, Write},
+    io::BufRead,
     num::ParseIntError,
+    ops::{Bound, Range},
     str::FromStr,
 };
 
 use thiserror::Error;
 
+use super::{BoardSpec, ParseOptions, ResourceClass, VisaBackend};
+
+mod alias;
+pub use alias::{NamedAddressError, NamedUsbAddress};
+
+mod borrowed;
+pub use borrowed::UsbAddressRef;
+
+mod builder;
+pub use builder::UsbAddressBuilder;
+
+mod cached;
+pub use cached::CachedUsbAddress;
+
+mod describe;
+pub use describe::UsbDescription;
+
+mod intfc;
+pub use intfc::UsbIntfc;
+
+mod pattern;
+pub use pattern::{PatternField, UsbPattern};
+
+mod raw;
+pub use raw::RawUsbAddress;
+
+mod report;
+pub use report::{validate_fields, FieldReport, FieldStatus};
+
+#[cfg(feature = "url")]
+mod uri;
+#[cfg(feature = "url")]
+pub use uri::UsbUrlError;
+
 /// Represents a USB VISA address
+///
+/// Fields are positional. The field after the model code is always the serial number,
+/// even if it looks like a number, so `USB::0x1A34::0x5678::12345` has the serial `12345`
+/// and no interface number. An interface number may only follow a non-empty serial.
 #[derive(Eq, PartialEq, Hash, Clone, Debug)]
 pub struct UsbAddress {
     /// Not exactly sure
     board: Option<u32>,
     /// The USB manufacturer ID. Always hex in UI.
-    manufactuer_id: u16,
+    manufacturer_id: u16,
     /// The USB model code. Always hex in the UI.
     model_code: u16,
     /// Serial number. Not actually a number, but a string. For UI purposes only and not analyzed.
     serial_number: String,
     /// Optional interface number. If None, then lowest number that matche is used.
     interface_number: Option<u16>,
-    /// USB INSTR lets the controller interact with the device associated with the resource.
-    instr: bool,
+    /// The resource class, if given. INSTR lets the controller interact with the device
+    /// associated with the resource, while RAW gives direct access to its endpoints.
+    class: Option<UsbClass>,
 }
 
 impl UsbAddress {
     /// Creates a new UsbAddress from an address.
-    /// Panics on failure. See Self::try_new for a Result
+    /// Panics on failure, with the address and the error. See Self::try_new for a Result
     /// > **Note:** Just because parsed does __not__ mean the resource exists.
     ///
     /// # Examples
     ///
     /// ```
+    /// # #![allow(deprecated)]
     /// # use fisa::parse::usb::UsbAddress;
     /// let addr = "USB::0x1A34::0x5678::A22-5";
     /// assert_eq!(UsbAddress::new(addr).to_string(), addr);
     /// ```
     ///
     /// ```should_panic
+    /// # #![allow(deprecated)]
     /// # use fisa::parse::usb::UsbAddress;
     /// let addr = "USB::";
     /// UsbAddress::new(addr);
     /// ```
     #[inline]
+    #[deprecated(
+        since = "0.1.0",
+        note = "panics on invalid addresses, use `UsbAddress::try_new` instead"
+    )]
     pub fn new(addr: &str) -> UsbAddress {
-        UsbAddress::from_str(addr).unwrap()
+        UsbAddress::from_str(addr)
+            .unwrap_or_else(|err| panic!("Invalid USB address {addr:?}: {err}"))
     }
 
     /// Failably creates a new UsbAddress from an address.
@@ -68,567 +119,3577 @@ impl UsbAddress {
     pub fn try_new(addr: &str) -> Result<Self, UsbParseError> {
         UsbAddress::from_str(addr)
     }
-}
 
-/// Errors that can return from USB address parsing.
-#[derive(Error, Debug)]
-pub enum UsbParseError {
-    /// When the given address does not have the USB prefix.
-    #[error("Expected \"USB\" at address start, found {0:?}")]
-    NotUSB(String),
+    /// Creates a new UsbAddress from the fields of a USB device descriptor, such as from
+    /// enumerating the bus. The board and interface number are left out, and the class is
+    /// left implicit, which VISA takes to be INSTR.
+    ///
+    /// Fails if the serial number could not be written in an address that parses back to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::from_descriptor_fields(0x1A34, 0x5678, "A22-5")?;
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbBuildError>(())
+    /// ```
+    pub fn from_descriptor_fields(
+        vendor: u16,
+        product: u16,
+        serial: &str,
+    ) -> Result<Self, UsbBuildError> {
+        UsbAddress::builder()
+            .manufacturer_id(vendor)
+            .model_code(product)
+            .serial_number(serial)
+            .build()
+    }
 
-    /// When parsing an integer fails.
-    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
-    NumParseError {
-        /// What was found instead of a number upon detecting an error.
-        found: String,
-        /// The full invalid address.
-        addr: String,
-        /// Start index of the address that contains the invalid integer.
-        start: usize,
-        /// Final index of the address that contains the invalid integer.
-        end: usize,
-        /// The original error returned.
-        #[source]
-        source: ParseIntError,
-    },
+    /// Starts building an address out of its fields. See [`UsbAddressBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::builder()
+    ///     .manufacturer_id(0x1A34)
+    ///     .model_code(0x5678)
+    ///     .serial_number("A22-5")
+    ///     .build()?;
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbBuildError>(())
+    /// ```
+    pub fn builder() -> UsbAddressBuilder {
+        UsbAddressBuilder::default()
+    }
 
-    /// When a field that is supposed to be hexidecimal is not properly formatted.
-    #[error("Invalid hexidecimal number: {found:?} at position {start:?} to {end:?} in\n {addr:?}\nNumber must start with '0x'")]
-    NotHex {
-        /// What was found instead if "0x"
-        found: String,
-        /// The address containing the invalid hex
-        addr: String,
-        /// Start of the span that was parsed before the error was detected.
-        start: usize,
-        /// End of the span that was parsed before the error was detected.
-        end: usize,
-    },
+    /// Failably creates a new UsbAddress from an OS string, such as from an environment variable
+    /// or a device path. The address must be valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// # use std::ffi::OsStr;
+    /// let addr = UsbAddress::from_os_str(OsStr::new("USB::0x1A34::0x5678::A22-5"))?;
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn from_os_str(addr: &OsStr) -> Result<Self, UsbParseError> {
+        match addr.to_str() {
+            Some(addr) => UsbAddress::from_str(addr),
+            None => Err(UsbParseError::NotUtf8(addr.to_string_lossy().into_owned())),
+        }
+    }
 
-    /// When an address is detected to not be complete.
-    #[error("{0:?} is an incomplete address missing: {1}")]
-    IncompleteAddress(String, String),
+    /// Failably creates a new UsbAddress from an address, parsed according to the given options.
+    /// With the default options this is the same as Self::try_new.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::{UsbAddress, UsbParseError}, ParseOptions};
+    /// let addr = "\"USB::0x1A34::0x5678::A22-5\"";
+    /// assert!(UsbAddress::parse_with(addr, &ParseOptions::default()).is_err());
+    ///
+    /// let lenient = ParseOptions::default().strip_wrappers(true);
+    /// assert_eq!(UsbAddress::parse_with(addr, &lenient)?.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), UsbParseError>(())
+    /// ```
+    #[inline]
+    pub fn parse_with(addr: &str, options: &ParseOptions) -> Result<Self, UsbParseError> {
+        UsbAddress::parse_with_warnings(addr, options).map(|(addr, _)| addr)
+    }
 
-    /// When an address indicates that is has an "INSTR" suffix, but is malformed.
-    #[error("In address \"INSTR\" was indicated but instead {found:?} was found at {start:?} to {end:?} of\n {addr:?}")]
-    NotInstr {
-        /// What was found instead of "INSTR"
-        found: String,
-        /// The full invalid address
-        addr: String,
-        /// Start of the span containing the invalid "INSTR"
-        start: usize,
-        /// End fo the span containing the invalid "INSTR"
-        end: usize,
-    },
+    /// Same as Self::parse_with, but also returns what leniencies had to be applied to the address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::{UsbAddress, UsbParseError, UsbParseWarning}, ParseOptions};
+    /// let lenient = ParseOptions::default().infer_hex_prefix(true);
+    /// let (addr, warnings) = UsbAddress::parse_with_warnings("USB::1A34::0x5678::A22-5", &lenient)?;
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// assert_eq!(
+    ///     warnings,
+    ///     [UsbParseWarning::InferredHexPrefix { found: "1A34".to_string(), start: 5, end: 9 }]
+    /// );
+    /// # Ok::<(), UsbParseError>(())
+    /// ```
+    pub fn parse_with_warnings(
+        addr: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<UsbParseWarning>), UsbParseError> {
+        let mut warnings = Vec::new();
+        let preprocessed = options.preprocess(addr);
+        let mut parsed = UsbAddress::parse_inner(preprocessed, options, &mut warnings)?;
 
-    /// When the end of a token in the address is detect but is malformed.
-    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
-    InvalidSeperator {
-        /// What was found instead of "::"
-        found: String,
-        /// The full invalid address
-        addr: String,
-        /// Start of the span containing the invalid "::"
-        start: usize,
-        /// End fo the span containing the invalid "::"
-        end: usize,
-    },
-}
+        if options.trim_serial {
+            parsed = parsed.with_trimmed_serial();
+        }
 
-/// State of the USB address parser state-machine
-///
-/// This always walks forwards, though it may skip
-/// some states. There may be a better way to indicate
-/// that to the compiler but idk. Speed isn't the top priority.
-enum UsbParserState {
-    /// Required, the initial state
-    Usb,
+        if options.require_canonical {
+            let canonical = parsed.to_string();
+            if canonical != preprocessed {
+                return Err(UsbParseError::NotCanonical {
+                    found: preprocessed.to_string(),
+                    canonical,
+                });
+            }
+        }
 
-    /// Optional, always transition to second.
-    Board,
+        Ok((parsed, warnings))
+    }
 
-    /// Required, always transition to third.
-    ManufactuerId,
+    /// The board number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
 
-    /// Required, always transition to fourth.
-    ModelCode,
+    /// The USB manufacturer ID, also known as the vendor ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::2::INSTR")?;
+    /// assert_eq!(addr.manufacturer_id(), 0x1A34);
+    /// assert_eq!(addr.model_code(), 0x5678);
+    /// assert_eq!(addr.serial_number(), "A22-5");
+    /// assert_eq!(addr.interface_number(), Some(2));
+    /// assert!(addr.is_instr());
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn manufacturer_id(&self) -> u16 {
+        self.manufacturer_id
+    }
 
-    /// Required, always transition to fifth.
-    SerialNumber,
+    /// The USB model code, also known as the product ID.
+    pub fn model_code(&self) -> u16 {
+        self.model_code
+    }
 
-    /// Optional, may trasition to sixth or never be transitioned to is address ends.
-    USBInterface,
+    /// The serial number, without any quotes it was written with.
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
 
-    /// Optional, may transition to sixth, seventh, of never.
-    Instr,
-}
+    /// The USB interface number, if the address has one.
+    pub fn interface_number(&self) -> Option<u16> {
+        self.interface_number
+    }
 
-impl FromStr for UsbAddress {
-    type Err = UsbParseError;
+    /// Whether the address is of the INSTR class. That is when it ends with INSTR, or has no
+    /// class at all, as VISA takes a missing class to be INSTR. See [`UsbAddress::class`].
+    pub fn is_instr(&self) -> bool {
+        self.class != Some(UsbClass::Raw)
+    }
 
-    #[inline]
-    fn from_str(address: &str) -> Result<Self, Self::Err> {
-        use UsbParseError::*;
-        use UsbParserState::*;
+    /// The same address, but on the given board.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
 
-        let mut addr_iter = address.char_indices().peekable();
+    /// The board to look on when discovering this resource. No board number means any board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, BoardSpec};
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// assert_eq!(addr.board_or_any(), BoardSpec::Any);
+    /// assert_eq!(addr.with_board(3).board_or_any(), BoardSpec::Specific(3));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn board_or_any(&self) -> BoardSpec {
+        BoardSpec::from(self.board)
+    }
 
-        // Scratch buffer for parsing.
-        let mut buffer = String::with_capacity(10);
-        let mut span = 0..0; // Parsing span. Primarily for errors.
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::{UsbAddress, UsbClass};
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::raw")?;
+    /// assert_eq!(addr.class(), Some(UsbClass::Raw));
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5::RAW");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn class(&self) -> Option<UsbClass> {
+        self.class
+    }
 
-        // I do not like defaults, so I will not implement the Default trait.
-        // but this is an invalid value to build upon.
-        let mut ret = Ok(UsbAddress {
-            board: None,
-            manufactuer_id: 0,
-            model_code: 0,
-            serial_number: String::new(),
-            interface_number: None,
-            instr: false,
-        });
-        let mut parser_state = Usb; // WOOO FSM
+    /// A heuristic for if the device has more than one USB interface, so it may be worth
+    /// enumerating the others.
+    ///
+    /// This is only true when the address has an explicit interface number, as the interface
+    /// number is only needed to tell interfaces apart. A false result does not mean the device
+    /// has one interface, only that this address gives no hint of more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// assert!(UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::1::INSTR")?.likely_multi_interface());
+    /// assert!(!UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::INSTR")?.likely_multi_interface());
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn likely_multi_interface(&self) -> bool {
+        self.interface_number.is_some()
+    }
 
-        // Checking for errors first before advancing the iterator is intentional.
-        // Using the if/else ensures that lifetime analysis is happy. If the iterator
-        // is advanced with the while let, then after it ends we still need to check
-        // what we ended on. Then for error creation the scratch buffer must be moved.
-        // But since we are out of the while loop, lifetime analysis sees that the buffer
-        // moved while in the loop. So it makes a frowny face.
-        while let Ok(resource) = &mut ret {
-            if let Some((addr_index, addr_char)) = addr_iter.next() {
-                // Span of the section of the address currently being parsed.
-                span.end = addr_index;
+    /// The vendor ID, product ID, serial number, and interface number, as USBTMC libraries take them
+    /// to open a device. A missing interface number is interface 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::INSTR")?;
+    /// assert_eq!(addr.to_usbtmc(), (0x1A34, 0x5678, "A22-5", 0));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn to_usbtmc(&self) -> (u16, u16, &str, u16) {
+        (
+            self.manufacturer_id,
+            self.model_code,
+            &self.serial_number,
+            self.interface_number.unwrap_or(0),
+        )
+    }
 
-                match (&parser_state, addr_char) {
-                    (Usb, 'U') if addr_index == 0 => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        // ↑
-                        // You are here
-                        continue;
-                    }
-                    (Usb, 'S') if addr_index == 1 => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //  ↑
-                        // You are here
-                        continue;
-                    }
-                    (Usb, 'B') if addr_index == 2 => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //   ↑
-                        // You are here
-                        span.start = addr_index + 1;
-                        buffer.clear();
+    /// Checks if this address matches an address from a user's configuration.
+    ///
+    /// Unlike `==`, a configured address without a board matches this address on any board.
+    /// Every other field must be equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let config = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// let discovered = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?;
+    /// assert_ne!(discovered, config);
+    /// assert!(discovered.matches_config(&config));
+    /// assert!(!config.matches_config(&discovered));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn matches_config(&self, config: &UsbAddress) -> bool {
+        (config.board.is_none() || self.board == config.board)
+            && self.manufacturer_id == config.manufacturer_id
+            && self.model_code == config.model_code
+            && self.serial_number == config.serial_number
+            && self.interface_number == config.interface_number
+            && self.class == config.class
+    }
 
-                        parser_state = Board;
-                        continue;
-                    }
-                    (Usb, _) => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        // ???
-                        // You are here (Error)
+    /// Checks if two addresses are the same device on the same board, whatever the interface or class.
+    ///
+    /// There are three levels of identity between addresses, from finest to coarsest:
+    /// * `==`: every field is equal, so they are the same resource.
+    /// * [`UsbAddress::same_device`]: the board, vendor, product, and serial are equal,
+    ///   so they are resources of the same device on the same controller.
+    /// * [`UsbAddress::is_same_unit`]: the vendor, product, and serial are equal,
+    ///   so they are the same physical instrument, wherever it is plugged in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let a = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::0::INSTR")?;
+    /// let b = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::1::INSTR")?;
+    /// assert!(a.same_device(&b));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn same_device(&self, other: &UsbAddress) -> bool {
+        self.board == other.board && self.is_same_unit(other)
+    }
 
-                        ret = Err(NotUSB(address[0..3].to_string()));
-                        break;
-                    }
-                    (ManufactuerId, char)
-                    | (ModelCode, char)
-                    | (SerialNumber, char)
-                    | (Instr, char)
-                        if span.start > span.end =>
-                    {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //            ↑       OR       ↑    OR     ↑      OR       ↑         OR           ↑
-                        // You are here
+    /// Checks if two addresses are the same physical instrument, whatever board it is on.
+    /// Only the vendor ID, product ID, and serial number are compared.
+    /// See [`UsbAddress::same_device`] for the other levels of identity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let a = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::INSTR")?;
+    /// let b = UsbAddress::try_new("USB1::0x1A34::0x5678::A22-5::2")?;
+    /// assert!(a.is_same_unit(&b));
+    /// assert!(!a.same_device(&b));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn is_same_unit(&self, other: &UsbAddress) -> bool {
+        self.manufacturer_id == other.manufacturer_id
+            && self.model_code == other.model_code
+            && self.serial_number == other.serial_number
+    }
 
-                        // Since the span of the slice of the address to be analyzed is set
+    /// Compares two addresses field by field, without formatting either of them.
+    ///
+    /// Fields are compared in the order they are written, with a missing board being the same
+    /// as board 0, as that is the default board. Unlike comparing the displayed addresses,
+    /// numbers are compared by value, so `0x9` comes before `0x10`, and a missing interface number
+    /// comes before any interface number, so `...::INSTR` comes before `...::3`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// # use std::cmp::Ordering;
+    /// let a = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// let b = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?;
+    /// assert_eq!(a.canonical_cmp(&b), Ordering::Equal);
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn canonical_cmp(&self, other: &UsbAddress) -> Ordering {
+        self.board
+            .unwrap_or(0)
+            .cmp(&other.board.unwrap_or(0))
+            .then(self.manufacturer_id.cmp(&other.manufacturer_id))
+            .then(self.model_code.cmp(&other.model_code))
+            .then_with(|| self.serial_number.cmp(&other.serial_number))
+            .then(self.interface_number.cmp(&other.interface_number))
+            .then(self.class.cmp(&other.class))
+    }
+
+    /// The address in the form that the given VISA backend is sure to accept.
+    ///
+    /// Every backend gets an explicit board, defaulting to 0, an explicit class, defaulting to INSTR,
+    /// and four digit hex codes. See [`VisaBackend`] for the quirks of each.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, VisaBackend};
+    /// let addr = UsbAddress::try_new("USB::0x957::0x1796::MY123")?;
+    /// assert_eq!(addr.opened_form(VisaBackend::NiVisa), "USB0::0x0957::0x1796::MY123::INSTR");
+    /// assert_eq!(addr.opened_form(VisaBackend::KeysightVisa), "USB0::0x0957::0x1796::MY123::0::INSTR");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn opened_form(&self, backend: VisaBackend) -> String {
+        let interface_number = match backend {
+            VisaBackend::NiVisa | VisaBackend::RohdeSchwarz => self.interface_number,
+            VisaBackend::KeysightVisa | VisaBackend::PyVisaPy => {
+                Some(self.interface_number.unwrap_or(0))
+            }
+        };
+
+        let mut opened = format!(
+            "USB{}::0x{:04X}::0x{:04X}::",
+            self.board.unwrap_or(0),
+            self.manufacturer_id,
+            self.model_code,
+        );
+        // Writing to a String cannot fail
+        let _ = write_serial(&mut opened, &self.serial_number);
+        if let Some(num) = interface_number {
+            let _ = write!(opened, "::{}", num);
+        }
+        let _ = write!(opened, "::{}", self.class.unwrap_or(UsbClass::Instr));
+        opened
+    }
+
+    /// Converts the address into a pattern that matches only this address.
+    /// So that APIs taking a [`UsbPattern`] can be given a concrete address too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// assert!(addr.clone().into_pattern().matches(&addr));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[inline]
+    pub fn into_pattern(self) -> UsbPattern {
+        UsbPattern::from(self)
+    }
+
+    /// The fields of the address as key/value pairs, for structured logging.
+    ///
+    /// The keys are always the same and in the same order. Missing optional fields are `"none"`.
+    /// If `redact_serial` is set the serial number is replaced with `"<redacted>"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// let fields = addr.log_fields(true);
+    /// assert_eq!(fields[1], ("usb.vendor", "0x1A34".to_string()));
+    /// assert_eq!(fields[3], ("usb.serial", "<redacted>".to_string()));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn log_fields(&self, redact_serial: bool) -> Vec<(&'static str, String)> {
+        /// Formats an optional field
+        fn or_none<T: ToString>(field: Option<T>) -> String {
+            field.map_or_else(|| "none".to_string(), |field| field.to_string())
+        }
+
+        vec![
+            ("usb.board", or_none(self.board)),
+            ("usb.vendor", format!("{:#X}", self.manufacturer_id)),
+            ("usb.product", format!("{:#X}", self.model_code)),
+            (
+                "usb.serial",
+                match redact_serial {
+                    true => "<redacted>".to_string(),
+                    false => self.serial_number.clone(),
+                },
+            ),
+            ("usb.interface", or_none(self.interface_number)),
+            ("usb.class", or_none(self.class)),
+        ]
+    }
+
+    /// The address as a versioned JSON object, for telemetry pipelines.
+    ///
+    /// Unlike [`Self::log_fields`] the shape of this is stable across crate versions.
+    /// The `"v"` field is only bumped when fields are added, and existing fields are never
+    /// renamed, removed, or reformatted. The IDs are always four hex digits, and missing
+    /// optional fields are `null`. See [`Self::to_redacted_telemetry_json`] to leave out the serial.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::INSTR")?;
+    /// assert_eq!(
+    ///     addr.to_telemetry_json(),
+    ///     r#"{"v":1,"scheme":"usb","board":0,"vendor":"0x1A34","product":"0x5678","serial":"A22-5","interface":null,"class":"INSTR"}"#
+    /// );
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn to_telemetry_json(&self) -> String {
+        self.telemetry_json_with_serial(&self.serial_number)
+    }
+
+    /// Same as [`Self::to_telemetry_json`], but with the serial number as `"<redacted>"`.
+    pub fn to_redacted_telemetry_json(&self) -> String {
+        self.telemetry_json_with_serial("<redacted>")
+    }
+
+    /// Writes the telemetry JSON with the given serial number.
+    fn telemetry_json_with_serial(&self, serial: &str) -> String {
+        /// Formats an optional field
+        fn or_null<T: ToString>(field: Option<T>) -> String {
+            field.map_or_else(|| "null".to_string(), |field| field.to_string())
+        }
+
+        let mut escaped = String::with_capacity(serial.len());
+        for char in serial.chars() {
+            match char {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                char if char.is_control() => escaped.push_str(&format!("\\u{:04x}", char as u32)),
+                char => escaped.push(char),
+            }
+        }
+
+        format!(
+            r#"{{"v":1,"scheme":"usb","board":{},"vendor":"0x{:04X}","product":"0x{:04X}","serial":"{escaped}","interface":{},"class":{}}}"#,
+            or_null(self.board),
+            self.manufacturer_id,
+            self.model_code,
+            or_null(self.interface_number),
+            match self.class {
+                Some(UsbClass::Instr) => r#""INSTR""#,
+                Some(UsbClass::Raw) => r#""RAW""#,
+                None => "null",
+            },
+        )
+    }
+
+    /// Lints the address for things that are legal, but likely to confuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::{UsbAddress, UsbAddressWarning};
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::INSTR")?;
+    /// assert_eq!(addr.warnings(), [UsbAddressWarning::ReservedSerial("INSTR".to_string())]);
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn warnings(&self) -> Vec<UsbAddressWarning> {
+        let mut warnings = Vec::new();
+        let serial = &self.serial_number;
+
+        if ["INSTR", "RAW", "INTFC"]
+            .iter()
+            .any(|keyword| serial.eq_ignore_ascii_case(keyword))
+        {
+            warnings.push(UsbAddressWarning::ReservedSerial(serial.clone()));
+        }
+        if serial.chars().all(|char| char.is_ascii_digit()) {
+            warnings.push(UsbAddressWarning::NumericSerial(serial.clone()));
+        }
+
+        warnings
+    }
+
+    /// Writes the canonical address to the writer, without allocating.
+    /// This is what Display uses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1a34::0x5678::A22-5")?;
+    /// let mut out = String::new();
+    /// addr.write_to(&mut out).unwrap();
+    /// assert_eq!(out, "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn write_to<W: std::fmt::Write + ?Sized>(&self, w: &mut W) -> std::fmt::Result {
+        self.write_with_serial(w, &self.serial_number)
+    }
+
+    /// Writes the canonical address with the serial number replaced by `"<redacted>"`,
+    /// without allocating. This is the same redaction as [`UsbAddress::log_fields`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::INSTR")?;
+    /// let mut out = String::new();
+    /// addr.write_redacted(&mut out).unwrap();
+    /// assert_eq!(out, "USB::0x1A34::0x5678::<redacted>::INSTR");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn write_redacted<W: std::fmt::Write + ?Sized>(&self, w: &mut W) -> std::fmt::Result {
+        self.write_with_serial(w, "<redacted>")
+    }
+
+    /// Writes the canonical address, but with the given serial number.
+    fn write_with_serial<W: std::fmt::Write + ?Sized>(
+        &self,
+        w: &mut W,
+        serial: &str,
+    ) -> std::fmt::Result {
+        // Reference:
+        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber]::RAW
+
+        w.write_str("USB")?;
+        if let Some(num) = self.board {
+            write!(w, "{}", num)?
+        }
+        write!(w, "::{:#X}::{:#X}::", self.manufacturer_id, self.model_code)?;
+        write_serial(w, serial)?;
+        if let Some(num) = self.interface_number {
+            write!(w, "::{}", num)?
+        }
+        if let Some(class) = self.class {
+            write!(w, "::{}", class)?
+        }
+        Ok(())
+    }
+
+    /// A cheap fingerprint of the address, for use as a cache key.
+    ///
+    /// This is the 64-bit FNV-1a hash of the canonical address string, so unlike
+    /// `DefaultHasher` it is stable across runs and builds. It is __not__ for security.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// assert_eq!(addr.fingerprint(), UsbAddress::try_new("USB::0x1a34::0x5678::A22-5")?.fingerprint());
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        /// FNV-1a 64-bit offset basis
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        /// FNV-1a 64-bit prime
+        const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+        self.to_string().bytes().fold(OFFSET, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+    }
+
+    /// The address as a JSON object, with the same fields as [`UsbParts`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let json = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::INSTR")?.to_json();
+    /// assert_eq!(json["manufacturer_id"], 0x1A34);
+    /// assert_eq!(json["board"], serde_json::Value::Null);
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "board": self.board,
+            "manufacturer_id": self.manufacturer_id,
+            "model_code": self.model_code,
+            "serial_number": self.serial_number,
+            "interface_number": self.interface_number,
+            "class": self.class.map(|class| class.as_str()),
+        })
+    }
+
+    /// Checks if this address is the device with the given USB descriptor values,
+    /// as exposed by `rusb::DeviceDescriptor` and `nusb::DeviceInfo`.
+    ///
+    /// Reading the serial number requires opening the device, so if `serial` is `None`
+    /// only the vendor and product IDs are compared.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?;
+    /// let timeout = std::time::Duration::from_secs(1);
+    /// for device in rusb::devices()?.iter() {
+    ///     let desc = device.device_descriptor()?;
+    ///     let serial = device
+    ///         .open()
+    ///         .and_then(|handle| handle.read_serial_number_string_ascii(&desc))
+    ///         .ok();
+    ///     if addr.matches_descriptor(desc.vendor_id(), desc.product_id(), serial.as_deref()) {
+    ///         println!("Found {addr} on bus {}", device.bus_number());
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "descriptor")]
+    pub fn matches_descriptor(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        serial: Option<&str>,
+    ) -> bool {
+        self.manufacturer_id == vendor_id
+            && self.model_code == product_id
+            && serial.is_none_or(|serial| serial == self.serial_number)
+    }
+
+    /// Fills in a missing board from an environment variable.
+    ///
+    /// If the address has no board, and the variable is set to a valid board number,
+    /// then that is the board. Otherwise the address is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// std::env::set_var("FISA_DOC_BOARD", "2");
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?.resolve_board("FISA_DOC_BOARD");
+    /// assert_eq!(addr.to_string(), "USB2::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[must_use]
+    pub fn resolve_board(mut self, var: &str) -> Self {
+        if self.board.is_none() {
+            self.board = std::env::var(var).ok().and_then(|board| board.parse().ok());
+        }
+        self
+    }
+
+    /// Uppercases the serial number.
+    ///
+    /// Some instruments report their serial in a different case between enumerations,
+    /// so this helps deduplicate them.
+    ///
+    /// > **Warning:** This is lossy. Serial numbers are compared exactly by VISA, so
+    /// > __only__ use this when the device's serials are known to be case-insensitive.
+    /// > Do not use this on addresses that will be handed back to a VISA backend to open,
+    /// > as a device with serial `a22-5` will not be found as `A22-5`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::a22-5")?.with_serial_uppercased();
+    /// assert_eq!(addr, UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?);
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[must_use]
+    pub fn with_serial_uppercased(mut self) -> Self {
+        self.serial_number = self.serial_number.to_uppercase();
+        self
+    }
+
+    /// Normalizes the address in place, so equivalent addresses become equal.
+    ///
+    /// Hex codes and the class are already normalized when parsed, so this only drops an
+    /// explicit board 0, as that is the default board. This is the same equivalence that
+    /// [`UsbAddress::canonical_cmp`] uses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let mut addr = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?;
+    /// addr.canonicalize();
+    /// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn canonicalize(&mut self) {
+        if self.board == Some(0) {
+            self.board = None;
+        }
+    }
+
+    /// Trims whitespace from both ends of the serial number.
+    /// A serial that is only whitespace is left alone, as a serial number cannot be empty.
+    ///
+    /// Some instruments pad their serial with spaces in their USB descriptor,
+    /// so this lets them match a trimmed serial from a configuration.
+    ///
+    /// > **Warning:** This is lossy, for the same reasons as [`UsbAddress::with_serial_uppercased`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5   ")?.with_trimmed_serial();
+    /// assert_eq!(addr, UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?);
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[must_use]
+    pub fn with_trimmed_serial(mut self) -> Self {
+        let trimmed = self.serial_number.trim();
+        if !trimmed.is_empty() && trimmed.len() != self.serial_number.len() {
+            self.serial_number = trimmed.to_string();
+        }
+        self
+    }
+}
+
+/// All the fields of a USB address at once, for destructuring.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{UsbAddress, UsbParts};
+/// let addr = UsbAddress::try_new("USB1::0x1A34::0x5678::A22-5::INSTR")?;
+/// let UsbParts { board, manufacturer_id, serial_number, .. } = UsbParts::from(&addr);
+/// assert_eq!((board, manufacturer_id, serial_number.as_str()), (Some(1), 0x1A34, "A22-5"));
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct UsbParts {
+    /// Board number, if any.
+    pub board: Option<u32>,
+    /// The USB manufacturer ID.
+    pub manufacturer_id: u16,
+    /// The USB model code.
+    pub model_code: u16,
+    /// Serial number.
+    pub serial_number: String,
+    /// Interface number, if any.
+    pub interface_number: Option<u16>,
+    /// The resource class, if any.
+    pub class: Option<UsbClass>,
+}
+
+impl From<&UsbAddress> for UsbParts {
+    fn from(addr: &UsbAddress) -> Self {
+        UsbParts {
+            board: addr.board,
+            manufacturer_id: addr.manufacturer_id,
+            model_code: addr.model_code,
+            serial_number: addr.serial_number.clone(),
+            interface_number: addr.interface_number,
+            class: addr.class,
+        }
+    }
+}
+
+/// The resource class at the end of a USB address.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::{usb::UsbClass, ResourceClass};
+/// assert_eq!(UsbClass::Raw.to_string(), "RAW");
+/// assert_eq!(ResourceClass::from(UsbClass::Instr), ResourceClass::Instr);
+/// ```
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Debug)]
+pub enum UsbClass {
+    /// A USBTMC device, `::INSTR`.
+    Instr,
+    /// Direct access to the device's endpoints, `::RAW`.
+    Raw,
+}
+
+impl UsbClass {
+    /// The class as written in an address.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsbClass::Instr => "INSTR",
+            UsbClass::Raw => "RAW",
+        }
+    }
+
+    /// Case-insensitively parses a class keyword. None if it is not a USB class.
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        [UsbClass::Instr, UsbClass::Raw]
+            .into_iter()
+            .find(|class| class.as_str().eq_ignore_ascii_case(keyword))
+    }
+}
+
+impl From<UsbClass> for ResourceClass {
+    fn from(class: UsbClass) -> Self {
+        match class {
+            UsbClass::Instr => ResourceClass::Instr,
+            UsbClass::Raw => ResourceClass::Raw,
+        }
+    }
+}
+
+impl Display for UsbClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Groups addresses by the physical device they refer to.
+///
+/// Devices are keyed by manufacturer ID, model code, and serial number, so every
+/// board and interface instance of the same device ends up in the same bucket.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{group_by_device, UsbAddress};
+/// let addrs = [
+///     UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::INSTR")?,
+///     UsbAddress::try_new("USB1::0x1A34::0x5678::A22-5::INSTR")?,
+/// ];
+/// let groups = group_by_device(&addrs);
+/// assert_eq!(groups[&(0x1A34, 0x5678, "A22-5".to_string())].len(), 2);
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+pub fn group_by_device(addrs: &[UsbAddress]) -> HashMap<(u16, u16, String), Vec<&UsbAddress>> {
+    let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+
+    for addr in addrs {
+        groups
+            .entry((
+                addr.manufacturer_id,
+                addr.model_code,
+                addr.serial_number.clone(),
+            ))
+            .or_default()
+            .push(addr);
+    }
+
+    groups
+}
+
+/// The bounds of every address from a vendor on a board, for [`BTreeSet::range`](std::collections::BTreeSet::range)
+/// queries without scanning the whole set.
+///
+/// This relies on the [`Ord`] of [`UsbAddress`], which orders by board first and then by vendor,
+/// so one vendor is only contiguous within a board. A missing board is board 0 here.
+/// Use [`filter_vendor`] to search every board.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{vendor_range, UsbAddress};
+/// # use std::collections::BTreeSet;
+/// let set: BTreeSet<_> = [
+///     "USB::0x1A34::0x5678::A22-5",
+///     "USB::0x1A35::0x0001::B1",
+///     "USB0::0x1A34::0x0001::C3::INSTR",
+/// ]
+/// .into_iter()
+/// .map(UsbAddress::try_new)
+/// .collect::<Result<_, _>>()?;
+/// assert_eq!(set.range(vendor_range(0, 0x1A34)).count(), 2);
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+pub fn vendor_range(board: u32, vendor: u16) -> (Bound<UsbAddress>, Bound<UsbAddress>) {
+    /// The first possible address with these codes.
+    fn first(board: Option<u32>, vendor: u16) -> UsbAddress {
+        UsbAddress {
+            board,
+            manufacturer_id: vendor,
+            model_code: 0,
+            serial_number: String::new(),
+            interface_number: None,
+            class: None,
+        }
+    }
+
+    // A missing board sorts just before board 0, so it is the start of board 0.
+    let start = first((board != 0).then_some(board), vendor);
+    let end = match (vendor.checked_add(1), board.checked_add(1)) {
+        (Some(next), _) => Bound::Excluded(first(start.board, next)),
+        (None, Some(next)) => Bound::Excluded(first(Some(next), 0)),
+        (None, None) => Bound::Unbounded,
+    };
+
+    (Bound::Included(start), end)
+}
+
+/// Every address from a vendor, on any board. See [`vendor_range`] for querying sorted sets.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{filter_vendor, UsbAddress};
+/// let addrs = [
+///     UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?,
+///     UsbAddress::try_new("USB1::0x0957::0x1796::B1")?,
+/// ];
+/// assert_eq!(filter_vendor(&addrs, 0x1A34), [&addrs[0]]);
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+pub fn filter_vendor(addrs: &[UsbAddress], vendor: u16) -> Vec<&UsbAddress> {
+    addrs
+        .iter()
+        .filter(|addr| addr.manufacturer_id == vendor)
+        .collect()
+}
+
+/// Lazily parses one address per line, yielding each result with its line number, starting from 1.
+///
+/// Lines are read one at a time, so large files are never loaded whole. Iteration stops at the
+/// first read error, such as invalid UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::parse_addresses;
+/// let file = std::io::Cursor::new("USB::0x1A34::0x5678::A22-5\nUSB::\n");
+/// let results: Vec<_> = parse_addresses(file).collect();
+/// assert_eq!(results[0].0, 1);
+/// assert!(results[0].1.is_ok());
+/// assert_eq!(results[1].0, 2);
+/// assert!(results[1].1.is_err());
+/// ```
+pub fn parse_addresses<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = (usize, Result<UsbAddress, UsbParseError>)> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .map(|(index, line)| (index + 1, UsbAddress::from_str(&line)))
+}
+
+/// Errors that can return from USB address parsing.
+#[derive(Error, Debug)]
+pub enum UsbParseError {
+    /// When the given address does not have the USB prefix.
+    #[error("Expected \"USB\" at address start, found {found:?} at {start:?} to {end:?}")]
+    NotUSB {
+        /// What was found instead of "USB"
+        found: String,
+        /// Start of the span that should be "USB". Always 0.
+        start: usize,
+        /// End of the span that should be "USB". At most 3 bytes past the start.
+        end: usize,
+    },
+
+    /// When parsing an integer fails.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When a field that is supposed to be hexidecimal is not properly formatted.
+    #[error("Invalid hexidecimal number: {found:?} at position {start:?} to {end:?} in\n {addr:?}\nNumber must start with '0x'{hint}", hint = transposed_hex_hint(.found))]
+    NotHex {
+        /// What was found instead if "0x"
+        found: String,
+        /// The address containing the invalid hex
+        addr: String,
+        /// Start of the span that was parsed before the error was detected.
+        start: usize,
+        /// End of the span that was parsed before the error was detected.
+        end: usize,
+    },
+
+    /// When the board number is written with a sign, such as "USB-1".
+    #[error("Board numbers must be non-negative integers without a sign, found {found:?} at position {start:?} to {end:?} of\n {addr:?}")]
+    InvalidBoard {
+        /// The signed board number
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the board number
+        start: usize,
+        /// End of the span containing the board number
+        end: usize,
+    },
+
+    /// When a manufacturer ID, model code, or interface number is written with a sign,
+    /// such as "0x+1A34".
+    #[error("The {field} must be written without a sign, found {found:?} at position {start:?} to {end:?} of\n {addr:?}")]
+    SignedNumber {
+        /// Which field was signed
+        field: &'static str,
+        /// The signed field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the field
+        start: usize,
+        /// End of the span containing the field
+        end: usize,
+    },
+
+    /// When a hexidecimal field is just "0x", with no digits.
+    #[error(
+        "The {field} has no digits after its \"0x\" at position {start:?} to {end:?} in\n {addr:?}"
+    )]
+    EmptyHex {
+        /// Which field was empty
+        field: &'static str,
+        /// The address containing the empty hex
+        addr: String,
+        /// Start of the span containing the "0x"
+        start: usize,
+        /// End of the span containing the "0x"
+        end: usize,
+    },
+
+    /// When a field between two "::" separators is empty.
+    #[error("The {field} is empty at position {start:?} in\n {addr:?}")]
+    EmptyField {
+        /// Which field was empty
+        field: &'static str,
+        /// The address containing the empty field
+        addr: String,
+        /// Where the field should start
+        start: usize,
+        /// Where the field should end. Same as the start.
+        end: usize,
+    },
+
+    /// When canonical addresses are required, but the address is written some other way.
+    #[error("{found:?} is not in canonical form, it should be written as {canonical:?}")]
+    NotCanonical {
+        /// The address as given
+        found: String,
+        /// How the address should have been written
+        canonical: String,
+    },
+
+    /// When control characters are rejected, but the address contains one.
+    #[error("Found the control character {found:?} at position {position:?}")]
+    UnexpectedControlChar {
+        /// The control character
+        found: char,
+        /// Byte index of the control character in the address
+        position: usize,
+    },
+
+    /// When an address from the OS is not valid UTF-8. Holds the address with the invalid parts replaced.
+    #[error("{0:?} is not valid UTF-8")]
+    NotUtf8(String),
+
+    /// When an address is detected to not be complete.
+    #[error("{0:?} is an incomplete address missing: {1}")]
+    IncompleteAddress(String, String),
+
+    /// When an address indicates that is has an "INSTR" suffix, but is malformed.
+    #[error("In address \"INSTR\" was indicated but instead {found:?} was found at {start:?} to {end:?} of\n {addr:?}")]
+    NotInstr {
+        /// What was found instead of "INSTR"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "INSTR"
+        start: usize,
+        /// End fo the span containing the invalid "INSTR"
+        end: usize,
+    },
+
+    /// When the address ends with a resource class other than INSTR or RAW, such as INTFC.
+    #[error("USB addresses of the {class} class are not supported, found {class} at position {start:?} to {end:?} of\n {addr:?}")]
+    UnsupportedClass {
+        /// The resource class that was found
+        class: ResourceClass,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the class
+        start: usize,
+        /// End of the span containing the class
+        end: usize,
+    },
+
+    /// When a controller interface address does not end with "INTFC".
+    #[error("In address \"INTFC\" was expected but instead {found:?} was found at {start:?} to {end:?} of\n {addr:?}")]
+    NotIntfc {
+        /// What was found instead of "INTFC"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "INTFC"
+        start: usize,
+        /// End of the span containing the invalid "INTFC"
+        end: usize,
+    },
+
+    /// When the end of a token in the address is detect but is malformed.
+    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
+    InvalidSeperator {
+        /// What was found instead of "::"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "::"
+        start: usize,
+        /// End fo the span containing the invalid "::"
+        end: usize,
+    },
+
+    /// When there is more address after the final "INSTR" suffix.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the suffix
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+
+    /// When the address contains a Unicode look-alike of an ASCII character,
+    /// such as a full-width colon from a copy-pasted document.
+    #[error("Found {found:?} (U+{code:04X}) at position {position:?}, which looks like {expected:?} but is not", code = *found as u32)]
+    ConfusableCharacter {
+        /// The look-alike character
+        found: char,
+        /// The ASCII character it looks like
+        expected: char,
+        /// Byte index of the look-alike in the address
+        position: usize,
+    },
+}
+
+impl UsbParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let err = UsbAddress::try_new("USB::0x1A34::x5678::A22-5").unwrap_err();
+    /// assert_eq!(err.span(), Some(13..18));
+    /// ```
+    pub fn span(&self) -> Option<Range<usize>> {
+        use UsbParseError::*;
+
+        match self {
+            NumParseError { start, end, .. }
+            | InvalidBoard { start, end, .. }
+            | SignedNumber { start, end, .. }
+            | NotHex { start, end, .. }
+            | EmptyHex { start, end, .. }
+            | EmptyField { start, end, .. }
+            | NotInstr { start, end, .. }
+            | NotIntfc { start, end, .. }
+            | UnsupportedClass { start, end, .. }
+            | InvalidSeperator { start, end, .. }
+            | TrailingData { start, end, .. }
+            | NotUSB { start, end, .. } => Some(*start..*end),
+            ConfusableCharacter {
+                found, position, ..
+            }
+            | UnexpectedControlChar { found, position } => {
+                Some(*position..*position + found.len_utf8())
+            }
+            IncompleteAddress(..) | NotCanonical { .. } | NotUtf8(..) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use UsbParseError::*;
+
+        match self {
+            NotUSB { .. } => "NotUSB",
+            NumParseError { .. } => "NumParseError",
+            NotHex { .. } => "NotHex",
+            InvalidBoard { .. } => "InvalidBoard",
+            SignedNumber { .. } => "SignedNumber",
+            EmptyHex { .. } => "EmptyHex",
+            EmptyField { .. } => "EmptyField",
+            NotCanonical { .. } => "NotCanonical",
+            UnexpectedControlChar { .. } => "UnexpectedControlChar",
+            NotUtf8(..) => "NotUtf8",
+            IncompleteAddress(..) => "IncompleteAddress",
+            NotInstr { .. } => "NotInstr",
+            UnsupportedClass { .. } => "UnsupportedClass",
+            NotIntfc { .. } => "NotIntfc",
+            InvalidSeperator { .. } => "InvalidSeperator",
+            TrailingData { .. } => "TrailingData",
+            ConfusableCharacter { .. } => "ConfusableCharacter",
+        }
+    }
+}
+
+/// Errors that can return from building a USB address out of its fields.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum UsbBuildError {
+    /// When a required field was never set.
+    #[error("The {0} is required, but was not set")]
+    MissingField(&'static str),
+
+    /// When the serial number is empty.
+    #[error("The serial number is empty")]
+    EmptySerial,
+
+    /// When the serial number cannot be written in an address, such as one with both a
+    /// colon and a quote in it.
+    #[error("The serial number {0:?} cannot be written in a USB address")]
+    UnrepresentableSerial(String),
+}
+
+/// Leniencies that were applied while parsing a USB address.
+/// These are only produced when enabled by the [`ParseOptions`].
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum UsbParseWarning {
+    /// A manufacturer ID or model code was missing its "0x", and it was assumed to be hex.
+    InferredHexPrefix {
+        /// The code that was missing the "0x"
+        found: String,
+        /// Start of the span containing the code
+        start: usize,
+        /// End of the span containing the code
+        end: usize,
+    },
+    /// The interface number was after the INSTR, and was moved before it.
+    ReorderedTail {
+        /// The INSTR and interface number, as written
+        found: String,
+        /// Start of the span containing the INSTR
+        start: usize,
+        /// End of the span containing the interface number
+        end: usize,
+    },
+}
+
+impl Display for UsbParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsbParseWarning::InferredHexPrefix { found, start, end } => write!(
+                f,
+                "Assumed {found:?} at position {start:?} to {end:?} is hexidecimal without a '0x'"
+            ),
+            UsbParseWarning::ReorderedTail { found, start, end } => write!(
+                f,
+                "Moved the interface number before the INSTR in {found:?} at position {start:?} to {end:?}"
+            ),
+        }
+    }
+}
+
+/// Things about a valid USB address that are likely to confuse. See [`UsbAddress::warnings`].
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum UsbAddressWarning {
+    /// The serial number is a resource class keyword, such as "INSTR".
+    /// It looks like the serial number is missing.
+    ReservedSerial(String),
+    /// The serial number is all digits.
+    /// It looks like the serial number is missing and this is the interface number.
+    NumericSerial(String),
+}
+
+impl Display for UsbAddressWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UsbAddressWarning::ReservedSerial(serial) => write!(
+                f,
+                "Serial number {serial:?} is a reserved keyword, and looks like the resource class"
+            ),
+            UsbAddressWarning::NumericSerial(serial) => write!(
+                f,
+                "Serial number {serial:?} is all digits, and looks like an interface number"
+            ),
+        }
+    }
+}
+
+/// State of the USB address parser state-machine
+///
+/// This always walks forwards, though it may skip
+/// some states. There may be a better way to indicate
+/// that to the compiler but idk. Speed isn't the top priority.
+enum UsbParserState {
+    /// Required, the initial state
+    Usb,
+
+    /// Optional, always transition to second.
+    Board,
+
+    /// Required, always transition to third.
+    ManufacturerId,
+
+    /// Required, always transition to fourth.
+    ModelCode,
+
+    /// Required, always transition to fifth.
+    SerialNumber,
+
+    /// Optional, may trasition to sixth or never be transitioned to is address ends.
+    USBInterface,
+
+    /// Optional, may transition to sixth, seventh, of never. Either INSTR or RAW.
+    Instr,
+}
+
+/// Span bookkeeping for the USB address parser.
+///
+/// Tracks where the current field starts and the index of the character being
+/// parsed, so that every error reports its span the same way.
+struct Spanner {
+    /// Start of the current field.
+    start: usize,
+    /// Index of the character currently being parsed.
+    end: usize,
+    /// Length of the address, which no span may point past.
+    limit: usize,
+}
+
+impl Spanner {
+    /// A spanner at the start of an address of `limit` bytes.
+    fn new(limit: usize) -> Self {
+        Spanner {
+            start: 0,
+            end: 0,
+            limit,
+        }
+    }
+
+    /// Moves to the character at `index`.
+    fn advance(&mut self, index: usize) {
+        self.end = index;
+    }
+
+    /// Starts the next field just past a delimiter of `len` bytes at `index`.
+    ///
+    /// A field start is set past the whole "::" when the first colon is found, so if the
+    /// address ends on a single colon this is clamped to keep spans inside the address.
+    fn begin_after(&mut self, index: usize, len: usize) {
+        self.start = index.saturating_add(len).min(self.limit);
+    }
+
+    /// Start of the current field.
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    /// How far into the current field the parser is. Zero at its first character.
+    fn offset(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Whether the parser is between the two colons of a "::", as the field start
+    /// was set past both when the first was found.
+    fn in_separator(&self) -> bool {
+        self.start > self.end
+    }
+
+    /// The current field, up to the current character. At a separator, or at the end
+    /// of the address, that is the whole field.
+    fn current(&self) -> Range<usize> {
+        self.start..self.end.max(self.start)
+    }
+
+    /// The character before the current one, and the current one, which is `char`.
+    /// For bad separators.
+    fn separator(&self, char: char) -> Range<usize> {
+        self.end - 1..self.end + char.len_utf8()
+    }
+}
+
+impl FromStr for UsbAddress {
+    type Err = UsbParseError;
+
+    #[inline]
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        UsbAddress::parse_inner(address, &ParseOptions::default(), &mut Vec::new())
+    }
+}
+
+impl UsbAddress {
+    /// The USB address parser. Leniencies are taken from the options, and any
+    /// leniency that was applied is pushed to the warnings.
+    fn parse_inner(
+        address: &str,
+        options: &ParseOptions,
+        warnings: &mut Vec<UsbParseWarning>,
+    ) -> Result<Self, UsbParseError> {
+        use UsbParseError::*;
+        use UsbParserState::*;
+
+        // A serial number could end in '/', but a trailing slash is taken to be left over
+        // from a URL instead. That is the choice of strip_trailing_slash, which removes it
+        // before this point. Without that option it is rejected, so the two modes never
+        // accept the same address with different serial numbers.
+        if address.ends_with('/')
+            && address
+                .get(..3)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("USB"))
+        {
+            return Err(TrailingData {
+                found: "/".to_string(),
+                addr: address.to_string(),
+                start: address.len() - 1,
+                end: address.len(),
+            });
+        }
+
+        if options.reject_control_chars {
+            if let Some((position, found)) =
+                address.char_indices().find(|(_, char)| char.is_control())
+            {
+                return Err(UnexpectedControlChar { found, position });
+            }
+        }
+
+        // The "USB" prefix may be any case, as VISA addresses are case-insensitive.
+        let is_prefix_char = |found: char, expected: char| found.eq_ignore_ascii_case(&expected);
+
+        // Most addresses that are not USB are rejected right here, before anything is allocated.
+        // Only when the start is ASCII, as a look-alike character just after the prefix is a
+        // better error than this, and the full parse looks for those.
+        let head = &address.as_bytes()[..address.len().min(4)];
+        if head.is_ascii()
+            && head
+                .iter()
+                .zip(b"USB")
+                .any(|(&found, &expected)| !is_prefix_char(found.into(), expected.into()))
+        {
+            let found = &address[..address.len().min(3)];
+            return Err(NotUSB {
+                found: found.to_string(),
+                start: 0,
+                end: found.len(),
+            });
+        }
+
+        let mut addr_iter = address.char_indices().peekable();
+
+        // Scratch buffer for parsing.
+        let mut buffer = String::with_capacity(10);
+        // Parsing span. Primarily for errors.
+        let mut spanner = Spanner::new(address.len());
+
+        // I do not like defaults, so I will not implement the Default trait.
+        // but this is an invalid value to build upon.
+        let mut ret = Ok(UsbAddress {
+            board: None,
+            manufacturer_id: 0,
+            model_code: 0,
+            serial_number: String::new(),
+            interface_number: None,
+            class: None,
+        });
+        let mut parser_state = Usb; // WOOO FSM
+
+        // Checking for errors first before advancing the iterator is intentional.
+        // Using the if/else ensures that lifetime analysis is happy. If the iterator
+        // is advanced with the while let, then after it ends we still need to check
+        // what we ended on. Then for error creation the scratch buffer must be moved.
+        // But since we are out of the while loop, lifetime analysis sees that the buffer
+        // moved while in the loop. So it makes a frowny face.
+        while let Ok(resource) = &mut ret {
+            if let Some((addr_index, addr_char)) = addr_iter.next() {
+                spanner.advance(addr_index);
+
+                match (&parser_state, addr_char) {
+                    (Usb, char) if addr_index == 0 && is_prefix_char(char, 'U') => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        // ↑
+                        // You are here
+                        continue;
+                    }
+                    (Usb, char) if addr_index == 1 && is_prefix_char(char, 'S') => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //  ↑
+                        // You are here
+                        continue;
+                    }
+                    (Usb, char) if addr_index == 2 && is_prefix_char(char, 'B') => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //   ↑
+                        // You are here
+                        spanner.begin_after(addr_index, 1);
+                        buffer.clear();
+
+                        parser_state = Board;
+                        continue;
+                    }
+                    (Usb, _) => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        // ???
+                        // You are here (Error)
+
+                        // The address may be shorter than the prefix, or not be ASCII at all,
+                        // so take up to three characters rather than three bytes.
+                        let found: String = address.chars().take(3).collect();
+                        ret = Err(NotUSB {
+                            start: 0,
+                            end: found.len(),
+                            found,
+                        });
+                        break;
+                    }
+                    (ManufacturerId, char)
+                    | (ModelCode, char)
+                    | (SerialNumber, char)
+                    | (Instr, char)
+                        if spanner.in_separator() =>
+                    {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //            ↑       OR       ↑    OR     ↑      OR       ↑         OR           ↑
+                        // You are here
+
+                        // Since the span of the slice of the address to be analyzed is set
                         // to be two ahead when the first colon is encounterd, this ensures
                         // checks to see if the second colon exists.
                         if char == ':' {
                             continue;
                         } else {
                             ret = Err(InvalidSeperator {
-                                found: format!(":{char}"),
+                                found: format!(":{char}"),
+                                addr: address.to_string(),
+                                start: spanner.separator(char).start,
+                                end: spanner.separator(char).end,
+                            })
+                        }
+                    }
+                    // Careful! is_empty is true for the above case as well!
+                    (Board, ':') if spanner.offset() == 0 => {
+                        // USB::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //    ↑
+                        // You are here (no board)
+                        resource.board = None;
+
+                        spanner.begin_after(addr_index, 2);
+                        buffer.clear();
+
+                        parser_state = ManufacturerId;
+                        continue;
+                    }
+                    (Board, ':') => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //           ↑
+                        // You are here
+
+                        if has_sign(&buffer) {
+                            ret = Err(InvalidBoard {
+                                addr: address.to_string(),
+                                start: spanner.start(),
+                                end: spanner.start() + buffer.len(),
+                                found: buffer,
+                            });
+                            break;
+                        }
+
+                        match buffer.parse() {
+                            Ok(board_num) => {
+                                resource.board = Some(board_num);
+
+                                spanner.begin_after(addr_index, 2);
+                                buffer.clear();
+
+                                parser_state = ManufacturerId;
+                                continue;
+                            }
+                            Err(err) => {
+                                ret = Err(NumParseError {
+                                    found: buffer,
+                                    addr: address.to_string(),
+                                    start: spanner.current().start,
+                                    end: spanner.current().end,
+                                    source: err,
+                                });
+                                break;
+                            }
+                        }
+                    }
+                    (ManufacturerId, ':') | (ModelCode, ':') => {
+                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+                        //                            ↑     OR    ↑
+                        // You are here
+
+                        // "0x" was there, but nothing after it
+                        if buffer.is_empty() && spanner.offset() >= 2 {
+                            ret = Err(EmptyHex {
+                                field: match parser_state {
+                                    ManufacturerId => "manufacturer ID",
+                                    _ => "model code",
+                                },
+                                addr: address.to_string(),
+                                start: spanner.current().start,
+                                end: spanner.current().end,
+                            });
+                            break;
+                        }
+
+                        if has_sign(&buffer) {
+                            ret = Err(SignedNumber {
+                                field: match parser_state {
+                                    ManufacturerId => "manufacturer ID",
+                                    _ => "model code",
+                                },
+                                found: address[spanner.current()].to_string(),
+                                addr: address.to_string(),
+                                start: spanner.current().start,
+                                end: spanner.current().end,
+                            });
+                            break;
+                        }
+
+                        // Parses hex number
+                        match u16::from_str_radix(buffer.as_str(), 16) {
+                            Ok(code) => {
+                                // Advanced to where the start of the modelcode or serialnumber will be.
+                                spanner.begin_after(addr_index, 2);
+                                buffer.clear();
+
+                                parser_state = match parser_state {
+                                    ManufacturerId => {
+                                        resource.manufacturer_id = code;
+                                        ModelCode
+                                    }
+                                    ModelCode => {
+                                        resource.model_code = code;
+                                        SerialNumber
+                                    }
+                                    _ => unreachable!(),
+                                };
+
+                                continue;
+                            }
+                            Err(err) => {
+                                ret = Err(NumParseError {
+                                    found: address[spanner.current()].to_string(),
+                                    addr: address.to_string(),
+                                    start: spanner.current().start,
+                                    end: spanner.current().end,
+                                    source: err,
+                                });
+                                break;
+                            }
+                        }
+                    }
+                    (ManufacturerId, _) | (ModelCode, _)
+                        if spanner.offset() == 0
+                            && options.infer_hex_prefix
+                            && is_bare_hex(
+                                address[addr_index..].split(':').next().unwrap_or(""),
+                            ) =>
+                    {
+                        // USB[board]::<CODE>::<CODE>::serial number[::USB interfacenumber][::INSTR]
+                        //             ↑   OR   ↑
+                        // You are here (lenient)
+
+                        // Take the whole code now, the colon arm parses it as usual.
+                        let end = addr_index.saturating_add(4).min(address.len());
+                        buffer.push_str(&address[addr_index..end]);
+                        for _ in 1..4 {
+                            addr_iter.next();
+                        }
+
+                        warnings.push(UsbParseWarning::InferredHexPrefix {
+                            found: buffer.clone(),
+                            start: addr_index,
+                            end,
+                        });
+                        continue;
+                    }
+                    (ManufacturerId, char) | (ModelCode, char) if spanner.offset() == 0 => {
+                        if char == '0' {
+                            // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
+                            //             ↑    OR   ↑
+                            // You are here
+
+                            // Validates that this is a hex format
+                            continue;
+                        } else {
+                            // Scan to the end of the code, so the span covers all of it.
+                            loop {
+                                match addr_iter.next() {
+                                    Some((index, ':')) => break spanner.advance(index),
+                                    Some(_) => {}
+                                    None => break spanner.advance(address.len()),
+                                }
+                            }
+
+                            ret = Err(NotHex {
+                                found: address[spanner.current()].to_string(),
+                                addr: address.to_string(),
+                                start: spanner.current().start,
+                                end: spanner.current().end,
+                            });
+                            break;
+                        }
+                    }
+                    (ManufacturerId, char) | (ModelCode, char) if spanner.offset() == 1 => {
+                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
+                        //              ↑    OR   ↑
+                        // You are here
+
+                        if char == 'x' || char == 'X' {
+                            continue;
+                        } else {
+                            // Scan to the end of the code, so the span covers all of it.
+                            loop {
+                                match addr_iter.next() {
+                                    Some((index, ':')) => break spanner.advance(index),
+                                    Some(_) => {}
+                                    None => break spanner.advance(address.len()),
+                                }
+                            }
+
+                            ret = Err(NotHex {
+                                found: address[spanner.current()].to_string(),
+                                addr: address.to_string(),
+                                start: spanner.current().start,
+                                end: spanner.current().end,
+                            });
+                            break;
+                        }
+                    }
+                    (SerialNumber, ':') => {
+                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
+                        //                                               ↑          OR          ↑
+                        // You are here
+
+                        // Intersting thought. Is it valid for a serial number to have a colon? See fisa#7
+
+                        // The serial is always the fourth field, whatever it looks like. So an empty
+                        // one must be an error, otherwise the interface number would be taken as it.
+                        if buffer.is_empty() {
+                            ret = Err(IncompleteAddress(address.into(), "Serial Number".into()));
+                            break;
+                        }
+                        resource.serial_number.clone_from(&buffer);
+                        buffer.clear();
+
+                        spanner.begin_after(addr_index, 2);
+
+                        // There are two distinct optional fields next
+                        match addr_iter.next() {
+                            Some((i, ':')) => {
+                                parser_state = if is_class_field(&address[i + 1..]) {
+                                    Instr
+                                } else {
+                                    USBInterface
+                                };
+                                spanner.advance(i + 1);
+                            }
+                            Some((i, char)) => {
+                                ret = Err(InvalidSeperator {
+                                    found: format!(":{char}"),
+                                    addr: address.to_string(),
+                                    start: i - 1,
+                                    end: i + char.len_utf8(),
+                                })
+                            }
+                            None => {
+                                // Means there was one but not a second colon.
+                                ret = Err(IncompleteAddress(
+                                    address.to_string(),
+                                    "either USB Interface or INSTR".to_string(),
+                                ))
+                            }
+                        }
+                        continue;
+                    }
+                    (Instr, ':') if UsbClass::from_keyword(&buffer).is_some() => {
+                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]::???
+                        //                                                                              ↑
+                        // You are here (Error)
+
+                        // Some tools write the interface number after the INSTR instead of before.
+                        if options.tolerate_reordered_tail && resource.interface_number.is_none() {
+                            let interface = address[addr_index..]
+                                .strip_prefix("::")
+                                .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_digit()))
+                                .and_then(|digits| digits.parse().ok());
+                            if let Some(interface) = interface {
+                                // USB[board]::0x<CODE>::0x<CODE>::serial number::INSTR::USB interfacenumber
+                                //                                                      ↑
+                                // You are here (lenient)
+                                resource.interface_number = Some(interface);
+                                resource.class = UsbClass::from_keyword(&buffer);
+
+                                warnings.push(UsbParseWarning::ReorderedTail {
+                                    found: address[spanner.start()..].to_string(),
+                                    start: spanner.start(),
+                                    end: address.len(),
+                                });
+                                break;
+                            }
+                        }
+
+                        // The class is always the end of the address, so anything after is garbage.
+                        ret = Err(TrailingData {
+                            found: address[addr_index..].to_string(),
+                            addr: address.to_string(),
+                            start: addr_index,
+                            end: address.len(),
+                        });
+                        break;
+                    }
+                    (SerialNumber, '"') if buffer.is_empty() => {
+                        // USB[board]::0x<CODE>::0x<CODE>::"serial number"[::USB interfacenumber][::INSTR]
+                        //                                 ↑
+                        // You are here (quoted)
+
+                        // Anything up to the closing quote is the serial, colons included.
+                        let closed = loop {
+                            match addr_iter.next() {
+                                Some((_, '"')) => break true,
+                                Some((index, char)) => {
+                                    spanner.advance(index);
+                                    buffer.push(char);
+                                }
+                                None => break false,
+                            }
+                        };
+
+                        if !closed {
+                            ret = Err(IncompleteAddress(
+                                address.to_string(),
+                                "closing quote of the Serial Number".to_string(),
+                            ));
+                        } else if let Some(&(index, char)) =
+                            addr_iter.peek().filter(|(_, char)| *char != ':')
+                        {
+                            ret = Err(InvalidSeperator {
+                                found: format!("\"{char}"),
                                 addr: address.to_string(),
-                                start: span.end - 1,
-                                end: span.end,
-                            })
+                                start: index - 1,
+                                end: index + char.len_utf8(),
+                            });
                         }
+                        continue;
                     }
-                    // Careful! is_empty is true for the above case as well!
-                    (Board, ':') if span.is_empty() => {
-                        // USB::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //    ↑
-                        // You are here (no board)
-                        resource.board = None;
+                    (USBInterface, ':') => {
+                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
+                        //                                                                      ↑
+                        // You are here
+
+                        // The "::" after the serial was followed by another colon.
+                        if buffer.is_empty() {
+                            ret = Err(EmptyField {
+                                field: "USB interface number",
+                                addr: address.to_string(),
+                                start: spanner.start(),
+                                end: spanner.start(),
+                            });
+                            break;
+                        }
+
+                        if let Some(err) = signed_interface(&buffer, address, spanner.start()) {
+                            ret = Err(err);
+                            break;
+                        }
+
+                        match buffer.parse() {
+                            Ok(num) => {
+                                resource.interface_number = Some(num);
+                                buffer.clear();
+
+                                spanner.begin_after(addr_index, 2);
+                                parser_state = Instr;
+                                continue;
+                            }
+                            Err(err) => {
+                                ret = Err(unsupported_class(&buffer, address, spanner.start())
+                                    .unwrap_or_else(|| NumParseError {
+                                        found: buffer,
+                                        addr: address.to_string(),
+                                        start: spanner.current().start,
+                                        end: spanner.current().end,
+                                        source: err,
+                                    }));
+                                break;
+                            }
+                        }
+                    }
+                    (Board, char)
+                    | (ManufacturerId, char)
+                    | (ModelCode, char)
+                    | (SerialNumber, char)
+                    | (USBInterface, char)
+                    | (Instr, char) => {
+                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
+                        //    ↑-----↑ OR  ↑---↑ OR ↑----↑OR↑-----------↑ OR↑------------------↑
+                        // You are here
+
+                        buffer.push(char);
+                        continue;
+                    }
+                }
+            } else {
+                // When it's the end of the str
+                // Using the if/else ensures that lifetime analysis is happy
+
+                // The end of the address ends the last field, as a separator would.
+                spanner.advance(address.len());
+
+                match parser_state {
+                    Usb => {
+                        ret = Err(IncompleteAddress(
+                            address.to_string(),
+                            "USB flag, Manufacture Code, Model Number, Serial number".to_string(),
+                        ))
+                    }
+                    Board if has_sign(&buffer) => {
+                        ret = Err(InvalidBoard {
+                            addr: address.to_string(),
+                            start: spanner.start(),
+                            end: spanner.start() + buffer.len(),
+                            found: buffer,
+                        })
+                    }
+                    Board | ManufacturerId => {
+                        ret = match buffer.parse::<u32>() {
+                            // The board was seen but is not a number, which is the actual problem.
+                            Err(err) if matches!(parser_state, Board) && !buffer.is_empty() => {
+                                Err(NumParseError {
+                                    found: buffer,
+                                    addr: address.to_string(),
+                                    start: spanner.current().start,
+                                    end: spanner.current().end,
+                                    source: err,
+                                })
+                            }
+                            _ => Err(IncompleteAddress(
+                                address.to_string(),
+                                "Manufacture Code, Model Number, Serial number".to_string(),
+                            )),
+                        }
+                    }
+                    ModelCode => {
+                        ret = Err(IncompleteAddress(
+                            address.to_string(),
+                            "Model Number, Serial number".to_string(),
+                        ))
+                    }
+                    SerialNumber => {
+                        // USB[board]::manufacturer ID::model code::serial number
+                        //                                                       ↑
+                        // You are here
+
+                        // I do not know what the proper format of a serial number is.
+                        // So I'll just accept anything that is not an empty string.
+                        match buffer.as_str() {
+                            "" => {
+                                ret = Err(IncompleteAddress(address.into(), "Serial Number".into()))
+                            }
+                            _ => resource.serial_number = buffer,
+                        }
+                    }
+                    USBInterface => {
+                        // USB[board]::manufacturer ID::model code::serial number::USB interfacenumber
+                        //                                                                            ↑
+                        // You are here
+
+                        // Nothing after the "::" that follows the serial.
+                        if buffer.is_empty() {
+                            ret = Err(IncompleteAddress(
+                                address.to_string(),
+                                "either USB Interface or INSTR".to_string(),
+                            ));
+                            break;
+                        }
+
+                        if let Some(err) = signed_interface(&buffer, address, spanner.start()) {
+                            ret = Err(err);
+                            break;
+                        }
+
+                        match buffer.parse() {
+                            Ok(num) => resource.interface_number = Some(num),
+                            Err(err) => {
+                                ret = Err(unsupported_class(&buffer, address, spanner.start())
+                                    .unwrap_or_else(|| NumParseError {
+                                        found: buffer,
+                                        addr: address.to_string(),
+                                        start: spanner.current().start,
+                                        end: spanner.current().end,
+                                        source: err,
+                                    }));
+                                break;
+                            }
+                        }
+                    }
+                    Instr => {
+                        // USB[board]::manufacturer ID::model code::serial number::USB interfacenumber::INSTR
+                        //                                                                                   ↑
+                        // You are here
+
+                        // Only ASCII case is ignored, as the ':' arm does. Full Unicode uppercasing
+                        // would turn a long s, 'ſ', into an 'S'.
+                        if let Some(class) = UsbClass::from_keyword(&buffer) {
+                            resource.class = Some(class);
+                        } else {
+                            ret = Err(unsupported_class(&buffer, address, spanner.start())
+                                .unwrap_or_else(|| NotInstr {
+                                    found: buffer,
+                                    addr: address.to_string(),
+                                    start: spanner.current().start,
+                                    end: spanner.current().end,
+                                }))
+                        }
+                    }
+                }
+                break;
+            }
+        }
+
+        // A look-alike character is a far better explanation than whatever it tripped up.
+        // This only happens on failure, as serial numbers may contain anything.
+        if let Err(err) = &ret {
+            let limit = err.span().map_or(address.len(), |err_span| err_span.end);
+            if let Some((position, found, expected)) = address
+                .char_indices()
+                .find_map(|(index, char)| confusable(char).map(|ascii| (index, char, ascii)))
+                .filter(|(position, ..)| *position <= limit)
+            {
+                ret = Err(ConfusableCharacter {
+                    found,
+                    expected,
+                    position,
+                });
+            }
+        }
+
+        if let Err(err) = &ret {
+            if let Some(err_span) = err.span() {
+                debug_assert!(
+                    err_span.start <= err_span.end && err_span.end <= address.len(),
+                    "Error span {err_span:?} is out of bounds of {address:?}"
+                );
+            }
+        }
+
+        ret
+    }
+}
+
+/// A regular expression matching the USB addresses accepted with the default [`ParseOptions`],
+/// for validating them in other tools such as form inputs or schemas.
+///
+/// It is anchored, and uses only syntax common to every regex dialect. It differs from the
+/// parser in what a regex cannot practically express:
+/// - The board, codes, and interface number are not range checked, so `0x10000` matches.
+/// - An address ending in `/` matches, but is rejected as trailing data by the parser.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::address_regex;
+/// assert!(address_regex().starts_with("^[uU][sS][bB]"));
+/// ```
+pub fn address_regex() -> &'static str {
+    r#"^[uU][sS][bB][0-9]*::0[xX][0-9a-fA-F]+::0[xX][0-9a-fA-F]+::(?:"[^"]+"|[^:"][^:]*)(?:::[0-9]+)?(?:::(?:[iI][nN][sS][tT][rR]|[rR][aA][wW]))?$"#
+}
+
+/// Checks if a serial number must be quoted to be written in an address.
+///
+/// That is when it contains a `:` or whitespace, or it is a resource class keyword such as "INSTR",
+/// as then it would be read back as a different address. A quoted serial number may contain
+/// anything except a `"`.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{needs_quoting, UsbAddress};
+/// assert!(needs_quoting("A22 5"));
+/// assert!(needs_quoting("instr"));
+/// assert!(!needs_quoting("A22-5"));
+///
+/// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::\"A22::5\"")?;
+/// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::\"A22::5\"");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+pub fn needs_quoting(serial: &str) -> bool {
+    serial.contains(|char: char| char == ':' || char.is_whitespace())
+        || ResourceClass::from_str(serial).is_ok()
+}
+
+/// Writes a serial number, quoted if needed.
+fn write_serial<W: std::fmt::Write + ?Sized>(w: &mut W, serial: &str) -> std::fmt::Result {
+    // A serial with a quote in it cannot be quoted, so is best left as it is.
+    if needs_quoting(serial) && !serial.contains('"') {
+        write!(w, "\"{}\"", serial)
+    } else {
+        w.write_str(serial)
+    }
+}
+
+/// The error for a final field that is a resource class USB addresses are not parsed with.
+/// None if the field is not a resource class, or is INSTR or RAW.
+///
+/// Section 4.3.1.1 of VPP-4.3 gives USB RAW resources the same form as INSTR ones,
+/// `USB[board]::manufacturer ID::model code::serial number[::USB interface number]::RAW`,
+/// so the interface number is optional with either class.
+fn unsupported_class(found: &str, addr: &str, start: usize) -> Option<UsbParseError> {
+    match ResourceClass::from_str(found) {
+        Ok(ResourceClass::Instr | ResourceClass::Raw) | Err(_) => None,
+        Ok(class) => Some(UsbParseError::UnsupportedClass {
+            class,
+            addr: addr.to_string(),
+            start,
+            end: start + found.len(),
+        }),
+    }
+}
+
+/// Whether a number is written with a sign. Rust's integer parsing accepts a leading '+',
+/// and reports a '-' as just an invalid digit, but VISA numbers have no sign at all.
+pub(super) fn has_sign(number: &str) -> bool {
+    number.starts_with(['+', '-'])
+}
+
+/// The error for an interface number written with a sign, if it is.
+fn signed_interface(found: &str, addr: &str, start: usize) -> Option<UsbParseError> {
+    has_sign(found).then(|| UsbParseError::SignedNumber {
+        field: "USB interface number",
+        found: found.to_string(),
+        addr: addr.to_string(),
+        start,
+        end: start + found.len(),
+    })
+}
+
+/// Whether an optional field is the resource class rather than the interface number.
+/// The parser tells them apart by the first character alone.
+fn is_class_field(field: &str) -> bool {
+    field.starts_with(['I', 'i', 'R', 'r'])
+}
+
+/// Splits an address into its fields the same way the parser walks them, for
+/// [`validate_fields`]. A quoted serial number is kept whole, colons and all. Each field
+/// comes with its byte offset into the address.
+fn split_fields(address: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    for _ in 0..3 {
+        match address[offset..].find("::") {
+            Some(len) => {
+                fields.push((offset, &address[offset..offset + len]));
+                offset += len + 2;
+            }
+            None => {
+                fields.push((offset, &address[offset..]));
+                return fields;
+            }
+        }
+    }
+
+    let rest = &address[offset..];
+    let serial_len = match rest.strip_prefix('"') {
+        // Closing quote included, or everything if it is never closed.
+        Some(quoted) => quoted.find('"').map_or(rest.len(), |end| end + 2),
+        None => rest.find("::").unwrap_or(rest.len()),
+    };
+    fields.push((offset, &rest[..serial_len]));
+    offset += serial_len;
+
+    if address[offset..].starts_with("::") {
+        offset += 2;
+        for field in address[offset..].split("::") {
+            fields.push((offset, field));
+            offset += field.len() + 2;
+        }
+    } else if offset < address.len() {
+        // Something other than "::" after a quoted serial
+        fields.push((offset, &address[offset..]));
+    }
+
+    fields
+}
+
+/// Suggests the fix for a hex code with its "0x" written backwards, such as "x01234".
+/// Empty if the code does not look like that.
+fn transposed_hex_hint(found: &str) -> String {
+    match found
+        .strip_prefix("x0")
+        .or_else(|| found.strip_prefix("X0"))
+    {
+        Some(digits) if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+            format!(", did you mean \"0x{digits}\"?")
+        }
+        _ => String::new(),
+    }
+}
+
+/// Checks if a manufacturer ID or model code is four hex digits without a "0x".
+fn is_bare_hex(code: &str) -> bool {
+    code.len() == 4 && code.chars().all(|char| char.is_ascii_hexdigit())
+}
+
+/// The ASCII character that a Unicode character is commonly mistaken for, if any.
+fn confusable(char: char) -> Option<char> {
+    match char {
+        // Full-width forms, as from CJK input methods and some word processors.
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(char as u32 - 0xFEE0),
+        // Colon look-alikes.
+        '\u{02D0}' | '\u{02F8}' | '\u{0589}' | '\u{05C3}' | '\u{2236}' | '\u{A789}'
+        | '\u{FE13}' | '\u{FE55}' => Some(':'),
+        // Mathematical digits, in five styles of ten.
+        '\u{1D7CE}'..='\u{1D7FF}' => char::from_digit((char as u32 - 0x1D7CE) % 10, 10),
+        // Multiplication sign for the "x" in "0x".
+        '\u{00D7}' => Some('x'),
+        _ => None,
+    }
+}
+
+/// Addresses are ordered the same as [`UsbAddress::canonical_cmp`], except that when that is a tie
+/// between a missing board and an explicit board 0, the missing board comes first.
+/// This way the order agrees with `==`.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::UsbAddress;
+/// let mut addrs = [
+///     UsbAddress::try_new("USB1::0x1A34::0x5678::A22-5")?,
+///     UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?,
+///     UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?,
+/// ];
+/// addrs.sort();
+/// assert_eq!(addrs[0].to_string(), "USB::0x1A34::0x5678::A22-5");
+/// assert_eq!(addrs[2].to_string(), "USB1::0x1A34::0x5678::A22-5");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+impl Ord for UsbAddress {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical_cmp(other).then(self.board.cmp(&other.board))
+    }
+}
+
+impl PartialOrd for UsbAddress {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for UsbAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_to(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of USB addresses to parse.
+    use super::*;
+    use crate::testing::{
+        check_never_panics, mutate, random_string, random_usb_address, usb, Regex, Rng, SEED,
+    };
+    use std::num::IntErrorKind;
+
+    /// Describes how the address displays differently than expected, with its fields
+    /// and both strings lined up. None if it displays as expected.
+    fn display_mismatch(address: &UsbAddress, expected: &str) -> Option<String> {
+        let actual = address.to_string();
+        if actual == expected {
+            return None;
+        }
+
+        Some(format!(
+            "Address displays differently than expected\n\
+             expected: {expected}\n\
+             actual:   {actual}\n\
+             fields:   board {:?}, manufacturer ID {:#X}, model code {:#X}, \
+             serial number {:?}, interface number {:?}, class {:?}",
+            address.board,
+            address.manufacturer_id,
+            address.model_code,
+            address.serial_number,
+            address.interface_number,
+            address.class,
+        ))
+    }
+
+    /// Asserts the address displays as expected, reporting its fields if it does not.
+    #[track_caller]
+    fn assert_displays(address: &UsbAddress, expected: &str) {
+        if let Some(mismatch) = display_mismatch(address, expected) {
+            panic!("{mismatch}");
+        }
+    }
+
+    #[test]
+    fn usb_display_mismatch() {
+        let addr = usb("USB1::0x1a34::0x5678::A22-5::INSTR");
+
+        assert_eq!(
+            display_mismatch(&addr, "USB1::0x1A34::0x5678::A22-5::INSTR"),
+            None
+        );
+        assert_eq!(
+            display_mismatch(&addr, "USB1::0x1a34::0x5678::A22-5::INSTR").as_deref(),
+            Some(
+                "Address displays differently than expected\n\
+                 expected: USB1::0x1a34::0x5678::A22-5::INSTR\n\
+                 actual:   USB1::0x1A34::0x5678::A22-5::INSTR\n\
+                 fields:   board Some(1), manufacturer ID 0x1A34, model code 0x5678, \
+                 serial number \"A22-5\", interface number None, class Some(Instr)"
+            )
+        );
+    }
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), UsbParseError> {
+                const ADDR: &str = $addr;
+                match UsbAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_displays(&address, ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(usb_parse_address, "USB::0x1A34::0x5678::A22-5");
+    test_parse!(usb_parse_board, "USB1::0x12B4::0x56F8::A22-5::INSTR");
+    test_parse!(usb_parse_instr, "USB::0xFFA1::0x56C8::A22-5::INSTR");
+    test_parse!(usb_parse_raw, "USB::0xFFA1::0x56C8::A22-5::RAW");
+    test_parse!(
+        usb_parse_raw_interface,
+        "USB0::0xFFA1::0x56C8::A22-5::2::RAW"
+    );
+    test_parse!(usb_parse_interface, "USB::0x1234::0x5D78::A22-5::123");
+    test_parse!(usb_parse_all, "USB34::0x12A4::0xFF1A::A22-5::12314::INSTR");
+    test_parse!(usb_parse_short_codes, "USB::0x1::0x2::X");
+
+    /// Valid addresses used as seeds for the randomized tests.
+    const SEEDS: &[&str] = &[
+        "USB::0x1A34::0x5678::A22-5",
+        "USB1::0x12B4::0x56F8::A22-5::INSTR",
+        "USB::0xFFA1::0x56C8::A22-5::INSTR",
+        "USB::0x1234::0x5D78::A22-5::123",
+        "USB34::0x12A4::0xFF1A::A22-5::12314::INSTR",
+    ];
+
+    #[test]
+    #[should_panic(expected = "Invalid USB address \"USB::0x1A34::\"")]
+    #[allow(deprecated)]
+    fn usb_new_panic_message() {
+        UsbAddress::new("USB::0x1A34::");
+    }
+
+    #[test]
+    fn usb_resolve_board() {
+        // Each test uses its own variable, as tests run in parallel.
+        std::env::set_var("FISA_TEST_USB_RESOLVE_BOARD", "4");
+        std::env::set_var("FISA_TEST_USB_RESOLVE_BOARD_INVALID", "four");
+
+        let addr = usb("USB::0x1A34::0x5678::A22-5");
+        assert_eq!(
+            addr.clone().resolve_board("FISA_TEST_USB_RESOLVE_BOARD"),
+            usb("USB4::0x1A34::0x5678::A22-5")
+        );
+        assert_eq!(
+            addr.clone()
+                .resolve_board("FISA_TEST_USB_RESOLVE_BOARD_UNSET"),
+            addr
+        );
+        assert_eq!(
+            addr.clone()
+                .resolve_board("FISA_TEST_USB_RESOLVE_BOARD_INVALID"),
+            addr
+        );
+
+        // Never overrides an explicit board.
+        let addr = usb("USB1::0x1A34::0x5678::A22-5");
+        assert_eq!(
+            addr.clone().resolve_board("FISA_TEST_USB_RESOLVE_BOARD"),
+            addr
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "descriptor")]
+    fn usb_matches_descriptor() {
+        let addr = usb("USB0::0x1A34::0x5678::A22-5::INSTR");
+
+        assert!(addr.matches_descriptor(0x1A34, 0x5678, Some("A22-5")));
+        assert!(addr.matches_descriptor(0x1A34, 0x5678, None));
+        assert!(!addr.matches_descriptor(0x1A34, 0x5678, Some("A22-6")));
+        assert!(!addr.matches_descriptor(0x1A34, 0x5679, None));
+        assert!(!addr.matches_descriptor(0x5678, 0x1A34, Some("A22-5")));
+    }
+
+    #[test]
+    fn usb_serial_uppercased() {
+        let lower = usb("USB::0x1A34::0x5678::a22-5b");
+        let upper = usb("USB::0x1A34::0x5678::A22-5B");
+
+        assert_ne!(lower, upper);
+        assert_eq!(lower.with_serial_uppercased(), upper);
+    }
+
+    #[test]
+    fn usb_matches_config_any_board() {
+        let config = usb("USB::0x1A34::0x5678::A22-5::INSTR");
+
+        assert!(usb("USB0::0x1A34::0x5678::A22-5::INSTR").matches_config(&config));
+        assert!(usb("USB3::0x1A34::0x5678::A22-5::INSTR").matches_config(&config));
+        assert!(config.matches_config(&config));
+    }
 
-                        span.start = addr_index + 2;
-                        buffer.clear();
+    #[test]
+    fn usb_matches_config_mismatch() {
+        let config = usb("USB1::0x1A34::0x5678::A22-5");
 
-                        parser_state = ManufactuerId;
-                        continue;
-                    }
-                    (Board, ':') => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //           ↑
-                        // You are here
+        assert!(!usb("USB1::0x1A34::0x5678::A22-6").matches_config(&config));
+        assert!(!usb("USB2::0x1A34::0x5678::A22-5").matches_config(&config));
+        assert!(!usb("USB::0x1A34::0x5678::A22-5").matches_config(&config));
+    }
 
-                        match buffer.parse() {
-                            Ok(board_num) => {
-                                resource.board = Some(board_num);
+    #[test]
+    fn usb_fingerprint() {
+        let addr = usb("USB1::0x12B4::0x56F8::A22-5::INSTR");
 
-                                span.start = addr_index + 2;
-                                buffer.clear();
+        assert_eq!(
+            addr.fingerprint(),
+            usb("USB1::0x12B4::0x56F8::A22-5::INSTR").fingerprint()
+        );
+        assert_ne!(
+            addr.fingerprint(),
+            usb("USB1::0x12B4::0x56F8::A22-6::INSTR").fingerprint()
+        );
+        // Pinned so that a change to the hash or the canonical form is noticed.
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::A22-5").fingerprint(),
+            0xD5CE_C14F_2135_603D
+        );
+    }
 
-                                parser_state = ManufactuerId;
-                                continue;
-                            }
-                            Err(err) => {
-                                ret = Err(NumParseError {
-                                    found: buffer,
-                                    addr: address.to_string(),
-                                    start: span.start,
-                                    end: span.end - 1,
-                                    source: err,
-                                });
-                                break;
-                            }
-                        }
-                    }
-                    (ManufactuerId, ':') | (ModelCode, ':') => {
-                        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
-                        //                            ↑     OR    ↑
-                        // You are here
+    #[test]
+    fn usb_group_by_device() {
+        let addrs = [
+            usb("USB0::0x1A34::0x5678::A22-5::INSTR"),
+            usb("USB1::0x1A34::0x5678::A22-5::INSTR"),
+            usb("USB1::0x1A34::0x5678::A22-5::2::INSTR"),
+            usb("USB0::0x1A34::0x5678::B17-1::INSTR"),
+            usb("USB0::0x0957::0x5678::A22-5"),
+        ];
+        let groups = group_by_device(&addrs);
 
-                        // Parses hex number
-                        match u16::from_str_radix(buffer.as_str(), 16) {
-                            Ok(code) => {
-                                // Advanced to where the start of the modelcode or serialnumber will be.
-                                span.start = addr_index + 2;
-                                buffer.clear();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(
+            groups[&(0x1A34, 0x5678, "A22-5".to_string())],
+            vec![&addrs[0], &addrs[1], &addrs[2]]
+        );
+        assert_eq!(
+            groups[&(0x1A34, 0x5678, "B17-1".to_string())],
+            vec![&addrs[3]]
+        );
+        assert_eq!(
+            groups[&(0x0957, 0x5678, "A22-5".to_string())],
+            vec![&addrs[4]]
+        );
+    }
 
-                                parser_state = match parser_state {
-                                    ManufactuerId => {
-                                        resource.manufactuer_id = code;
-                                        ModelCode
-                                    }
-                                    ModelCode => {
-                                        resource.model_code = code;
-                                        SerialNumber
-                                    }
-                                    _ => unreachable!(),
-                                };
+    #[cfg(feature = "json")]
+    #[test]
+    fn usb_to_json() {
+        use serde_json::Value;
 
-                                continue;
-                            }
-                            Err(err) => {
-                                ret = Err(NumParseError {
-                                    found: buffer,
-                                    addr: address.to_string(),
-                                    start: span.start,
-                                    end: span.end - 1,
-                                    source: err,
-                                });
-                                break;
-                            }
-                        }
+        let json = usb("USB3::0x1A34::0x5678::A22-5::7::INSTR").to_json();
+        let object = json.as_object().unwrap();
+
+        assert_eq!(object.len(), 6);
+        assert_eq!(object["board"], Value::from(3));
+        assert_eq!(object["manufacturer_id"], Value::from(0x1A34));
+        assert_eq!(object["model_code"], Value::from(0x5678));
+        assert_eq!(object["serial_number"], Value::from("A22-5"));
+        assert_eq!(object["interface_number"], Value::from(7));
+        assert_eq!(object["class"], Value::from("INSTR"));
+
+        let json = usb("USB::0x1A34::0x5678::A22-5").to_json();
+        assert!(json["board"].is_null());
+        assert!(json["interface_number"].is_null());
+        assert!(json["class"].is_null());
+
+        let json = usb("USB::0x1A34::0x5678::A22-5::RAW").to_json();
+        assert_eq!(json["class"], Value::from("RAW"));
+    }
+
+    #[test]
+    fn usb_canonical_cmp() {
+        // Same width numbers, so the displayed addresses sort the same way.
+        let addrs = [
+            "USB1::0x1A34::0x5678::A22-5",
+            "USB1::0x1A34::0x5678::A22-5::3",
+            "USB1::0x1A34::0x5678::A22-5::3::INSTR",
+            "USB1::0x1A34::0x5678::B22-5",
+            "USB1::0x1A34::0x5679::A22-5",
+            "USB1::0x1B34::0x5678::A22-5",
+            "USB2::0x1A34::0x5678::A22-5",
+        ]
+        .map(usb);
+
+        for a in &addrs {
+            for b in &addrs {
+                assert_eq!(
+                    a.canonical_cmp(b),
+                    a.to_string().cmp(&b.to_string()),
+                    "{a} vs {b}"
+                );
+            }
+        }
+
+        // Where it intentionally differs.
+        let nine = usb("USB::0x9::0x5678::A22-5");
+        let sixteen = usb("USB::0x10::0x5678::A22-5");
+        assert_eq!(nine.canonical_cmp(&sixteen), Ordering::Less);
+        assert_eq!(
+            nine.to_string().cmp(&sixteen.to_string()),
+            Ordering::Greater
+        );
+
+        let instr = usb("USB::0x1A34::0x5678::A22-5::INSTR");
+        let interface = usb("USB::0x1A34::0x5678::A22-5::3");
+        assert_eq!(instr.canonical_cmp(&interface), Ordering::Less);
+        assert_eq!(
+            instr.to_string().cmp(&interface.to_string()),
+            Ordering::Greater
+        );
+
+        let no_board = usb("USB::0x1A34::0x5678::A22-5");
+        let board_zero = usb("USB0::0x1A34::0x5678::A22-5");
+        assert_eq!(no_board.canonical_cmp(&board_zero), Ordering::Equal);
+    }
+
+    #[test]
+    fn usb_opened_form() {
+        let addr = usb("USB::0x1A::0x5678::A22-5");
+        assert_eq!(
+            addr.opened_form(VisaBackend::NiVisa),
+            "USB0::0x001A::0x5678::A22-5::INSTR"
+        );
+        assert_eq!(
+            addr.opened_form(VisaBackend::RohdeSchwarz),
+            "USB0::0x001A::0x5678::A22-5::INSTR"
+        );
+        assert_eq!(
+            addr.opened_form(VisaBackend::KeysightVisa),
+            "USB0::0x001A::0x5678::A22-5::0::INSTR"
+        );
+        assert_eq!(
+            addr.opened_form(VisaBackend::PyVisaPy),
+            "USB0::0x001A::0x5678::A22-5::0::INSTR"
+        );
+
+        let addr = usb("USB2::0x1A34::0x5678::A22-5::3::INSTR");
+        assert_eq!(
+            addr.opened_form(VisaBackend::NiVisa),
+            "USB2::0x1A34::0x5678::A22-5::3::INSTR"
+        );
+        assert_eq!(
+            addr.opened_form(VisaBackend::KeysightVisa),
+            "USB2::0x1A34::0x5678::A22-5::3::INSTR"
+        );
+
+        let addr = usb("USB::0x1A34::0x5678::A22-5::raw");
+        assert_eq!(
+            addr.opened_form(VisaBackend::PyVisaPy),
+            "USB0::0x1A34::0x5678::A22-5::0::RAW"
+        );
+    }
+
+    #[test]
+    fn usb_log_fields() {
+        let addr = usb("USB3::0x1A34::0x5678::A22-5::7::INSTR");
+        let expected = [
+            ("usb.board", "3"),
+            ("usb.vendor", "0x1A34"),
+            ("usb.product", "0x5678"),
+            ("usb.serial", "A22-5"),
+            ("usb.interface", "7"),
+            ("usb.class", "INSTR"),
+        ]
+        .map(|(key, value)| (key, value.to_string()));
+        assert_eq!(addr.log_fields(false), expected);
+
+        let addr = usb("USB::0x1A34::0x5678::A22-5");
+        let expected = [
+            ("usb.board", "none"),
+            ("usb.vendor", "0x1A34"),
+            ("usb.product", "0x5678"),
+            ("usb.serial", "<redacted>"),
+            ("usb.interface", "none"),
+            ("usb.class", "none"),
+        ]
+        .map(|(key, value)| (key, value.to_string()));
+        assert_eq!(addr.log_fields(true), expected);
+    }
+
+    #[test]
+    fn usb_warnings() {
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::INSTR").warnings(),
+            [UsbAddressWarning::ReservedSerial("INSTR".to_string())]
+        );
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::raw::INSTR").warnings(),
+            [UsbAddressWarning::ReservedSerial("raw".to_string())]
+        );
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::12345").warnings(),
+            [UsbAddressWarning::NumericSerial("12345".to_string())]
+        );
+        assert!(usb("USB::0x1A34::0x5678::A22-5::3::INSTR")
+            .warnings()
+            .is_empty());
+    }
+
+    #[test]
+    fn usb_confusable_serial_allowed() -> Result<(), UsbParseError> {
+        // Serial numbers are free-form, so look-alikes are only reported on failure.
+        let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22\u{FF1A}5")?;
+        assert_eq!(addr.serial_number, "A22\u{FF1A}5");
+        Ok(())
+    }
+
+    #[test]
+    fn usb_write_redacted() {
+        let mut out = String::new();
+        for (addr, redacted) in [
+            (
+                "USB::0x1A34::0x5678::A22-5",
+                "USB::0x1A34::0x5678::<redacted>",
+            ),
+            (
+                "USB3::0x1a34::0x5678::A22-5::2::instr",
+                "USB3::0x1A34::0x5678::<redacted>::2::INSTR",
+            ),
+        ] {
+            out.clear();
+            usb(addr).write_redacted(&mut out).unwrap();
+            assert_eq!(out, redacted);
+        }
+    }
+
+    #[test]
+    fn usb_write_to_fixed() {
+        /// A fixed size sink, like one would use without an allocator.
+        struct Fixed {
+            /// Storage
+            buf: [u8; 64],
+            /// Bytes written
+            len: usize,
+        }
+
+        impl Write for Fixed {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                let end = self.len + s.len();
+                self.buf
+                    .get_mut(self.len..end)
+                    .ok_or(std::fmt::Error)?
+                    .copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+
+        const ADDR: &str = "USB34::0x12A4::0xFF1A::A22-5::12314::INSTR";
+        let mut sink = Fixed {
+            buf: [0; 64],
+            len: 0,
+        };
+        usb(ADDR).write_to(&mut sink).unwrap();
+        assert_eq!(&sink.buf[..sink.len], ADDR.as_bytes());
+
+        // Too small, so it must fail rather than truncate.
+        sink.len = 40;
+        assert!(usb(ADDR).write_to(&mut sink).is_err());
+    }
+
+    #[test]
+    fn usb_accessors() {
+        let addr = usb("USB3::0x12a4::0xFF1A::\"A22::5\"::7::raw");
+        assert_eq!(addr.board(), Some(3));
+        assert_eq!(addr.manufacturer_id(), 0x12A4);
+        assert_eq!(addr.model_code(), 0xFF1A);
+        assert_eq!(addr.serial_number(), "A22::5");
+        assert_eq!(addr.interface_number(), Some(7));
+        assert!(!addr.is_instr());
+
+        let addr = usb("USB::0x1A34::0x5678::A22-5");
+        assert_eq!((addr.board(), addr.interface_number()), (None, None));
+        assert!(addr.is_instr());
+    }
+
+    #[test]
+    fn usb_parts() {
+        let parts = UsbParts::from(&usb("USB34::0x12A4::0xFF1A::A22-5::12314::INSTR"));
+
+        assert_eq!(
+            parts,
+            UsbParts {
+                board: Some(34),
+                manufacturer_id: 0x12A4,
+                model_code: 0xFF1A,
+                serial_number: "A22-5".to_string(),
+                interface_number: Some(12314),
+                class: Some(UsbClass::Instr),
+            }
+        );
+
+        let parts = UsbParts::from(&usb("USB::0x1A34::0x5678::A22-5"));
+        assert_eq!(
+            (parts.board, parts.interface_number, parts.class),
+            (None, None, None)
+        );
+    }
+
+    /// Helper macro
+    /// test_wrapped!(function_identifier, wrapped_address);
+    macro_rules! test_wrapped {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), UsbParseError> {
+                const ADDR: &str = $addr;
+                let lenient = ParseOptions::default().strip_wrappers(true);
+
+                assert!(UsbAddress::parse_with(ADDR, &ParseOptions::default()).is_err());
+                assert_eq!(
+                    UsbAddress::parse_with(ADDR, &lenient)?.to_string(),
+                    "USB::0x1A34::0x5678::A22-5"
+                );
+                Ok(())
+            }
+        };
+    }
+
+    test_wrapped!(usb_wrapped_double_quote, "\"USB::0x1A34::0x5678::A22-5\"");
+    test_wrapped!(usb_wrapped_single_quote, "'USB::0x1A34::0x5678::A22-5'");
+    test_wrapped!(usb_wrapped_backtick, "`USB::0x1A34::0x5678::A22-5`");
+    test_wrapped!(usb_wrapped_angle, "<USB::0x1A34::0x5678::A22-5>");
+
+    #[test]
+    fn usb_wrapped_mismatched() {
+        let lenient = ParseOptions::default().strip_wrappers(true);
+
+        assert!(UsbAddress::parse_with("\"USB::0x1A34::0x5678::A22-5'", &lenient).is_err());
+        assert!(UsbAddress::parse_with("<USB::0x1A34::0x5678::A22-5", &lenient).is_err());
+    }
+
+    #[test]
+    fn usb_infer_hex_prefix() -> Result<(), UsbParseError> {
+        const ADDR: &str = "USB::1a34::0x5678::A22-5::INSTR";
+        let lenient = ParseOptions::default().infer_hex_prefix(true);
+
+        assert!(UsbAddress::from_str(ADDR).is_err());
+
+        let (addr, warnings) = UsbAddress::parse_with_warnings(ADDR, &lenient)?;
+        assert_eq!(addr, usb("USB::0x1A34::0x5678::A22-5::INSTR"));
+        assert_eq!(
+            warnings,
+            [UsbParseWarning::InferredHexPrefix {
+                found: "1a34".to_string(),
+                start: 5,
+                end: 9
+            }]
+        );
+
+        let (addr, warnings) = UsbAddress::parse_with_warnings("USB::0A34::5678::A22-5", &lenient)?;
+        assert_eq!(addr.to_string(), "USB::0xA34::0x5678::A22-5");
+        assert_eq!(warnings.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn usb_infer_hex_prefix_ambiguous() {
+        let lenient = ParseOptions::default().infer_hex_prefix(true);
+
+        for addr in [
+            "USB::1A3::0x5678::A22-5",
+            "USB::1A345::0x5678::A22-5",
+            "USB::1G34::0x5678::A22-5",
+            "USB::0x1A34::WXYZ::A22-5",
+        ] {
+            assert!(matches!(
+                UsbAddress::parse_with(addr, &lenient),
+                Err(UsbParseError::NotHex { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn usb_trailing_slash() -> Result<(), UsbParseError> {
+        const ADDR: &str = "USB::0x1A34::0x5678::A22-5/";
+        let lenient = ParseOptions::default().strip_trailing_slash(true);
+
+        assert!(matches!(
+            UsbAddress::from_str(ADDR),
+            Err(UsbParseError::TrailingData {
+                start: 26,
+                end: 27,
+                ..
+            })
+        ));
+        assert_eq!(
+            UsbAddress::parse_with(ADDR, &lenient)?,
+            usb("USB::0x1A34::0x5678::A22-5")
+        );
+        assert_eq!(
+            UsbAddress::parse_with("USB::0x1A34::0x5678::A22-5::INSTR/", &lenient)?,
+            usb("USB::0x1A34::0x5678::A22-5::INSTR")
+        );
+        assert!(UsbAddress::parse_with("USB::0x1A34::0x5678::A22-5//", &lenient).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn usb_zero_padded_board() -> Result<(), UsbParseError> {
+        // Canonicalized on purpose, only RawUsbAddress keeps the padding.
+        const ADDR: &str = "USB07::0x1A34::0x5678::A22-5";
+        let addr = UsbAddress::from_str(ADDR)?;
+
+        assert_eq!(addr.board, Some(7));
+        assert_eq!(addr.to_string(), "USB7::0x1A34::0x5678::A22-5");
+
+        let raw = RawUsbAddress::from_str(ADDR)?;
+        assert_eq!(raw.board_text(), "07");
+        assert_eq!(raw.to_string(), ADDR);
+        Ok(())
+    }
+
+    #[test]
+    fn usb_numeric_serial() {
+        let addr = usb("USB::0x1A34::0x5678::12345");
+        assert_eq!(addr.serial_number, "12345");
+        assert_eq!(addr.interface_number, None);
+
+        let addr = usb("USB::0x1A34::0x5678::12345::5");
+        assert_eq!(addr.serial_number, "12345");
+        assert_eq!(addr.interface_number, Some(5));
+
+        let addr = usb("USB::0x1A34::0x5678::A22-5::5::INSTR");
+        assert_eq!(addr.serial_number, "A22-5");
+        assert_eq!(addr.interface_number, Some(5));
+    }
+
+    #[test]
+    fn usb_presets() -> Result<(), UsbParseError> {
+        const CANONICAL: &str = "USB::0x1A34::0x5678::A22-5::INSTR";
+        let (strict, default, lenient) = (
+            ParseOptions::strict(),
+            ParseOptions::default(),
+            ParseOptions::lenient(),
+        );
+
+        for options in [strict, default, lenient] {
+            assert_eq!(
+                UsbAddress::parse_with(CANONICAL, &options)?.to_string(),
+                CANONICAL
+            );
+        }
+
+        // Valid, but not how it is displayed.
+        const LOWER: &str = "USB::0x1a34::0x5678::A22-5::instr";
+        assert!(matches!(
+            UsbAddress::parse_with(LOWER, &strict),
+            Err(UsbParseError::NotCanonical { found, canonical })
+                if found == LOWER && canonical == CANONICAL
+        ));
+        assert_eq!(
+            UsbAddress::parse_with(LOWER, &default)?,
+            UsbAddress::from_str(LOWER)?
+        );
+        assert_eq!(
+            UsbAddress::parse_with(LOWER, &lenient)?.to_string(),
+            CANONICAL
+        );
+
+        // Only readable as an address with some cleanup.
+        const MESSY: &str = " 'usb::1a34::5678::A22-5::INSTR/' ";
+        assert!(UsbAddress::parse_with(MESSY, &strict).is_err());
+        assert!(UsbAddress::parse_with(MESSY, &default).is_err());
+        assert_eq!(
+            UsbAddress::parse_with(MESSY, &lenient)?.to_string(),
+            CANONICAL
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usb_pyvisa_compat() -> Result<(), UsbParseError> {
+        let pyvisa = ParseOptions::pyvisa_compat();
+
+        for (addr, expected) in [
+            (
+                "usb0::0x1A34::0x5678::A22-5::instr",
+                "USB0::0x1A34::0x5678::A22-5::INSTR",
+            ),
+            ("Usb::1a34::5678::A22-5", "USB::0x1A34::0x5678::A22-5"),
+            (
+                "USB0::0957::1796::MY1234::INSTR",
+                "USB0::0x957::0x1796::MY1234::INSTR",
+            ),
+        ] {
+            assert_eq!(UsbAddress::parse_with(addr, &pyvisa)?.to_string(), expected);
+        }
+        assert!(UsbAddress::from_str("Usb::1a34::5678::A22-5").is_err());
+        assert_eq!(
+            UsbAddress::from_str("usb0::0x1A34::0x5678::A22-5::instr")?.to_string(),
+            "USB0::0x1A34::0x5678::A22-5::INSTR"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usb_error_spans_in_bounds() {
+        let mut rng = Rng::new(SEED);
+
+        for i in 0..20_000 {
+            let input = if i % 4 == 0 {
+                random_string(&mut rng, 50)
+            } else {
+                let seed = *rng.pick(SEEDS);
+                mutate(&mut rng, seed)
+            };
+
+            if let Err(err) = UsbAddress::from_str(&input) {
+                assert!(format!("{err:?}").starts_with(err.kind()), "{err:?}");
+                if let Some(span) = err.span() {
+                    assert!(
+                        span.start <= span.end && span.end <= input.len(),
+                        "Span {span:?} out of bounds for {input:?}: {err}"
+                    );
+                    if let Some(found) = found_text(&err) {
+                        assert!(
+                            input[span.clone()].eq_ignore_ascii_case(&found),
+                            "Span {span:?} of {input:?} is not what was found: {err}"
+                        );
                     }
-                    (ManufactuerId, char) | (ModelCode, char) if span.is_empty() => {
-                        if char == '0' {
-                            // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
-                            //             ↑    OR   ↑
-                            // You are here
+                }
+            }
+        }
+    }
+
+    /// What an error says it found, for comparing with its span.
+    fn found_text(err: &UsbParseError) -> Option<String> {
+        use UsbParseError::*;
+
+        match err {
+            NotUSB { found, .. }
+            | NumParseError { found, .. }
+            | NotHex { found, .. }
+            | InvalidBoard { found, .. }
+            | SignedNumber { found, .. }
+            | NotInstr { found, .. }
+            | NotIntfc { found, .. }
+            | InvalidSeperator { found, .. }
+            | TrailingData { found, .. } => Some(found.clone()),
+            ConfusableCharacter { found, .. } | UnexpectedControlChar { found, .. } => {
+                Some(found.to_string())
+            }
+            UnsupportedClass { class, .. } => Some(class.to_string()),
+            EmptyField { .. } => Some(String::new()),
+            EmptyHex { .. } => Some("0x".to_string()),
+            IncompleteAddress(..) | NotCanonical { .. } | NotUtf8(..) => None,
+        }
+    }
+
+    #[test]
+    fn usb_error_spans_cover_found() {
+        for addr in [
+            "TCPIP::1.2.3.4::inst0::INSTR",
+            "USB3x",
+            "USB3x::0x1A34::0x5678::A22-5",
+            "USB-1::0x1A34::0x5678::A22-5",
+            "USB+3",
+            "USB::1A34::0x5678::A22-5",
+            "USB::0y1A34::0x5678::A22-5",
+            "USB::0x1A34::x567",
+            "USB::0x1A34::0x56Z8::A",
+            "USB::0x+1A34::0x5678::A22-5",
+            "USB::0x1A34::0x5678::A22-5::+3",
+            "USB::0x1A34::0x5678::A22-5::+3::INSTR",
+            "USB::0x::0x5678::A22-5",
+            "USB:0x1A34::0x5678::A22-5",
+            "USB::0x1A34::0x5678::A22-5:x",
+            "USB::0x1A34::0x5678::A22-5:é",
+            "USB::0x1A34::0x5678::\"A22\"x",
+            "USB::0x1A34::0x5678::A22-5::::INSTR",
+            "USB::0x1A34::0x5678::A22-5::12x",
+            "USB::0x1A34::0x5678::A22-5::12x::INSTR",
+            "USB::0x1A34::0x5678::A22-5::INST",
+            "USB::0x1A34::0x5678::A22-5::5::INTFC",
+            "USB::0x1A34::0x5678::A22-5::INSTR::5",
+            "USＢ::0x1A34::0x5678::A22-5",
+        ] {
+            let err = UsbAddress::from_str(addr).unwrap_err();
+            let span = err.span().unwrap_or_else(|| panic!("{addr:?} gave {err}"));
+            let found = found_text(&err).unwrap_or_else(|| panic!("{addr:?} gave {err}"));
+            assert!(
+                addr[span.clone()].eq_ignore_ascii_case(&found),
+                "{addr:?} spans {span:?} but found {found:?}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn usb_regex_differential() {
+        let regex = Regex::new(address_regex());
+        let mut rng = Rng::new(SEED);
+
+        let quoted = [
+            "USB::0x1A34::0x5678::\"A22::5\"::INSTR",
+            "USB::0x1A34::0x5678::\"\"",
+            "USB::0x1A34::0x5678::\"A22\"5",
+            "USB::0x1A34::0x5678::A22\"5",
+            "USB::0x1A34::0x5678::\"A22",
+            "USB::0x1A34::0x5678::A22-5::INſTR",
+            "USB::0x+1A34::0x5678::A22-5",
+            "USB::0x1A34::0x-5678::A22-5",
+            "USB::0x1A34::0x5678::A22-5::+3",
+            "USB::0x1A34::0x5678::A22-5::+3::INSTR",
+        ];
+        let mut accepted = 0;
+
+        for i in 0..20_000 {
+            let input = match quoted.get(i) {
+                Some(input) => input.to_string(),
+                None if i % 4 == 0 => random_string(&mut rng, 50),
+                None if i % 4 == 1 => random_usb_address(&mut rng),
+                None => {
+                    let seed = random_usb_address(&mut rng);
+                    mutate(&mut rng, &seed)
+                }
+            };
+
+            let parsed = UsbAddress::from_str(&input);
+            if parsed.is_ok() == regex.is_match(&input) {
+                accepted += usize::from(parsed.is_ok());
+                continue;
+            }
+
+            // The documented divergences, where only the parser rejects
+            let diverges = match &parsed {
+                Err(UsbParseError::NumParseError { source, .. }) => {
+                    *source.kind() == IntErrorKind::PosOverflow
+                }
+                Err(UsbParseError::TrailingData { found, .. }) => found == "/",
+                _ => false,
+            };
+            assert!(
+                diverges,
+                "Parser and regex disagree on {input:?}: {:?}",
+                parsed.map(|addr| addr.to_string())
+            );
+        }
+
+        // Make sure this is not just comparing failures
+        assert!(accepted > 5_000, "Only {accepted} were accepted by both");
+    }
+
+    #[test]
+    fn usb_canonical_idempotent() {
+        let mut rng = Rng::new(SEED);
+        let mut parsed = 0;
+
+        for _ in 0..20_000 {
+            let input = random_usb_address(&mut rng);
+
+            // Not every generated address is valid, a serial may end in a '/' for example.
+            let Ok(addr) = UsbAddress::from_str(&input) else {
+                continue;
+            };
+            parsed += 1;
+
+            let canonical = addr.to_string();
+            let reparsed = UsbAddress::from_str(&canonical)
+                .unwrap_or_else(|err| panic!("{canonical:?} from {input:?} did not parse: {err}"));
+            assert_eq!(reparsed, addr, "{input:?}");
+            assert_eq!(reparsed.to_string(), canonical, "{input:?}");
+        }
+
+        // Make sure the generator is actually making valid addresses.
+        assert!(parsed > 15_000, "Only {parsed} addresses parsed");
+    }
+
+    #[test]
+    fn usb_likely_multi_interface() {
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5::0",
+            "USB0::0x1A34::0x5678::A22-5::2::INSTR",
+        ] {
+            assert!(usb(addr).likely_multi_interface(), "{addr}");
+        }
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5",
+            "USB0::0x1A34::0x5678::A22-5::INSTR",
+        ] {
+            assert!(!usb(addr).likely_multi_interface(), "{addr}");
+        }
+    }
+
+    #[test]
+    fn usb_to_usbtmc() {
+        let full = usb("USB34::0x12A4::0xFF1A::A22-5::3::INSTR");
+        assert_eq!(full.to_usbtmc(), (0x12A4, 0xFF1A, "A22-5", 3));
+
+        let minimal = usb("USB::0x1A34::0x5678::A22-5");
+        assert_eq!(minimal.to_usbtmc(), (0x1A34, 0x5678, "A22-5", 0));
+    }
+
+    #[test]
+    fn usb_needs_quoting() {
+        for serial in [
+            "A22 5", "A22:5", "A22\t5", "INSTR", "raw", "Intfc", "socket",
+        ] {
+            assert!(needs_quoting(serial), "{serial:?}");
+        }
+        for serial in ["A22-5", "INSTR5", "12345", "a\"b", "A22/5"] {
+            assert!(!needs_quoting(serial), "{serial:?}");
+        }
+    }
+
+    #[test]
+    fn usb_quoted_serial() -> Result<(), UsbParseError> {
+        for (addr, serial) in [
+            ("USB::0x1A34::0x5678::\"A22::5\"", "A22::5"),
+            ("USB0::0x1A34::0x5678::\"A22 5\"::2::INSTR", "A22 5"),
+            ("USB::0x1A34::0x5678::\"INSTR\"::INSTR", "INSTR"),
+        ] {
+            let parsed = UsbAddress::from_str(addr)?;
+            assert_eq!(parsed.serial_number, serial);
+            assert_eq!(parsed.to_string(), addr);
+            assert_eq!(UsbAddress::from_str(&parsed.to_string())?, parsed);
+        }
+
+        // Quotes are dropped when they are not needed.
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::\"A22-5\"").to_string(),
+            "USB::0x1A34::0x5678::A22-5"
+        );
+        // And added when they are.
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::A22 5").to_string(),
+            "USB::0x1A34::0x5678::\"A22 5\""
+        );
+
+        assert!(UsbAddress::from_str("USB::0x1A34::0x5678::\"A22::5").is_err());
+        assert!(UsbAddress::from_str("USB::0x1A34::0x5678::\"A22\"5").is_err());
+        assert!(UsbAddress::from_str("USB::0x1A34::0x5678::\"\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn usb_identity_levels() {
+        let base = usb("USB0::0x1A34::0x5678::A22-5::0::INSTR");
+
+        // (address, ==, same_device, is_same_unit)
+        for (other, equal, device, unit) in [
+            ("USB0::0x1A34::0x5678::A22-5::0::INSTR", true, true, true),
+            ("USB0::0x1A34::0x5678::A22-5::1::INSTR", false, true, true),
+            ("USB0::0x1A34::0x5678::A22-5", false, true, true),
+            ("USB1::0x1A34::0x5678::A22-5::0::INSTR", false, false, true),
+            ("USB::0x1A34::0x5678::A22-5::0::INSTR", false, false, true),
+            ("USB0::0x1A34::0x5678::A22-6::0::INSTR", false, false, false),
+            ("USB0::0x1A35::0x5678::A22-5::0::INSTR", false, false, false),
+            ("USB0::0x1A34::0x5679::A22-5::0::INSTR", false, false, false),
+        ] {
+            let other = usb(other);
+            assert_eq!(base == other, equal, "{other}");
+            assert_eq!(base.same_device(&other), device, "{other}");
+            assert_eq!(base.is_same_unit(&other), unit, "{other}");
+            assert_eq!(other.is_same_unit(&base), unit, "{other}");
+        }
+    }
+
+    #[test]
+    fn usb_vendor_range() {
+        let addrs: Vec<_> = [
+            "USB::0x1A34::0x0::\"   \"",
+            "USB::0x1A34::0x5678::A22-5::INSTR",
+            "USB0::0x1A34::0xFFFF::Z::65535::INSTR",
+            "USB::0x1A33::0xFFFF::Z",
+            "USB0::0x1A35::0x0::0",
+            "USB1::0x1A34::0x5678::A22-5",
+            "USB1::0xFFFF::0x1::A",
+            "USB2::0x0::0x0::A",
+        ]
+        .into_iter()
+        .map(usb)
+        .collect();
+        let set: std::collections::BTreeSet<_> = addrs.iter().cloned().collect();
+
+        for (board, vendor) in [(0, 0x1A34), (1, 0x1A34), (1, 0xFFFF), (0, 0), (3, 0x1A34)] {
+            let ranged: Vec<_> = set.range(vendor_range(board, vendor)).collect();
+            let mut scanned: Vec<_> = filter_vendor(&addrs, vendor)
+                .into_iter()
+                .filter(|addr| addr.board.unwrap_or(0) == board)
+                .collect();
+            scanned.sort();
+            assert_eq!(ranged, scanned, "board {board}, vendor {vendor:#X}");
+        }
+
+        assert_eq!(filter_vendor(&addrs, 0x1A34).len(), 4);
+        assert!(filter_vendor(&addrs, 0x1234).is_empty());
+    }
+
+    #[test]
+    fn usb_telemetry_json() {
+        assert_eq!(
+            usb("USB::0x1::0x5678::A22-5::3").to_telemetry_json(),
+            r#"{"v":1,"scheme":"usb","board":null,"vendor":"0x0001","product":"0x5678","serial":"A22-5","interface":3,"class":null}"#
+        );
+        assert_eq!(
+            usb("USB2::0x1A34::0x5678::A22-5::INSTR").to_redacted_telemetry_json(),
+            r#"{"v":1,"scheme":"usb","board":2,"vendor":"0x1A34","product":"0x5678","serial":"<redacted>","interface":null,"class":"INSTR"}"#
+        );
+        assert_eq!(
+            usb("USB::0x1A34::0x5678::A\"2\\2\t5").to_telemetry_json(),
+            r#"{"v":1,"scheme":"usb","board":null,"vendor":"0x1A34","product":"0x5678","serial":"A\"2\\2\u00095","interface":null,"class":null}"#
+        );
+
+        let addr = usb("USB::0x1A34::0x5678::A\"2\\2\t5");
+        let json: serde_json::Value =
+            serde_json::from_str(&addr.to_telemetry_json()).expect("valid JSON");
+        assert_eq!(json["serial"], "A\"2\\2\t5");
+    }
+
+    #[test]
+    fn usb_board_or_any() {
+        let on_three = usb("USB3::0x1A34::0x5678::A22-5::INSTR");
+        let anywhere = usb("USB::0x1A34::0x5678::A22-5::INSTR");
+
+        assert_eq!(on_three.board_or_any(), BoardSpec::Specific(3));
+        assert_eq!(anywhere.board_or_any(), BoardSpec::Any);
+        assert_eq!(
+            usb("USB0::0x1A34::0x5678::A22-5").board_or_any(),
+            BoardSpec::Specific(0)
+        );
+    }
+
+    #[test]
+    fn usb_canonicalize() {
+        let mut zero = usb("USB0::0x1A34::0x5678::A22-5::INSTR");
+        let none = usb("USB::0x1A34::0x5678::A22-5::INSTR");
+        assert_ne!(zero, none);
+
+        zero.canonicalize();
+        assert_eq!(zero.board, None);
+        assert_eq!(zero, none);
+
+        let mut one = usb("USB1::0x1A34::0x5678::A22-5::INSTR");
+        one.canonicalize();
+        assert_eq!(one.board, Some(1));
+    }
+
+    #[test]
+    fn usb_reordered_tail() -> Result<(), UsbParseError> {
+        let reorder = ParseOptions::default().tolerate_reordered_tail(true);
+
+        let (addr, warnings) =
+            UsbAddress::parse_with_warnings("USB::0x1A34::0x5678::A22-5::instr::5", &reorder)?;
+        assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5::5::INSTR");
+        assert_eq!(
+            warnings,
+            [UsbParseWarning::ReorderedTail {
+                found: "instr::5".to_string(),
+                start: 28,
+                end: 36,
+            }]
+        );
+        assert_eq!(
+            UsbAddress::parse_with(
+                "USB::0x1A34::0x5678::A22-5::INSTR::5",
+                &ParseOptions::lenient()
+            )?,
+            addr
+        );
 
-                            // Validates that this is a hex format
-                            continue;
-                        } else {
-                            buffer.push(char);
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5::INSTR::5",
+            "USB::0x1A34::0x5678::A22-5::INSTR::5::",
+            "USB::0x1A34::0x5678::A22-5::INSTR::+5",
+            "USB::0x1A34::0x5678::A22-5::INSTR::99999",
+            "USB::0x1A34::0x5678::A22-5::3::INSTR::5",
+        ] {
+            assert!(matches!(
+                UsbAddress::parse_with(addr, &ParseOptions::strict()),
+                Err(UsbParseError::TrailingData { .. })
+            ));
+            if addr != "USB::0x1A34::0x5678::A22-5::INSTR::5" {
+                assert!(UsbAddress::parse_with(addr, &reorder).is_err(), "{addr}");
+            }
+        }
+        Ok(())
+    }
 
-                            ret = Err(NotHex {
-                                found: 'scanning0: loop {
-                                    if let Some((index, char)) = addr_iter.next() {
-                                        span.end = index;
-                                        if char == ':' {
-                                            break buffer;
-                                        } else {
-                                            buffer.push(char);
-                                        }
-                                    } else {
-                                        break 'scanning0 buffer;
-                                    }
-                                },
-                                addr: address.to_string(),
-                                start: span.start,
-                                end: span.end,
-                            });
-                            break;
-                        }
-                    }
-                    (ManufactuerId, char) | (ModelCode, char) if span.len() == 1 => {
-                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
-                        //              ↑    OR   ↑
-                        // You are here
+    #[test]
+    fn usb_trimmed_serial() -> Result<(), UsbParseError> {
+        let unpadded = usb("USB::0x1A34::0x5678::A22-5::INSTR");
+        let padded = usb("USB::0x1A34::0x5678::  A22-5   ::INSTR");
 
-                        if char == 'x' || char == 'X' {
-                            continue;
-                        } else {
-                            buffer.push(char);
+        assert_ne!(padded, unpadded);
+        assert_eq!(padded.clone().with_trimmed_serial(), unpadded);
 
-                            ret = Err(NotHex {
-                                found: 'scanningX: loop {
-                                    if let Some((index, char)) = addr_iter.next() {
-                                        span.end = index;
-                                        if char == ':' {
-                                            break buffer;
-                                        } else {
-                                            buffer.push(char);
-                                        }
-                                    } else {
-                                        break 'scanningX buffer;
-                                    }
-                                },
-                                addr: address.to_string(),
-                                start: span.start,
-                                end: span.end,
-                            });
-                            break;
-                        }
-                    }
-                    (SerialNumber, ':') => {
-                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
-                        //                                               ↑          OR          ↑
-                        // You are here
+        let trim = ParseOptions::default().trim_serial(true);
+        assert_eq!(
+            UsbAddress::parse_with("USB::0x1A34::0x5678::  A22-5   ::INSTR", &trim)?,
+            unpadded
+        );
 
-                        // Intersting thought. Is it valid for a serial number to have a colon? See fisa#7
-                        resource.serial_number.clone_from(&buffer);
-                        buffer.clear();
+        // Never trimmed to nothing
+        let blank = usb("USB::0x1A34::0x5678::\"   \"");
+        assert_eq!(blank.clone().with_trimmed_serial(), blank);
+        Ok(())
+    }
 
-                        span.start = addr_index + 2;
+    #[test]
+    fn usb_from_descriptor_fields() -> Result<(), UsbBuildError> {
+        let addr = UsbAddress::from_descriptor_fields(0x1234, 0x5678, "SN")?;
+        assert_eq!(addr.to_string(), "USB::0x1234::0x5678::SN");
+        assert_eq!(addr, usb("USB::0x1234::0x5678::SN"));
 
-                        // There are two distinct optional fields next
-                        match addr_iter.next() {
-                            Some((i, ':')) => {
-                                parser_state = match addr_iter.peek() {
-                                    Some((_, 'I')) | Some((_, 'i')) => Instr,
-                                    _ => USBInterface,
-                                };
-                                span.end = i + 1;
-                            }
-                            Some((i, char)) => {
-                                ret = Err(InvalidSeperator {
-                                    found: format!(":{char}"),
-                                    addr: address.to_string(),
-                                    start: i - 1,
-                                    end: i,
-                                })
-                            }
-                            None => {
-                                // Means there was one but not a second colon.
-                                ret = Err(IncompleteAddress(
-                                    address.to_string(),
-                                    "either USB Interface or INSTR".to_string(),
-                                ))
-                            }
-                        }
-                        continue;
-                    }
-                    (USBInterface, ':') => {
-                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
-                        //                                                                      ↑
-                        // You are here
+        let addr = UsbAddress::from_descriptor_fields(0x1, 0xA, "A22::5")?;
+        assert_eq!(addr.to_string(), "USB::0x1::0xA::\"A22::5\"");
 
-                        match buffer.parse() {
-                            Ok(num) => {
-                                resource.interface_number = Some(num);
-                                buffer.clear();
+        assert_eq!(
+            UsbAddress::from_descriptor_fields(0x1234, 0x5678, ""),
+            Err(UsbBuildError::EmptySerial)
+        );
+        assert_eq!(
+            UsbAddress::from_descriptor_fields(0x1234, 0x5678, "A\"::5"),
+            Err(UsbBuildError::UnrepresentableSerial("A\"::5".to_string()))
+        );
+        Ok(())
+    }
 
-                                span.start = addr_index + 2;
-                                parser_state = Instr;
-                                continue;
-                            }
-                            Err(err) => {
-                                ret = Err(NumParseError {
-                                    found: buffer,
-                                    addr: address.to_string(),
-                                    start: span.start,
-                                    end: span.end - 1,
-                                    source: err,
-                                });
-                                break;
-                            }
-                        }
-                    }
-                    (Board, char)
-                    | (ManufactuerId, char)
-                    | (ModelCode, char)
-                    | (SerialNumber, char)
-                    | (USBInterface, char)
-                    | (Instr, char) => {
-                        // USB[board]::0x<CODE>::0x<CODE>::serial number[::USB interfacenumber][::INSTR]
-                        //    ↑-----↑ OR  ↑---↑ OR ↑----↑OR↑-----------↑ OR↑------------------↑
-                        // You are here
+    #[test]
+    fn usb_from_os_str() -> Result<(), UsbParseError> {
+        let addr = UsbAddress::from_os_str(OsStr::new("USB0::0x1a34::0x5678::A22-5::INSTR"))?;
+        assert_eq!(addr.to_string(), "USB0::0x1A34::0x5678::A22-5::INSTR");
 
-                        buffer.push(char);
-                        continue;
-                    }
-                }
-            } else {
-                // When it's the end of the str
-                // Using the if/else ensures that lifetime analysis is happy
-                match parser_state {
-                    Usb => {
-                        ret = Err(IncompleteAddress(
-                            address.to_string(),
-                            "USB flag, Manufacture Code, Model Number, Serial number".to_string(),
-                        ))
-                    }
-                    Board | ManufactuerId => {
-                        ret = Err(IncompleteAddress(
-                            address.to_string(),
-                            "Manufacture Code, Model Number, Serial number".to_string(),
-                        ))
-                    }
-                    ModelCode => {
-                        ret = Err(IncompleteAddress(
-                            address.to_string(),
-                            "Model Number, Serial number".to_string(),
-                        ))
-                    }
-                    SerialNumber => {
-                        // USB[board]::manufacturer ID::model code::serial number
-                        //                                                       ↑
-                        // You are here
+        assert!(matches!(
+            UsbAddress::from_os_str(OsStr::new("USB::0x1A34")),
+            Err(UsbParseError::IncompleteAddress(..))
+        ));
+        Ok(())
+    }
 
-                        // I do not know what the proper format of a serial number is.
-                        // So I'll just accept anything that is not an empty string.
-                        match buffer.as_str() {
-                            "" => {
-                                ret = Err(IncompleteAddress(address.into(), "Serial Number".into()))
-                            }
-                            _ => resource.serial_number = buffer,
-                        }
-                    }
-                    USBInterface => {
-                        // USB[board]::manufacturer ID::model code::serial number::USB interfacenumber
-                        //                                                                            ↑
-                        // You are here
+    #[test]
+    #[cfg(unix)]
+    fn usb_from_os_str_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
 
-                        match buffer.parse() {
-                            Ok(num) => resource.interface_number = Some(num),
-                            Err(err) => {
-                                ret = Err(NumParseError {
-                                    found: buffer,
-                                    addr: address.to_string(),
-                                    start: span.start,
-                                    end: span.end - 1,
-                                    source: err,
-                                });
-                                break;
-                            }
-                        }
-                    }
-                    Instr => {
-                        // USB[board]::manufacturer ID::model code::serial number::USB interfacenumber::INSTR
-                        //                                                                                   ↑
-                        // You are here
+        let addr = OsStr::from_bytes(b"USB::0x1A34::0x5678::A22\xFF5");
+        assert!(matches!(
+            UsbAddress::from_os_str(addr),
+            Err(UsbParseError::NotUtf8(lossy)) if lossy == "USB::0x1A34::0x5678::A22\u{FFFD}5"
+        ));
+    }
 
-                        let buff_upper = buffer.to_uppercase();
+    #[test]
+    fn usb_parse_addresses() {
+        let file = std::io::Cursor::new(
+            "USB::0x1A34::0x5678::A22-5\r\nUSB0::0x1a34::0x5678::A22-6::INSTR\n\nGPIB::1::INSTR",
+        );
+        let results: Vec<_> = parse_addresses(file).collect();
 
-                        if buff_upper == "INSTR" {
-                            resource.instr = true;
-                        } else {
-                            ret = Err(NotInstr {
-                                found: buffer,
-                                addr: address.to_string(),
-                                start: span.start,
-                                end: span.end - 1,
-                            })
-                        }
-                    }
-                }
-                break;
-            }
+        assert_eq!(results.len(), 4);
+        for (index, expected) in [
+            "USB::0x1A34::0x5678::A22-5",
+            "USB0::0x1A34::0x5678::A22-6::INSTR",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (line, Ok(addr)) = &results[index] else {
+                panic!("Line {} did not parse", index + 1);
+            };
+            assert_eq!(*line, index + 1);
+            assert_eq!(addr.to_string(), expected);
         }
-        ret
+        assert!(matches!(
+            results[2],
+            (3, Err(UsbParseError::IncompleteAddress(..)))
+        ));
+        assert!(matches!(results[3], (4, Err(UsbParseError::NotUSB { .. }))));
     }
-}
 
-impl Display for UsbAddress {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // Reference:
-        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+    #[test]
+    fn usb_model_to_serial_boundary() {
+        // The "::" after the model code must not take any of the serial with it.
+        let addr = usb("USB::0x1::0x2::X");
+        assert_eq!(addr.model_code, 0x2);
+        assert_eq!(addr.serial_number, "X");
+        assert_eq!(addr.interface_number, None);
 
-        let mut board_str = String::with_capacity(2);
-        let mut interface_str = String::with_capacity(5);
-        let mut instr_str = String::with_capacity(5);
+        let addr = usb("USB::0x1::0x2::X::3");
+        assert_eq!(addr.serial_number, "X");
+        assert_eq!(addr.interface_number, Some(3));
 
-        if let Some(num) = self.board {
-            write!(board_str, "{}", num)?
-        }
-        if let Some(num) = self.interface_number {
-            write!(interface_str, "::{}", num)?
+        for (input, missing) in [
+            ("USB::0x1::0x2::", "Serial Number"),
+            ("USB::0x1::0x2:", "Serial Number"),
+            ("USB::0x1::0x2::X:", "either USB Interface or INSTR"),
+        ] {
+            assert!(
+                matches!(
+                    UsbAddress::from_str(input),
+                    Err(UsbParseError::IncompleteAddress(addr, found)) if addr == input && found == missing
+                ),
+                "{input:?}"
+            );
         }
-        if self.instr {
-            instr_str.write_str("::INSTR")?
+    }
+
+    #[test]
+    fn usb_trailing_delimiter_spans() {
+        for addr in [
+            "USB:",
+            "USB0:",
+            "USB::0x1A34:",
+            "USB::1A34:",
+            "USB::0x1A34::0x5678:",
+            "USB::0x1A34::0x5678::A22-5:",
+            "USB::0x1A34::0x5678::A22-5::3:",
+            "USB::0x1A34::0x5678::A22-5::INSTR:",
+        ] {
+            let err = UsbAddress::from_str(addr).unwrap_err();
+            if let Some(span) = err.span() {
+                assert!(span.start <= span.end, "{addr:?} gave {span:?}");
+                assert!(span.end <= addr.len(), "{addr:?} gave {span:?}");
+            }
         }
+    }
 
-        write!(
-            f,
-            "USB{}::{:#X}::{:#X}::{}{}{}",
-            board_str,
-            self.manufactuer_id,
-            self.model_code,
-            self.serial_number,
-            interface_str,
-            instr_str
-        )
+    #[test]
+    fn usb_binary_heap() {
+        let mut heap: std::collections::BinaryHeap<_> = [
+            "USB::0x1A34::0x5678::A22-5::INSTR",
+            "USB1::0x1A34::0x5678::A22-5",
+            "USB::0x1A34::0x5678::A22-5",
+            "USB0::0x1A34::0x5678::A22-5",
+            "USB::0x9::0x5678::A22-5",
+            "USB::0x1A34::0x5678::A22-5::2",
+        ]
+        .into_iter()
+        .map(usb)
+        .collect();
+
+        let mut popped = Vec::new();
+        while let Some(addr) = heap.pop() {
+            popped.push(addr.to_string());
+        }
+        assert_eq!(
+            popped,
+            [
+                "USB1::0x1A34::0x5678::A22-5",
+                "USB::0x1A34::0x5678::A22-5::2",
+                "USB::0x1A34::0x5678::A22-5::INSTR",
+                "USB0::0x1A34::0x5678::A22-5",
+                "USB::0x1A34::0x5678::A22-5",
+                "USB::0x9::0x5678::A22-5",
+            ]
+        );
     }
-}
 
-#[cfg(test)]
-mod test {
-    //! Different permutations of USB addresses to parse.
-    use super::*;
+    #[test]
+    fn usb_total_order() {
+        // Few distinct values per field, so that ties and near ties are common.
+        let mut rng = Rng::new(SEED);
+        let pool: Vec<_> = (0..150)
+            .map(|_| UsbAddress {
+                board: *rng.pick(&[None, Some(0), Some(1)]),
+                manufacturer_id: *rng.pick(&[0x1, 0x10]),
+                model_code: *rng.pick(&[0x2, 0x20]),
+                serial_number: rng.pick(&["A", "A0", "B"]).to_string(),
+                interface_number: *rng.pick(&[None, Some(0), Some(1)]),
+                class: *rng.pick(&[None, Some(UsbClass::Instr), Some(UsbClass::Raw)]),
+            })
+            .collect();
 
-    /// Helper macro
-    /// test_parse!(function_identifier, address_to_parse);
-    macro_rules! test_parse {
-        ($name:ident, $addr:literal) => {
-            #[test]
-            fn $name() -> Result<(), UsbParseError> {
-                const ADDR: &str = $addr;
-                match UsbAddress::from_str(ADDR) {
-                    Ok(address) => {
-                        assert_eq!(address.to_string(), ADDR);
-                        Ok(())
-                    }
-                    Err(err) => Err(err),
-                }
+        for a in &pool {
+            for b in &pool {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a} and {b}");
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{a} and {b}");
             }
-        };
+        }
+
+        for _ in 0..50_000 {
+            let (a, b, c) = (rng.pick(&pool), rng.pick(&pool), rng.pick(&pool));
+            if a <= b && b <= c {
+                assert!(a <= c, "{a} <= {b} <= {c}");
+            }
+        }
     }
 
-    test_parse!(usb_parse_address, "USB::0x1A34::0x5678::A22-5");
-    test_parse!(usb_parse_board, "USB1::0x12B4::0x56F8::A22-5::INSTR");
-    test_parse!(usb_parse_instr, "USB::0xFFA1::0x56C8::A22-5::INSTR");
-    test_parse!(usb_parse_interface, "USB::0x1234::0x5D78::A22-5::123");
-    test_parse!(usb_parse_all, "USB34::0x12A4::0xFF1A::A22-5::12314::INSTR");
+    #[test]
+    fn usb_never_panics() {
+        let options = [
+            ParseOptions::default(),
+            ParseOptions::pyvisa_compat()
+                .strip_wrappers(true)
+                .strip_trailing_slash(true),
+        ];
+
+        check_never_panics("USB", SEEDS, |input| {
+            for options in &options {
+                let _ = UsbAddress::parse_with(input, options);
+            }
+            let _ = UsbPattern::from_str(input);
+            let _ = RawUsbAddress::from_str(input);
+            let _ = NamedUsbAddress::from_str(input);
+            UsbAddress::from_str(input).err().and_then(|err| err.span())
+        });
+    }
 
     mod ui {
         //! USB Address UI tests.
         use super::*;
 
         /// Helper macro
-        /// test_ui!(function_identifier, address_to_parse, expected_error);
+        /// test_ui!(function_identifier, address_to_parse, expected_error[, parse_options]);
         macro_rules! test_ui {
             ($name:ident, $addr:literal, $expected:literal) => {
+                test_ui!($name, $addr, $expected, ParseOptions::default());
+            };
+            ($name:ident, $addr:literal, $expected:literal, $options:expr) => {
                 #[test]
                 fn $name() -> Result<(), String> {
                     const ADDR: &str = $addr;
                     const EXPECT: &str = $expected;
-                    if let Err(err) = UsbAddress::from_str(ADDR) {
+                    if let Err(err) = UsbAddress::parse_with(ADDR, &$options) {
                         if err.to_string() == EXPECT {
                             Ok(())
                         } else {
@@ -641,12 +3702,112 @@ mod test {
             };
         }
 
+        test_ui!(
+            usb_ui_embedded_tab,
+            "USB::0x1A34::0x5678\tA22-5",
+            "Found the control character '\\t' at position 19",
+            ParseOptions::default().reject_control_chars(true)
+        );
+        test_ui!(
+            usb_ui_embedded_cr,
+            "USB::0x1A34::0x5678::A22-5\r::INSTR\r\n",
+            "Found the control character '\\r' at position 26",
+            ParseOptions::lenient()
+        );
+        test_ui!(
+            usb_ui_fullwidth_colon,
+            "USB::0x1A34\u{FF1A}\u{FF1A}0x5678::A22-5",
+            "Found '：' (U+FF1A) at position 11, which looks like ':' but is not"
+        );
+        test_ui!(
+            usb_ui_confusable_digit,
+            "USB::0x1A\u{FF13}4::0x5678::A22-5",
+            "Found '３' (U+FF13) at position 9, which looks like '3' but is not"
+        );
+        test_ui!(
+            usb_ui_empty_interface,
+            "USB::0x1A34::0x5678::A22-5:::3::INSTR",
+            "The USB interface number is empty at position 28 in\n \"USB::0x1A34::0x5678::A22-5:::3::INSTR\""
+        );
+        test_ui!(
+            usb_ui_empty_interface_instr,
+            "USB::0x1A34::0x5678::A22-5::::INSTR",
+            "The USB interface number is empty at position 28 in\n \"USB::0x1A34::0x5678::A22-5::::INSTR\""
+        );
+        test_ui!(
+            usb_ui_cut_after_serial,
+            "USB::0x1A34::0x5678::A22-5::",
+            "\"USB::0x1A34::0x5678::A22-5::\" is an incomplete address missing: either USB Interface or INSTR"
+        );
         test_ui!(
             usb_ui_not_usb,
             "TCPIP::1.2.3.4::inst0::INSTR",
-            "Expected \"USB\" at address start, found \"TCP\""
+            "Expected \"USB\" at address start, found \"TCP\" at 0 to 3"
+        );
+        test_ui!(
+            usb_ui_not_usb_short,
+            "GP",
+            "Expected \"USB\" at address start, found \"GP\" at 0 to 2"
+        );
+        test_ui!(
+            usb_ui_not_usb_confusable,
+            "USＢ::0x1A34::0x5678::A22-5",
+            "Found 'Ｂ' (U+FF22) at position 2, which looks like 'B' but is not"
+        );
+        test_ui!(
+            usb_ui_reordered_tail,
+            "USB::0x1A34::0x5678::A22-5::INSTR::5",
+            "Found \"::5\" after the end of the address at 33 to 36 of\n \"USB::0x1A34::0x5678::A22-5::INSTR::5\""
         );
         test_ui!(usb_ui_cut_usb, "US", "\"US\" is an incomplete address missing: USB flag, Manufacture Code, Model Number, Serial number");
+        test_ui!(usb_ui_only_prefix, "USB", "\"USB\" is an incomplete address missing: Manufacture Code, Model Number, Serial number");
+        test_ui!(usb_ui_only_board, "USB3", "\"USB3\" is an incomplete address missing: Manufacture Code, Model Number, Serial number");
+        test_ui!(usb_ui_only_board_sep, "USB3::", "\"USB3::\" is an incomplete address missing: Manufacture Code, Model Number, Serial number");
+        test_ui!(
+            usb_ui_only_bad_board,
+            "USB3x",
+            "Found \"3x\" instead of a number at position 3 to 5 of \n\"USB3x\""
+        );
+        test_ui!(
+            usb_ui_bad_board,
+            "USB3x::0x1A34::0x5678::A22-5",
+            "Found \"3x\" instead of a number at position 3 to 5 of \n\"USB3x::0x1A34::0x5678::A22-5\""
+        );
+        test_ui!(
+            usb_ui_negative_board,
+            "USB-1::0x1A34::0x5678::A22-5",
+            "Board numbers must be non-negative integers without a sign, found \"-1\" at position 3 to 5 of\n \"USB-1::0x1A34::0x5678::A22-5\""
+        );
+        test_ui!(
+            usb_ui_plus_board,
+            "USB+3::0x1A34::0x5678::A22-5",
+            "Board numbers must be non-negative integers without a sign, found \"+3\" at position 3 to 5 of\n \"USB+3::0x1A34::0x5678::A22-5\""
+        );
+        test_ui!(
+            usb_ui_signed_manu,
+            "USB::0x+1A34::0x5678::A22-5",
+            "The manufacturer ID must be written without a sign, found \"0x+1A34\" at position 5 to 12 of\n \"USB::0x+1A34::0x5678::A22-5\""
+        );
+        test_ui!(
+            usb_ui_signed_interface,
+            "USB::0x1A34::0x5678::A22-5::+3",
+            "The USB interface number must be written without a sign, found \"+3\" at position 28 to 30 of\n \"USB::0x1A34::0x5678::A22-5::+3\""
+        );
+        test_ui!(
+            usb_ui_only_signed_board,
+            "USB+3",
+            "Board numbers must be non-negative integers without a sign, found \"+3\" at position 3 to 5 of\n \"USB+3\""
+        );
+        test_ui!(
+            usb_ui_raw_trailing,
+            "USB::0x1A34::0x5678::A22-5::RAW::5",
+            "Found \"::5\" after the end of the address at 31 to 34 of\n \"USB::0x1A34::0x5678::A22-5::RAW::5\""
+        );
+        test_ui!(
+            usb_ui_intfc_interface,
+            "USB::0x1A34::0x5678::A22-5::5::INTFC",
+            "USB addresses of the INTFC class are not supported, found INTFC at position 31 to 36 of\n \"USB::0x1A34::0x5678::A22-5::5::INTFC\""
+        );
         test_ui!(usb_ui_cut_manu, "USB::0x", "\"USB::0x\" is an incomplete address missing: Manufacture Code, Model Number, Serial number");
         test_ui!(
             usb_ui_cut_model,
@@ -658,18 +3819,33 @@ mod test {
             "USB::0x321::0x132::",
             "\"USB::0x321::0x132::\" is an incomplete address missing: Serial Number"
         );
+        test_ui!(
+            usb_ui_empty_serial,
+            "USB::0x321::0x132::::5",
+            "\"USB::0x321::0x132::::5\" is an incomplete address missing: Serial Number"
+        );
+        test_ui!(
+            usb_ui_transposed_hex,
+            "USB::x01234::0x5678::SN",
+            "Invalid hexidecimal number: \"x01234\" at position 5 to 11 in\n \"USB::x01234::0x5678::SN\"\nNumber must start with '0x', did you mean \"0x1234\"?"
+        );
         test_ui!(usb_ui_manu_hex, "USB34::x1H34::0x5678::A22-5::12314::INSTR", "Invalid hexidecimal number: \"x1H34\" at position 7 to 12 in\n \"USB34::x1H34::0x5678::A22-5::12314::INSTR\"\nNumber must start with '0x'");
         test_ui!(usb_ui_model_hex, "USB34::0x1B34::x56A8::A22-5::12314::INSTR", "Invalid hexidecimal number: \"x56A8\" at position 15 to 20 in\n \"USB34::0x1B34::x56A8::A22-5::12314::INSTR\"\nNumber must start with '0x'");
-        test_ui!(usb_ui_wrong_inst_long, "USB34::0x12C4::0x5678::A22-5::12314::INSTRfdss", "In address \"INSTR\" was indicated but instead \"INSTRfdss\" was found at 37 to 44 of\n \"USB34::0x12C4::0x5678::A22-5::12314::INSTRfdss\"");
-        test_ui!(usb_ui_wrong_inst_short, "USB34::0x1234::0x5D78::A22-5::INST", "In address \"INSTR\" was indicated but instead \"INST\" was found at 30 to 32 of\n \"USB34::0x1234::0x5D78::A22-5::INST\"");
-        test_ui!(usb_ui_num_err_model, "USB34::0x1234::0x56Z8::A22-5::12314::INSTR", "Found \"56Z8\" instead of a number at position 15 to 20 of \n\"USB34::0x1234::0x56Z8::A22-5::12314::INSTR\"");
-        test_ui!(usb_ui_num_err_manu, "USB34::0xTEST::0x568::A22-5::12314::INSTR", "Found \"TEST\" instead of a number at position 7 to 12 of \n\"USB34::0xTEST::0x568::A22-5::12314::INSTR\"");
+        test_ui!(usb_ui_wrong_inst_long, "USB34::0x12C4::0x5678::A22-5::12314::INSTRfdss", "In address \"INSTR\" was indicated but instead \"INSTRfdss\" was found at 37 to 46 of\n \"USB34::0x12C4::0x5678::A22-5::12314::INSTRfdss\"");
+        test_ui!(usb_ui_wrong_inst_short, "USB34::0x1234::0x5D78::A22-5::INST", "In address \"INSTR\" was indicated but instead \"INST\" was found at 30 to 34 of\n \"USB34::0x1234::0x5D78::A22-5::INST\"");
+        test_ui!(usb_ui_num_err_model, "USB34::0x1234::0x56Z8::A22-5::12314::INSTR", "Found \"0x56Z8\" instead of a number at position 15 to 21 of \n\"USB34::0x1234::0x56Z8::A22-5::12314::INSTR\"");
+        test_ui!(usb_ui_num_err_manu, "USB34::0xTEST::0x568::A22-5::12314::INSTR", "Found \"0xTEST\" instead of a number at position 7 to 13 of \n\"USB34::0xTEST::0x568::A22-5::12314::INSTR\"");
+        test_ui!(usb_ui_empty_manu, "USB::0x::0x5678::A22-5", "The manufacturer ID has no digits after its \"0x\" at position 5 to 7 in\n \"USB::0x::0x5678::A22-5\"");
+        test_ui!(usb_ui_empty_model, "USB::0x1::0X::A22-5", "The model code has no digits after its \"0x\" at position 10 to 12 in\n \"USB::0x1::0X::A22-5\"");
         test_ui!(usb_ui_colon, "USB:0x1A34::0x5678::A22-5", "Double colons must seperate address portions. Found \":0\" in:\n \"USB:0x1A34::0x5678::A22-5\".");
         test_ui!(usb_ui_board_colon, "USB1:0x1A34::0x5678::A22-5", "Double colons must seperate address portions. Found \":0\" in:\n \"USB1:0x1A34::0x5678::A22-5\".");
         test_ui!(usb_ui_manu_colon, "USB1::0x1A34:0x5678::A22-5", "Double colons must seperate address portions. Found \":0\" in:\n \"USB1::0x1A34:0x5678::A22-5\".");
         test_ui!(usb_ui_model_colon, "USB1::0x1A34::0x5678:A22-5", "Double colons must seperate address portions. Found \":A\" in:\n \"USB1::0x1A34::0x5678:A22-5\".");
         test_ui!(usb_ui_serial_colon, "USB1::0x1A34::0x5678::A22-5:01", "Double colons must seperate address portions. Found \":0\" in:\n \"USB1::0x1A34::0x5678::A22-5:01\".");
         test_ui!(usb_ui_instr_colon, "USB1::0x1A34::0x5678::A22-5::01:INSTR", "Double colons must seperate address portions. Found \":I\" in:\n \"USB1::0x1A34::0x5678::A22-5::01:INSTR\".");
+        test_ui!(usb_ui_interface_raw_trailing, "USB::0x1A34::0x5678::A22-5::3::RAW::5", "Found \"::5\" after the end of the address at 34 to 37 of\n \"USB::0x1A34::0x5678::A22-5::3::RAW::5\"");
+        test_ui!(usb_ui_instr_trailing_all, "USB1::0x1A34::0x5678::A22-5::01::instr::INSTR", "Found \"::INSTR\" after the end of the address at 38 to 45 of\n \"USB1::0x1A34::0x5678::A22-5::01::instr::INSTR\"");
+        test_ui!(usb_ui_trailing_slash, "USB::0x1A34::0x5678::A22-5::INSTR/", "Found \"/\" after the end of the address at 33 to 34 of\n \"USB::0x1A34::0x5678::A22-5::INSTR/\"");
         test_ui!(usb_ui_instr_colon2, "USB1::0x1A34::0x5678::A22-5:INSTR", "Double colons must seperate address portions. Found \":I\" in:\n \"USB1::0x1A34::0x5678::A22-5:INSTR\".");
     }
 }
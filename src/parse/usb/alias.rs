@@ -0,0 +1,94 @@
+//! Module for USB addresses carrying a user assigned alias.
+use std::{fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+use super::{UsbAddress, UsbParseError};
+
+/// A USB address with a friendly name, written as `alias=USB::...`.
+///
+/// NI-VISA lets users give resources aliases. The alias is not resolved here,
+/// it is just carried along with the address. Handy for config files.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::NamedUsbAddress;
+/// let named: NamedUsbAddress = "scope=USB::0x1A34::0x5678::A22-5".parse()?;
+/// assert_eq!(named.alias, "scope");
+/// assert_eq!(named.address.to_string(), "USB::0x1A34::0x5678::A22-5");
+/// # Ok::<(), fisa::parse::usb::NamedAddressError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct NamedUsbAddress {
+    /// The user's name for the resource. Never empty and never contains '='.
+    pub alias: String,
+    /// The resource the alias refers to.
+    pub address: UsbAddress,
+}
+
+/// Errors that can return from aliased USB address parsing.
+#[derive(Error, Debug)]
+pub enum NamedAddressError {
+    /// When there is no "alias=" before the address, or the alias is empty.
+    #[error("Expected \"alias=\" before the address in {0:?}")]
+    MissingAlias(String),
+
+    /// When the address after the alias is invalid.
+    #[error(transparent)]
+    Address(#[from] UsbParseError),
+}
+
+impl FromStr for NamedUsbAddress {
+    type Err = NamedAddressError;
+
+    fn from_str(named: &str) -> Result<Self, Self::Err> {
+        match named.split_once('=') {
+            Some((alias, address)) if !alias.is_empty() => Ok(NamedUsbAddress {
+                alias: alias.to_string(),
+                address: address.parse()?,
+            }),
+            _ => Err(NamedAddressError::MissingAlias(named.to_string())),
+        }
+    }
+}
+
+impl Display for NamedUsbAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.alias, self.address)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Aliased address round trips.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_alias_round_trip() -> Result<(), NamedAddressError> {
+        const NAMED: &str = "bench-dmm=USB1::0x12B4::0x56F8::A22-5::INSTR";
+        let named = NamedUsbAddress::from_str(NAMED)?;
+
+        assert_eq!(named.alias, "bench-dmm");
+        assert_eq!(named.address, usb("USB1::0x12B4::0x56F8::A22-5::INSTR"));
+        assert_eq!(named.to_string(), NAMED);
+        Ok(())
+    }
+
+    #[test]
+    fn usb_alias_missing() {
+        assert!(matches!(
+            NamedUsbAddress::from_str("USB::0x1A34::0x5678::A22-5"),
+            Err(NamedAddressError::MissingAlias(_))
+        ));
+        assert!(matches!(
+            NamedUsbAddress::from_str("=USB::0x1A34::0x5678::A22-5"),
+            Err(NamedAddressError::MissingAlias(_))
+        ));
+        assert!(matches!(
+            NamedUsbAddress::from_str("scope=USB::0x1A34"),
+            Err(NamedAddressError::Address(_))
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Module for USB addresses that borrow their serial number from the parsed string.
+use super::{raw::serial_span, UsbAddress, UsbClass, UsbParseError};
+
+/// A USB address that borrows its serial number from the string it was parsed from,
+/// so it can be kept and copied around without owning a copy of the serial.
+///
+/// Compares equal to a [`UsbAddress`] with the same fields.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{UsbAddress, UsbAddressRef};
+/// let addr = UsbAddressRef::parse("USB::0x1a34::0x5678::\"A22::5\"::INSTR")?;
+/// assert_eq!(addr.serial_number(), "A22::5");
+/// assert_eq!(addr, UsbAddress::try_new("USB::0x1A34::0x5678::\"A22::5\"::INSTR")?);
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct UsbAddressRef<'a> {
+    /// Board number, if any
+    board: Option<u32>,
+    /// The USB manufacturer ID
+    manufacturer_id: u16,
+    /// The USB model code
+    model_code: u16,
+    /// Serial number, without any quotes
+    serial_number: &'a str,
+    /// Interface number, if any
+    interface_number: Option<u16>,
+    /// The resource class, if any
+    class: Option<UsbClass>,
+}
+
+impl<'a> UsbAddressRef<'a> {
+    /// Parses an address, borrowing the serial number from it.
+    ///
+    /// The address is validated by [`UsbAddress::try_new`], so parsing allocates as much as
+    /// that does. Only the result borrows.
+    pub fn parse(address: &'a str) -> Result<Self, UsbParseError> {
+        let parsed = UsbAddress::try_new(address)?;
+
+        let serial = &address[serial_span(address)];
+        let serial_number = serial
+            .strip_prefix('"')
+            .and_then(|serial| serial.strip_suffix('"'))
+            .unwrap_or(serial);
+
+        Ok(UsbAddressRef {
+            board: parsed.board,
+            manufacturer_id: parsed.manufacturer_id,
+            model_code: parsed.model_code,
+            serial_number,
+            interface_number: parsed.interface_number,
+            class: parsed.class,
+        })
+    }
+
+    /// The serial number, borrowed from the parsed string.
+    pub fn serial_number(&self) -> &'a str {
+        self.serial_number
+    }
+
+    /// Copies the address into an owned one.
+    pub fn to_owned_address(&self) -> UsbAddress {
+        UsbAddress {
+            board: self.board,
+            manufacturer_id: self.manufacturer_id,
+            model_code: self.model_code,
+            serial_number: self.serial_number.to_string(),
+            interface_number: self.interface_number,
+            class: self.class,
+        }
+    }
+}
+
+impl<'a> From<&'a UsbAddress> for UsbAddressRef<'a> {
+    fn from(address: &'a UsbAddress) -> Self {
+        UsbAddressRef {
+            board: address.board,
+            manufacturer_id: address.manufacturer_id,
+            model_code: address.model_code,
+            serial_number: &address.serial_number,
+            interface_number: address.interface_number,
+            class: address.class,
+        }
+    }
+}
+
+impl PartialEq<UsbAddress> for UsbAddressRef<'_> {
+    fn eq(&self, other: &UsbAddress) -> bool {
+        *self == UsbAddressRef::from(other)
+    }
+}
+
+impl PartialEq<UsbAddressRef<'_>> for UsbAddress {
+    fn eq(&self, other: &UsbAddressRef<'_>) -> bool {
+        other == self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Borrowed and owned address comparisons.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_ref_eq_owned() -> Result<(), UsbParseError> {
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5",
+            "USB1::0x1a34::0x5678::A22-5::3::instr",
+            "USB::0x1A34::0x5678::\"A22 5\"::INSTR",
+        ] {
+            let owned = usb(addr);
+            let borrowed = UsbAddressRef::parse(addr)?;
+
+            assert_eq!(borrowed, owned);
+            assert_eq!(owned, borrowed);
+            assert_eq!(borrowed.to_owned_address(), owned);
+        }
+
+        let other = usb("USB::0x1A34::0x5678::A22-6");
+        assert_ne!(UsbAddressRef::parse("USB::0x1A34::0x5678::A22-5")?, other);
+        assert_ne!(other, UsbAddressRef::parse("USB::0x1A34::0x5678::A22-5")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Module for building USB addresses field by field.
+use std::str::FromStr;
+
+use super::{UsbAddress, UsbBuildError, UsbClass};
+
+/// Builds a [`UsbAddress`] out of its fields, without formatting and re-parsing a string.
+/// See [`UsbAddress::builder`].
+///
+/// The manufacturer ID, model code, and serial number are required. Everything else is
+/// left out of the address unless it is set.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{UsbAddress, UsbClass};
+/// let addr = UsbAddress::builder()
+///     .board(0)
+///     .manufacturer_id(0x1A34)
+///     .model_code(0x5678)
+///     .serial_number("A22-5")
+///     .interface_number(3)
+///     .class(UsbClass::Instr)
+///     .build()?;
+/// assert_eq!(addr.to_string(), "USB0::0x1A34::0x5678::A22-5::3::INSTR");
+/// # Ok::<(), fisa::parse::usb::UsbBuildError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Default)]
+#[must_use]
+pub struct UsbAddressBuilder {
+    /// Board number, if set.
+    board: Option<u32>,
+    /// The USB manufacturer ID, if set.
+    manufacturer_id: Option<u16>,
+    /// The USB model code, if set.
+    model_code: Option<u16>,
+    /// Serial number, if set.
+    serial_number: Option<String>,
+    /// Interface number, if set.
+    interface_number: Option<u16>,
+    /// The resource class, if set.
+    class: Option<UsbClass>,
+}
+
+impl UsbAddressBuilder {
+    /// Sets the board number.
+    pub fn board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// Sets the USB manufacturer ID, also known as the vendor ID.
+    pub fn manufacturer_id(mut self, manufacturer_id: u16) -> Self {
+        self.manufacturer_id = Some(manufacturer_id);
+        self
+    }
+
+    /// Sets the USB model code, also known as the product ID.
+    pub fn model_code(mut self, model_code: u16) -> Self {
+        self.model_code = Some(model_code);
+        self
+    }
+
+    /// Sets the serial number. It is quoted in the address if it needs to be.
+    pub fn serial_number(mut self, serial_number: impl Into<String>) -> Self {
+        self.serial_number = Some(serial_number.into());
+        self
+    }
+
+    /// Sets the USB interface number.
+    pub fn interface_number(mut self, interface_number: u16) -> Self {
+        self.interface_number = Some(interface_number);
+        self
+    }
+
+    /// Sets the resource class.
+    pub fn class(mut self, class: UsbClass) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    /// Builds the address.
+    ///
+    /// Fails if a required field was not set, or if the serial number could not be
+    /// written in an address that parses back to it.
+    pub fn build(self) -> Result<UsbAddress, UsbBuildError> {
+        let built = UsbAddress {
+            board: self.board,
+            manufacturer_id: self
+                .manufacturer_id
+                .ok_or(UsbBuildError::MissingField("manufacturer ID"))?,
+            model_code: self
+                .model_code
+                .ok_or(UsbBuildError::MissingField("model code"))?,
+            serial_number: self
+                .serial_number
+                .ok_or(UsbBuildError::MissingField("serial number"))?,
+            interface_number: self.interface_number,
+            class: self.class,
+        };
+
+        if built.serial_number.is_empty() {
+            return Err(UsbBuildError::EmptySerial);
+        }
+
+        // Whatever the serial contains, the address is only useful if it survives a round trip.
+        match UsbAddress::from_str(&built.to_string()) {
+            Ok(parsed) if parsed == built => Ok(built),
+            _ => Err(UsbBuildError::UnrepresentableSerial(built.serial_number)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Building addresses.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_builder_minimal() -> Result<(), UsbBuildError> {
+        let addr = UsbAddress::builder()
+            .manufacturer_id(0x1A34)
+            .model_code(0x5678)
+            .serial_number("A22-5")
+            .build()?;
+        assert_eq!(addr, usb("USB::0x1A34::0x5678::A22-5"));
+
+        let addr = UsbAddress::builder()
+            .manufacturer_id(0x1A34)
+            .model_code(0x5678)
+            .serial_number("12345")
+            .class(UsbClass::Raw)
+            .build()?;
+        assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::12345::RAW");
+
+        let addr = UsbAddress::builder()
+            .manufacturer_id(0x1A34)
+            .model_code(0x5678)
+            .serial_number("INSTR")
+            .build()?;
+        assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::\"INSTR\"");
+        Ok(())
+    }
+
+    #[test]
+    fn usb_builder_invalid() {
+        let builder = UsbAddress::builder()
+            .manufacturer_id(0x1A34)
+            .model_code(0x5678);
+
+        assert_eq!(
+            builder.clone().build(),
+            Err(UsbBuildError::MissingField("serial number"))
+        );
+        assert_eq!(
+            UsbAddress::builder().serial_number("A22-5").build(),
+            Err(UsbBuildError::MissingField("manufacturer ID"))
+        );
+        assert_eq!(
+            builder.clone().serial_number("").build(),
+            Err(UsbBuildError::EmptySerial)
+        );
+        assert_eq!(
+            builder.serial_number("A\"::5").build(),
+            Err(UsbBuildError::UnrepresentableSerial("A\"::5".to_string()))
+        );
+    }
+}
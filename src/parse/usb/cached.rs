@@ -0,0 +1,156 @@
+//! Module for USB addresses with their canonical form precomputed.
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use super::UsbAddress;
+
+/// A USB address along with its canonical string, for hot map lookups and displaying.
+/// The hex vendor and product IDs are cached too, for UIs that show them on their own.
+///
+/// Hashing and equality use the canonical string, so they are exactly as strict
+/// as comparing the displayed addresses.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{CachedUsbAddress, UsbAddress};
+/// let addr = CachedUsbAddress::new(UsbAddress::try_new("USB::0x1a34::0x5678::A22-5")?);
+/// assert_eq!(addr.as_str(), "USB::0x1A34::0x5678::A22-5");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct CachedUsbAddress {
+    /// The address
+    address: UsbAddress,
+    /// The address's Display output
+    canonical: String,
+    /// The manufacturer ID as displayed, such as "0x1A34"
+    vendor_hex: String,
+    /// The model code as displayed, such as "0x5678"
+    product_hex: String,
+}
+
+impl CachedUsbAddress {
+    /// Caches the canonical form of the address.
+    pub fn new(address: UsbAddress) -> Self {
+        CachedUsbAddress {
+            canonical: address.to_string(),
+            vendor_hex: format!("{:#X}", address.manufacturer_id),
+            product_hex: format!("{:#X}", address.model_code),
+            address,
+        }
+    }
+
+    /// The cached address.
+    pub fn address(&self) -> &UsbAddress {
+        &self.address
+    }
+
+    /// The canonical form of the address.
+    pub fn as_str(&self) -> &str {
+        &self.canonical
+    }
+
+    /// The manufacturer ID as it is displayed in the address, such as "0x1A34".
+    pub fn vendor_hex(&self) -> &str {
+        &self.vendor_hex
+    }
+
+    /// The model code as it is displayed in the address, such as "0x5678".
+    pub fn product_hex(&self) -> &str {
+        &self.product_hex
+    }
+
+    /// Changes the address, then recomputes everything cached from it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::{CachedUsbAddress, UsbAddress};
+    /// let mut addr = CachedUsbAddress::new(UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?);
+    /// addr.update(|addr| *addr = addr.clone().with_board(2));
+    /// assert_eq!(addr.as_str(), "USB2::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn update(&mut self, change: impl FnOnce(&mut UsbAddress)) {
+        change(&mut self.address);
+        *self = CachedUsbAddress::new(self.address.clone());
+    }
+
+    /// Takes the address back out.
+    pub fn into_inner(self) -> UsbAddress {
+        self.address
+    }
+}
+
+impl From<UsbAddress> for CachedUsbAddress {
+    fn from(address: UsbAddress) -> Self {
+        CachedUsbAddress::new(address)
+    }
+}
+
+impl PartialEq for CachedUsbAddress {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for CachedUsbAddress {}
+
+impl Hash for CachedUsbAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+
+impl Display for CachedUsbAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.canonical)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Cached address hashing.
+    use std::collections::{hash_map::DefaultHasher, HashSet};
+
+    use super::*;
+
+    use crate::testing::usb;
+
+    /// Hashes with the std hasher.
+    fn hash_of(addr: &CachedUsbAddress) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        addr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn usb_cached_hash() {
+        let upper = CachedUsbAddress::new(usb("USB::0x1A34::0x5678::A22-5::INSTR"));
+        let lower = CachedUsbAddress::new(usb("USB::0x1a34::0X5678::A22-5::instr"));
+        let other = CachedUsbAddress::new(usb("USB::0x1A34::0x5678::A22-6::INSTR"));
+
+        assert_eq!(upper, lower);
+        assert_eq!(hash_of(&upper), hash_of(&lower));
+        assert_ne!(upper, other);
+
+        let set: HashSet<_> = [upper, lower, other].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn usb_cached_hex() {
+        let mut addr = CachedUsbAddress::new(usb("USB::0x1a34::0x5678::A22-5"));
+        assert_eq!(addr.vendor_hex(), format!("{:#X}", 0x1A34));
+        assert_eq!(addr.product_hex(), format!("{:#X}", 0x5678));
+
+        addr.update(|addr| addr.manufacturer_id = 0xBEEF);
+        assert_eq!(addr.vendor_hex(), format!("{:#X}", 0xBEEF));
+        assert_eq!(addr.product_hex(), "0x5678");
+        assert_eq!(addr.as_str(), "USB::0xBEEF::0x5678::A22-5");
+        assert_eq!(addr.address().manufacturer_id, 0xBEEF);
+    }
+}
@@ -0,0 +1,115 @@
+//! Module for describing USB addresses as structured data, for UIs.
+use super::UsbAddress;
+use crate::parse::ResourceClass;
+
+/// USB vendor IDs of common instrument manufacturers, sorted by ID for binary search.
+const VENDORS: &[(u16, &str)] = &[
+    (0x05E6, "Keithley Instruments"),
+    (0x0699, "Tektronix"),
+    (0x0957, "Agilent Technologies"),
+    (0x0AAD, "Rohde & Schwarz"),
+    (0x0F7E, "Fluke"),
+    (0x1AB1, "Rigol Technologies"),
+    (0x2A8D, "Keysight Technologies"),
+    (0x3923, "National Instruments"),
+    (0xF4EC, "Siglent Technologies"),
+];
+
+/// The parts of a USB address, along with anything known about them.
+/// See [`UsbAddress::describe`].
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct UsbDescription {
+    /// Board number, if any
+    pub board: Option<u32>,
+    /// The USB vendor ID
+    pub vendor_id: u16,
+    /// The vendor's name, if it is a known instrument manufacturer
+    pub vendor_name: Option<&'static str>,
+    /// The USB product ID
+    pub product_id: u16,
+    /// Serial number
+    pub serial_number: String,
+    /// Interface number, if any
+    pub interface_number: Option<u16>,
+    /// The resource class, if any
+    pub class: Option<ResourceClass>,
+}
+
+impl UsbAddress {
+    /// The name of the vendor, if it is a known instrument manufacturer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB::0x2A8D::0x1766::MY123")?;
+    /// assert_eq!(addr.vendor_name(), Some("Keysight Technologies"));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn vendor_name(&self) -> Option<&'static str> {
+        VENDORS
+            .binary_search_by_key(&self.manufacturer_id, |(id, _)| *id)
+            .ok()
+            .map(|index| VENDORS[index].1)
+    }
+
+    /// Describes the address as structured data, such as for a UI or a template.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, ResourceClass};
+    /// let desc = UsbAddress::try_new("USB::0x0699::0x0368::C012345::INSTR")?.describe();
+    /// assert_eq!(desc.vendor_name, Some("Tektronix"));
+    /// assert_eq!(desc.class, Some(ResourceClass::Instr));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn describe(&self) -> UsbDescription {
+        UsbDescription {
+            board: self.board,
+            vendor_id: self.manufacturer_id,
+            vendor_name: self.vendor_name(),
+            product_id: self.model_code,
+            serial_number: self.serial_number.clone(),
+            interface_number: self.interface_number,
+            class: self.class.map(ResourceClass::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Address descriptions.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_vendors_sorted() {
+        assert!(VENDORS.is_sorted_by_key(|(id, _)| *id));
+        for (id, name) in VENDORS {
+            let addr = usb(&format!("USB::{id:#06X}::0x0001::A"));
+            assert_eq!(addr.vendor_name(), Some(*name));
+        }
+    }
+
+    #[test]
+    fn usb_describe() {
+        assert_eq!(
+            usb("USB1::0x0957::0x1796::MY123::2::INSTR").describe(),
+            UsbDescription {
+                board: Some(1),
+                vendor_id: 0x0957,
+                vendor_name: Some("Agilent Technologies"),
+                product_id: 0x1796,
+                serial_number: "MY123".to_string(),
+                interface_number: Some(2),
+                class: Some(ResourceClass::Instr),
+            }
+        );
+
+        let desc = usb("USB::0x1A34::0x5678::A22-5").describe();
+        assert_eq!(desc.vendor_name, None);
+        assert_eq!(desc.board, None);
+        assert_eq!(desc.class, None);
+    }
+}
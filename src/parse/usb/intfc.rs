@@ -0,0 +1,145 @@
+//! Module for USB controller interface addresses.
+use std::{fmt::Display, str::FromStr};
+
+use super::{has_sign, UsbParseError};
+use crate::parse::strip_keyword;
+
+/// The address of a USB controller itself, written as `USB[board]::INTFC`.
+///
+/// Some VISA implementations list the controller as a resource. It has no vendor,
+/// product, or serial number, so it is not a [`UsbAddress`](super::UsbAddress).
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::UsbIntfc;
+/// let intfc: UsbIntfc = "USB0::intfc".parse()?;
+/// assert_eq!(intfc.board, Some(0));
+/// assert_eq!(intfc.to_string(), "USB0::INTFC");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct UsbIntfc {
+    /// Board number, if any.
+    pub board: Option<u32>,
+}
+
+impl FromStr for UsbIntfc {
+    type Err = UsbParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use UsbParseError::*;
+
+        let Some(rest) = strip_keyword(address, "USB") else {
+            let found: String = address.chars().take(3).collect();
+            return Err(NotUSB {
+                start: 0,
+                end: found.len(),
+                found,
+            });
+        };
+        let Some((board, class)) = rest.split_once("::") else {
+            return Err(IncompleteAddress(address.to_string(), "INTFC".to_string()));
+        };
+
+        // USB[board]::INTFC
+        //    ↑-----↑
+        let start = "USB".len();
+        let board = match board {
+            "" => None,
+            board if has_sign(board) => {
+                return Err(InvalidBoard {
+                    found: board.to_string(),
+                    addr: address.to_string(),
+                    start,
+                    end: start + board.len(),
+                })
+            }
+            board => Some(board.parse().map_err(|source| NumParseError {
+                found: board.to_string(),
+                addr: address.to_string(),
+                start,
+                end: start + board.len(),
+                source,
+            })?),
+        };
+
+        // USB[board]::INTFC
+        //             ↑---↑
+        if !class.eq_ignore_ascii_case("INTFC") {
+            let start = address.len() - class.len();
+            return Err(NotIntfc {
+                found: class.to_string(),
+                addr: address.to_string(),
+                start,
+                end: address.len(),
+            });
+        }
+
+        Ok(UsbIntfc { board })
+    }
+}
+
+impl Display for UsbIntfc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("USB")?;
+        if let Some(board) = self.board {
+            write!(f, "{board}")?;
+        }
+        f.write_str("::INTFC")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Controller interface round trips.
+    use super::*;
+
+    #[test]
+    fn usb_intfc_round_trip() -> Result<(), UsbParseError> {
+        for addr in ["USB0::INTFC", "USB::INTFC", "USB12::INTFC"] {
+            assert_eq!(UsbIntfc::from_str(addr)?.to_string(), addr);
+        }
+        assert_eq!(
+            UsbIntfc::from_str("USB0::INTFC")?,
+            UsbIntfc { board: Some(0) }
+        );
+        assert_eq!(UsbIntfc::from_str("USB::Intfc")?, UsbIntfc { board: None });
+        assert_eq!(
+            UsbIntfc::from_str("USB007::intfc")?.to_string(),
+            "USB7::INTFC"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usb_intfc_invalid() {
+        for (addr, expected) in [
+            (
+                "GPIB0::INTFC",
+                "Expected \"USB\" at address start, found \"GPI\" at 0 to 3",
+            ),
+            (
+                "USB0",
+                "\"USB0\" is an incomplete address missing: INTFC",
+            ),
+            (
+                "USB0::INSTR",
+                "In address \"INTFC\" was expected but instead \"INSTR\" was found at 6 to 11 of\n \"USB0::INSTR\"",
+            ),
+            (
+                "USB0::0x1A34::0x5678::A22-5",
+                "In address \"INTFC\" was expected but instead \"0x1A34::0x5678::A22-5\" was found at 6 to 27 of\n \"USB0::0x1A34::0x5678::A22-5\"",
+            ),
+            (
+                "USB-1::INTFC",
+                "Board numbers must be non-negative integers without a sign, found \"-1\" at position 3 to 5 of\n \"USB-1::INTFC\"",
+            ),
+        ] {
+            match UsbIntfc::from_str(addr) {
+                Ok(intfc) => panic!("Accepted invalid address {addr:?} as {intfc:?}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+    }
+}
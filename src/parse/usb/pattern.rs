@@ -0,0 +1,377 @@
+//! Module for USB address patterns, as used in resource discovery.
+//! Such as `USB0::0x1A34::0x5678::A22-5::?*INSTR`
+use std::{fmt::Display, str::FromStr};
+
+use super::{UsbAddress, UsbClass, UsbParseError};
+
+/// A field of a pattern, which is either a literal or matches anything.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum PatternField<T> {
+    /// `?*`, matches any value.
+    Any,
+    /// Matches only this value.
+    Exactly(T),
+}
+
+impl<T: PartialEq> PatternField<T> {
+    /// Checks if the value is matched by this field.
+    pub fn matches(&self, value: &T) -> bool {
+        match self {
+            PatternField::Any => true,
+            PatternField::Exactly(expected) => expected == value,
+        }
+    }
+}
+
+/// Represents a USB VISA address pattern, for resource discovery.
+///
+/// The most common discovery idiom is a `?*` after the serial number, meaning any interface
+/// number and any resource class, or `?*INSTR` meaning any interface number but only INSTR,
+/// and likewise `?*RAW`.
+/// The manufacturer ID, model code, and serial number may each also be `?*`, and the board
+/// is always matched exactly. Patterns display in the same syntax, so they can be saved and reloaded.
+/// Concrete addresses are still parsed strictly by [`UsbAddress`], wildcards are only accepted here.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{UsbAddress, UsbPattern};
+/// let pattern: UsbPattern = "USB0::0x1A34::0x5678::A22-5::?*INSTR".parse()?;
+/// assert!(pattern.matches(&UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::INSTR")?));
+/// assert!(pattern.matches(&UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::3::INSTR")?));
+/// assert!(!pattern.matches(&UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5")?));
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct UsbPattern {
+    /// Board number. Matched exactly.
+    board: Option<u32>,
+    /// The USB manufacturer ID.
+    manufacturer_id: PatternField<u16>,
+    /// The USB model code.
+    model_code: PatternField<u16>,
+    /// Serial number.
+    serial_number: PatternField<String>,
+    /// Optional interface number.
+    interface_number: PatternField<Option<u16>>,
+    /// The resource class, if any.
+    class: PatternField<Option<UsbClass>>,
+}
+
+impl UsbPattern {
+    /// Checks if the address is matched by this pattern.
+    pub fn matches(&self, addr: &UsbAddress) -> bool {
+        self.board == addr.board
+            && self.manufacturer_id.matches(&addr.manufacturer_id)
+            && self.model_code.matches(&addr.model_code)
+            && self.serial_number.matches(&addr.serial_number)
+            && self.interface_number.matches(&addr.interface_number)
+            && self.class.matches(&addr.class)
+    }
+}
+
+impl From<UsbAddress> for UsbPattern {
+    fn from(addr: UsbAddress) -> Self {
+        UsbPattern {
+            board: addr.board,
+            manufacturer_id: PatternField::Exactly(addr.manufacturer_id),
+            model_code: PatternField::Exactly(addr.model_code),
+            serial_number: PatternField::Exactly(addr.serial_number),
+            interface_number: PatternField::Exactly(addr.interface_number),
+            class: PatternField::Exactly(addr.class),
+        }
+    }
+}
+
+impl FromStr for UsbPattern {
+    type Err = UsbParseError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        // The interface wildcard may only be the final field, directly after the serial number.
+        // Otherwise a final "?*" is the serial number.
+        let (concrete, class) = match pattern.rsplit_once("::") {
+            Some((concrete, _)) if concrete.split("::").count() < 4 => (pattern, None),
+            Some((concrete, "?*")) => (concrete, Some(PatternField::Any)),
+            Some((concrete, tail)) if tail.starts_with("?*") => {
+                match UsbClass::from_keyword(&tail[2..]) {
+                    Some(class) => (concrete, Some(PatternField::Exactly(Some(class)))),
+                    None => (pattern, None),
+                }
+            }
+            _ => (pattern, None),
+        };
+
+        // The manufacturer ID, model code, and serial number may each be a wildcard.
+        // Wildcard codes are swapped for a placeholder so the rest is still parsed strictly.
+        let mut substituted = Vec::new();
+        let mut any = [false; 3];
+        let mut fields = Vec::new();
+        let mut offset = 0;
+        for (index, field) in concrete.split("::").enumerate() {
+            match index {
+                1 | 2 if field == "?*" => {
+                    substituted.push(offset);
+                    any[index - 1] = true;
+                    fields.push("0x0");
+                }
+                3 if field == "?*" => {
+                    any[2] = true;
+                    fields.push(field);
+                }
+                _ => fields.push(field),
+            }
+            offset += field.len() + 2;
+        }
+
+        let addr = UsbAddress::from_str(&fields.join("::"))
+            .map_err(|err| unsubstitute(err, pattern, &substituted))?;
+
+        let mut ret = UsbPattern::from(addr);
+        if any[0] {
+            ret.manufacturer_id = PatternField::Any;
+        }
+        if any[1] {
+            ret.model_code = PatternField::Any;
+        }
+        if any[2] {
+            ret.serial_number = PatternField::Any;
+        }
+
+        if let Some(class) = class {
+            if ret.interface_number != PatternField::Exactly(None)
+                || ret.class != PatternField::Exactly(None)
+            {
+                // The concrete part already ended, so the wildcard is superfluous.
+                return Err(UsbParseError::TrailingData {
+                    found: pattern[concrete.len()..].to_string(),
+                    addr: pattern.to_string(),
+                    start: concrete.len(),
+                    end: pattern.len(),
+                });
+            }
+
+            ret.interface_number = PatternField::Any;
+            ret.class = class;
+        }
+
+        Ok(ret)
+    }
+}
+
+/// Maps an error from parsing a pattern with placeholders back onto the original pattern.
+///
+/// `substituted` holds where each two character `?*` was swapped for the three character `0x0`.
+fn unsubstitute(mut err: UsbParseError, pattern: &str, substituted: &[usize]) -> UsbParseError {
+    use UsbParseError::*;
+
+    let unshift = |index: &mut usize| {
+        *index -= substituted.iter().filter(|&&at| *index > at + 2).count();
+    };
+
+    match &mut err {
+        NumParseError {
+            addr, start, end, ..
+        }
+        | InvalidBoard {
+            addr, start, end, ..
+        }
+        | SignedNumber {
+            addr, start, end, ..
+        }
+        | NotHex {
+            addr, start, end, ..
+        }
+        | EmptyHex {
+            addr, start, end, ..
+        }
+        | EmptyField {
+            addr, start, end, ..
+        }
+        | NotInstr {
+            addr, start, end, ..
+        }
+        | NotIntfc {
+            addr, start, end, ..
+        }
+        | UnsupportedClass {
+            addr, start, end, ..
+        }
+        | InvalidSeperator {
+            addr, start, end, ..
+        }
+        | TrailingData {
+            addr, start, end, ..
+        } => {
+            unshift(start);
+            unshift(end);
+            *addr = pattern.to_string();
+        }
+        NotUSB { start, end, .. } => {
+            unshift(start);
+            unshift(end);
+        }
+        ConfusableCharacter { position, .. } | UnexpectedControlChar { position, .. } => {
+            unshift(position)
+        }
+        IncompleteAddress(addr, _) => *addr = pattern.to_string(),
+        NotCanonical { .. } | NotUtf8(..) => {}
+    }
+    err
+}
+
+impl Display for UsbPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // USB[board]::manufacturer ID::model code::serial number[::USB interfacenumber][::INSTR]
+
+        write!(f, "USB")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+
+        match &self.manufacturer_id {
+            PatternField::Any => write!(f, "::?*")?,
+            PatternField::Exactly(id) => write!(f, "::{:#X}", id)?,
+        }
+        match &self.model_code {
+            PatternField::Any => write!(f, "::?*")?,
+            PatternField::Exactly(code) => write!(f, "::{:#X}", code)?,
+        }
+        match &self.serial_number {
+            PatternField::Any => write!(f, "::?*")?,
+            PatternField::Exactly(serial) => write!(f, "::{}", serial)?,
+        }
+
+        match (&self.interface_number, &self.class) {
+            (PatternField::Any, PatternField::Exactly(Some(class))) => write!(f, "::?*{}", class),
+            (PatternField::Any, _) => write!(f, "::?*"),
+            (PatternField::Exactly(interface), class) => {
+                if let Some(num) = interface {
+                    write!(f, "::{}", num)?
+                }
+                match class {
+                    PatternField::Any => write!(f, "::?*"),
+                    PatternField::Exactly(Some(class)) => write!(f, "::{}", class),
+                    PatternField::Exactly(None) => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Discovery patterns.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_pattern_any_tail() -> Result<(), UsbParseError> {
+        const PATTERN: &str = "USB0::0x1A34::0x5678::A22-5::?*";
+        let pattern = UsbPattern::from_str(PATTERN)?;
+
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5")));
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::INSTR")));
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::2")));
+        assert!(!pattern.matches(&usb("USB0::0x1A34::0x5678::A22-6")));
+        assert!(!pattern.matches(&usb("USB1::0x1A34::0x5678::A22-5")));
+        assert_eq!(pattern.to_string(), PATTERN);
+        Ok(())
+    }
+
+    #[test]
+    fn usb_pattern_any_instr() -> Result<(), UsbParseError> {
+        const PATTERN: &str = "USB0::0x1A34::0x5678::A22-5::?*INSTR";
+        let pattern = UsbPattern::from_str(PATTERN)?;
+
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::INSTR")));
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::7::INSTR")));
+        assert!(!pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::7")));
+        assert_eq!(pattern.to_string(), PATTERN);
+        assert_eq!(
+            UsbPattern::from_str("USB0::0x1A34::0x5678::A22-5::?*instr")?,
+            pattern
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usb_pattern_any_raw() -> Result<(), UsbParseError> {
+        const PATTERN: &str = "USB0::0x1A34::0x5678::A22-5::?*RAW";
+        let pattern = UsbPattern::from_str(PATTERN)?;
+
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::RAW")));
+        assert!(pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::1::RAW")));
+        assert!(!pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5::1::INSTR")));
+        assert!(!pattern.matches(&usb("USB0::0x1A34::0x5678::A22-5")));
+        assert_eq!(pattern.to_string(), PATTERN);
+        Ok(())
+    }
+
+    #[test]
+    fn usb_pattern_from_address() {
+        const ADDR: &str = "USB0::0x1A34::0x5678::A22-5::INSTR";
+        let pattern = usb(ADDR).into_pattern();
+
+        assert!(pattern.matches(&usb(ADDR)));
+        for other in [
+            "USB::0x1A34::0x5678::A22-5::INSTR",
+            "USB1::0x1A34::0x5678::A22-5::INSTR",
+            "USB0::0x1A35::0x5678::A22-5::INSTR",
+            "USB0::0x1A34::0x5679::A22-5::INSTR",
+            "USB0::0x1A34::0x5678::A22-6::INSTR",
+            "USB0::0x1A34::0x5678::A22-5::0::INSTR",
+            "USB0::0x1A34::0x5678::A22-5",
+        ] {
+            assert!(!pattern.matches(&usb(other)), "{other}");
+        }
+        assert_eq!(pattern.to_string(), ADDR);
+    }
+
+    #[test]
+    fn usb_pattern_wildcard_round_trip() -> Result<(), UsbParseError> {
+        let addr = usb("USB0::0x1234::0x5678::A22-5::INSTR");
+
+        for (pattern, matches) in [
+            ("USB0::0x1234::?*::?*::INSTR", true),
+            ("USB0::?*::0x5678::A22-5::?*", true),
+            ("USB0::?*::?*::?*::?*", true),
+            ("USB0::?*::?*::A22-6::?*INSTR", false),
+            ("USB0::0x1234::0x5678::?*", false),
+            ("USB::?*::?*::?*::?*INSTR", false),
+        ] {
+            let parsed = UsbPattern::from_str(pattern)?;
+            assert_eq!(parsed.to_string(), pattern);
+            assert_eq!(UsbPattern::from_str(&parsed.to_string())?, parsed);
+            assert_eq!(parsed.matches(&addr), matches, "{pattern}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn usb_pattern_wildcard_errors() {
+        // Spans still point into the pattern as written.
+        const PATTERN: &str = "USB0::?*::0x56G8::A22-5";
+        let err = UsbPattern::from_str(PATTERN).unwrap_err();
+        assert_eq!(err.span(), Some(10..16));
+        assert!(err.to_string().contains(PATTERN));
+
+        // Only whole fields are wildcards.
+        assert!(UsbPattern::from_str("USB0::0x?*::0x5678::A22-5").is_err());
+        assert!(UsbPattern::from_str("USB?*::0x1234::0x5678::A22-5").is_err());
+    }
+
+    #[test]
+    fn usb_pattern_concrete_is_strict() {
+        assert!(UsbAddress::from_str("USB0::0x1A34::0x5678::A22-5::?*INSTR").is_err());
+        assert!(matches!(
+            UsbPattern::from_str("USB0::0x1A34::0x5678::A22-5::INSTR::?*"),
+            Err(UsbParseError::TrailingData {
+                start: 34,
+                end: 38,
+                ..
+            })
+        ));
+        assert!(UsbPattern::from_str("USB0::0x1A34::?*").is_err());
+    }
+}
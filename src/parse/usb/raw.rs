@@ -0,0 +1,201 @@
+//! Module for losslessly parsed USB addresses.
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use super::{UsbAddress, UsbParseError};
+
+/// A USB address that remembers how it was written.
+///
+/// [`UsbAddress`] canonicalizes, so `0x1a34` is displayed as `0x1A34`. This keeps
+/// the original text of each field next to the parsed values, so an editor can load
+/// and re-save an address without rewriting it.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::RawUsbAddress;
+/// let addr = "USB07::0x1a34::0X5678::A22-5::instr";
+/// let raw: RawUsbAddress = addr.parse()?;
+/// assert_eq!(raw.to_string(), addr);
+/// assert_eq!(raw.manufacturer_id_text(), "0x1a34");
+/// assert_eq!(raw.address().to_string(), "USB7::0x1A34::0x5678::A22-5::INSTR");
+/// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct RawUsbAddress {
+    /// The parsed address.
+    address: UsbAddress,
+    /// The address exactly as given.
+    original: String,
+    /// Board digits, empty if there is no board.
+    board: Range<usize>,
+    /// Manufacturer ID, including the "0x".
+    manufacturer_id: Range<usize>,
+    /// Model code, including the "0x".
+    model_code: Range<usize>,
+    /// Serial number.
+    serial_number: Range<usize>,
+    /// Interface number, if any.
+    interface_number: Option<Range<usize>>,
+    /// Class suffix, if any.
+    class: Option<Range<usize>>,
+}
+
+impl RawUsbAddress {
+    /// The parsed, canonicalizing, address.
+    pub fn address(&self) -> &UsbAddress {
+        &self.address
+    }
+
+    /// The original text of the board number. Empty if there is no board.
+    pub fn board_text(&self) -> &str {
+        &self.original[self.board.clone()]
+    }
+
+    /// The original text of the manufacturer ID, including its "0x".
+    pub fn manufacturer_id_text(&self) -> &str {
+        &self.original[self.manufacturer_id.clone()]
+    }
+
+    /// The original text of the model code, including its "0x".
+    pub fn model_code_text(&self) -> &str {
+        &self.original[self.model_code.clone()]
+    }
+
+    /// The original text of the serial number, including any quotes.
+    pub fn serial_number_text(&self) -> &str {
+        &self.original[self.serial_number.clone()]
+    }
+
+    /// The original text of the interface number, if there is one.
+    pub fn interface_number_text(&self) -> Option<&str> {
+        self.interface_number
+            .clone()
+            .map(|range| &self.original[range])
+    }
+
+    /// The original text of the class suffix, such as "INSTR" or "RAW", if there is one.
+    pub fn class_text(&self) -> Option<&str> {
+        self.class.clone().map(|range| &self.original[range])
+    }
+}
+
+impl FromStr for RawUsbAddress {
+    type Err = UsbParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let parsed = UsbAddress::from_str(address)?;
+
+        // The address is known to be valid now, so every field is separated by "::",
+        // and only a quoted serial number can contain a colon.
+        let mut fields = Vec::with_capacity(6);
+        let mut start = 0;
+        for field in address.splitn(4, "::").take(3) {
+            fields.push(start..start + field.len());
+            start += field.len() + 2;
+        }
+        let serial_number = serial_span(address);
+        start = serial_number.end + 2;
+        fields.push(serial_number);
+        if start <= address.len() {
+            for field in address[start..].split("::") {
+                fields.push(start..start + field.len());
+                start += field.len() + 2;
+            }
+        }
+
+        let mut tail = fields.split_off(4).into_iter();
+        let interface_number = match parsed.interface_number {
+            Some(_) => tail.next(),
+            None => None,
+        };
+        let class = match parsed.class {
+            Some(_) => tail.next(),
+            None => None,
+        };
+
+        Ok(RawUsbAddress {
+            board: "USB".len()..fields[0].end,
+            manufacturer_id: fields[1].clone(),
+            model_code: fields[2].clone(),
+            serial_number: fields[3].clone(),
+            interface_number,
+            class,
+            address: parsed,
+            original: address.to_string(),
+        })
+    }
+}
+
+/// Where the serial number is in a valid address, including any quotes.
+pub(super) fn serial_span(address: &str) -> Range<usize> {
+    let start: usize = address
+        .splitn(4, "::")
+        .take(3)
+        .map(|field| field.len() + 2)
+        .sum();
+    let len = match address[start..].strip_prefix('"') {
+        // Closing quote included
+        Some(quoted) => quoted.find('"').map_or(0, |end| end + 2),
+        None => address[start..].find("::").unwrap_or(address.len() - start),
+    };
+    start..start + len
+}
+
+impl Display for RawUsbAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Lossless round trips.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_raw_round_trip() -> Result<(), UsbParseError> {
+        const ADDR: &str = "USB034::0x12a4::0Xff1a::a22-5::0012314::Instr";
+        let raw = RawUsbAddress::from_str(ADDR)?;
+
+        assert_eq!(raw.to_string(), ADDR);
+        assert_ne!(raw.address().to_string(), ADDR);
+        assert_eq!(raw.board_text(), "034");
+        assert_eq!(raw.manufacturer_id_text(), "0x12a4");
+        assert_eq!(raw.model_code_text(), "0Xff1a");
+        assert_eq!(raw.serial_number_text(), "a22-5");
+        assert_eq!(raw.interface_number_text(), Some("0012314"));
+        assert_eq!(raw.class_text(), Some("Instr"));
+        assert_eq!(
+            raw.address(),
+            &usb("USB34::0x12A4::0xFF1A::a22-5::12314::INSTR")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn usb_raw_quoted_serial() -> Result<(), UsbParseError> {
+        const ADDR: &str = "USB::0x1a34::0x5678::\"A22::5\"::3::instr";
+        let raw = RawUsbAddress::from_str(ADDR)?;
+
+        assert_eq!(raw.to_string(), ADDR);
+        assert_eq!(raw.serial_number_text(), "\"A22::5\"");
+        assert_eq!(raw.interface_number_text(), Some("3"));
+        assert_eq!(raw.class_text(), Some("instr"));
+        Ok(())
+    }
+
+    #[test]
+    fn usb_raw_minimal() -> Result<(), UsbParseError> {
+        let raw = RawUsbAddress::from_str("USB::0x1a34::0x5678::A22-5")?;
+
+        assert_eq!(raw.board_text(), "");
+        assert_eq!(raw.interface_number_text(), None);
+        assert_eq!(raw.class_text(), None);
+
+        let raw = RawUsbAddress::from_str("USB::0x1a34::0x5678::A22-5::instr")?;
+        assert_eq!(raw.interface_number_text(), None);
+        assert_eq!(raw.class_text(), Some("instr"));
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Module for field by field validation of USB addresses, for validating as the user types.
+use std::str::FromStr;
+
+use super::{has_sign, is_class_field, split_fields, UsbAddress, UsbClass};
+use crate::parse::strip_keyword;
+
+/// The state of one field of an address. See [`validate_fields`].
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum FieldStatus {
+    /// The field is valid, or it is optional and left out.
+    Ok,
+    /// The field is required, but not written yet.
+    Missing,
+    /// The field is written, but invalid.
+    Invalid,
+}
+
+/// The status of every field of a possibly partial USB address. See [`validate_fields`].
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct FieldReport {
+    /// The "USB" prefix and board number
+    pub board: FieldStatus,
+    /// The manufacturer ID
+    pub vendor: FieldStatus,
+    /// The model code
+    pub model: FieldStatus,
+    /// The serial number
+    pub serial: FieldStatus,
+    /// The interface number
+    pub interface: FieldStatus,
+    /// The resource class
+    pub class: FieldStatus,
+}
+
+impl FieldReport {
+    /// Checks if every field is valid, in which case the address parses.
+    pub fn is_ok(&self) -> bool {
+        [
+            self.board,
+            self.vendor,
+            self.model,
+            self.serial,
+            self.interface,
+            self.class,
+        ]
+        .iter()
+        .all(|status| *status == FieldStatus::Ok)
+    }
+}
+
+/// Validates each field of an address on its own, so a partial address still gets a status
+/// for every field. This is for showing which field needs attention in a UI, the address should
+/// still be parsed with [`UsbAddress::try_new`] once it is complete.
+///
+/// The fields are split the way the parser walks them, and the parser has the final say:
+/// the report is all [`Ok`](FieldStatus::Ok) exactly when the address parses. When the parser
+/// rejects an address whose fields each look fine, the field its error points at is
+/// [`Invalid`](FieldStatus::Invalid).
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::usb::{validate_fields, FieldStatus};
+/// let report = validate_fields("USB0::0x1A34::0x56G");
+/// assert_eq!(report.vendor, FieldStatus::Ok);
+/// assert_eq!(report.model, FieldStatus::Invalid);
+/// assert_eq!(report.serial, FieldStatus::Missing);
+/// ```
+pub fn validate_fields(input: &str) -> FieldReport {
+    use FieldStatus::*;
+
+    let fields = split_fields(input);
+    let field = |index: usize| fields.get(index).map(|(_, field)| *field);
+
+    let board = match field(0) {
+        Some(prefix) => match strip_keyword(prefix, "USB") {
+            Some(board) if board.chars().all(|char| char.is_ascii_digit()) => {
+                if board.is_empty() || board.parse::<u32>().is_ok() {
+                    Ok
+                } else {
+                    Invalid
+                }
+            }
+            Some(_) => Invalid,
+            // Still being typed
+            None if "USB".starts_with(&prefix.to_ascii_uppercase()) => Missing,
+            None => Invalid,
+        },
+        None => Missing,
+    };
+
+    let hex = |code: Option<&str>| match code {
+        None | Some("") => Missing,
+        Some(code) => match code
+            .strip_prefix("0x")
+            .or_else(|| code.strip_prefix("0X"))
+            .filter(|digits| !has_sign(digits))
+            .map(|digits| u16::from_str_radix(digits, 16))
+        {
+            Some(result) if result.is_ok() => Ok,
+            _ => Invalid,
+        },
+    };
+
+    let serial = match field(3) {
+        None | Some("") | Some("\"\"") => Missing,
+        // An unclosed quote
+        Some(serial) if serial.starts_with('"') && (serial.len() < 2 || !serial.ends_with('"')) => {
+            Invalid
+        }
+        Some(_) => Ok,
+    };
+
+    let (interface, class) = match (field(4), field(5), fields.len() > 6) {
+        (None, _, _) => (Ok, Ok),
+        (Some(class), None, _) if is_class_field(class) => (Ok, status_of_class(class)),
+        (Some(interface), class, extra) => (
+            match interface.parse::<u16>() {
+                Result::Ok(_) if !has_sign(interface) => Ok,
+                _ => Invalid,
+            },
+            match class {
+                _ if extra => Invalid,
+                None => Ok,
+                Some(class) => status_of_class(class),
+            },
+        ),
+    };
+
+    let mut report = FieldReport {
+        board,
+        vendor: hex(field(1)),
+        model: hex(field(2)),
+        serial,
+        interface,
+        class,
+    };
+
+    if report.is_ok() {
+        if let Err(err) = UsbAddress::from_str(input) {
+            // Blame the field the error points at, or the last one written.
+            let at = err.span().map_or(input.len(), |span| span.start);
+            let index = fields
+                .iter()
+                .rposition(|(offset, _)| *offset <= at)
+                .unwrap_or(0);
+            *report.field_mut(index) = Invalid;
+        }
+    }
+
+    report
+}
+
+impl FieldReport {
+    /// The status of a field, by its position in the address. The class is last, even when
+    /// the interface number is left out.
+    fn field_mut(&mut self, index: usize) -> &mut FieldStatus {
+        match index {
+            0 => &mut self.board,
+            1 => &mut self.vendor,
+            2 => &mut self.model,
+            3 => &mut self.serial,
+            4 => &mut self.interface,
+            _ => &mut self.class,
+        }
+    }
+}
+
+/// The status of the resource class field, which may only be INSTR or RAW for USB.
+fn status_of_class(class: &str) -> FieldStatus {
+    match UsbClass::from_keyword(class) {
+        Some(_) => FieldStatus::Ok,
+        None => FieldStatus::Invalid,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Field reports for partial addresses.
+    use super::*;
+    use crate::testing::{mutate, random_usb_address, Rng, SEED};
+    use FieldStatus::*;
+
+    /// Shorthand for building a report
+    fn report(statuses: [FieldStatus; 6]) -> FieldReport {
+        let [board, vendor, model, serial, interface, class] = statuses;
+        FieldReport {
+            board,
+            vendor,
+            model,
+            serial,
+            interface,
+            class,
+        }
+    }
+
+    #[test]
+    fn usb_report_partial() {
+        for (input, expected) in [
+            ("", [Missing, Missing, Missing, Missing, Ok, Ok]),
+            ("US", [Missing, Missing, Missing, Missing, Ok, Ok]),
+            ("USB0", [Ok, Missing, Missing, Missing, Ok, Ok]),
+            ("USBx::", [Invalid, Missing, Missing, Missing, Ok, Ok]),
+            ("USB0::0x1A34", [Ok, Ok, Missing, Missing, Ok, Ok]),
+            ("USB0::0x1A34::0x", [Ok, Ok, Invalid, Missing, Ok, Ok]),
+            ("USB0::1A34::0x5678::", [Ok, Invalid, Ok, Missing, Ok, Ok]),
+            ("USB0::0x1A34::0x5678::A22-5", [Ok, Ok, Ok, Ok, Ok, Ok]),
+            (
+                "USB0::0x1A34::0x5678::\"A22::5",
+                [Ok, Ok, Ok, Invalid, Ok, Ok],
+            ),
+            (
+                "USB0::0x1A34::0x5678::A22-5::INS",
+                [Ok, Ok, Ok, Ok, Ok, Invalid],
+            ),
+            (
+                "USB0::0x1A34::0x5678::A22-5::x::INSTR",
+                [Ok, Ok, Ok, Ok, Invalid, Ok],
+            ),
+            (
+                "USB0::0x1A34::0x5678::A22-5::3::RAW",
+                [Ok, Ok, Ok, Ok, Ok, Ok],
+            ),
+            (
+                "USB0::0x1A34::0x5678::A22-5::3::SOCKET",
+                [Ok, Ok, Ok, Ok, Ok, Invalid],
+            ),
+            ("usb0::0x1A34::0x5678::A22-5", [Ok, Ok, Ok, Ok, Ok, Ok]),
+            (
+                "USB0::0x1A34::0x5678::A22-5::3::INSTR::",
+                [Ok, Ok, Ok, Ok, Ok, Invalid],
+            ),
+        ] {
+            assert_eq!(validate_fields(input), report(expected), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn usb_report_agrees_with_parser() {
+        let mut rng = Rng::new(SEED);
+
+        for _ in 0..5_000 {
+            let mut input = random_usb_address(&mut rng);
+            if rng.below(2) == 0 {
+                input = mutate(&mut rng, &input);
+            }
+            assert_eq!(
+                UsbAddress::from_str(&input).is_ok(),
+                validate_fields(&input).is_ok(),
+                "{input:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Module for converting USB addresses to and from `visa+usb://` URLs.
+//!
+//! The vendor and product IDs are the host, the serial number is the path,
+//! and the optional fields are query parameters:
+//! `visa+usb://0x1A34.0x5678/A22-5?board=0&interface=3&class=INSTR`
+use std::fmt::Write;
+
+use percent_encoding::percent_decode_str;
+use thiserror::Error;
+use url::Url;
+
+use super::{write_serial, UsbAddress, UsbParseError};
+
+/// The URL scheme for USB addresses.
+const SCHEME: &str = "visa+usb";
+
+/// Errors that can return from converting a URL to a USB address.
+#[derive(Error, Debug)]
+pub enum UsbUrlError {
+    /// When the URL is not a `visa+usb` URL.
+    #[error("Expected the \"visa+usb\" scheme, found {0:?}")]
+    Scheme(String),
+
+    /// When the host is not the vendor and product IDs.
+    #[error(
+        "Expected the vendor and product IDs as the host, such as \"0x1A34.0x5678\", found {0:?}"
+    )]
+    Host(String),
+
+    /// When the path is not exactly one serial number.
+    #[error("Expected the serial number as the path, found {0:?}")]
+    Serial(String),
+
+    /// When a query parameter is unknown, repeated, or invalid.
+    #[error("Invalid query parameter {key:?} = {value:?}")]
+    Query {
+        /// The parameter name
+        key: String,
+        /// The parameter value
+        value: String,
+    },
+
+    /// When the URL is well-formed, but the address it describes is not.
+    #[error(transparent)]
+    Address(#[from] UsbParseError),
+}
+
+impl UsbAddress {
+    /// The address as a `visa+usb://` URL. The serial number is percent-encoded, so one
+    /// containing `%`, `?`, `#`, or `/` survives the round trip back to an address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::usb::UsbAddress;
+    /// let addr = UsbAddress::try_new("USB0::0x1A34::0x5678::A22-5::INSTR")?;
+    /// let url = addr.to_url();
+    /// assert_eq!(url.as_str(), "visa+usb://0x1A34.0x5678/A22-5?board=0&class=INSTR");
+    /// assert_eq!(UsbAddress::try_from(&url)?, addr);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_url(&self) -> Url {
+        let mut url = Url::parse(&format!(
+            "{SCHEME}://{:#X}.{:#X}/",
+            self.manufacturer_id, self.model_code
+        ))
+        .expect("hex IDs are a valid host");
+
+        url.path_segments_mut()
+            .expect("URLs with a host have a path")
+            .pop()
+            .push(&self.serial_number);
+
+        if self.board.is_some() || self.interface_number.is_some() || self.class.is_some() {
+            let mut query = url.query_pairs_mut();
+            if let Some(board) = self.board {
+                query.append_pair("board", &board.to_string());
+            }
+            if let Some(interface) = self.interface_number {
+                query.append_pair("interface", &interface.to_string());
+            }
+            if let Some(class) = self.class {
+                query.append_pair("class", class.as_str());
+            }
+        }
+
+        url
+    }
+}
+
+impl TryFrom<&Url> for UsbAddress {
+    type Error = UsbUrlError;
+
+    fn try_from(url: &Url) -> Result<Self, Self::Error> {
+        if url.scheme() != SCHEME {
+            return Err(UsbUrlError::Scheme(url.scheme().to_string()));
+        }
+
+        let host = url.host_str().unwrap_or_default();
+        let (vendor, product) = host
+            .split_once('.')
+            .filter(|(vendor, product)| !vendor.is_empty() && !product.is_empty())
+            .ok_or_else(|| UsbUrlError::Host(host.to_string()))?;
+
+        let serial = match url.path().strip_prefix('/') {
+            Some(serial) if !serial.is_empty() && !serial.contains('/') => {
+                percent_decode_str(serial)
+                    .decode_utf8()
+                    .map_err(|_| UsbUrlError::Serial(url.path().to_string()))?
+            }
+            _ => return Err(UsbUrlError::Serial(url.path().to_string())),
+        };
+
+        let (mut board, mut interface, mut class) = (None, None, None);
+        for (key, value) in url.query_pairs() {
+            let slot = match key.as_ref() {
+                "board" => Some(&mut board),
+                "interface" => Some(&mut interface),
+                "class" => Some(&mut class),
+                _ => None,
+            };
+            match slot {
+                Some(slot) if slot.is_none() => *slot = Some(value),
+                // Unknown and repeated parameters are both errors.
+                _ => {
+                    return Err(UsbUrlError::Query {
+                        key: key.into_owned(),
+                        value: value.into_owned(),
+                    })
+                }
+            }
+        }
+
+        // Every field is validated by the address parser.
+        let mut addr = format!("USB{}::{vendor}::{product}::", board.unwrap_or_default());
+        // Writing to a String cannot fail
+        let _ = write_serial(&mut addr, &serial);
+        if let Some(interface) = interface {
+            let _ = write!(addr, "::{interface}");
+        }
+        if let Some(class) = class {
+            let _ = write!(addr, "::{class}");
+        }
+
+        Ok(addr.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! URL round trips.
+    use super::*;
+    use crate::testing::usb;
+
+    #[test]
+    fn usb_url_round_trip() -> Result<(), UsbUrlError> {
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5",
+            "USB0::0x1A34::0x5678::A22-5::INSTR",
+            "USB34::0x12A4::0xFF1A::A22-5::12314::INSTR",
+            "USB::0x1::0x2::\"A22 5/6?#\"::3",
+        ] {
+            let addr = usb(addr);
+            let url = addr.to_url();
+            assert_eq!(UsbAddress::try_from(&url)?, addr, "{url}");
+            assert_eq!(
+                UsbAddress::try_from(&Url::parse(url.as_str()).unwrap())?,
+                addr
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn usb_url_special_serials() -> Result<(), UsbUrlError> {
+        for serial in [
+            "A22%5", "A22?5", "A22#5", "A22&5", "A22/5", "%2F", "A%20?&#/",
+        ] {
+            let addr = usb(&format!("USB::0x1A34::0x5678::{serial}::INSTR"));
+            let url = addr.to_url();
+
+            let path = url.path();
+            assert!(!path[1..].contains(['/', '?', '#']), "{url}");
+            assert_eq!(UsbAddress::try_from(&url)?, addr, "{url}");
+            assert_eq!(
+                UsbAddress::try_from(&Url::parse(url.as_str()).unwrap())?.serial_number,
+                serial
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn usb_url_errors() {
+        for (url, expected) in [
+            ("http://example.com/A22-5", "Expected the \"visa+usb\" scheme, found \"http\""),
+            ("visa+usb://0x1A34/A22-5", "Expected the vendor and product IDs as the host, such as \"0x1A34.0x5678\", found \"0x1A34\""),
+            ("visa+usb://0x1A34.0x5678/", "Expected the serial number as the path, found \"/\""),
+            ("visa+usb://0x1A34.0x5678/A22/5", "Expected the serial number as the path, found \"/A22/5\""),
+            ("visa+usb://0x1A34.0x5678/A22-5?speed=fast", "Invalid query parameter \"speed\" = \"fast\""),
+            ("visa+usb://0x1A34.0x5678/A22-5?board=1&board=2", "Invalid query parameter \"board\" = \"2\""),
+            ("visa+usb://0x1A34.0x5678/A22-5?board=one", "Found \"one\" instead of a number at position 3 to 6 of \n\"USBone::0x1A34::0x5678::A22-5\""),
+        ] {
+            let err = UsbAddress::try_from(&Url::parse(url).unwrap()).unwrap_err();
+            assert_eq!(err.to_string(), expected, "{url}");
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! Module for addresses of any interface type.
+use std::{collections::BTreeMap, fmt::Display, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{
+    asrl::{AsrlAddress, AsrlParseError},
+    gpib::{GpibAddress, GpibParseError},
+    pxi::{PxiAddress, PxiParseError},
+    tcpip::{TcpipAddress, TcpipParseError, TcpipSocketAddress},
+    usb::{UsbAddress, UsbParseError},
+    vxi::{VxiAddress, VxiParseError},
+    ResourceClass,
+};
+
+/// Interface type prefixes, of supported types and not.
+/// "GPIB-VXI" comes before "GPIB" so that it is matched first.
+const INTERFACES: [&str; 7] = ["USB", "TCPIP", "GPIB-VXI", "GPIB", "ASRL", "VXI", "PXI"];
+
+/// A summary of validating many addresses of any interface type. See [`VisaAddress::validate_all`].
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// How many addresses were valid, by interface type, such as "USB".
+    pub valid: BTreeMap<&'static str, usize>,
+    /// How many addresses were invalid, by interface type and kind of error, such as `("USB", "NotHex")`.
+    ///
+    /// Interface types that cannot be parsed yet have the error kind "Unsupported",
+    /// and addresses with no known interface type are `("unknown", "UnknownInterface")`.
+    pub invalid: BTreeMap<(&'static str, &'static str), usize>,
+}
+
+impl ValidationReport {
+    /// How many addresses were valid in total.
+    pub fn total_valid(&self) -> usize {
+        self.valid.values().sum()
+    }
+
+    /// How many addresses were invalid in total.
+    pub fn total_invalid(&self) -> usize {
+        self.invalid.values().sum()
+    }
+}
+
+impl Display for ValidationReport {
+    /// One line per interface type and error kind, such as "120 USB ok" or "3 GPIB-VXI errors (Unsupported)".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (interface, count) in &self.valid {
+            writeln!(f, "{count} {interface} ok")?;
+        }
+        for ((interface, kind), count) in &self.invalid {
+            let errors = if *count == 1 { "error" } else { "errors" };
+            writeln!(f, "{count} {interface} {errors} ({kind})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the last field of the address is the resource class, case-insensitively.
+fn has_class(address: &str, class: &str) -> bool {
+    address
+        .rsplit("::")
+        .next()
+        .is_some_and(|last| last.eq_ignore_ascii_case(class))
+}
+
+/// A VISA address of any supported interface type.
+///
+/// Parsing picks the interface type from the start of the address, so this is the one
+/// entry point for resource strings of unknown type, such as from a config file.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::{usb::UsbAddress, VisaAddress};
+/// let addr = VisaAddress::from(UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?);
+/// assert_eq!(addr.to_string(), "USB::0x1A34::0x5678::A22-5");
+///
+/// let addr: VisaAddress = "GPIB0::22::INSTR".parse()?;
+/// assert!(matches!(addr, VisaAddress::Gpib(_)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum VisaAddress {
+    /// A USB address
+    Usb(UsbAddress),
+    /// A GPIB address
+    Gpib(GpibAddress),
+    /// A serial port address
+    Asrl(AsrlAddress),
+    /// A TCPIP address
+    Tcpip(TcpipAddress),
+    /// A TCPIP raw socket address
+    TcpipSocket(TcpipSocketAddress),
+    /// A VXI address
+    Vxi(VxiAddress),
+    /// A PXI address
+    Pxi(PxiAddress),
+}
+
+impl VisaAddress {
+    /// The board number, if the address has one, whatever the interface type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, VisaAddress};
+    /// let addr = VisaAddress::from(UsbAddress::try_new("USB2::0x1A34::0x5678::A22-5")?);
+    /// assert_eq!(addr.board(), Some(2));
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    pub fn board(&self) -> Option<u32> {
+        match self {
+            VisaAddress::Usb(addr) => addr.board(),
+            VisaAddress::Gpib(addr) => addr.board(),
+            VisaAddress::Asrl(addr) => addr.board(),
+            VisaAddress::Tcpip(addr) => addr.board(),
+            VisaAddress::TcpipSocket(addr) => addr.board(),
+            VisaAddress::Vxi(addr) => addr.board(),
+            VisaAddress::Pxi(addr) => addr.board(),
+        }
+    }
+
+    /// The same address, but on the given board, whatever the interface type.
+    /// For moving a resource to a different controller.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{usb::UsbAddress, VisaAddress};
+    /// let addr = VisaAddress::from(UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?);
+    /// assert_eq!(addr.with_board(1).to_string(), "USB1::0x1A34::0x5678::A22-5");
+    /// # Ok::<(), fisa::parse::usb::UsbParseError>(())
+    /// ```
+    #[must_use]
+    pub fn with_board(self, board: u32) -> Self {
+        match self {
+            VisaAddress::Usb(addr) => VisaAddress::Usb(addr.with_board(board)),
+            VisaAddress::Gpib(addr) => VisaAddress::Gpib(addr.with_board(board)),
+            VisaAddress::Asrl(addr) => VisaAddress::Asrl(addr.with_board(board)),
+            VisaAddress::Tcpip(addr) => VisaAddress::Tcpip(addr.with_board(board)),
+            VisaAddress::TcpipSocket(addr) => VisaAddress::TcpipSocket(addr.with_board(board)),
+            VisaAddress::Vxi(addr) => VisaAddress::Vxi(addr.with_board(board)),
+            VisaAddress::Pxi(addr) => VisaAddress::Pxi(addr.with_board(board)),
+        }
+    }
+
+    /// Validates many addresses of any interface type, such as the lines of a config file,
+    /// and summarizes the results. Blank lines are skipped.
+    ///
+    /// Each address is parsed the same way as [`VisaAddress`]'s `FromStr`,
+    /// with the default [`ParseOptions`](super::ParseOptions).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::VisaAddress;
+    /// let report = VisaAddress::validate_all(&[
+    ///     "USB::0x1A34::0x5678::A22-5",
+    ///     "TCPIP::10.0.0.1::INSTR",
+    ///     "GPIB-VXI0::1::INSTR",
+    /// ]);
+    /// assert_eq!(report.to_string(), "1 TCPIP ok\n1 USB ok\n1 GPIB-VXI error (Unsupported)\n");
+    /// ```
+    pub fn validate_all(lines: &[&str]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            match VisaAddress::from_str(line) {
+                Ok(addr) => *report.valid.entry(addr.interface()).or_default() += 1,
+                Err(err) => {
+                    *report
+                        .invalid
+                        .entry((err.interface(), err.kind()))
+                        .or_default() += 1
+                }
+            }
+        }
+
+        report
+    }
+
+    /// The interface type of the address, as written at its start, such as "USB".
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::VisaAddress;
+    /// let addr: VisaAddress = "TCPIP0::10.0.0.2::5025::SOCKET".parse()?;
+    /// assert_eq!(addr.interface(), "TCPIP");
+    /// # Ok::<(), fisa::parse::VisaParseError>(())
+    /// ```
+    pub fn interface(&self) -> &'static str {
+        match self {
+            VisaAddress::Usb(_) => "USB",
+            VisaAddress::Gpib(_) => "GPIB",
+            VisaAddress::Asrl(_) => "ASRL",
+            VisaAddress::Tcpip(_) | VisaAddress::TcpipSocket(_) => "TCPIP",
+            VisaAddress::Vxi(_) => "VXI",
+            VisaAddress::Pxi(_) => "PXI",
+        }
+    }
+
+    /// The resource class, if the address has one, whatever the interface type.
+    pub fn class(&self) -> Option<ResourceClass> {
+        match self {
+            VisaAddress::Usb(addr) => addr.class().map(ResourceClass::from),
+            VisaAddress::Gpib(addr) => addr.class(),
+            VisaAddress::Asrl(addr) => addr.class(),
+            VisaAddress::Tcpip(addr) => addr.class(),
+            VisaAddress::TcpipSocket(addr) => Some(addr.class()),
+            VisaAddress::Vxi(addr) => addr.class(),
+            VisaAddress::Pxi(addr) => addr.class(),
+        }
+    }
+
+    /// The resource class of the address, where a missing class is INSTR as VISA takes it to be.
+    /// For filtering resources by class, whatever their interface type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{ResourceClass, VisaAddress};
+    /// let addrs = [
+    ///     "USB::0x1A34::0x5678::A22-5",
+    ///     "USB::0x1A34::0x5678::A22-5::RAW",
+    ///     "TCPIP0::10.0.0.2::5025::SOCKET",
+    ///     "GPIB0::22::INSTR",
+    /// ]
+    /// .map(|addr| addr.parse::<VisaAddress>());
+    /// let instr = addrs
+    ///     .iter()
+    ///     .flatten()
+    ///     .filter(|addr| addr.resource_class() == ResourceClass::Instr)
+    ///     .count();
+    /// assert_eq!(instr, 2);
+    /// ```
+    pub fn resource_class(&self) -> ResourceClass {
+        self.class().unwrap_or(ResourceClass::Instr)
+    }
+
+    /// A key for sorting mixed addresses, grouped by interface type and then by their canonical form.
+    ///
+    /// The interface types are ranked in the order that the VISA specification lists them:
+    ///
+    /// | Rank | Interface |
+    /// |------|-----------|
+    /// | 0    | USB       |
+    /// | 1    | GPIB      |
+    /// | 2    | ASRL      |
+    /// | 3    | TCPIP     |
+    /// | 4    | VXI       |
+    /// | 5    | PXI       |
+    ///
+    /// Ranks are never reused, so interfaces that are not supported yet keep their place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fisa::parse::{tcpip::TcpipAddress, usb::UsbAddress, VisaAddress};
+    /// let mut addrs = vec![
+    ///     VisaAddress::from(TcpipAddress::try_new("TCPIP::1.2.3.4::INSTR")?),
+    ///     VisaAddress::from(UsbAddress::try_new("USB::0x1A34::0x5678::A22-5")?),
+    /// ];
+    /// addrs.sort_by_key(VisaAddress::sort_key);
+    /// assert!(matches!(addrs[0], VisaAddress::Usb(_)));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn sort_key(&self) -> (u8, String) {
+        let rank = match self {
+            VisaAddress::Usb(_) => 0,
+            VisaAddress::Gpib(_) => 1,
+            VisaAddress::Asrl(_) => 2,
+            VisaAddress::Tcpip(_) | VisaAddress::TcpipSocket(_) => 3,
+            VisaAddress::Vxi(_) => 4,
+            VisaAddress::Pxi(_) => 5,
+        };
+        (rank, self.to_string())
+    }
+}
+
+impl From<UsbAddress> for VisaAddress {
+    fn from(addr: UsbAddress) -> Self {
+        VisaAddress::Usb(addr)
+    }
+}
+
+impl From<GpibAddress> for VisaAddress {
+    fn from(addr: GpibAddress) -> Self {
+        VisaAddress::Gpib(addr)
+    }
+}
+
+impl From<AsrlAddress> for VisaAddress {
+    fn from(addr: AsrlAddress) -> Self {
+        VisaAddress::Asrl(addr)
+    }
+}
+
+impl From<TcpipAddress> for VisaAddress {
+    fn from(addr: TcpipAddress) -> Self {
+        VisaAddress::Tcpip(addr)
+    }
+}
+
+impl From<TcpipSocketAddress> for VisaAddress {
+    fn from(addr: TcpipSocketAddress) -> Self {
+        VisaAddress::TcpipSocket(addr)
+    }
+}
+
+impl From<VxiAddress> for VisaAddress {
+    fn from(addr: VxiAddress) -> Self {
+        VisaAddress::Vxi(addr)
+    }
+}
+
+impl From<PxiAddress> for VisaAddress {
+    fn from(addr: PxiAddress) -> Self {
+        VisaAddress::Pxi(addr)
+    }
+}
+
+impl FromStr for VisaAddress {
+    type Err = VisaParseError;
+
+    /// Parses an address of any supported interface type, picking the parser by the
+    /// address's prefix. The prefix is matched case-insensitively, so that an address
+    /// such as "usb::..." gets the USB parser's error rather than an unknown interface.
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let interface = INTERFACES.into_iter().find(|prefix| {
+            address
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        });
+
+        match interface {
+            Some("USB") => Ok(UsbAddress::from_str(address)?.into()),
+            Some("GPIB") => Ok(GpibAddress::from_str(address)?.into()),
+            Some("ASRL") => Ok(AsrlAddress::from_str(address)?.into()),
+            Some("TCPIP") if has_class(address, "SOCKET") => {
+                Ok(TcpipSocketAddress::from_str(address)?.into())
+            }
+            Some("TCPIP") => Ok(TcpipAddress::from_str(address)?.into()),
+            Some("VXI") => Ok(VxiAddress::from_str(address)?.into()),
+            Some("PXI") => Ok(PxiAddress::from_str(address)?.into()),
+            Some(interface) => Err(VisaParseError::Unsupported {
+                interface,
+                addr: address.to_string(),
+            }),
+            None => Err(VisaParseError::UnknownInterface(address.to_string())),
+        }
+    }
+}
+
+/// Errors that can return from parsing an address of any interface type.
+#[derive(Error, Debug)]
+pub enum VisaParseError {
+    /// When a USB address is invalid.
+    #[error(transparent)]
+    Usb(#[from] UsbParseError),
+
+    /// When a GPIB address is invalid.
+    #[error(transparent)]
+    Gpib(#[from] GpibParseError),
+
+    /// When a serial port address is invalid.
+    #[error(transparent)]
+    Asrl(#[from] AsrlParseError),
+
+    /// When a TCPIP address is invalid, of either the INSTR or SOCKET class.
+    #[error(transparent)]
+    Tcpip(#[from] TcpipParseError),
+
+    /// When a VXI address is invalid.
+    #[error(transparent)]
+    Vxi(#[from] VxiParseError),
+
+    /// When a PXI address is invalid.
+    #[error(transparent)]
+    Pxi(#[from] PxiParseError),
+
+    /// When the interface type is a VISA one, but cannot be parsed yet.
+    #[error("{interface} addresses are not supported, found {addr:?}")]
+    Unsupported {
+        /// The interface type, such as "GPIB-VXI"
+        interface: &'static str,
+        /// The full address
+        addr: String,
+    },
+
+    /// When the address does not start with any VISA interface type.
+    #[error("Expected an interface type such as \"USB\" or \"TCPIP\" at the start of {0:?}")]
+    UnknownInterface(String),
+}
+
+impl VisaParseError {
+    /// The interface type of the address that failed to parse, such as "USB".
+    /// "unknown" if it has none.
+    pub fn interface(&self) -> &'static str {
+        match self {
+            VisaParseError::Usb(_) => "USB",
+            VisaParseError::Gpib(_) => "GPIB",
+            VisaParseError::Asrl(_) => "ASRL",
+            VisaParseError::Tcpip(_) => "TCPIP",
+            VisaParseError::Vxi(_) => "VXI",
+            VisaParseError::Pxi(_) => "PXI",
+            VisaParseError::Unsupported { interface, .. } => interface,
+            VisaParseError::UnknownInterface(_) => "unknown",
+        }
+    }
+
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            VisaParseError::Usb(err) => err.span(),
+            VisaParseError::Gpib(err) => err.span(),
+            VisaParseError::Asrl(err) => err.span(),
+            VisaParseError::Tcpip(err) => err.span(),
+            VisaParseError::Vxi(err) => err.span(),
+            VisaParseError::Pxi(err) => err.span(),
+            VisaParseError::Unsupported { .. } | VisaParseError::UnknownInterface(_) => None,
+        }
+    }
+
+    /// The name of the kind of the underlying error, such as "NotHex", for [`ValidationReport`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            VisaParseError::Usb(err) => err.kind(),
+            VisaParseError::Gpib(err) => err.kind(),
+            VisaParseError::Asrl(err) => err.kind(),
+            VisaParseError::Tcpip(err) => err.kind(),
+            VisaParseError::Vxi(err) => err.kind(),
+            VisaParseError::Pxi(err) => err.kind(),
+            VisaParseError::Unsupported { .. } => "Unsupported",
+            VisaParseError::UnknownInterface(_) => "UnknownInterface",
+        }
+    }
+}
+
+impl Display for VisaAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VisaAddress::Usb(addr) => addr.fmt(f),
+            VisaAddress::Gpib(addr) => addr.fmt(f),
+            VisaAddress::Asrl(addr) => addr.fmt(f),
+            VisaAddress::Tcpip(addr) => addr.fmt(f),
+            VisaAddress::TcpipSocket(addr) => addr.fmt(f),
+            VisaAddress::Vxi(addr) => addr.fmt(f),
+            VisaAddress::Pxi(addr) => addr.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Mixed address handling.
+    use super::*;
+    use crate::testing;
+
+    /// Parses a TCPIP address for tests
+    fn tcpip(addr: &str) -> VisaAddress {
+        VisaAddress::Tcpip(TcpipAddress::try_new(addr).unwrap())
+    }
+
+    /// Parses a GPIB address for tests
+    fn gpib(addr: &str) -> VisaAddress {
+        VisaAddress::Gpib(GpibAddress::try_new(addr).unwrap())
+    }
+
+    /// Parses a USB address for tests
+    fn usb(addr: &str) -> VisaAddress {
+        VisaAddress::Usb(testing::usb(addr))
+    }
+
+    #[test]
+    fn visa_board() {
+        assert_eq!(usb("USB3::0x1A34::0x5678::A22-5").board(), Some(3));
+        assert_eq!(usb("USB::0x1A34::0x5678::A22-5").board(), None);
+        assert_eq!(tcpip("TCPIP1::10.0.0.1::INSTR").board(), Some(1));
+        assert_eq!(tcpip("TCPIP::10.0.0.1::INSTR").board(), None);
+        assert_eq!(gpib("GPIB2::5::INSTR").board(), Some(2));
+        assert_eq!(gpib("GPIB0::5::INSTR").board(), Some(0));
+        assert_eq!(gpib("GPIB::5::INSTR").board(), None);
+    }
+
+    #[test]
+    fn visa_with_board() {
+        let addr = usb("USB0::0x1A34::0x5678::A22-5::INSTR").with_board(2);
+        assert_eq!(addr, usb("USB2::0x1A34::0x5678::A22-5::INSTR"));
+        assert_eq!(addr.board(), Some(2));
+
+        let addr = tcpip("TCPIP::10.0.0.1::INSTR").with_board(1);
+        assert_eq!(addr, tcpip("TCPIP1::10.0.0.1::INSTR"));
+
+        let addr = gpib("GPIB::5::INSTR").with_board(3);
+        assert_eq!(addr, gpib("GPIB3::5::INSTR"));
+        assert_eq!(addr.board(), Some(3));
+    }
+
+    #[test]
+    fn visa_from_str() -> Result<(), VisaParseError> {
+        for addr in [
+            "USB::0x1A34::0x5678::A22-5::INSTR",
+            "GPIB0::22::INSTR",
+            "ASRL/dev/ttyUSB0::INSTR",
+            "TCPIP0::10.0.0.2::inst0::INSTR",
+            "TCPIP0::10.0.0.2::5025::SOCKET",
+            "VXI0::128::INSTR",
+            "PXI0::CHASSIS1::SLOT4::INSTR",
+        ] {
+            let parsed = VisaAddress::from_str(addr)?;
+            assert_eq!(parsed.to_string(), addr);
+            assert!(addr.starts_with(parsed.interface()));
+        }
+        assert!(matches!(
+            VisaAddress::from_str("tcpip::10.0.0.2::5025::socket")?,
+            VisaAddress::TcpipSocket(_)
+        ));
+        assert_eq!(
+            VisaAddress::from_str("gpib0::22::instr")?.to_string(),
+            "GPIB0::22::INSTR"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn visa_class() -> Result<(), VisaParseError> {
+        for (addr, class) in [
+            ("USB::0x1A34::0x5678::A22-5", None),
+            ("USB::0x1A34::0x5678::A22-5::raw", Some(ResourceClass::Raw)),
+            ("GPIB0::22::instr", Some(ResourceClass::Instr)),
+            ("ASRL1", None),
+            ("TCPIP::10.0.0.2::5025::SOCKET", Some(ResourceClass::Socket)),
+            ("VXI::1::INSTR", Some(ResourceClass::Instr)),
+            ("PXI::1", None),
+        ] {
+            let parsed = VisaAddress::from_str(addr)?;
+            assert_eq!(parsed.class(), class, "{addr}");
+            assert_eq!(
+                parsed.resource_class(),
+                class.unwrap_or(ResourceClass::Instr)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn visa_from_str_errors() {
+        for (addr, interface, expected) in [
+            ("USB::0x1A34", "USB", "\"USB::0x1A34\" is an incomplete address missing: Manufacture Code, Model Number, Serial number"),
+            ("GPIB0::31", "GPIB", "The primary address must be 0 to 30, found 31 at position 7 to 9 of\n \"GPIB0::31\""),
+            ("GPIB-VXI0::1::INSTR", "GPIB-VXI", "GPIB-VXI addresses are not supported, found \"GPIB-VXI0::1::INSTR\""),
+            ("COM1", "unknown", "Expected an interface type such as \"USB\" or \"TCPIP\" at the start of \"COM1\""),
+        ] {
+            match VisaAddress::from_str(addr) {
+                Ok(visa) => panic!("Accepted invalid address {addr:?} as {visa}"),
+                Err(err) => {
+                    assert_eq!(err.interface(), interface);
+                    assert_eq!(err.to_string(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn visa_validate_all() {
+        let report = VisaAddress::validate_all(&[
+            "USB::0x1A34::0x5678::A22-5",
+            "USB0::0x1A34::0x5678::A22-6::INSTR",
+            "USB::x1A34::0x5678::A22-5",
+            "USB::0x1A34::0x5678",
+            "",
+            "  ",
+            "TCPIP::10.0.0.1::INSTR",
+            "TCPIP:10.0.0.1::INSTR",
+            "TCPIP0::10.0.0.2::5025::SOCKET",
+            "TCPIP0::10.0.0.2::inst0::SOCKET",
+            "GPIB0::1::INSTR",
+            "GPIB0::31::INSTR",
+            "GPIB-VXI::9::INSTR",
+            "ASRL1::INSTR",
+            "asrl2::INSTR",
+            "VXI0::1::INSTR",
+            "VXI0::256::INSTR",
+            "PXI0::CHASSIS1::SLOT2",
+            "PXI0::CHASSIS1",
+            "COM1",
+        ]);
+
+        assert_eq!(
+            report.valid,
+            BTreeMap::from([
+                ("ASRL", 2),
+                ("GPIB", 1),
+                ("PXI", 1),
+                ("TCPIP", 2),
+                ("USB", 2),
+                ("VXI", 1)
+            ])
+        );
+        assert_eq!(
+            report.invalid,
+            BTreeMap::from([
+                (("GPIB", "OutOfRange"), 1),
+                (("GPIB-VXI", "Unsupported"), 1),
+                (("PXI", "IncompleteAddress"), 1),
+                (("TCPIP", "InvalidSeperator"), 1),
+                (("TCPIP", "NumParseError"), 1),
+                (("USB", "IncompleteAddress"), 1),
+                (("USB", "NotHex"), 1),
+                (("VXI", "OutOfRange"), 1),
+                (("unknown", "UnknownInterface"), 1),
+            ])
+        );
+        assert_eq!(report.total_valid(), 9);
+        assert_eq!(report.total_invalid(), 9);
+    }
+
+    #[test]
+    fn visa_sort_key_groups() {
+        let mut addrs = [
+            tcpip("TCPIP::10.0.0.2::INSTR"),
+            usb("USB::0x1A34::0x5678::B1"),
+            tcpip("TCPIP0::10.0.0.1::hislip0::INSTR"),
+            VisaAddress::Gpib(GpibAddress::try_new("GPIB0::22::INSTR").unwrap()),
+            usb("USB0::0x0957::0x1796::A2"),
+        ];
+        addrs.sort_by_key(VisaAddress::sort_key);
+
+        let sorted: Vec<_> = addrs.iter().map(VisaAddress::to_string).collect();
+        assert_eq!(
+            sorted,
+            [
+                "USB0::0x957::0x1796::A2",
+                "USB::0x1A34::0x5678::B1",
+                "GPIB0::22::INSTR",
+                "TCPIP0::10.0.0.1::hislip0::INSTR",
+                "TCPIP::10.0.0.2::INSTR",
+            ]
+        );
+    }
+}
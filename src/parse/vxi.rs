@@ -0,0 +1,344 @@
+//! Module for VXI VISA addresses.
+//! Includes primarily the main struct and the errors.
+use std::{fmt::Display, num::ParseIntError, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::{strip_keyword, ResourceClass};
+
+/// Represents a VXI VISA address
+///
+/// This is the INSTR form, `VXI[board]::VXI logical address[::INSTR]`.
+/// Logical addresses are 0 to 255.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::parse::vxi::VxiAddress;
+/// let addr: VxiAddress = "VXI0::128::instr".parse()?;
+/// assert_eq!(addr.logical_address(), 128);
+/// assert_eq!(addr.to_string(), "VXI0::128::INSTR");
+/// # Ok::<(), fisa::parse::vxi::VxiParseError>(())
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct VxiAddress {
+    /// Board number, if any.
+    board: Option<u32>,
+    /// VXI logical address, 0 to 255.
+    logical_address: u8,
+    /// The resource class, if given. Only INSTR is parsed.
+    class: Option<ResourceClass>,
+}
+
+impl VxiAddress {
+    /// Failably creates a new VxiAddress from an address.
+    #[inline]
+    pub fn try_new(addr: &str) -> Result<Self, VxiParseError> {
+        VxiAddress::from_str(addr)
+    }
+
+    /// The board number, if the address has one.
+    pub fn board(&self) -> Option<u32> {
+        self.board
+    }
+
+    /// The same address, but on the given board.
+    #[must_use]
+    pub fn with_board(mut self, board: u32) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// The resource class, if the address has one. VISA takes a missing class to be INSTR.
+    pub fn class(&self) -> Option<ResourceClass> {
+        self.class
+    }
+
+    /// The VXI logical address, 0 to 255.
+    pub fn logical_address(&self) -> u8 {
+        self.logical_address
+    }
+}
+
+/// Errors that can return from VXI address parsing.
+#[derive(Error, Debug)]
+pub enum VxiParseError {
+    /// When the given address does not have the VXI prefix.
+    #[error("Expected \"VXI\" at address start, found {0:?}")]
+    NotVxi(String),
+
+    /// When parsing a number fails.
+    #[error("Found {found:?} instead of a number at position {start:?} to {end:?} of \n{addr:?}")]
+    NumParseError {
+        /// What was found instead of a number upon detecting an error.
+        found: String,
+        /// The full invalid address.
+        addr: String,
+        /// Start index of the address that contains the invalid integer.
+        start: usize,
+        /// Final index of the address that contains the invalid integer.
+        end: usize,
+        /// The original error returned.
+        #[source]
+        source: ParseIntError,
+    },
+
+    /// When the logical address is not a valid VXI logical address.
+    #[error("The logical address must be 0 to 255, found {found} at position {start:?} to {end:?} of\n {addr:?}")]
+    OutOfRange {
+        /// The number that was found
+        found: u32,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the number
+        start: usize,
+        /// End of the span containing the number
+        end: usize,
+    },
+
+    /// When an address is detected to not be complete.
+    #[error("{0:?} is an incomplete address missing: {1}")]
+    IncompleteAddress(String, String),
+
+    /// When the end of a token in the address is detect but is malformed.
+    #[error("Double colons must seperate address portions. Found {found:?} in:\n {addr:?}.")]
+    InvalidSeperator {
+        /// What was found instead of "::"
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the span containing the invalid "::"
+        start: usize,
+        /// End fo the span containing the invalid "::"
+        end: usize,
+    },
+
+    /// When there is more address after the final field.
+    #[error("Found {found:?} after the end of the address at {start:?} to {end:?} of\n {addr:?}")]
+    TrailingData {
+        /// What was found after the final field
+        found: String,
+        /// The full invalid address
+        addr: String,
+        /// Start of the trailing data
+        start: usize,
+        /// End of the trailing data
+        end: usize,
+    },
+}
+
+impl VxiParseError {
+    /// The span of the address that the error refers to, if the error carries one.
+    pub fn span(&self) -> Option<Range<usize>> {
+        use VxiParseError::*;
+
+        match self {
+            NumParseError { start, end, .. }
+            | OutOfRange { start, end, .. }
+            | InvalidSeperator { start, end, .. }
+            | TrailingData { start, end, .. } => Some(*start..*end),
+            NotVxi(_) | IncompleteAddress(..) => None,
+        }
+    }
+
+    /// The name of the kind of error, such as "NumParseError", for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        use VxiParseError::*;
+
+        match self {
+            NotVxi(..) => "NotVxi",
+            NumParseError { .. } => "NumParseError",
+            OutOfRange { .. } => "OutOfRange",
+            IncompleteAddress(..) => "IncompleteAddress",
+            InvalidSeperator { .. } => "InvalidSeperator",
+            TrailingData { .. } => "TrailingData",
+        }
+    }
+}
+
+impl FromStr for VxiAddress {
+    type Err = VxiParseError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        use VxiParseError::*;
+
+        // VXI[board]::VXI logical address[::INSTR]
+        let Some(rest) = strip_keyword(address, "VXI") else {
+            return Err(NotVxi(address.chars().take(3).collect()));
+        };
+        let mut index = "VXI".len();
+
+        // VXI[board]::VXI logical address[::INSTR]
+        //    ↑-----↑
+        let board_len = rest.find(':').unwrap_or(rest.len());
+        let board = match &rest[..board_len] {
+            "" => None,
+            digits => Some(number(address, index, digits)?),
+        };
+        index += board_len;
+
+        // VXI[board]::VXI logical address[::INSTR]
+        //              ↑-----------------↑
+        let rest = separator(address, index)?
+            .ok_or_else(|| IncompleteAddress(address.to_string(), "Logical Address".to_string()))?;
+        index += 2;
+        let logical_len = rest.find(':').unwrap_or(rest.len());
+        let found = number(address, index, &rest[..logical_len])?;
+        let Ok(logical_address) = u8::try_from(found) else {
+            return Err(OutOfRange {
+                found,
+                addr: address.to_string(),
+                start: index,
+                end: index + logical_len,
+            });
+        };
+        index += logical_len;
+
+        // VXI[board]::VXI logical address[::INSTR]
+        //                                   ↑---↑
+        let instr = match separator(address, index)? {
+            None => false,
+            Some(class) if class.eq_ignore_ascii_case("INSTR") => true,
+            Some(_) => {
+                return Err(TrailingData {
+                    found: address[index..].to_string(),
+                    addr: address.to_string(),
+                    start: index,
+                    end: address.len(),
+                })
+            }
+        };
+
+        Ok(VxiAddress {
+            board,
+            logical_address,
+            class: instr.then_some(ResourceClass::Instr),
+        })
+    }
+}
+
+/// Parses a number at the index of the address.
+fn number(address: &str, index: usize, digits: &str) -> Result<u32, VxiParseError> {
+    // u32 parsing accepts a leading '+', which is never written in an address.
+    // Parsing just the sign fails with an invalid digit, which is the error for it.
+    let parsed = match digits.starts_with('+') {
+        true => "+".parse(),
+        false => digits.parse(),
+    };
+    parsed.map_err(|err| VxiParseError::NumParseError {
+        found: digits.to_string(),
+        addr: address.to_string(),
+        start: index,
+        end: index + digits.len(),
+        source: err,
+    })
+}
+
+/// Checks for a "::" at the index, returning the rest of the address after it.
+/// Returns None at the end of the address.
+fn separator(address: &str, index: usize) -> Result<Option<&str>, VxiParseError> {
+    let rest = &address[index..];
+
+    if rest.is_empty() {
+        Ok(None)
+    } else if let Some(rest) = rest.strip_prefix("::") {
+        Ok(Some(rest))
+    } else {
+        Err(VxiParseError::InvalidSeperator {
+            found: rest.chars().take(2).collect(),
+            addr: address.to_string(),
+            start: index,
+            end: (index + 2).min(address.len()),
+        })
+    }
+}
+
+impl Display for VxiAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reference:
+        // VXI[board]::VXI logical address[::INSTR]
+
+        write!(f, "VXI")?;
+        if let Some(num) = self.board {
+            write!(f, "{}", num)?
+        }
+        write!(f, "::{}", self.logical_address)?;
+        if let Some(class) = self.class {
+            write!(f, "::{}", class)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Different permutations of VXI addresses to parse.
+    use super::*;
+    use crate::testing::check_never_panics;
+
+    /// Helper macro
+    /// test_parse!(function_identifier, address_to_parse);
+    macro_rules! test_parse {
+        ($name:ident, $addr:literal) => {
+            #[test]
+            fn $name() -> Result<(), VxiParseError> {
+                const ADDR: &str = $addr;
+                match VxiAddress::from_str(ADDR) {
+                    Ok(address) => {
+                        assert_eq!(address.to_string(), ADDR);
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+    }
+
+    test_parse!(vxi_parse_logical, "VXI::1");
+    test_parse!(vxi_parse_instr, "VXI0::128::INSTR");
+    test_parse!(vxi_parse_max, "VXI2::255");
+    test_parse!(vxi_parse_zero, "VXI::0::INSTR");
+
+    #[test]
+    fn vxi_fields() -> Result<(), VxiParseError> {
+        let addr = VxiAddress::from_str("VXI1::24::instr")?;
+        assert_eq!(addr.board(), Some(1));
+        assert_eq!(addr.logical_address(), 24);
+        assert_eq!(addr.to_string(), "VXI1::24::INSTR");
+        assert_eq!(addr.with_board(3).to_string(), "VXI3::24::INSTR");
+        assert_eq!(
+            VxiAddress::from_str("vxi1::24::instr")?.to_string(),
+            "VXI1::24::INSTR"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn vxi_invalid() {
+        for (addr, expected) in [
+            ("VXI::256::INSTR", "The logical address must be 0 to 255, found 256 at position 5 to 8 of\n \"VXI::256::INSTR\""),
+            ("VXI::+1", "Found \"+1\" instead of a number at position 5 to 7 of \n\"VXI::+1\""),
+            ("VXI::-1", "Found \"-1\" instead of a number at position 5 to 7 of \n\"VXI::-1\""),
+            ("VXI0::MEMACC", "Found \"MEMACC\" instead of a number at position 6 to 12 of \n\"VXI0::MEMACC\""),
+            ("VXI0", "\"VXI0\" is an incomplete address missing: Logical Address"),
+            ("VXI0:1", "Double colons must seperate address portions. Found \":1\" in:\n \"VXI0:1\"."),
+            ("VXI::1::2", "Found \"::2\" after the end of the address at 6 to 9 of\n \"VXI::1::2\""),
+            ("VXI::1::INSTR::2", "Found \"::INSTR::2\" after the end of the address at 6 to 16 of\n \"VXI::1::INSTR::2\""),
+            ("VXI::1::", "Found \"::\" after the end of the address at 6 to 8 of\n \"VXI::1::\""),
+            ("GPIB-VXI::9::INSTR", "Expected \"VXI\" at address start, found \"GPI\""),
+        ] {
+            match VxiAddress::from_str(addr) {
+                Ok(vxi) => panic!("Accepted invalid address {addr:?} as {vxi}"),
+                Err(err) => assert_eq!(err.to_string(), expected),
+            }
+        }
+    }
+
+    #[test]
+    fn vxi_never_panics() {
+        let seeds = ["VXI0::128::INSTR", "VXI::1"];
+        check_never_panics("VXI", &seeds, |input| {
+            VxiAddress::from_str(input).err().and_then(|err| err.span())
+        });
+    }
+}
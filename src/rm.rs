@@ -0,0 +1,470 @@
+//! Module for the resource manager, which finds and opens resources.
+//!
+//! This is the equivalent of `viOpenDefaultRM`. Each kind of hardware is reached through
+//! a [`Backend`], and the [`ResourceManager`] hands every address to the first backend
+//! that supports it.
+use std::{fmt::Debug, io, time::Duration};
+
+use thiserror::Error;
+
+use crate::{
+    parse::tcpip::LanProtocol,
+    parse::VisaAddress,
+    session::{
+        hislip::HislipSession, socket::SocketSession, vxi11::Vxi11Session, Session, SessionError,
+        DEFAULT_TIMEOUT,
+    },
+};
+
+/// A way of reaching resources, such as USB devices or TCP sockets.
+pub trait Backend: Debug {
+    /// The name of the backend, for errors.
+    fn name(&self) -> &'static str;
+
+    /// Checks if this backend is able to open the address.
+    fn supports(&self, addr: &VisaAddress) -> bool;
+
+    /// Lists the resources that are available right now.
+    /// Backends that can not discover resources return none.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to look for resources.
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>>;
+
+    /// Opens a session to the address.
+    /// Only called with addresses that the backend [supports](Backend::supports).
+    ///
+    /// # Errors
+    ///
+    /// If the resource could not be opened.
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError>;
+}
+
+/// Errors from the resource manager.
+#[derive(Error, Debug)]
+pub enum RmError {
+    /// When no backend supports the address.
+    #[error("No backend is able to open {0}")]
+    NoBackend(VisaAddress),
+    /// When a backend failed to look for resources.
+    #[error("The {backend} backend failed: {source}")]
+    Backend {
+        /// The name of the backend
+        backend: &'static str,
+        /// What went wrong
+        #[source]
+        source: io::Error,
+    },
+    /// When a backend failed to open a resource.
+    #[error("The {backend} backend could not open {addr}: {source}")]
+    Open {
+        /// The name of the backend
+        backend: &'static str,
+        /// The resource being opened
+        addr: VisaAddress,
+        /// What went wrong
+        #[source]
+        source: SessionError,
+    },
+}
+
+/// Owns the backends, and finds and opens resources through them.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use fisa::rm::ResourceManager;
+/// let mut rm = ResourceManager::open_default();
+/// let mut session = rm.open(&"TCPIP::10.0.0.5::5025::SOCKET".parse()?)?;
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ResourceManager {
+    /// Tried in the order they were added.
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl ResourceManager {
+    /// A resource manager without any backends.
+    pub fn new() -> Self {
+        ResourceManager::default()
+    }
+
+    /// A resource manager with every backend built in to FISA.
+    /// Backends for libraries that are not installed, such as linux-gpib, are left out.
+    pub fn open_default() -> Self {
+        let mut rm = ResourceManager::new();
+        rm.add_backend(SocketBackend::default());
+        rm.add_backend(Vxi11Backend::default());
+        rm.add_backend(HislipBackend::default());
+        #[cfg(feature = "usbtmc")]
+        rm.add_backend(crate::session::usbtmc::UsbtmcBackend::new(
+            crate::session::usbtmc::NusbDevices,
+            Some(DEFAULT_TIMEOUT),
+        ));
+        #[cfg(feature = "serial")]
+        rm.add_backend(crate::session::serial::SerialBackend::new(
+            crate::session::serial::SerialportPorts,
+            crate::session::serial::SerialSettings::default(),
+            Some(DEFAULT_TIMEOUT),
+        ));
+        #[cfg(all(feature = "linux-gpib", target_os = "linux"))]
+        if let Ok(gpib) = crate::session::linux_gpib::LinuxGpibBackend::load(Some(DEFAULT_TIMEOUT))
+        {
+            rm.add_backend(gpib);
+        }
+        rm
+    }
+
+    /// Adds a backend. Backends added first are tried first when opening.
+    pub fn add_backend(&mut self, backend: impl Backend + 'static) {
+        self.backends.push(Box::new(backend));
+    }
+
+    /// The names of the backends, in the order they are tried.
+    pub fn backends(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.backends.iter().map(|backend| backend.name())
+    }
+
+    /// Lists the resources that every backend found, sorted and without duplicates.
+    ///
+    /// # Errors
+    ///
+    /// If any backend fails to look for resources.
+    pub fn find_resources(&mut self) -> Result<Vec<VisaAddress>, RmError> {
+        let mut found = Vec::new();
+        for backend in &mut self.backends {
+            let resources = backend
+                .find_resources()
+                .map_err(|source| RmError::Backend {
+                    backend: backend.name(),
+                    source,
+                })?;
+            found.extend(resources);
+        }
+        found.sort_by_cached_key(VisaAddress::sort_key);
+        found.dedup();
+        Ok(found)
+    }
+
+    /// Opens a session to the address, with the first backend that supports it.
+    ///
+    /// # Errors
+    ///
+    /// If no backend supports the address, or the backend fails to open it.
+    pub fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, RmError> {
+        let backend = self
+            .backends
+            .iter_mut()
+            .find(|backend| backend.supports(addr))
+            .ok_or_else(|| RmError::NoBackend(addr.clone()))?;
+        backend.open(addr).map_err(|source| RmError::Open {
+            backend: backend.name(),
+            addr: addr.clone(),
+            source,
+        })
+    }
+}
+
+/// Opens `TCPIP::host::port::SOCKET` addresses as plain TCP streams.
+///
+/// Sockets can not be discovered, so this never finds any resources.
+#[derive(Debug, Clone)]
+pub struct SocketBackend {
+    /// How long connecting, reading, and writing may take.
+    timeout: Duration,
+}
+
+impl SocketBackend {
+    /// A socket backend with the given connect, read, and write timeout.
+    pub fn new(timeout: Duration) -> Self {
+        SocketBackend { timeout }
+    }
+}
+
+impl Default for SocketBackend {
+    /// Times out after [`DEFAULT_TIMEOUT`].
+    fn default() -> Self {
+        SocketBackend::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Backend for SocketBackend {
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::TcpipSocket(_))
+    }
+
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::TcpipSocket(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a socket"),
+            )));
+        };
+        Ok(Box::new(SocketSession::connect(addr, Some(self.timeout))?))
+    }
+}
+
+/// Opens `TCPIP::host::inst0::INSTR` addresses, and VXI-11 GPIB gateways, over VXI-11.
+///
+/// VXI-11 devices can be discovered by broadcasting to portmappers, but that is not done,
+/// so this never finds any resources.
+#[derive(Debug, Clone)]
+pub struct Vxi11Backend {
+    /// The timeout given to each session.
+    timeout: Duration,
+}
+
+impl Vxi11Backend {
+    /// A VXI-11 backend with the given timeout for each operation.
+    pub fn new(timeout: Duration) -> Self {
+        Vxi11Backend { timeout }
+    }
+}
+
+impl Default for Vxi11Backend {
+    /// Times out after [`DEFAULT_TIMEOUT`].
+    fn default() -> Self {
+        Vxi11Backend::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Backend for Vxi11Backend {
+    fn name(&self) -> &'static str {
+        "vxi11"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::Tcpip(tcpip) if tcpip.lan_protocol() != LanProtocol::HiSlip)
+    }
+
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::Tcpip(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a TCPIP INSTR address"),
+            )));
+        };
+        Ok(Box::new(Vxi11Session::connect(addr, Some(self.timeout))?))
+    }
+}
+
+/// Opens `TCPIP::host::hislip0::INSTR` addresses over HiSLIP.
+///
+/// HiSLIP servers advertise themselves with mDNS, but that is not done,
+/// so this never finds any resources.
+#[derive(Debug, Clone)]
+pub struct HislipBackend {
+    /// The timeout given to each session.
+    timeout: Duration,
+}
+
+impl HislipBackend {
+    /// A HiSLIP backend with the given timeout for each operation.
+    pub fn new(timeout: Duration) -> Self {
+        HislipBackend { timeout }
+    }
+}
+
+impl Default for HislipBackend {
+    /// Times out after [`DEFAULT_TIMEOUT`].
+    fn default() -> Self {
+        HislipBackend::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Backend for HislipBackend {
+    fn name(&self) -> &'static str {
+        "hislip"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::Tcpip(tcpip) if tcpip.lan_protocol() == LanProtocol::HiSlip)
+    }
+
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::Tcpip(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a TCPIP INSTR address"),
+            )));
+        };
+        Ok(Box::new(HislipSession::connect(addr, Some(self.timeout))?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Finding and opening through backends.
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        str::FromStr,
+        thread,
+    };
+
+    use super::*;
+
+    /// Answers the first query with a fake identity.
+    #[derive(Debug)]
+    struct FakeSession(io::Cursor<&'static [u8]>);
+
+    impl Session for FakeSession {
+        fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+            Ok(data.len())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+            self.0.read(buf).map_err(SessionError::Io)
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        fn set_timeout(&mut self, _timeout: Option<Duration>) -> Result<(), SessionError> {
+            Ok(())
+        }
+    }
+
+    /// Pretends to have some USB devices attached.
+    #[derive(Debug)]
+    struct FakeUsb(Vec<VisaAddress>);
+
+    impl Backend for FakeUsb {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn supports(&self, addr: &VisaAddress) -> bool {
+            self.0.contains(addr)
+        }
+
+        fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+            Ok(self.0.clone())
+        }
+
+        fn open(&mut self, _addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+            Ok(Box::new(FakeSession(io::Cursor::new(b"FAKE,1,2,3\n"))))
+        }
+    }
+
+    /// Parses an address that is known to be valid.
+    fn addr(addr: &str) -> VisaAddress {
+        VisaAddress::from_str(addr).unwrap()
+    }
+
+    #[test]
+    fn rm_find_and_open() {
+        let mut expected = vec!["socket", "vxi11", "hislip"];
+        if cfg!(feature = "usbtmc") {
+            expected.push("usbtmc");
+        }
+        if cfg!(feature = "serial") {
+            expected.push("serial");
+        }
+        let default = ResourceManager::open_default();
+        // linux-gpib is only there when the library is installed.
+        assert_eq!(
+            default
+                .backends()
+                .filter(|name| *name != "linux-gpib")
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        // Without the USB backend, which would find the real devices.
+        let mut rm = ResourceManager::new();
+        rm.add_backend(SocketBackend::default());
+        rm.add_backend(Vxi11Backend::default());
+        rm.add_backend(HislipBackend::default());
+        rm.add_backend(FakeUsb(vec![
+            addr("USB::0x1A34::0x5678::B::INSTR"),
+            addr("GPIB0::1::INSTR"),
+        ]));
+        rm.add_backend(FakeUsb(vec![addr("USB::0x1A34::0x5678::A::INSTR")]));
+        rm.add_backend(FakeUsb(vec![addr("GPIB0::1::INSTR")]));
+        assert_eq!(
+            rm.backends().collect::<Vec<_>>(),
+            ["socket", "vxi11", "hislip", "fake", "fake", "fake"]
+        );
+
+        assert_eq!(
+            rm.find_resources().unwrap(),
+            [
+                addr("USB::0x1A34::0x5678::A::INSTR"),
+                addr("USB::0x1A34::0x5678::B::INSTR"),
+                addr("GPIB0::1::INSTR"),
+            ]
+        );
+
+        let mut session = rm.open(&addr("GPIB0::1::INSTR")).unwrap();
+        assert_eq!(session.query("*IDN?").unwrap(), "FAKE,1,2,3");
+
+        assert!(matches!(
+            rm.open(&addr("GPIB0::2::INSTR")),
+            Err(RmError::NoBackend(_))
+        ));
+        assert!(matches!(
+            ResourceManager::new().open(&addr("TCPIP::localhost::hislip0::INSTR")),
+            Err(RmError::NoBackend(_))
+        ));
+        assert!(matches!(
+            ResourceManager::new().open(&addr("TCPIP::localhost::5025::SOCKET")),
+            Err(RmError::NoBackend(_))
+        ));
+    }
+
+    #[test]
+    fn rm_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            assert_eq!(line, "*IDN?\n");
+            (&stream).write_all(b"FISA,0,0,0\n").unwrap();
+        });
+
+        let mut rm = ResourceManager::open_default();
+        let mut session = rm
+            .open(&addr(&format!("TCPIP::127.0.0.1::{port}::SOCKET")))
+            .unwrap();
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+        server.join().unwrap();
+
+        // Nothing listens on a port that was just closed.
+        let err = rm
+            .open(&addr(&format!("TCPIP::127.0.0.1::{port}::SOCKET")))
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RmError::Open {
+                    backend: "socket",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
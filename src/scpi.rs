@@ -0,0 +1,282 @@
+//! Module for typed SCPI queries, which parse responses into numbers, booleans, and lists.
+//!
+//! [`ScpiQuery`] adds the typed queries to every [`Session`]. The parsers are public too,
+//! for responses that were read some other way, such as from an
+//! [`AsyncSession`](crate::session::nonblocking::AsyncSession).
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::session::{Session, SessionError};
+
+/// Errors from typed SCPI queries.
+#[derive(Error, Debug)]
+pub enum ScpiError {
+    /// When the query itself failed.
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    /// When the response, or an element of a list, was not the expected type.
+    #[error("Expected {expected}, found {response:?}")]
+    Parse {
+        /// The text that did not parse.
+        response: String,
+        /// What the text was expected to be.
+        expected: &'static str,
+    },
+}
+
+/// Parses a number, such as `+1.234E-03`. Infinity and NaN are not SCPI numbers, as
+/// instruments send `9.91E37` instead, so `inf` and `NaN` are errors.
+///
+/// # Errors
+///
+/// If the response is not a finite number.
+pub fn parse_f64(response: &str) -> Result<f64, ScpiError> {
+    let number: f64 = parse(response, "a number")?;
+    if number.is_finite() {
+        Ok(number)
+    } else {
+        Err(ScpiError::Parse {
+            response: response.trim().to_string(),
+            expected: "a number",
+        })
+    }
+}
+
+/// Parses an integer, such as `+42`, or a whole number in NR3 form, such as
+/// `+7.00000E+00`, which many instruments answer integer queries with.
+///
+/// # Errors
+///
+/// If the response is not a whole number that fits in an `i64`.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::scpi::parse_i64;
+/// assert_eq!(parse_i64("+42")?, 42);
+/// assert_eq!(parse_i64("+7.00000E+00")?, 7);
+/// assert!(parse_i64("7.5").is_err());
+/// # Ok::<(), fisa::scpi::ScpiError>(())
+/// ```
+pub fn parse_i64(response: &str) -> Result<i64, ScpiError> {
+    if let Ok(integer) = parse(response, "an integer") {
+        return Ok(integer);
+    }
+    // Every float from -2^63 up to, but not including, 2^63 fits.
+    let range = -9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0;
+    match parse_f64(response) {
+        Ok(number) if number.fract() == 0.0 && range.contains(&number) => {
+            // The number is whole and in range, so nothing is truncated.
+            #[allow(clippy::cast_possible_truncation)]
+            let integer = number as i64;
+            Ok(integer)
+        }
+        _ => Err(ScpiError::Parse {
+            response: response.trim().to_string(),
+            expected: "an integer",
+        }),
+    }
+}
+
+/// Parses a boolean, which is `1`, `0`, `ON`, or `OFF`, in any case.
+///
+/// # Errors
+///
+/// If the response is not one of those.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::scpi::parse_bool;
+/// assert!(parse_bool("ON\n")?);
+/// assert!(!parse_bool("0")?);
+/// assert!(parse_bool("maybe").is_err());
+/// # Ok::<(), fisa::scpi::ScpiError>(())
+/// ```
+pub fn parse_bool(response: &str) -> Result<bool, ScpiError> {
+    let trimmed = response.trim();
+    if trimmed == "1" || trimmed.eq_ignore_ascii_case("ON") {
+        Ok(true)
+    } else if trimmed == "0" || trimmed.eq_ignore_ascii_case("OFF") {
+        Ok(false)
+    } else {
+        Err(ScpiError::Parse {
+            response: trimmed.to_string(),
+            expected: "a boolean",
+        })
+    }
+}
+
+/// Parses a comma separated list. An empty response is an empty list.
+///
+/// # Errors
+///
+/// If an element does not parse. The error has just that element.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::scpi::parse_csv;
+/// let readings: Vec<f64> = parse_csv("+1.5E+00, -2.0E-01\n")?;
+/// assert_eq!(readings, [1.5, -0.2]);
+/// assert!(parse_csv::<u8>("1,2,x").is_err());
+/// # Ok::<(), fisa::scpi::ScpiError>(())
+/// ```
+pub fn parse_csv<T: FromStr>(response: &str) -> Result<Vec<T>, ScpiError> {
+    let trimmed = response.trim();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+    trimmed
+        .split(',')
+        .map(|element| parse(element, std::any::type_name::<T>()))
+        .collect()
+}
+
+/// Parses the response without the whitespace and terminators around it.
+fn parse<T: FromStr>(response: &str, expected: &'static str) -> Result<T, ScpiError> {
+    let trimmed = response.trim();
+    trimmed.parse().map_err(|_| ScpiError::Parse {
+        response: trimmed.to_string(),
+        expected,
+    })
+}
+
+/// Typed queries, for every [`Session`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{parse::tcpip::TcpipSocketAddress, scpi::ScpiQuery, session::socket::SocketSession};
+/// # let addr = TcpipSocketAddress::try_new("TCPIP::10.0.0.5::5025::SOCKET")?;
+/// let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(2)))?;
+/// let volts = session.query_f64("MEAS:VOLT:DC?")?;
+/// let output = session.query_bool("OUTP?")?;
+/// let readings: Vec<f64> = session.query_csv("FETC?")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait ScpiQuery: Session {
+    /// Queries a number.
+    ///
+    /// # Errors
+    ///
+    /// If the query failed, or the response is not a number.
+    fn query_f64(&mut self, command: &str) -> Result<f64, ScpiError> {
+        parse_f64(&self.query(command)?)
+    }
+
+    /// Queries an integer.
+    ///
+    /// # Errors
+    ///
+    /// If the query failed, or the response is not an integer.
+    fn query_i64(&mut self, command: &str) -> Result<i64, ScpiError> {
+        parse_i64(&self.query(command)?)
+    }
+
+    /// Queries a boolean, which is `1`, `0`, `ON`, or `OFF`.
+    ///
+    /// # Errors
+    ///
+    /// If the query failed, or the response is not a boolean.
+    fn query_bool(&mut self, command: &str) -> Result<bool, ScpiError> {
+        parse_bool(&self.query(command)?)
+    }
+
+    /// Queries a comma separated list.
+    ///
+    /// # Errors
+    ///
+    /// If the query failed, or an element does not parse.
+    fn query_csv<T: FromStr>(&mut self, command: &str) -> Result<Vec<T>, ScpiError> {
+        parse_csv(&self.query(command)?)
+    }
+}
+
+impl<S: Session + ?Sized> ScpiQuery for S {}
+
+#[cfg(test)]
+mod test {
+    //! Typed queries against canned responses.
+    use std::{io, time::Duration};
+
+    use super::*;
+
+    /// Answers queries from a list of responses.
+    #[derive(Debug)]
+    struct Canned(io::Cursor<&'static [u8]>);
+
+    impl Session for Canned {
+        fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+            Ok(data.len())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+            io::Read::read(&mut self.0, buf).map_err(SessionError::Io)
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            None
+        }
+
+        fn set_timeout(&mut self, _timeout: Option<Duration>) -> Result<(), SessionError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn scpi_queries() {
+        let mut session: Box<dyn Session> = Box::new(Canned(io::Cursor::new(
+            b"+1.234E-03\r\n-42\nOFF\n1, 2 ,3\n\n9.9E37\nNAN?\n",
+        )));
+        assert!((session.query_f64("MEAS?").unwrap() - 1.234e-3).abs() < f64::EPSILON);
+        assert_eq!(session.query_i64("COUN?").unwrap(), -42);
+        assert!(!session.query_bool("OUTP?").unwrap());
+        assert_eq!(session.query_csv::<u32>("FETC?").unwrap(), [1, 2, 3]);
+        assert!(session.query_csv::<f64>("FETC?").unwrap().is_empty());
+        assert_eq!(session.query_f64("MEAS?").unwrap(), 9.9e37);
+
+        let err = session.query_f64("MEAS?").unwrap_err();
+        assert_eq!(err.to_string(), "Expected a number, found \"NAN?\"");
+        assert!(matches!(
+            session.query_bool("OUTP?"),
+            Err(ScpiError::Session(SessionError::Closed))
+        ));
+    }
+
+    #[test]
+    fn scpi_parse() {
+        assert!(parse_bool("on").unwrap());
+        assert!(parse_bool(" 1\n").unwrap());
+        assert!(!parse_bool("Off").unwrap());
+        assert!(matches!(
+            parse_bool("2"),
+            Err(ScpiError::Parse { response, expected: "a boolean" }) if response == "2"
+        ));
+
+        assert_eq!(parse_i64("+7").unwrap(), 7);
+        assert_eq!(parse_i64("7.0").unwrap(), 7);
+        assert_eq!(parse_i64(" -3.0E+02\n").unwrap(), -300);
+        for response in ["7.5", "1E19", "inf", "NaN"] {
+            assert!(
+                matches!(
+                    parse_i64(response),
+                    Err(ScpiError::Parse {
+                        expected: "an integer",
+                        ..
+                    })
+                ),
+                "{response:?}"
+            );
+        }
+        for response in ["inf", "-Infinity", "NaN", "1E999"] {
+            assert!(parse_f64(response).is_err(), "{response:?}");
+        }
+        assert!(matches!(
+            parse_csv::<i64>("1,x,3"),
+            Err(ScpiError::Parse { response, .. }) if response == "x"
+        ));
+    }
+}
@@ -0,0 +1,779 @@
+//! Module for sessions, the open connections that messages are sent over.
+//!
+//! Every transport, such as USBTMC, sockets, or serial ports, is a [`Session`],
+//! so instrument code does not need to care how the instrument is attached.
+use std::{fmt::Debug, io, ops::Range, string::FromUtf8Error, time::Duration};
+
+use thiserror::Error;
+
+pub mod hislip;
+#[cfg(all(feature = "linux-gpib", target_os = "linux"))]
+pub mod linux_gpib;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "serial")]
+pub mod serial;
+pub mod socket;
+#[cfg(feature = "usbtmc")]
+pub mod usbtmc;
+pub mod vxi11;
+
+/// How much more of a block is read at a time, when nothing else limits the read.
+pub(crate) const BLOCK_CHUNK: usize = 64 * 1024;
+
+/// The timeout that sessions are opened with, unless another is given.
+/// The same as VISA's default `VI_ATTR_TMO_VALUE` of 2000 ms.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Errors from talking to a resource.
+#[derive(Error, Debug)]
+pub enum SessionError {
+    /// When the resource did not respond in time.
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+    /// When the resource closed the connection.
+    #[error("The connection was closed")]
+    Closed,
+    /// When a response was not text.
+    #[error("The response was not UTF-8: {0}")]
+    NotUtf8(#[from] FromUtf8Error),
+    /// When the transport failed.
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+/// How messages are framed on transports that do not frame them, such as sockets and
+/// serial ports. The same as VISA's `VI_ATTR_TERMCHAR` and `VI_ATTR_TERMCHAR_EN`, along
+/// with what is added to the end of commands.
+///
+/// Both terminations are newlines, and enabled, by default.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::session::Termination;
+/// let termination = Termination::default().with_read(b'\r').with_write("\r\n");
+/// assert_eq!(termination.terminate("*IDN?"), "*IDN?\r\n");
+/// assert_eq!(termination.without_write().terminate("*IDN?"), "*IDN?");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Termination {
+    /// The character that responses end with.
+    pub read: u8,
+    /// Whether reads stop after the read termination.
+    /// Reading lines always ends at it.
+    pub read_enabled: bool,
+    /// What is added to the end of commands.
+    pub write: String,
+    /// Whether the write termination is added to commands.
+    pub write_enabled: bool,
+}
+
+impl Termination {
+    /// The same termination, but with responses ending at the given ASCII character.
+    #[must_use]
+    pub fn with_read(mut self, termination: u8) -> Self {
+        self.read = termination;
+        self.read_enabled = true;
+        self
+    }
+
+    /// The same termination, but with reads not stopping at the read termination.
+    #[must_use]
+    pub fn without_read(mut self) -> Self {
+        self.read_enabled = false;
+        self
+    }
+
+    /// The same termination, but adding the given text to the end of commands.
+    #[must_use]
+    pub fn with_write(mut self, termination: impl Into<String>) -> Self {
+        self.write = termination.into();
+        self.write_enabled = true;
+        self
+    }
+
+    /// The same termination, but with commands sent as they are.
+    #[must_use]
+    pub fn without_write(mut self) -> Self {
+        self.write_enabled = false;
+        self
+    }
+
+    /// The command with the write termination added, if it is enabled and the command
+    /// does not end with it already.
+    pub fn terminate(&self, command: &str) -> String {
+        let mut message = command.to_string();
+        if self.write_enabled && !message.ends_with(&self.write) {
+            message.push_str(&self.write);
+        }
+        message
+    }
+}
+
+impl Default for Termination {
+    fn default() -> Self {
+        Termination {
+            read: b'\n',
+            read_enabled: true,
+            write: "\n".to_string(),
+            write_enabled: true,
+        }
+    }
+}
+
+/// An open connection to a resource.
+///
+/// Transports implement [`read`](Session::read), [`write`](Session::write), and the timeout.
+/// Message based helpers, like [`query`](Session::query), are built on top of them.
+///
+/// Like VISA's `VI_ATTR_TMO_VALUE`, the session's timeout applies to each operation, and
+/// running out of it is a [`SessionError::Timeout`]. Slow measurements, such as long
+/// averaging, can be given more time for one call with
+/// [`query_with_timeout`](Session::query_with_timeout), without changing the session.
+pub trait Session: Debug {
+    /// Writes some of the data, returning how many bytes were written.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError>;
+
+    /// Reads some data into the buffer, returning how many bytes were read.
+    ///
+    /// # Errors
+    ///
+    /// If nothing arrived in time, the connection was closed, or the transport failed.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError>;
+
+    /// How long reads and writes may take. `None` waits forever.
+    fn timeout(&self) -> Option<Duration>;
+
+    /// Changes how long reads and writes may take. `None` waits forever.
+    ///
+    /// # Errors
+    ///
+    /// If the transport does not allow the timeout.
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError>;
+
+    /// How messages are framed, for transports that do not frame them.
+    /// `None` for message based transports, such as VXI-11, whose messages end on their own.
+    fn termination(&self) -> Option<&Termination> {
+        None
+    }
+
+    /// Changes how messages are framed.
+    ///
+    /// # Errors
+    ///
+    /// If the transport frames messages itself.
+    fn set_termination(&mut self, _termination: Termination) -> Result<(), SessionError> {
+        Err(SessionError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The transport frames messages itself",
+        )))
+    }
+
+    /// Writes all of the data.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn write_all(&mut self, mut data: &[u8]) -> Result<(), SessionError> {
+        while !data.is_empty() {
+            match self.write(data)? {
+                0 => return Err(SessionError::Closed),
+                written => data = &data[written..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a message up to and including the read termination, or a newline if the
+    /// session has none. Message based transports override this to read until the message
+    /// ends, as their messages may hold any byte.
+    ///
+    /// # Errors
+    ///
+    /// If the message did not end in time, or the connection was closed.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let end = self
+            .termination()
+            .map_or(b'\n', |termination| termination.read);
+        let mut message = Vec::new();
+        let mut byte = [0];
+        while message.last() != Some(&end) {
+            match self.read(&mut byte)? {
+                0 => return Err(SessionError::Closed),
+                _ => message.push(byte[0]),
+            }
+        }
+        Ok(message)
+    }
+
+    /// Reads a message as text. See [`read_message`](Session::read_message).
+    ///
+    /// # Errors
+    ///
+    /// If the message did not end in time, the connection was closed, or it was not UTF-8.
+    fn read_line(&mut self) -> Result<String, SessionError> {
+        Ok(String::from_utf8(self.read_message()?)?)
+    }
+
+    /// Sends a command, adding the write termination if it does not end with it already.
+    /// Sessions without a termination add a newline.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn send(&mut self, command: &str) -> Result<(), SessionError> {
+        // Written at once, as message based transports end a message with each write.
+        let message = match self.termination() {
+            Some(termination) => termination.terminate(command),
+            None => Termination::default().terminate(command),
+        };
+        self.write_all(message.as_bytes())
+    }
+
+    /// Sends a command and reads the response, without the read termination or a
+    /// carriage return before it. Responses of message based transports may end without
+    /// one, and are kept whole.
+    ///
+    /// # Errors
+    ///
+    /// If sending or reading failed. See [`send`](Session::send) and
+    /// [`read_line`](Session::read_line).
+    fn query(&mut self, command: &str) -> Result<String, SessionError> {
+        self.send(command)?;
+        let mut response = self.read_line()?;
+        let end = self
+            .termination()
+            .map_or(b'\n', |termination| termination.read);
+        strip_termination(&mut response, end);
+        Ok(response)
+    }
+
+    /// Reads some data, with a different timeout for just this read.
+    ///
+    /// # Errors
+    ///
+    /// If the transport does not allow the timeout, or the read failed.
+    /// See [`read`](Session::read).
+    fn read_with_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize, SessionError> {
+        let previous = self.timeout();
+        self.set_timeout(timeout)?;
+        let read = self.read(buf);
+        let restored = self.set_timeout(previous);
+        let read = read?;
+        restored?;
+        Ok(read)
+    }
+
+    /// Sends a command and reads the response, with a different timeout for just this query.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use fisa::{parse::tcpip::TcpipSocketAddress, session::{Session, socket::SocketSession}};
+    /// # let addr = TcpipSocketAddress::try_new("TCPIP::10.0.0.5::5025::SOCKET")?;
+    /// let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(2)))?;
+    /// // Averaging 1000 sweeps takes far longer than the usual timeout.
+    /// session.send("AVER:COUN 1000")?;
+    /// let trace = session.query_with_timeout("TRAC?", Some(Duration::from_secs(60)))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the transport does not allow the timeout, or the query failed.
+    /// See [`query`](Session::query).
+    fn query_with_timeout(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> Result<String, SessionError> {
+        let previous = self.timeout();
+        self.set_timeout(timeout)?;
+        let response = self.query(command);
+        let restored = self.set_timeout(previous);
+        let response = response?;
+        restored?;
+        Ok(response)
+    }
+
+    /// Reads an IEEE 488.2 arbitrary block, such as a waveform, returning its data.
+    ///
+    /// Definite length blocks, `#<digits><length><data>`, are followed by the read
+    /// termination, or a newline if the session has none, which is read too. When the read
+    /// termination is disabled, nothing after the block is read.
+    /// Indefinite length blocks, `#0<data>`, end with the message, and the newline at its end
+    /// is dropped. On transports without message framing that is the first read termination,
+    /// as that is all a byte stream can tell apart from data.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use fisa::{parse::tcpip::TcpipSocketAddress, session::{Session, socket::SocketSession}};
+    /// # let addr = TcpipSocketAddress::try_new("TCPIP::10.0.0.5::5025::SOCKET")?;
+    /// let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(2)))?;
+    /// session.send("CURVE?")?;
+    /// let waveform = session.read_binary_block()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the response is not a block, or reading failed. See [`read`](Session::read).
+    fn read_binary_block(&mut self) -> Result<Vec<u8>, SessionError> {
+        let (end, enabled) = self.termination().map_or((b'\n', true), |termination| {
+            (termination.read, termination.read_enabled)
+        });
+        if !enabled {
+            // Nothing marks where the block ends, so only what it holds is read.
+            while !block_start(read_byte(self)?)? {}
+            let digits = block_digits(read_byte(self)?)?;
+            if digits == 0 {
+                let mut data = self.read_message()?;
+                data.pop();
+                return Ok(data);
+            }
+            let mut len = vec![0; digits];
+            read_exact(self, &mut len)?;
+            return read_block(self, block_length(&len)?);
+        }
+
+        let mut message = self.read_message()?;
+        let (data, ended) = loop {
+            match find_block(&message, end)? {
+                Some(block) => break block,
+                // The data held the termination, so the block goes on in the next message.
+                None => message.extend(self.read_message()?),
+            }
+        };
+        if !ended && self.termination().is_some() {
+            match read_byte(self)? {
+                byte if byte == end => {}
+                byte => return Err(not_ended(byte)),
+            }
+        }
+        message.truncate(data.end);
+        message.drain(..data.start);
+        Ok(message)
+    }
+
+    /// Sends a command with an IEEE 488.2 definite length block after it, such as
+    /// `TRACE:DATA #15hello`, adding the write termination.
+    ///
+    /// # Errors
+    ///
+    /// If the data is a gigabyte or more, which a block can not hold, or the write failed.
+    fn write_binary_block(&mut self, command: &str, data: &[u8]) -> Result<(), SessionError> {
+        let mut message = command.as_bytes().to_vec();
+        message.extend(block_header(data.len())?.bytes());
+        message.extend_from_slice(data);
+        let end = match self.termination() {
+            Some(termination) => termination.terminate(""),
+            None => Termination::default().terminate(""),
+        };
+        message.extend(end.bytes());
+        // Written at once, as message based transports end a message with each write.
+        self.write_all(&message)
+    }
+}
+
+/// Reads one byte.
+fn read_byte<S: Session + ?Sized>(session: &mut S) -> Result<u8, SessionError> {
+    let mut byte = [0];
+    match session.read(&mut byte)? {
+        0 => Err(SessionError::Closed),
+        _ => Ok(byte[0]),
+    }
+}
+
+/// Fills the buffer.
+fn read_exact<S: Session + ?Sized>(
+    session: &mut S,
+    mut buf: &mut [u8],
+) -> Result<(), SessionError> {
+    while !buf.is_empty() {
+        match session.read(buf)? {
+            0 => return Err(SessionError::Closed),
+            read => buf = &mut buf[read..],
+        }
+    }
+    Ok(())
+}
+
+/// Reads the data of a definite length block. The buffer grows as the data arrives, so a
+/// corrupt length does not allocate up to a gigabyte before anything is read.
+fn read_block<S: Session + ?Sized>(session: &mut S, len: usize) -> Result<Vec<u8>, SessionError> {
+    let mut data = Vec::new();
+    while data.len() < len {
+        let start = data.len();
+        data.resize(len.min(start + BLOCK_CHUNK), 0);
+        match session.read(&mut data[start..])? {
+            0 => return Err(SessionError::Closed),
+            read => data.truncate(start + read),
+        }
+    }
+    Ok(data)
+}
+
+/// Where the data of the block at the start of the response is, if all of it has been read,
+/// and whether the end of the response was read after it. Whitespace before the block is
+/// skipped.
+pub(crate) fn find_block(
+    message: &[u8],
+    end: u8,
+) -> Result<Option<(Range<usize>, bool)>, SessionError> {
+    let Some(start) = message.iter().position(|byte| !byte.is_ascii_whitespace()) else {
+        return Ok(None);
+    };
+    block_start(message[start])?;
+    let Some(digits) = message.get(start + 1) else {
+        return Ok(None);
+    };
+    let header = start + 2 + block_digits(*digits)?;
+    if header == start + 2 {
+        // Indefinite blocks take up the rest of the message.
+        let len = message.len() - usize::from(message.last() == Some(&end));
+        return Ok(Some((header..len.max(header), true)));
+    }
+
+    let Some(len) = message.get(start + 2..header) else {
+        return Ok(None);
+    };
+    let data = header..header + block_length(len)?;
+    match message.get(data.end..) {
+        None => Ok(None),
+        Some([]) => Ok(Some((data, false))),
+        Some([byte]) if *byte == end => Ok(Some((data, true))),
+        Some(rest) => {
+            let byte = rest.iter().find(|byte| **byte != end).unwrap_or(&end);
+            Err(not_ended(*byte))
+        }
+    }
+}
+
+/// An error for a response that goes on after its block.
+pub(crate) fn not_ended(byte: u8) -> SessionError {
+    protocol_error(format!(
+        "Expected the response to end after the block, found {byte:#04X}"
+    ))
+}
+
+/// Whether the byte starts a block. Whitespace before it is skipped, and anything else
+/// is an error.
+pub(crate) fn block_start(byte: u8) -> Result<bool, SessionError> {
+    match byte {
+        b'#' => Ok(true),
+        byte if byte.is_ascii_whitespace() => Ok(false),
+        byte => Err(protocol_error(format!(
+            "Expected a block, found {byte:#04X}"
+        ))),
+    }
+}
+
+/// How many digits the block's length has, where zero is an indefinite length block.
+pub(crate) fn block_digits(byte: u8) -> Result<usize, SessionError> {
+    match byte {
+        b'0'..=b'9' => Ok(usize::from(byte - b'0')),
+        byte => Err(protocol_error(format!(
+            "Expected the number of length digits in a block, found {byte:#04X}"
+        ))),
+    }
+}
+
+/// The block's length from its digits.
+pub(crate) fn block_length(digits: &[u8]) -> Result<usize, SessionError> {
+    std::str::from_utf8(digits)
+        .ok()
+        .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_digit()))
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| {
+            protocol_error(format!(
+                "Expected the length of a block, found {:?}",
+                String::from_utf8_lossy(digits)
+            ))
+        })
+}
+
+/// The `#<digits><length>` header of a definite length block.
+pub(crate) fn block_header(len: usize) -> Result<String, SessionError> {
+    let len = len.to_string();
+    if len.len() > 9 {
+        return Err(SessionError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("A block can not hold {len} bytes"),
+        )));
+    }
+    Ok(format!("#{}{len}", len.len()))
+}
+
+/// Removes the read termination from the end of a response, and a carriage return before
+/// it, if the response ends with it.
+pub(crate) fn strip_termination(response: &mut String, end: u8) {
+    if let Some(line) = response.strip_suffix(char::from(end)) {
+        let len = line.strip_suffix('\r').unwrap_or(line).len();
+        response.truncate(len);
+    }
+}
+
+/// Removes the brackets around an IPv6 host, which the resolver does not accept.
+pub(crate) fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Reads a response up to and including the termination character, for transports
+/// without message framing. What is read past the termination is kept in `pending`.
+pub(crate) fn read_terminated(
+    pending: &mut Vec<u8>,
+    termination: u8,
+    mut read: impl FnMut(&mut [u8]) -> Result<usize, SessionError>,
+) -> Result<Vec<u8>, SessionError> {
+    let mut searched = 0;
+    loop {
+        if let Some(line) = take_terminated(pending, searched, termination) {
+            return Ok(line);
+        }
+        searched = pending.len();
+
+        let mut chunk = [0; 4096];
+        match read(&mut chunk)? {
+            0 => return Err(SessionError::Closed),
+            read => pending.extend_from_slice(&chunk[..read]),
+        }
+    }
+}
+
+/// Copies out what it can from data that was already received.
+pub(crate) fn read_pending(pending: &mut Vec<u8>, buf: &mut [u8]) -> usize {
+    let read = buf.len().min(pending.len());
+    buf[..read].copy_from_slice(&pending[..read]);
+    pending.drain(..read);
+    read
+}
+
+/// Stops a read that went past the read termination, if it is enabled, for transports
+/// without message framing. What came after it is put back at the front of `pending`.
+pub(crate) fn stop_at_termination(
+    pending: &mut Vec<u8>,
+    buf: &[u8],
+    read: usize,
+    termination: &Termination,
+) -> usize {
+    let end = buf[..read]
+        .iter()
+        .position(|byte| *byte == termination.read)
+        .filter(|_| termination.read_enabled);
+    match end {
+        Some(end) => {
+            pending.splice(..0, buf[end + 1..read].iter().copied());
+            end + 1
+        }
+        None => read,
+    }
+}
+
+/// Takes everything up to and including the termination character out of `pending`,
+/// if it is there. Searching starts at `searched`, as what came before was searched already.
+pub(crate) fn take_terminated(
+    pending: &mut Vec<u8>,
+    searched: usize,
+    termination: u8,
+) -> Option<Vec<u8>> {
+    let end = pending[searched..]
+        .iter()
+        .position(|byte| *byte == termination)?;
+    let rest = pending.split_off(searched + end + 1);
+    Some(std::mem::replace(pending, rest))
+}
+
+/// An error for when the other end broke the protocol.
+pub(crate) fn protocol_error(message: String) -> SessionError {
+    SessionError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+/// Turns timeouts into [`SessionError::Timeout`].
+pub(crate) fn io_error(err: io::Error, timeout: Option<Duration>) -> SessionError {
+    match (err.kind(), timeout) {
+        (io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock, Some(timeout)) => {
+            SessionError::Timeout(timeout)
+        }
+        _ => SessionError::Io(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Message helpers.
+    use std::io::Read;
+
+    use super::*;
+
+    /// Answers every command with the same response, a byte at a time.
+    #[derive(Debug)]
+    struct Echo {
+        /// What was written.
+        written: Vec<u8>,
+        /// What is left to read.
+        response: io::Cursor<Vec<u8>>,
+        /// Reads time out when this is zero, like an instrument that is never quick enough.
+        timeout: Option<Duration>,
+    }
+
+    impl Session for Echo {
+        fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+            self.written.push(data[0]);
+            Ok(1)
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+            match self.timeout {
+                Some(Duration::ZERO) => Err(SessionError::Timeout(Duration::ZERO)),
+                _ => self.response.read(buf).map_err(SessionError::Io),
+            }
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            self.timeout
+        }
+
+        fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+            self.timeout = timeout;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn session_query() {
+        let mut echo = Echo {
+            written: Vec::new(),
+            response: io::Cursor::new(b"FISA,0,0,0\r\n1.5\n".to_vec()),
+            timeout: None,
+        };
+        assert_eq!(echo.query("*IDN?").unwrap(), "FISA,0,0,0");
+        assert_eq!(echo.query("MEAS?\n").unwrap(), "1.5");
+        assert_eq!(echo.written, b"*IDN?\nMEAS?\n");
+        assert!(matches!(echo.query("*IDN?"), Err(SessionError::Closed)));
+
+        echo.response = io::Cursor::new(b"\xFF\n".to_vec());
+        assert!(matches!(echo.read_line(), Err(SessionError::NotUtf8(_))));
+
+        // Message based transports may end a response without a termination.
+        for (response, stripped) in [
+            ("1.5\r\n", "1.5"),
+            ("1.5\n", "1.5"),
+            ("1.5", "1.5"),
+            ("1.5\r", "1.5\r"),
+            ("", ""),
+        ] {
+            let mut response = response.to_string();
+            strip_termination(&mut response, b'\n');
+            assert_eq!(response, stripped);
+        }
+        let mut response = "1.5\r".to_string();
+        strip_termination(&mut response, b'\r');
+        assert_eq!(response, "1.5");
+    }
+
+    #[test]
+    fn session_termination() {
+        let termination = Termination::default().with_read(b'\r');
+        let mut pending = b"rest".to_vec();
+        let buf = b"1.5\r2.5\r";
+        assert_eq!(stop_at_termination(&mut pending, buf, 8, &termination), 4);
+        assert_eq!(pending, b"2.5\rrest");
+        assert_eq!(stop_at_termination(&mut pending, buf, 3, &termination), 3);
+
+        let termination = termination.without_read();
+        assert_eq!(stop_at_termination(&mut pending, buf, 8, &termination), 8);
+        assert_eq!(pending, b"2.5\rrest");
+
+        let termination = Termination::default();
+        assert_eq!(termination.terminate("*IDN?"), "*IDN?\n");
+        assert_eq!(termination.terminate("*IDN?\n"), "*IDN?\n");
+        assert_eq!(termination.with_write("").terminate("*IDN?"), "*IDN?");
+
+        let echo = Echo {
+            written: Vec::new(),
+            response: io::Cursor::new(Vec::new()),
+            timeout: None,
+        };
+        assert!(echo.termination().is_none());
+    }
+
+    #[test]
+    fn session_binary_block() {
+        let mut echo = Echo {
+            written: Vec::new(),
+            response: io::Cursor::new(b"#15a\nb\0c\n #0\x01\x02\n#3002xy\n#12abc\n#x\n".to_vec()),
+            timeout: None,
+        };
+        assert_eq!(echo.read_binary_block().unwrap(), b"a\nb\0c");
+        assert_eq!(echo.read_binary_block().unwrap(), b"\x01\x02");
+        assert_eq!(echo.read_binary_block().unwrap(), b"xy");
+        let err = echo.read_binary_block().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected the response to end after the block, found 0x63"
+        );
+        let err = echo.read_binary_block().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected the number of length digits in a block, found 0x78"
+        );
+        assert!(matches!(
+            echo.read_binary_block(),
+            Err(SessionError::Closed)
+        ));
+
+        echo.write_binary_block("TRAC:DATA ", b"hello").unwrap();
+        assert_eq!(echo.written, b"TRAC:DATA #15hello\n");
+        echo.written.clear();
+        echo.write_binary_block("TRAC:DATA ", &[0; 1234]).unwrap();
+        assert_eq!(&echo.written[..16], b"TRAC:DATA #41234");
+        assert_eq!(echo.written.len(), 16 + 1234 + 1);
+
+        assert_eq!(block_header(0).unwrap(), "#10");
+        assert!(block_header(1_000_000_000).is_err());
+        assert!(block_length(b"+12").is_err());
+    }
+
+    #[test]
+    fn session_timeout_override() {
+        let mut echo = Echo {
+            written: Vec::new(),
+            response: io::Cursor::new(b"1.5\n2.5\n".to_vec()),
+            timeout: Some(Duration::ZERO),
+        };
+        assert!(matches!(
+            echo.query("MEAS?"),
+            Err(SessionError::Timeout(Duration::ZERO))
+        ));
+
+        let long = Some(Duration::from_secs(60));
+        assert_eq!(echo.query_with_timeout("MEAS?", long).unwrap(), "1.5");
+        assert_eq!(echo.timeout(), Some(Duration::ZERO));
+
+        let mut buf = [0; 4];
+        assert_eq!(echo.read_with_timeout(&mut buf, long).unwrap(), 4);
+        assert_eq!(&buf, b"2.5\n");
+
+        // The session's timeout is back even when the override ran out.
+        echo.timeout = long;
+        assert!(matches!(
+            echo.read_with_timeout(&mut buf, Some(Duration::ZERO)),
+            Err(SessionError::Timeout(Duration::ZERO))
+        ));
+        assert_eq!(echo.timeout(), long);
+    }
+}
@@ -0,0 +1,650 @@
+//! Module for HiSLIP, the High-Speed LAN Instrument Protocol from the IVI Foundation.
+//!
+//! A session is two TCP connections to the instrument: the synchronous channel that
+//! messages are sent over, and the asynchronous channel for out of band control.
+//! Only the messages needed to open a session and exchange data are implemented.
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use super::{io_error, protocol_error, unbracket, Session, SessionError};
+use crate::parse::tcpip::TcpipAddress;
+
+/// The port that HiSLIP servers listen on.
+pub const HISLIP_PORT: u16 = 4880;
+
+/// Size of the header before every message.
+pub(super) const HEADER_LEN: usize = 16;
+
+/// Protocol version 1.0, in the upper half of the Initialize parameter.
+pub(super) const PROTOCOL_VERSION: u32 = 0x0100;
+
+/// The vendor ID that FISA identifies as, "ZZ" for unregistered clients.
+pub(super) const VENDOR_ID: u32 = u32::from_be_bytes([0, 0, b'Z', b'Z']);
+
+/// The MessageID of the first message a client sends.
+pub(super) const FIRST_MESSAGE_ID: u32 = 0xFFFF_FF00;
+
+/// The largest message FISA asks the server to send. Longer payloads are refused.
+pub(super) const MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
+
+/// The MessageID of data that the server did not send in answer to a message.
+const UNSOLICITED_MESSAGE_ID: u32 = 0xFFFF_FFFF;
+
+/// The message types that are used.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub(super) enum MessageType {
+    /// Opens the synchronous channel.
+    Initialize = 0,
+    /// The server's answer to Initialize.
+    InitializeResponse = 1,
+    /// An error that ends the session.
+    FatalError = 2,
+    /// An error that the session survives.
+    Error = 3,
+    /// Part of a message.
+    Data = 6,
+    /// The last part of a message.
+    DataEnd = 7,
+    /// Tells the server how large a message the client accepts.
+    AsyncMaximumMessageSize = 15,
+    /// The server's answer, with how large a message it accepts.
+    AsyncMaximumMessageSizeResponse = 16,
+    /// Opens the asynchronous channel.
+    AsyncInitialize = 17,
+    /// The server's answer to AsyncInitialize.
+    AsyncInitializeResponse = 18,
+}
+
+impl MessageType {
+    /// Every message type that is used.
+    const ALL: [MessageType; 10] = [
+        MessageType::Initialize,
+        MessageType::InitializeResponse,
+        MessageType::FatalError,
+        MessageType::Error,
+        MessageType::Data,
+        MessageType::DataEnd,
+        MessageType::AsyncMaximumMessageSize,
+        MessageType::AsyncMaximumMessageSizeResponse,
+        MessageType::AsyncInitialize,
+        MessageType::AsyncInitializeResponse,
+    ];
+}
+
+/// A message on either channel.
+#[derive(Debug)]
+pub(super) struct Message {
+    /// The raw message type, as types that are not used may arrive too.
+    kind: u8,
+    /// Meaning depends on the type, such as an error code.
+    control: u8,
+    /// Meaning depends on the type, such as the MessageID.
+    pub(super) parameter: u32,
+    /// The data.
+    pub(super) payload: Vec<u8>,
+}
+
+impl Message {
+    /// A message to send.
+    pub(super) fn new(kind: MessageType, control: u8, parameter: u32, payload: &[u8]) -> Self {
+        Message {
+            kind: kind as u8,
+            control,
+            parameter,
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// The message type, if it is one that is used.
+    pub(super) fn kind(&self) -> Option<MessageType> {
+        MessageType::ALL
+            .into_iter()
+            .find(|kind| *kind as u8 == self.kind)
+    }
+
+    /// The message as it is sent.
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut message = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        message.extend_from_slice(b"HS");
+        message.push(self.kind);
+        message.push(self.control);
+        message.extend_from_slice(&self.parameter.to_be_bytes());
+        message.extend_from_slice(&(self.payload.len() as u64).to_be_bytes());
+        message.extend_from_slice(&self.payload);
+        message
+    }
+
+    /// A message from its header, with the length of the payload that follows.
+    /// The payload is filled in by the caller.
+    pub(super) fn decode_header(header: [u8; HEADER_LEN]) -> Result<(Self, usize), SessionError> {
+        if &header[..2] != b"HS" {
+            return Err(protocol_error(format!(
+                "Expected a HiSLIP header, found {header:02X?}"
+            )));
+        }
+        let [_, _, kind, control, p0, p1, p2, p3, len @ ..] = header;
+        let len = u64::from_be_bytes(len);
+        // Checked before the payload is allocated.
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|_| len <= MAX_MESSAGE_SIZE)
+            .ok_or_else(|| {
+                protocol_error(format!(
+                    "The message is {len} bytes, but at most {MAX_MESSAGE_SIZE} were asked for"
+                ))
+            })?;
+        let message = Message {
+            kind,
+            control,
+            parameter: u32::from_be_bytes([p0, p1, p2, p3]),
+            payload: Vec::new(),
+        };
+        Ok((message, len))
+    }
+
+    /// Writes the message to a channel.
+    fn send(&self, stream: &mut TcpStream) -> io::Result<()> {
+        stream.write_all(&self.encode())
+    }
+
+    /// Reads a message from a channel.
+    fn receive(stream: &mut TcpStream) -> Result<Self, SessionError> {
+        Message::receive_rest(stream, [0; HEADER_LEN], 0)
+    }
+
+    /// Reads the rest of a message, whose first `read` bytes are already in the header.
+    fn receive_rest(
+        stream: &mut TcpStream,
+        mut header: [u8; HEADER_LEN],
+        read: usize,
+    ) -> Result<Self, SessionError> {
+        stream
+            .read_exact(&mut header[read..])
+            .map_err(SessionError::Io)?;
+        let (mut message, len) = Message::decode_header(header)?;
+        message.payload = vec![0; len];
+        stream
+            .read_exact(&mut message.payload)
+            .map_err(SessionError::Io)?;
+        Ok(message)
+    }
+
+    /// Checks that this is the expected type, turning error messages into errors.
+    pub(super) fn expect(self, expected: MessageType) -> Result<Self, SessionError> {
+        match self.kind() {
+            Some(kind) if kind == expected => Ok(self),
+            Some(MessageType::FatalError | MessageType::Error) => Err(self.into_error()),
+            _ => Err(protocol_error(format!(
+                "Expected {expected:?}, found message type {}",
+                self.kind
+            ))),
+        }
+    }
+
+    /// Turns an Error or FatalError message into an error.
+    fn into_error(self) -> SessionError {
+        let fatal = if self.kind == MessageType::FatalError as u8 {
+            "fatal "
+        } else {
+            ""
+        };
+        SessionError::Io(io::Error::other(format!(
+            "HiSLIP {fatal}error {}: {}",
+            self.control,
+            String::from_utf8_lossy(&self.payload)
+        )))
+    }
+}
+
+/// Opens a channel.
+fn connect_channel(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+) -> Result<TcpStream, SessionError> {
+    let stream =
+        TcpStream::connect((unbracket(host), port)).map_err(|err| io_error(err, timeout))?;
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|()| stream.set_write_timeout(timeout))
+        .and_then(|()| stream.set_nodelay(true))
+        .map_err(SessionError::Io)?;
+    Ok(stream)
+}
+
+/// The Initialize message, for the address's LAN device name.
+pub(super) fn initialize(addr: &TcpipAddress) -> Message {
+    Message::new(
+        MessageType::Initialize,
+        0,
+        PROTOCOL_VERSION << 16 | VENDOR_ID,
+        addr.device_name().unwrap_or("hislip0").as_bytes(),
+    )
+}
+
+/// The session ID from InitializeResponse.
+pub(super) fn decode_session_id(response: Message) -> Result<u16, SessionError> {
+    let response = response.expect(MessageType::InitializeResponse)?;
+    let [_, _, id @ ..] = response.parameter.to_be_bytes();
+    Ok(u16::from_be_bytes(id))
+}
+
+/// The largest message the server accepts, from AsyncMaximumMessageSizeResponse.
+pub(super) fn decode_max_message_size(response: Message) -> Result<u64, SessionError> {
+    let response = response.expect(MessageType::AsyncMaximumMessageSizeResponse)?;
+    let max = <[u8; 8]>::try_from(response.payload.as_slice())
+        .map(u64::from_be_bytes)
+        .map_err(|_| protocol_error("The maximum message size is not 8 bytes".to_string()))?;
+    // There must be room for at least one byte of data.
+    Ok(max.max(HEADER_LEN as u64 + 1))
+}
+
+/// Splits data in to Data messages and a final DataEnd, each within the server's maximum size.
+/// An empty write is still a message.
+pub(super) fn data_messages(
+    data: &[u8],
+    max_message_size: u64,
+    message_id: &mut u32,
+    rmt_delivered: &mut bool,
+) -> Vec<Message> {
+    let max_payload = usize::try_from(max_message_size - HEADER_LEN as u64).unwrap_or(usize::MAX);
+    let mut chunks: Vec<&[u8]> = data.chunks(max_payload).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let last = chunks.len() - 1;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let kind = if index == last {
+                MessageType::DataEnd
+            } else {
+                MessageType::Data
+            };
+            let message = Message::new(kind, u8::from(*rmt_delivered), *message_id, chunk);
+            *rmt_delivered = false;
+            *message_id = message_id.wrapping_add(2);
+            message
+        })
+        .collect()
+}
+
+/// The payload of a Data or DataEnd message, and whether it ended the response.
+pub(super) fn decode_data(message: Message) -> Result<(Vec<u8>, bool), SessionError> {
+    match message.kind() {
+        Some(MessageType::Data) => Ok((message.payload, false)),
+        Some(MessageType::DataEnd) => Ok((message.payload, true)),
+        _ => Err(message.expect(MessageType::DataEnd).unwrap_err()),
+    }
+}
+
+/// Whether a Data or DataEnd message answers something other than the last message sent,
+/// such as a query that timed out, so it should be dropped. `message_id` is the MessageID
+/// of the next message sent.
+pub(super) fn is_stale(message: &Message, message_id: u32) -> bool {
+    matches!(
+        message.kind(),
+        Some(MessageType::Data | MessageType::DataEnd)
+    ) && message.parameter != message_id.wrapping_sub(2)
+        && message.parameter != UNSOLICITED_MESSAGE_ID
+}
+
+/// The error for a session that stopped part way through a message, as the channel can no
+/// longer be read or written in step with the server.
+pub(super) fn interrupted() -> SessionError {
+    protocol_error(
+        "A message was interrupted part way through, so the session must be reopened".to_string(),
+    )
+}
+
+/// A session to a `TCPIP::host::hislip0::INSTR` address.
+///
+/// A timeout part way through a message leaves the session unusable, and every
+/// later read and write fails. Responses to queries that timed out are dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{parse::tcpip::TcpipAddress, session::{Session, hislip::HislipSession}};
+/// let addr = TcpipAddress::try_new("TCPIP::10.0.0.5::hislip0::INSTR")?;
+/// let mut session = HislipSession::connect(&addr, Some(Duration::from_secs(2)))?;
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct HislipSession {
+    /// The synchronous channel, that data is sent over.
+    sync: TcpStream,
+    /// The asynchronous channel. Kept open for as long as the session is.
+    async_channel: TcpStream,
+    /// The session ID from the server.
+    session_id: u16,
+    /// The MessageID of the next message sent.
+    message_id: u32,
+    /// Whether a whole response was read since the last message was sent.
+    rmt_delivered: bool,
+    /// The largest message the server accepts, header included.
+    max_message_size: u64,
+    /// How long reads and writes may take.
+    timeout: Option<Duration>,
+    /// Response data that has not been read yet.
+    pending: Vec<u8>,
+    /// Whether the server ended the message in `pending`.
+    end_of_message: bool,
+    /// Whether a message was interrupted part way through.
+    broken: bool,
+}
+
+impl HislipSession {
+    /// Opens a session on the default HiSLIP port.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the server refused the session.
+    pub fn connect(addr: &TcpipAddress, timeout: Option<Duration>) -> Result<Self, SessionError> {
+        HislipSession::connect_to_port(addr, HISLIP_PORT, timeout)
+    }
+
+    /// Opens a session on a port other than the default.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the server refused the session.
+    pub fn connect_to_port(
+        addr: &TcpipAddress,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let mut sync = connect_channel(addr.host(), port, timeout)?;
+        initialize(addr)
+            .send(&mut sync)
+            .map_err(|err| io_error(err, timeout))?;
+        let response = Message::receive(&mut sync).map_err(|err| timed_out(err, timeout))?;
+        let session_id = decode_session_id(response)?;
+
+        let mut async_channel = connect_channel(addr.host(), port, timeout)?;
+        Message::new(MessageType::AsyncInitialize, 0, u32::from(session_id), &[])
+            .send(&mut async_channel)
+            .map_err(|err| io_error(err, timeout))?;
+        Message::receive(&mut async_channel)
+            .map_err(|err| timed_out(err, timeout))?
+            .expect(MessageType::AsyncInitializeResponse)?;
+
+        Message::new(
+            MessageType::AsyncMaximumMessageSize,
+            0,
+            0,
+            &MAX_MESSAGE_SIZE.to_be_bytes(),
+        )
+        .send(&mut async_channel)
+        .map_err(|err| io_error(err, timeout))?;
+        let response =
+            Message::receive(&mut async_channel).map_err(|err| timed_out(err, timeout))?;
+        let max_message_size = decode_max_message_size(response)?;
+
+        Ok(HislipSession {
+            sync,
+            async_channel,
+            session_id,
+            message_id: FIRST_MESSAGE_ID,
+            rmt_delivered: false,
+            max_message_size,
+            timeout,
+            pending: Vec::new(),
+            end_of_message: true,
+            broken: false,
+        })
+    }
+
+    /// The session ID that the server gave.
+    pub fn session_id(&self) -> u16 {
+        self.session_id
+    }
+
+    /// Receives the next Data or DataEnd message into `pending`.
+    fn receive_data(&mut self) -> Result<(), SessionError> {
+        let message = loop {
+            let message = self.receive()?;
+            if !is_stale(&message, self.message_id) {
+                break message;
+            }
+        };
+        let (data, end_of_message) = decode_data(message)?;
+        self.end_of_message = end_of_message;
+        self.rmt_delivered |= end_of_message;
+        self.pending.extend(data);
+        Ok(())
+    }
+
+    /// Receives a message on the synchronous channel.
+    fn receive(&mut self) -> Result<Message, SessionError> {
+        if self.broken {
+            return Err(interrupted());
+        }
+        // Nothing is lost if the first read times out, but any later read is mid message.
+        let mut header = [0; HEADER_LEN];
+        let read = match self.sync.read(&mut header) {
+            Ok(0) => return Err(SessionError::Closed),
+            Ok(read) => read,
+            Err(err) => return Err(io_error(err, self.timeout)),
+        };
+        self.broken = true;
+        let message = Message::receive_rest(&mut self.sync, header, read)
+            .map_err(|err| timed_out(err, self.timeout))?;
+        self.broken = false;
+        Ok(message)
+    }
+}
+
+/// Turns a timed out receive into [`SessionError::Timeout`].
+fn timed_out(err: SessionError, timeout: Option<Duration>) -> SessionError {
+    match err {
+        SessionError::Io(err) => io_error(err, timeout),
+        err => err,
+    }
+}
+
+impl Session for HislipSession {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        if self.broken {
+            return Err(interrupted());
+        }
+        let messages = data_messages(
+            data,
+            self.max_message_size,
+            &mut self.message_id,
+            &mut self.rmt_delivered,
+        );
+        self.broken = true;
+        for message in messages {
+            message
+                .send(&mut self.sync)
+                .map_err(|err| io_error(err, self.timeout))?;
+        }
+        self.broken = false;
+        Ok(data.len())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            self.receive_data()?;
+        }
+        let read = buf.len().min(self.pending.len());
+        buf[..read].copy_from_slice(&self.pending[..read]);
+        self.pending.drain(..read);
+        Ok(read)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        for channel in [&self.sync, &self.async_channel] {
+            channel
+                .set_read_timeout(timeout)
+                .and_then(|()| channel.set_write_timeout(timeout))
+                .map_err(SessionError::Io)?;
+        }
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Reads a whole message, ending at DataEnd rather than at a newline.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = std::mem::take(&mut self.pending);
+        while message.is_empty() || !self.end_of_message {
+            self.receive_data()?;
+            message.append(&mut self.pending);
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    //! Opening sessions and exchanging data with a fake server.
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    /// Serves one HiSLIP session that answers `*IDN?` in two parts, after a stale answer,
+    /// and stops part way through its answer to `HALF?`. Accepts messages of at most
+    /// 32 bytes. Returns every Data and DataEnd message it got.
+    pub(in crate::session) fn fake_server() -> (u16, thread::JoinHandle<Vec<Message>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut sync, _) = listener.accept().unwrap();
+            let init = Message::receive(&mut sync).unwrap();
+            assert_eq!(init.kind(), Some(MessageType::Initialize));
+            assert_eq!(init.parameter, 0x0100_5A5A);
+            assert_eq!(init.payload, b"hislip0");
+            Message::new(MessageType::InitializeResponse, 0, 0x0100_0042, &[])
+                .send(&mut sync)
+                .unwrap();
+
+            let (mut async_channel, _) = listener.accept().unwrap();
+            let init = Message::receive(&mut async_channel).unwrap();
+            assert_eq!(init.kind(), Some(MessageType::AsyncInitialize));
+            assert_eq!(init.parameter, 0x42);
+            Message::new(MessageType::AsyncInitializeResponse, 0, VENDOR_ID, &[])
+                .send(&mut async_channel)
+                .unwrap();
+            let max = Message::receive(&mut async_channel).unwrap();
+            assert_eq!(max.kind(), Some(MessageType::AsyncMaximumMessageSize));
+            Message::new(
+                MessageType::AsyncMaximumMessageSizeResponse,
+                0,
+                0,
+                &32u64.to_be_bytes(),
+            )
+            .send(&mut async_channel)
+            .unwrap();
+
+            let mut received = Vec::new();
+            let mut command = Vec::new();
+            while let Ok(message) = Message::receive(&mut sync) {
+                command.extend_from_slice(&message.payload);
+                let end = message.kind() == Some(MessageType::DataEnd);
+                let id = message.parameter;
+                received.push(message);
+                if end && command == b"*IDN?\n" {
+                    Message::new(MessageType::DataEnd, 0, id.wrapping_sub(2), b"STALE\n")
+                        .send(&mut sync)
+                        .unwrap();
+                    Message::new(MessageType::Data, 0, id, b"FISA,")
+                        .send(&mut sync)
+                        .unwrap();
+                    Message::new(MessageType::DataEnd, 0, id, b"0,0,0\n")
+                        .send(&mut sync)
+                        .unwrap();
+                } else if end && command == b"HALF?\n" {
+                    let response = Message::new(MessageType::DataEnd, 0, id, b"HALF\n");
+                    sync.write_all(&response.encode()[..8]).unwrap();
+                } else if end && received.len() > 2 {
+                    Message::new(MessageType::Error, 1, 0, b"Unrecognized command")
+                        .send(&mut sync)
+                        .unwrap();
+                }
+                if end {
+                    command.clear();
+                }
+            }
+            received
+        });
+        (port, server)
+    }
+
+    #[test]
+    fn hislip_query() {
+        let (port, server) = fake_server();
+        let addr = TcpipAddress::try_new("TCPIP::127.0.0.1::hislip0::INSTR").unwrap();
+        let mut session =
+            HislipSession::connect_to_port(&addr, port, Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(session.session_id(), 0x42);
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+
+        // A long command is split across messages of the server's maximum size.
+        session.write_all(b"SYSTEM:ERROR:NEXT?\n").unwrap();
+        let err = session.read_line().unwrap_err();
+        assert_eq!(err.to_string(), "HiSLIP error 1: Unrecognized command");
+
+        session
+            .set_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        session.write_all(b"HALF?\n").unwrap();
+        assert!(matches!(session.read_line(), Err(SessionError::Timeout(_))));
+        let err = session.query("*IDN?").unwrap_err();
+        assert_eq!(err.to_string(), interrupted().to_string());
+        drop(session);
+
+        let received = server.join().unwrap();
+        let summary: Vec<_> = received
+            .iter()
+            .map(|message| (message.kind(), message.control, message.parameter))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some(MessageType::DataEnd), 0, 0xFFFF_FF00),
+                (Some(MessageType::Data), 1, 0xFFFF_FF02),
+                (Some(MessageType::DataEnd), 0, 0xFFFF_FF04),
+                (Some(MessageType::DataEnd), 0, 0xFFFF_FF06),
+            ]
+        );
+        assert_eq!(received[1].payload, b"SYSTEM:ERROR:NEX");
+        assert_eq!(received[2].payload, b"T?\n");
+    }
+
+    #[test]
+    fn hislip_message_limits() {
+        let header = |len: u64| {
+            let mut header = *b"HS\x07\0\0\0\0\x01\0\0\0\0\0\0\0\0";
+            header[8..].copy_from_slice(&len.to_be_bytes());
+            header
+        };
+        assert_eq!(Message::decode_header(header(5)).unwrap().1, 5);
+        assert!(Message::decode_header(header(MAX_MESSAGE_SIZE)).is_ok());
+        assert!(Message::decode_header(header(MAX_MESSAGE_SIZE + 1)).is_err());
+        assert!(Message::decode_header(header(u64::MAX)).is_err());
+
+        let response = |id| Message::new(MessageType::DataEnd, 0, id, b"");
+        assert!(!is_stale(&response(FIRST_MESSAGE_ID), FIRST_MESSAGE_ID + 2));
+        assert!(is_stale(&response(FIRST_MESSAGE_ID), FIRST_MESSAGE_ID + 4));
+        assert!(!is_stale(
+            &response(UNSOLICITED_MESSAGE_ID),
+            FIRST_MESSAGE_ID + 4
+        ));
+        let error = Message::new(MessageType::Error, 1, 0, b"");
+        assert!(!is_stale(&error, FIRST_MESSAGE_ID + 4));
+    }
+}
@@ -0,0 +1,456 @@
+//! Module for GPIB through linux-gpib, for NI and Agilent GPIB cards on Linux.
+//!
+//! libgpib is loaded when the backend is created rather than linked, so that FISA builds
+//! and runs on machines without it, and the backend is only used where it is installed.
+//! Calling in to libgpib can not be done safely, so the unsafe code is kept to `Library`,
+//! whose methods are safe.
+
+use std::{
+    ffi::{c_char, c_int, c_long, c_short, c_void, CStr, CString},
+    io,
+    time::Duration,
+};
+
+use super::{Session, SessionError};
+use crate::{
+    parse::{gpib::GpibAddress, VisaAddress},
+    rm::Backend,
+};
+
+/// The names libgpib is installed as, tried in order.
+const LIBRARY_NAMES: [&str; 2] = ["libgpib.so.0", "libgpib.so"];
+
+/// dlopen flag to resolve every symbol at once.
+const RTLD_NOW: c_int = 2;
+
+/// Set in ibsta when a call failed.
+const ERR: c_int = 0x8000;
+/// Set in ibsta when a call timed out.
+const TIMO: c_int = 0x4000;
+/// Set in ibsta when the device ended a message with EOI.
+const END: c_int = 0x2000;
+
+/// The iberr for a nonexistent board.
+const ENEB: c_int = 7;
+
+/// The timeouts libgpib supports, in the order of its T constants, from T10us as 1.
+const TIMEOUTS_US: [u64; 17] = [
+    10,
+    30,
+    100,
+    300,
+    1_000,
+    3_000,
+    10_000,
+    30_000,
+    100_000,
+    300_000,
+    1_000_000,
+    3_000_000,
+    10_000_000,
+    30_000_000,
+    100_000_000,
+    300_000_000,
+    1_000_000_000,
+];
+
+/// The boards that are searched for devices.
+const MAX_BOARDS: c_int = 4;
+
+#[allow(unsafe_code)]
+#[link(name = "dl")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dlerror() -> *const c_char;
+}
+
+/// `ibdev(board, pad, sad, timeout, send_eoi, eos)`
+type IbDev = unsafe extern "C" fn(c_int, c_int, c_int, c_int, c_int, c_int) -> c_int;
+/// `ibfind(name)`
+type IbFind = unsafe extern "C" fn(*const c_char) -> c_int;
+/// `ibln(ud, pad, sad, found_listener)`
+type IbLn = unsafe extern "C" fn(c_int, c_int, c_int, *mut c_short) -> c_int;
+/// `ibonl(ud, online)` and `ibtmo(ud, timeout)`
+type IbSet = unsafe extern "C" fn(c_int, c_int) -> c_int;
+/// `ibrd(ud, buf, count)`
+type IbRd = unsafe extern "C" fn(c_int, *mut c_void, c_long) -> c_int;
+/// `ibwrt(ud, buf, count)`
+type IbWrt = unsafe extern "C" fn(c_int, *const c_void, c_long) -> c_int;
+/// `ThreadIberr()`
+type IbErr = unsafe extern "C" fn() -> c_int;
+/// `ThreadIbcntl()`
+type IbCnt = unsafe extern "C" fn() -> c_long;
+
+/// The libgpib functions that are used. The library is never unloaded, so they stay valid.
+#[derive(Clone, Copy, Debug)]
+struct Library {
+    /// Opens a device.
+    ibdev: IbDev,
+    /// Opens a board by name.
+    ibfind: IbFind,
+    /// Checks for a listener at an address.
+    ibln: IbLn,
+    /// Takes a device offline, which closes it.
+    ibonl: IbSet,
+    /// Reads from a device.
+    ibrd: IbRd,
+    /// Sets the timeout.
+    ibtmo: IbSet,
+    /// Writes to a device.
+    ibwrt: IbWrt,
+    /// The error of the last failed call on this thread.
+    iberr: IbErr,
+    /// The bytes moved by the last call on this thread.
+    ibcnt: IbCnt,
+}
+
+/// The last dlopen or dlsym error.
+#[allow(unsafe_code)]
+fn dl_error(context: String) -> io::Error {
+    // SAFETY: dlerror returns null or a C string that lives until the next dl call.
+    let message = unsafe {
+        let err = dlerror();
+        if err.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    };
+    io::Error::new(io::ErrorKind::NotFound, format!("{context}: {message}"))
+}
+
+/// Looks up a function in libgpib, and casts it to its type.
+macro_rules! symbol {
+    ($handle:expr, $name:literal as $ty:ty) => {{
+        // SAFETY: The name is a C string, and the handle is from dlopen.
+        let symbol = unsafe { dlsym($handle, concat!($name, "\0").as_ptr().cast()) };
+        if symbol.is_null() {
+            return Err(dl_error(format!("libgpib does not have {}", $name)));
+        }
+        // SAFETY: The type is the function's signature in gpib/ib.h.
+        unsafe { std::mem::transmute::<*mut c_void, $ty>(symbol) }
+    }};
+}
+
+#[allow(unsafe_code)]
+impl Library {
+    /// Loads libgpib. The library is never unloaded.
+    fn load() -> io::Result<Self> {
+        let handle = LIBRARY_NAMES
+            .into_iter()
+            .map(|name| {
+                let name = CString::new(name).expect("library names have no nulls");
+                // SAFETY: The name is a C string. dlopen runs the initializers of libgpib,
+                // and of the libraries it needs, on this thread. That is sound as long as
+                // the library under this name is linux-gpib's, which is trusted as much as
+                // a library linked at build time would be.
+                unsafe { dlopen(name.as_ptr(), RTLD_NOW) }
+            })
+            .find(|handle| !handle.is_null())
+            .ok_or_else(|| dl_error("libgpib is not installed".to_string()))?;
+
+        Ok(Library {
+            ibdev: symbol!(handle, "ibdev" as IbDev),
+            ibfind: symbol!(handle, "ibfind" as IbFind),
+            ibln: symbol!(handle, "ibln" as IbLn),
+            ibonl: symbol!(handle, "ibonl" as IbSet),
+            ibrd: symbol!(handle, "ibrd" as IbRd),
+            ibtmo: symbol!(handle, "ibtmo" as IbSet),
+            ibwrt: symbol!(handle, "ibwrt" as IbWrt),
+            iberr: symbol!(handle, "ThreadIberr" as IbErr),
+            ibcnt: symbol!(handle, "ThreadIbcntl" as IbCnt),
+        })
+    }
+
+    /// Opens a device, returning its descriptor, or a negative number if it failed.
+    fn dev(&self, board: c_int, primary: c_int, secondary: c_int, timeout: c_int) -> c_int {
+        // Sends EOI at the end of writes, and has no end of string character.
+        // SAFETY: ibdev only takes integers.
+        unsafe { (self.ibdev)(board, primary, secondary, timeout, 1, 0) }
+    }
+
+    /// Opens a board by name, returning its descriptor, or a negative number if it failed.
+    fn find(&self, name: &CStr) -> c_int {
+        // SAFETY: The name is a C string.
+        unsafe { (self.ibfind)(name.as_ptr()) }
+    }
+
+    /// Checks whether a device listens at the address on the board.
+    fn listener(&self, ud: c_int, primary: c_int) -> bool {
+        let mut listener: c_short = 0;
+        // SAFETY: ibln writes one short to the pointer.
+        let ibsta = unsafe { (self.ibln)(ud, primary, 0, &mut listener) };
+        ibsta & ERR == 0 && listener != 0
+    }
+
+    /// Takes a device or board offline, which closes its descriptor.
+    fn close(&self, ud: c_int) {
+        // SAFETY: ibonl only takes integers. A descriptor that is not open just errors.
+        unsafe { (self.ibonl)(ud, 0) };
+    }
+
+    /// Reads from a device, returning ibsta.
+    fn read(&self, ud: c_int, buf: &mut [u8]) -> c_int {
+        let len = c_long::try_from(buf.len()).unwrap_or(c_long::MAX);
+        // SAFETY: ibrd writes at most len bytes to the slice.
+        unsafe { (self.ibrd)(ud, buf.as_mut_ptr().cast(), len) }
+    }
+
+    /// Writes to a device, returning ibsta.
+    fn write(&self, ud: c_int, data: &[u8]) -> c_int {
+        let len = c_long::try_from(data.len()).unwrap_or(c_long::MAX);
+        // SAFETY: ibwrt reads at most len bytes from the slice.
+        unsafe { (self.ibwrt)(ud, data.as_ptr().cast(), len) }
+    }
+
+    /// Sets a device's timeout, returning ibsta.
+    fn set_timeout(&self, ud: c_int, timeout: c_int) -> c_int {
+        // SAFETY: ibtmo only takes integers.
+        unsafe { (self.ibtmo)(ud, timeout) }
+    }
+
+    /// The error of the last failed call on this thread.
+    fn error(&self) -> c_int {
+        // SAFETY: Only reads thread local state.
+        unsafe { (self.iberr)() }
+    }
+
+    /// Turns a failed ibsta into an error, or returns how many bytes were moved.
+    fn check(&self, ibsta: c_int, timeout: Option<Duration>) -> Result<usize, SessionError> {
+        if ibsta & ERR == 0 {
+            // SAFETY: Only reads thread local state.
+            let count = unsafe { (self.ibcnt)() };
+            return Ok(usize::try_from(count).unwrap_or(0));
+        }
+        if ibsta & TIMO != 0 {
+            if let Some(timeout) = timeout {
+                return Err(SessionError::Timeout(timeout));
+            }
+        }
+        let iberr = self.error();
+        Err(SessionError::Io(io::Error::other(format!(
+            "linux-gpib error {iberr}, with status {ibsta:#06X}"
+        ))))
+    }
+}
+
+/// The T constant for the shortest timeout that is at least `timeout`.
+fn timeout_constant(timeout: Option<Duration>) -> c_int {
+    let Some(timeout) = timeout else {
+        return 0; // TNONE
+    };
+    let micros = u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX);
+    (1..)
+        .zip(TIMEOUTS_US)
+        .find(|(_, limit)| *limit >= micros)
+        .map_or(17, |(constant, _)| constant)
+}
+
+/// A session to a `GPIB::primary[::secondary]::INSTR` address, through linux-gpib.
+///
+/// The device is taken offline when the session is dropped.
+#[derive(Debug)]
+pub struct LinuxGpibSession {
+    /// The library.
+    library: Library,
+    /// The device descriptor from ibdev.
+    ud: c_int,
+    /// How long reads and writes may take.
+    timeout: Option<Duration>,
+    /// Whether the device ended its last message with EOI.
+    end_of_message: bool,
+}
+
+impl LinuxGpibSession {
+    /// Opens the device. Every write is ended with EOI.
+    ///
+    /// # Errors
+    ///
+    /// If libgpib is not installed, or the board does not exist.
+    pub fn open(addr: &GpibAddress, timeout: Option<Duration>) -> Result<Self, SessionError> {
+        LinuxGpibSession::open_with(Library::load().map_err(SessionError::Io)?, addr, timeout)
+    }
+
+    /// Opens the device with an already loaded library.
+    fn open_with(
+        library: Library,
+        addr: &GpibAddress,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let board = c_int::try_from(addr.board().unwrap_or(0)).map_err(|_| {
+            SessionError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The board number is too large",
+            ))
+        })?;
+        // Secondary addresses are put on the bus as 96 to 126, and 0 is none.
+        let secondary = addr
+            .secondary()
+            .map_or(0, |secondary| 0x60 + c_int::from(secondary));
+        let ud = library.dev(
+            board,
+            c_int::from(addr.primary()),
+            secondary,
+            timeout_constant(timeout),
+        );
+        if ud < 0 {
+            let iberr = library.error();
+            let reason = if iberr == ENEB {
+                format!("GPIB board {board} does not exist")
+            } else {
+                format!("linux-gpib could not open {addr}, with error {iberr}")
+            };
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                reason,
+            )));
+        }
+        Ok(LinuxGpibSession {
+            library,
+            ud,
+            timeout,
+            end_of_message: true,
+        })
+    }
+}
+
+impl Drop for LinuxGpibSession {
+    fn drop(&mut self) {
+        self.library.close(self.ud);
+    }
+}
+
+impl Session for LinuxGpibSession {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        let ibsta = self.library.write(self.ud, data);
+        self.library.check(ibsta, self.timeout)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        let ibsta = self.library.read(self.ud, buf);
+        let read = self.library.check(ibsta, self.timeout)?;
+        self.end_of_message = ibsta & END != 0;
+        Ok(read.min(buf.len()))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        let ibsta = self.library.set_timeout(self.ud, timeout_constant(timeout));
+        self.library.check(ibsta, self.timeout)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Reads a whole message, ending when the device asserts EOI.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = Vec::new();
+        let mut chunk = [0; 4096];
+        loop {
+            let read = self.read(&mut chunk)?;
+            message.extend_from_slice(&chunk[..read]);
+            if self.end_of_message {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// Opens `GPIB` addresses through linux-gpib.
+#[derive(Debug)]
+pub struct LinuxGpibBackend {
+    /// The library.
+    library: Library,
+    /// The timeout given to each session.
+    timeout: Option<Duration>,
+}
+
+impl LinuxGpibBackend {
+    /// Loads linux-gpib, for a backend with the given timeout for each operation.
+    ///
+    /// # Errors
+    ///
+    /// If libgpib is not installed, so that it is only used where it is.
+    pub fn load(timeout: Option<Duration>) -> io::Result<Self> {
+        Ok(LinuxGpibBackend {
+            library: Library::load()?,
+            timeout,
+        })
+    }
+}
+
+impl Backend for LinuxGpibBackend {
+    fn name(&self) -> &'static str {
+        "linux-gpib"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::Gpib(_))
+    }
+
+    /// Asks each configured board which primary addresses have a listener.
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        let mut found = Vec::new();
+        for board in 0..MAX_BOARDS {
+            let name = CString::new(format!("gpib{board}")).expect("board names have no nulls");
+            let ud = self.library.find(&name);
+            if ud < 0 {
+                continue;
+            }
+            for primary in 1..=30 {
+                if self.library.listener(ud, primary) {
+                    let addr = format!("GPIB{board}::{primary}::INSTR");
+                    found.push(addr.parse().expect("listeners are valid addresses"));
+                }
+            }
+            // ibfind opened the board, which must be closed like any descriptor.
+            self.library.close(ud);
+        }
+        Ok(found)
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::Gpib(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a GPIB address"),
+            )));
+        };
+        Ok(Box::new(LinuxGpibSession::open_with(
+            self.library,
+            addr,
+            self.timeout,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! What can be checked without a GPIB card.
+    use super::*;
+
+    #[test]
+    fn linux_gpib_timeouts() {
+        assert_eq!(timeout_constant(None), 0);
+        assert_eq!(timeout_constant(Some(Duration::ZERO)), 1);
+        assert_eq!(timeout_constant(Some(Duration::from_micros(10))), 1);
+        assert_eq!(timeout_constant(Some(Duration::from_micros(11))), 2);
+        assert_eq!(timeout_constant(Some(Duration::from_secs(1))), 11);
+        assert_eq!(timeout_constant(Some(Duration::from_millis(2500))), 12);
+        assert_eq!(timeout_constant(Some(Duration::from_secs(1000))), 17);
+        assert_eq!(timeout_constant(Some(Duration::from_secs(5000))), 17);
+    }
+
+    #[test]
+    fn linux_gpib_detection() {
+        // Whether or not libgpib is installed, loading must not panic, and a missing
+        // library is reported as not found.
+        if let Err(err) = LinuxGpibBackend::load(None) {
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(err.to_string().contains("libgpib"), "{err}");
+        }
+    }
+}
@@ -0,0 +1,1091 @@
+//! Module for async sessions, so instrument I/O can be awaited inside async services.
+//!
+//! The socket, VXI-11, and HiSLIP transports are written against an [`AsyncStream`],
+//! rather than one runtime's TCP stream. [`TokioStream`] runs them on tokio, with the
+//! `tokio` feature, and other runtimes implement the trait for their own streams.
+use std::{fmt::Debug, future::Future, io, time::Duration};
+
+#[cfg(feature = "tokio")]
+mod tokio;
+#[cfg(feature = "tokio")]
+pub use self::tokio::TokioStream;
+
+use super::{
+    block_digits, block_header, block_length, block_start, find_block,
+    hislip::{
+        data_messages, decode_data, decode_max_message_size, decode_session_id, initialize,
+        interrupted, is_stale, Message, MessageType, FIRST_MESSAGE_ID, HEADER_LEN, HISLIP_PORT,
+        MAX_MESSAGE_SIZE,
+    },
+    not_ended, read_pending, stop_at_termination, strip_termination, take_terminated, unbracket,
+    vxi11::{
+        create_link_args, decode_link, decode_port, decode_read, decode_reply, decode_status,
+        decode_write, encode_call, fragment_mark, getport_args, mark_record, next_write, read_args,
+        write_args, XdrWriter, CREATE_LINK, DESTROY_LINK, DEVICE_CORE, DEVICE_READ, DEVICE_WRITE,
+        PMAPPROC_GETPORT, PORTMAPPER, PORTMAPPER_PORT, READ_CHUNK,
+    },
+    SessionError, Termination, BLOCK_CHUNK,
+};
+use crate::parse::tcpip::{TcpipAddress, TcpipSocketAddress};
+
+/// A TCP stream from an async runtime, along with the runtime's timer.
+pub trait AsyncStream: Debug + Send + Sized {
+    /// Connects to a port on the host. Instrument messages are small, so Nagle's
+    /// algorithm should be turned off if the runtime allows it.
+    fn connect(host: &str, port: u16) -> impl Future<Output = io::Result<Self>> + Send;
+
+    /// Reads some data into the buffer, returning how many bytes were read.
+    /// Zero means the connection was closed.
+    fn read(&mut self, buf: &mut [u8]) -> impl Future<Output = io::Result<usize>> + Send;
+
+    /// Writes all of the data.
+    fn write_all(&mut self, data: &[u8]) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Runs the future, giving up with `None` if it did not finish in time.
+    fn timeout<F: Future + Send>(
+        duration: Duration,
+        future: F,
+    ) -> impl Future<Output = Option<F::Output>> + Send;
+
+    /// Fills the buffer.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> impl Future<Output = io::Result<()>> + Send {
+        async move {
+            while !buf.is_empty() {
+                match self.read(buf).await? {
+                    0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    read => buf = &mut buf[read..],
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// An open connection to a resource, that is used from async code.
+///
+/// The same as a [`Session`](super::Session), except that I/O is awaited.
+pub trait AsyncSession: Debug + Send {
+    /// Writes some of the data, returning how many bytes were written.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn write(&mut self, data: &[u8]) -> impl Future<Output = Result<usize, SessionError>> + Send;
+
+    /// Reads some data into the buffer, returning how many bytes were read.
+    ///
+    /// # Errors
+    ///
+    /// If nothing arrived in time, the connection was closed, or the transport failed.
+    fn read(&mut self, buf: &mut [u8]) -> impl Future<Output = Result<usize, SessionError>> + Send;
+
+    /// How long reads and writes may take. `None` waits forever.
+    fn timeout(&self) -> Option<Duration>;
+
+    /// Changes how long reads and writes may take. `None` waits forever.
+    fn set_timeout(&mut self, timeout: Option<Duration>);
+
+    /// How messages are framed, for transports that do not frame them.
+    /// `None` for message based transports, whose messages end on their own.
+    fn termination(&self) -> Option<&Termination> {
+        None
+    }
+
+    /// Changes how messages are framed.
+    ///
+    /// # Errors
+    ///
+    /// If the transport frames messages itself.
+    fn set_termination(&mut self, _termination: Termination) -> Result<(), SessionError> {
+        Err(SessionError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The transport frames messages itself",
+        )))
+    }
+
+    /// Writes all of the data.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn write_all(
+        &mut self,
+        mut data: &[u8],
+    ) -> impl Future<Output = Result<(), SessionError>> + Send {
+        async move {
+            while !data.is_empty() {
+                match self.write(data).await? {
+                    0 => return Err(SessionError::Closed),
+                    written => data = &data[written..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Reads a message up to and including the read termination, or a newline if the
+    /// session has none. Message based transports override this to read until the message
+    /// ends, as their messages may hold any byte.
+    ///
+    /// # Errors
+    ///
+    /// If the message did not end in time, or the connection was closed.
+    fn read_message(&mut self) -> impl Future<Output = Result<Vec<u8>, SessionError>> + Send {
+        async move {
+            let end = self
+                .termination()
+                .map_or(b'\n', |termination| termination.read);
+            let mut message = Vec::new();
+            let mut byte = [0];
+            while message.last() != Some(&end) {
+                match self.read(&mut byte).await? {
+                    0 => return Err(SessionError::Closed),
+                    _ => message.push(byte[0]),
+                }
+            }
+            Ok(message)
+        }
+    }
+
+    /// Reads a message as text. See [`read_message`](AsyncSession::read_message).
+    ///
+    /// # Errors
+    ///
+    /// If the message did not end in time, the connection was closed, or it was not UTF-8.
+    fn read_line(&mut self) -> impl Future<Output = Result<String, SessionError>> + Send {
+        async move { Ok(String::from_utf8(self.read_message().await?)?) }
+    }
+
+    /// Sends a command, adding the write termination if it does not end with it already.
+    /// Sessions without a termination add a newline.
+    ///
+    /// # Errors
+    ///
+    /// If the write timed out or the transport failed.
+    fn send(&mut self, command: &str) -> impl Future<Output = Result<(), SessionError>> + Send {
+        async move {
+            // Written at once, as message based transports end a message with each write.
+            let message = match self.termination() {
+                Some(termination) => termination.terminate(command),
+                None => Termination::default().terminate(command),
+            };
+            self.write_all(message.as_bytes()).await
+        }
+    }
+
+    /// Sends a command and reads the response, without the read termination or a
+    /// carriage return before it.
+    ///
+    /// # Errors
+    ///
+    /// If sending or reading failed. See [`send`](AsyncSession::send) and
+    /// [`read_line`](AsyncSession::read_line).
+    fn query(
+        &mut self,
+        command: &str,
+    ) -> impl Future<Output = Result<String, SessionError>> + Send {
+        async move {
+            self.send(command).await?;
+            let mut response = self.read_line().await?;
+            let end = self
+                .termination()
+                .map_or(b'\n', |termination| termination.read);
+            strip_termination(&mut response, end);
+            Ok(response)
+        }
+    }
+
+    /// Reads some data, with a different timeout for just this read.
+    ///
+    /// # Errors
+    ///
+    /// If the read failed. See [`read`](AsyncSession::read).
+    fn read_with_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<usize, SessionError>> + Send {
+        async move {
+            let previous = self.timeout();
+            self.set_timeout(timeout);
+            let read = self.read(buf).await;
+            self.set_timeout(previous);
+            read
+        }
+    }
+
+    /// Sends a command and reads the response, with a different timeout for just this query.
+    ///
+    /// # Errors
+    ///
+    /// If the query failed. See [`query`](AsyncSession::query).
+    fn query_with_timeout(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+    ) -> impl Future<Output = Result<String, SessionError>> + Send {
+        async move {
+            let previous = self.timeout();
+            self.set_timeout(timeout);
+            let response = self.query(command).await;
+            self.set_timeout(previous);
+            response
+        }
+    }
+
+    /// Reads an IEEE 488.2 arbitrary block, such as a waveform, returning its data.
+    /// See [`Session::read_binary_block`](super::Session::read_binary_block).
+    ///
+    /// # Errors
+    ///
+    /// If the response is not a block, or reading failed. See [`read`](AsyncSession::read).
+    fn read_binary_block(&mut self) -> impl Future<Output = Result<Vec<u8>, SessionError>> + Send {
+        async move {
+            let (end, enabled) = self.termination().map_or((b'\n', true), |termination| {
+                (termination.read, termination.read_enabled)
+            });
+            if !enabled {
+                // Nothing marks where the block ends, so only what it holds is read.
+                while !block_start(read_byte(self).await?)? {}
+                let digits = block_digits(read_byte(self).await?)?;
+                if digits == 0 {
+                    let mut data = self.read_message().await?;
+                    data.pop();
+                    return Ok(data);
+                }
+                let mut len = vec![0; digits];
+                read_exact(self, &mut len).await?;
+                return read_block(self, block_length(&len)?).await;
+            }
+
+            let mut message = self.read_message().await?;
+            let (data, ended) = loop {
+                match find_block(&message, end)? {
+                    Some(block) => break block,
+                    // The data held the termination, so the block goes on in the next message.
+                    None => message.extend(self.read_message().await?),
+                }
+            };
+            if !ended && self.termination().is_some() {
+                match read_byte(self).await? {
+                    byte if byte == end => {}
+                    byte => return Err(not_ended(byte)),
+                }
+            }
+            message.truncate(data.end);
+            message.drain(..data.start);
+            Ok(message)
+        }
+    }
+
+    /// Sends a command with an IEEE 488.2 definite length block after it, adding the
+    /// write termination.
+    ///
+    /// # Errors
+    ///
+    /// If the data is a gigabyte or more, which a block can not hold, or the write failed.
+    fn write_binary_block(
+        &mut self,
+        command: &str,
+        data: &[u8],
+    ) -> impl Future<Output = Result<(), SessionError>> + Send {
+        async move {
+            let mut message = command.as_bytes().to_vec();
+            message.extend(block_header(data.len())?.bytes());
+            message.extend_from_slice(data);
+            let end = match self.termination() {
+                Some(termination) => termination.terminate(""),
+                None => Termination::default().terminate(""),
+            };
+            message.extend(end.bytes());
+            // Written at once, as message based transports end a message with each write.
+            self.write_all(&message).await
+        }
+    }
+}
+
+/// Reads one byte.
+async fn read_byte<S: AsyncSession + ?Sized>(session: &mut S) -> Result<u8, SessionError> {
+    let mut byte = [0];
+    match session.read(&mut byte).await? {
+        0 => Err(SessionError::Closed),
+        _ => Ok(byte[0]),
+    }
+}
+
+/// Fills the buffer.
+async fn read_exact<S: AsyncSession + ?Sized>(
+    session: &mut S,
+    mut buf: &mut [u8],
+) -> Result<(), SessionError> {
+    while !buf.is_empty() {
+        match session.read(buf).await? {
+            0 => return Err(SessionError::Closed),
+            read => buf = &mut buf[read..],
+        }
+    }
+    Ok(())
+}
+
+/// Reads the data of a definite length block, growing the buffer as the data arrives.
+/// See [`Session::read_binary_block`](super::Session::read_binary_block).
+async fn read_block<S: AsyncSession + ?Sized>(
+    session: &mut S,
+    len: usize,
+) -> Result<Vec<u8>, SessionError> {
+    let mut data = Vec::new();
+    while data.len() < len {
+        let start = data.len();
+        data.resize(len.min(start + BLOCK_CHUNK), 0);
+        match session.read(&mut data[start..]).await? {
+            0 => return Err(SessionError::Closed),
+            read => data.truncate(start + read),
+        }
+    }
+    Ok(data)
+}
+
+/// Runs an operation with the runtime's timer, giving up with [`SessionError::Timeout`].
+async fn timed<S: AsyncStream, T: Send>(
+    timeout: Option<Duration>,
+    operation: impl Future<Output = Result<T, SessionError>> + Send,
+) -> Result<T, SessionError> {
+    match timeout {
+        Some(timeout) => S::timeout(timeout, operation)
+            .await
+            .unwrap_or(Err(SessionError::Timeout(timeout))),
+        None => operation.await,
+    }
+}
+
+/// A raw TCP socket to a `TCPIP::host::port::SOCKET` address.
+///
+/// The async counterpart of [`SocketSession`](super::socket::SocketSession),
+/// with the same read and write terminations.
+#[derive(Debug)]
+pub struct AsyncSocketSession<S> {
+    /// The connection.
+    stream: S,
+    /// How long reads and writes may take.
+    timeout: Option<Duration>,
+    /// How messages are framed.
+    termination: Termination,
+    /// Data that was read past the end of a response.
+    pending: Vec<u8>,
+}
+
+impl<S: AsyncStream> AsyncSocketSession<S> {
+    /// Connects to the address. The timeout is used for connecting as well.
+    ///
+    /// # Errors
+    ///
+    /// If no connection could be made in time.
+    pub async fn connect(
+        addr: &TcpipSocketAddress,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let connect = async {
+            S::connect(unbracket(addr.host()), addr.port())
+                .await
+                .map_err(SessionError::Io)
+        };
+        Ok(AsyncSocketSession {
+            stream: timed::<S, _>(timeout, connect).await?,
+            timeout,
+            termination: Termination::default(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// The same session, but with responses ending at the given ASCII character.
+    #[must_use]
+    pub fn with_read_termination(mut self, termination: u8) -> Self {
+        self.termination = self.termination.with_read(termination);
+        self
+    }
+
+    /// The same session, but adding the given text to the end of commands.
+    /// Can be empty, for commands that are sent with their termination already.
+    #[must_use]
+    pub fn with_write_termination(mut self, termination: impl Into<String>) -> Self {
+        self.termination = self.termination.with_write(termination);
+        self
+    }
+
+    /// The same session, but with messages framed by the termination.
+    #[must_use]
+    pub fn with_termination(mut self, termination: Termination) -> Self {
+        self.termination = termination;
+        self
+    }
+
+    /// The character that responses end with.
+    pub fn read_termination(&self) -> u8 {
+        self.termination.read
+    }
+
+    /// What is added to the end of commands.
+    pub fn write_termination(&self) -> &str {
+        &self.termination.write
+    }
+
+    /// Takes the stream back out. Any data that was read but not returned is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Reads from the stream, without looking at `pending`.
+    async fn read_stream(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        let read = async { self.stream.read(buf).await.map_err(SessionError::Io) };
+        timed::<S, _>(self.timeout, read).await
+    }
+}
+
+impl<S: AsyncStream> AsyncSession for AsyncSocketSession<S> {
+    async fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        let write = async { self.stream.write_all(data).await.map_err(SessionError::Io) };
+        timed::<S, _>(self.timeout, write).await?;
+        Ok(data.len())
+    }
+
+    /// Reads some data, stopping after the read termination if it is enabled.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        let read = if self.pending.is_empty() {
+            self.read_stream(buf).await?
+        } else {
+            read_pending(&mut self.pending, buf)
+        };
+        Ok(stop_at_termination(
+            &mut self.pending,
+            buf,
+            read,
+            &self.termination,
+        ))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn termination(&self) -> Option<&Termination> {
+        Some(&self.termination)
+    }
+
+    fn set_termination(&mut self, termination: Termination) -> Result<(), SessionError> {
+        self.termination = termination;
+        Ok(())
+    }
+
+    /// Sends a command, adding the write termination if it is enabled and the command
+    /// does not end with it already.
+    async fn send(&mut self, command: &str) -> Result<(), SessionError> {
+        let message = self.termination.terminate(command);
+        self.write_all(message.as_bytes()).await
+    }
+
+    /// Reads a response up to and including the read termination.
+    async fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut searched = 0;
+        loop {
+            if let Some(line) = take_terminated(&mut self.pending, searched, self.termination.read)
+            {
+                return Ok(line);
+            }
+            searched = self.pending.len();
+
+            let mut chunk = [0; 4096];
+            match self.read_stream(&mut chunk).await? {
+                0 => return Err(SessionError::Closed),
+                read => self.pending.extend_from_slice(&chunk[..read]),
+            }
+        }
+    }
+}
+
+/// An ONC RPC client over one connection.
+#[derive(Debug)]
+struct AsyncRpcClient<S> {
+    /// The connection.
+    stream: S,
+    /// The program number and version that is called.
+    program: (u32, u32),
+    /// The transaction ID of the last call.
+    xid: u32,
+}
+
+impl<S: AsyncStream> AsyncRpcClient<S> {
+    /// Connects to a program.
+    async fn connect(host: &str, port: u16, program: (u32, u32)) -> Result<Self, SessionError> {
+        let stream = S::connect(unbracket(host), port)
+            .await
+            .map_err(SessionError::Io)?;
+        Ok(AsyncRpcClient {
+            stream,
+            program,
+            xid: 0,
+        })
+    }
+
+    /// Calls a procedure, returning its encoded results.
+    async fn call(&mut self, procedure: u32, args: XdrWriter) -> Result<Vec<u8>, SessionError> {
+        self.xid = self.xid.wrapping_add(1);
+        let call = encode_call(self.xid, self.program, procedure, args);
+        let record = mark_record(&call).map_err(SessionError::Io)?;
+        self.stream
+            .write_all(&record)
+            .await
+            .map_err(SessionError::Io)?;
+        let reply = self.read_record().await.map_err(SessionError::Io)?;
+        decode_reply(self.xid, &reply)
+    }
+
+    /// Reads a record, joining all of its fragments.
+    async fn read_record(&mut self) -> io::Result<Vec<u8>> {
+        let mut record = Vec::new();
+        loop {
+            let mut mark = [0; 4];
+            self.stream.read_exact(&mut mark).await?;
+            let (len, last) = fragment_mark(mark, record.len())?;
+            let start = record.len();
+            record.resize(start + len, 0);
+            self.stream.read_exact(&mut record[start..]).await?;
+            if last {
+                return Ok(record);
+            }
+        }
+    }
+}
+
+/// A session to a `TCPIP::host::inst0::INSTR` address, over a VXI-11 core channel link.
+///
+/// The async counterpart of [`Vxi11Session`](super::vxi11::Vxi11Session).
+/// Dropping cannot be awaited, so the link should be closed with
+/// [`close`](AsyncVxi11Session::close). Otherwise the device destroys it
+/// when it notices that the connection is gone.
+#[derive(Debug)]
+pub struct AsyncVxi11Session<S> {
+    /// The core channel.
+    core: AsyncRpcClient<S>,
+    /// The link ID from create_link.
+    link: u32,
+    /// The most that the device accepts in one write.
+    max_write: usize,
+    /// How long the device may take for each operation.
+    timeout: Option<Duration>,
+    /// Response data that has not been read yet.
+    pending: Vec<u8>,
+    /// Whether the device ended the message in `pending`.
+    end_of_message: bool,
+}
+
+impl<S: AsyncStream> AsyncVxi11Session<S> {
+    /// Looks up the core channel with the host's portmapper, and links to the device.
+    /// The timeout is used for each RPC call as well.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the device refused the link.
+    pub async fn connect(
+        addr: &TcpipAddress,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let get_port = async {
+            let mut portmapper =
+                AsyncRpcClient::<S>::connect(addr.host(), PORTMAPPER_PORT, PORTMAPPER).await?;
+            let reply = portmapper
+                .call(PMAPPROC_GETPORT, getport_args(DEVICE_CORE))
+                .await?;
+            decode_port(&reply, addr.host())
+        };
+        let port = timed::<S, _>(timeout, get_port).await?;
+        AsyncVxi11Session::connect_to_port(addr, port, timeout).await
+    }
+
+    /// Links to the device, with the core channel on a known port rather than asking
+    /// the portmapper.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the device refused the link.
+    pub async fn connect_to_port(
+        addr: &TcpipAddress,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let connect = AsyncRpcClient::connect(addr.host(), port, DEVICE_CORE);
+        let mut session = AsyncVxi11Session {
+            core: timed::<S, _>(rpc_timeout(timeout), connect).await?,
+            link: 0,
+            max_write: 1,
+            timeout,
+            pending: Vec::new(),
+            end_of_message: true,
+        };
+        let reply = session.call(CREATE_LINK, create_link_args(addr)?).await?;
+        (session.link, session.max_write) = decode_link(&reply, timeout)?;
+        Ok(session)
+    }
+
+    /// Calls a core channel procedure.
+    async fn call(&mut self, procedure: u32, args: XdrWriter) -> Result<Vec<u8>, SessionError> {
+        let timeout = rpc_timeout(self.timeout);
+        timed::<S, _>(timeout, self.core.call(procedure, args)).await
+    }
+
+    /// Asks the device for up to `max` bytes, and stores what it sends in `pending`.
+    async fn request(&mut self, max: u32) -> Result<(), SessionError> {
+        let reply = self
+            .call(DEVICE_READ, read_args(self.link, max, self.timeout))
+            .await?;
+        let (data, end_of_message) = decode_read(&reply, self.timeout)?;
+        self.pending.extend(data);
+        self.end_of_message = end_of_message;
+        Ok(())
+    }
+
+    /// Closes the link.
+    ///
+    /// # Errors
+    ///
+    /// If the device did not close the link.
+    pub async fn close(mut self) -> Result<(), SessionError> {
+        let reply = self
+            .call(DESTROY_LINK, XdrWriter::default().u32(self.link))
+            .await?;
+        decode_status(&reply, self.timeout)
+    }
+}
+
+/// Gives the device its whole timeout before the connection gives up on it.
+fn rpc_timeout(timeout: Option<Duration>) -> Option<Duration> {
+    timeout.map(|timeout| timeout + Duration::from_secs(1))
+}
+
+impl<S: AsyncStream> AsyncSession for AsyncVxi11Session<S> {
+    async fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        // The device may accept less than it was sent, so the rest is sent again.
+        let mut rest = data;
+        let mut accepted = None;
+        while !rest.is_empty() {
+            let (chunk, end) = next_write(rest, self.max_write, accepted)?;
+            let args = write_args(self.link, chunk, end, self.timeout)?;
+            let reply = self.call(DEVICE_WRITE, args).await?;
+            let sent = decode_write(&reply, self.timeout)?.min(chunk.len());
+            rest = &rest[sent..];
+            accepted = Some(sent);
+        }
+        Ok(data.len())
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            self.request(u32::try_from(buf.len()).unwrap_or(u32::MAX).min(READ_CHUNK))
+                .await?;
+        }
+        Ok(read_pending(&mut self.pending, buf))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Reads a whole message, ending when the device says it has ended.
+    async fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = std::mem::take(&mut self.pending);
+        while message.is_empty() || !self.end_of_message {
+            self.request(READ_CHUNK).await?;
+            message.append(&mut self.pending);
+        }
+        Ok(message)
+    }
+}
+
+/// Writes a HiSLIP message to a channel.
+async fn send_message<S: AsyncStream>(
+    channel: &mut S,
+    message: Message,
+) -> Result<(), SessionError> {
+    channel
+        .write_all(&message.encode())
+        .await
+        .map_err(SessionError::Io)
+}
+
+/// Reads a HiSLIP message from a channel.
+async fn receive_message<S: AsyncStream>(channel: &mut S) -> Result<Message, SessionError> {
+    receive_rest(channel, [0; HEADER_LEN], 0).await
+}
+
+/// Reads the rest of a HiSLIP message, whose first `read` bytes are already in the header.
+async fn receive_rest<S: AsyncStream>(
+    channel: &mut S,
+    mut header: [u8; HEADER_LEN],
+    read: usize,
+) -> Result<Message, SessionError> {
+    channel
+        .read_exact(&mut header[read..])
+        .await
+        .map_err(SessionError::Io)?;
+    let (mut message, len) = Message::decode_header(header)?;
+    message.payload = vec![0; len];
+    channel
+        .read_exact(&mut message.payload)
+        .await
+        .map_err(SessionError::Io)?;
+    Ok(message)
+}
+
+/// A session to a `TCPIP::host::hislip0::INSTR` address.
+///
+/// The async counterpart of [`HislipSession`](super::hislip::HislipSession), which is
+/// likewise unusable after a timeout part way through a message.
+#[derive(Debug)]
+pub struct AsyncHislipSession<S> {
+    /// The synchronous channel, that data is sent over.
+    sync: S,
+    /// The asynchronous channel. Kept open for as long as the session is.
+    #[allow(dead_code)] // Only held, as the server closes the session with it.
+    async_channel: S,
+    /// The session ID from the server.
+    session_id: u16,
+    /// The MessageID of the next message sent.
+    message_id: u32,
+    /// Whether a whole response was read since the last message was sent.
+    rmt_delivered: bool,
+    /// The largest message the server accepts, header included.
+    max_message_size: u64,
+    /// How long reads and writes may take.
+    timeout: Option<Duration>,
+    /// Response data that has not been read yet.
+    pending: Vec<u8>,
+    /// Whether the server ended the message in `pending`.
+    end_of_message: bool,
+    /// Whether a message was interrupted part way through.
+    broken: bool,
+}
+
+impl<S: AsyncStream> AsyncHislipSession<S> {
+    /// Opens a session on the default HiSLIP port.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the server refused the session.
+    pub async fn connect(
+        addr: &TcpipAddress,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        AsyncHislipSession::connect_to_port(addr, HISLIP_PORT, timeout).await
+    }
+
+    /// Opens a session on a port other than the default.
+    /// The timeout is for opening the whole session.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the server refused the session.
+    pub async fn connect_to_port(
+        addr: &TcpipAddress,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let host = unbracket(addr.host());
+        let open = async {
+            let mut sync = S::connect(host, port).await.map_err(SessionError::Io)?;
+            send_message(&mut sync, initialize(addr)).await?;
+            let session_id = decode_session_id(receive_message(&mut sync).await?)?;
+
+            let mut async_channel = S::connect(host, port).await.map_err(SessionError::Io)?;
+            let init = Message::new(MessageType::AsyncInitialize, 0, u32::from(session_id), &[]);
+            send_message(&mut async_channel, init).await?;
+            receive_message(&mut async_channel)
+                .await?
+                .expect(MessageType::AsyncInitializeResponse)?;
+
+            let max = Message::new(
+                MessageType::AsyncMaximumMessageSize,
+                0,
+                0,
+                &MAX_MESSAGE_SIZE.to_be_bytes(),
+            );
+            send_message(&mut async_channel, max).await?;
+            let max_message_size =
+                decode_max_message_size(receive_message(&mut async_channel).await?)?;
+
+            Ok(AsyncHislipSession {
+                sync,
+                async_channel,
+                session_id,
+                message_id: FIRST_MESSAGE_ID,
+                rmt_delivered: false,
+                max_message_size,
+                timeout,
+                pending: Vec::new(),
+                end_of_message: true,
+                broken: false,
+            })
+        };
+        timed::<S, _>(timeout, open).await
+    }
+
+    /// The session ID that the server gave.
+    pub fn session_id(&self) -> u16 {
+        self.session_id
+    }
+
+    /// Receives the next Data or DataEnd message into `pending`.
+    async fn receive_data(&mut self) -> Result<(), SessionError> {
+        let message = loop {
+            let message = self.receive().await?;
+            if !is_stale(&message, self.message_id) {
+                break message;
+            }
+        };
+        let (data, end_of_message) = decode_data(message)?;
+        self.end_of_message = end_of_message;
+        self.rmt_delivered |= end_of_message;
+        self.pending.extend(data);
+        Ok(())
+    }
+
+    /// Receives a message on the synchronous channel.
+    async fn receive(&mut self) -> Result<Message, SessionError> {
+        if self.broken {
+            return Err(interrupted());
+        }
+        // Nothing is lost if the first read times out, but any later read is mid message.
+        let mut header = [0; HEADER_LEN];
+        let first = async { self.sync.read(&mut header).await.map_err(SessionError::Io) };
+        let read = match timed::<S, _>(self.timeout, first).await? {
+            0 => return Err(SessionError::Closed),
+            read => read,
+        };
+        self.broken = true;
+        let message =
+            timed::<S, _>(self.timeout, receive_rest(&mut self.sync, header, read)).await?;
+        self.broken = false;
+        Ok(message)
+    }
+}
+
+impl<S: AsyncStream> AsyncSession for AsyncHislipSession<S> {
+    async fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        if self.broken {
+            return Err(interrupted());
+        }
+        let messages = data_messages(
+            data,
+            self.max_message_size,
+            &mut self.message_id,
+            &mut self.rmt_delivered,
+        );
+        self.broken = true;
+        for message in messages {
+            timed::<S, _>(self.timeout, send_message(&mut self.sync, message)).await?;
+        }
+        self.broken = false;
+        Ok(data.len())
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            self.receive_data().await?;
+        }
+        Ok(read_pending(&mut self.pending, buf))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Reads a whole message, ending at DataEnd rather than at a newline.
+    async fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = std::mem::take(&mut self.pending);
+        while message.is_empty() || !self.end_of_message {
+            self.receive_data().await?;
+            message.append(&mut self.pending);
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Async sessions against the blocking fake servers.
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        pin::pin,
+        task::{Context, Poll, Waker},
+        thread,
+    };
+
+    use super::*;
+    use crate::session::{hislip, vxi11};
+
+    /// A blocking stream, so that every future is ready the first time it is polled.
+    #[derive(Debug)]
+    struct Blocking(TcpStream);
+
+    impl AsyncStream for Blocking {
+        async fn connect(host: &str, port: u16) -> io::Result<Self> {
+            TcpStream::connect((host, port)).map(Blocking)
+        }
+
+        async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+
+        async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+            self.0.write_all(data)
+        }
+
+        /// Zero durations time out at once, and others never do.
+        async fn timeout<F: Future + Send>(duration: Duration, future: F) -> Option<F::Output> {
+            if duration.is_zero() {
+                None
+            } else {
+                Some(future.await)
+            }
+        }
+    }
+
+    /// Polls a future that never waits.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("The future waited"),
+        }
+    }
+
+    #[test]
+    fn async_socket_query() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let stream = listener.accept().unwrap().0;
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            assert_eq!(line, "*IDN?\r\n");
+            (&stream).write_all(b"FISA,0,0,0\r\n1.5").unwrap();
+            let mut block = [0; 15];
+            (&stream).read_exact(&mut block).unwrap();
+            assert_eq!(&block, b"TRAC #15ab\ncd\r\n");
+            (&stream).write_all(b"#15ab\ncd\n").unwrap();
+        });
+        let addr =
+            TcpipSocketAddress::try_new(&format!("TCPIP::127.0.0.1::{port}::SOCKET")).unwrap();
+
+        block_on(async {
+            let mut session: AsyncSocketSession<Blocking> =
+                AsyncSocketSession::connect(&addr, Some(Duration::from_secs(1)))
+                    .await
+                    .unwrap()
+                    .with_write_termination("\r\n");
+            assert_eq!(session.query("*IDN?").await.unwrap(), "FISA,0,0,0");
+            let mut rest = [0; 8];
+            assert_eq!(session.read(&mut rest).await.unwrap(), 3);
+            assert_eq!(&rest[..3], b"1.5");
+            session
+                .write_binary_block("TRAC ", b"ab\ncd")
+                .await
+                .unwrap();
+            server.join().unwrap();
+
+            session.set_timeout(Some(Duration::ZERO));
+            assert!(matches!(
+                session.read_line().await,
+                Err(SessionError::Timeout(Duration::ZERO))
+            ));
+            let long = Some(Duration::from_secs(1));
+            session.set_timeout(long);
+            assert_eq!(session.read_binary_block().await.unwrap(), b"ab\ncd");
+
+            // The server hung up, which only shows with a timeout that lets the read happen.
+            session.set_timeout(Some(Duration::ZERO));
+            assert_eq!(session.read_with_timeout(&mut rest, long).await.unwrap(), 0);
+            assert_eq!(session.timeout(), Some(Duration::ZERO));
+        });
+    }
+
+    #[test]
+    fn async_vxi11_query() {
+        let (port, server) = vxi11::test::fake_instrument(4);
+        let addr = TcpipAddress::try_new("TCPIP::127.0.0.1::inst0::INSTR").unwrap();
+
+        block_on(async {
+            let mut session: AsyncVxi11Session<Blocking> =
+                AsyncVxi11Session::connect_to_port(&addr, port, Some(Duration::from_millis(100)))
+                    .await
+                    .unwrap();
+            assert_eq!(session.max_write, 4);
+            assert_eq!(session.query("*IDN?").await.unwrap(), "FISA,0,0,0");
+            assert!(matches!(
+                session.read_line().await,
+                Err(SessionError::Timeout(_))
+            ));
+            session.close().await.unwrap();
+        });
+        assert_eq!(server.join().unwrap(), b"*IDN?\n");
+    }
+
+    #[test]
+    fn async_hislip_query() {
+        let (port, server) = hislip::test::fake_server();
+        let addr = TcpipAddress::try_new("TCPIP::127.0.0.1::hislip0::INSTR").unwrap();
+
+        block_on(async {
+            let mut session: AsyncHislipSession<Blocking> =
+                AsyncHislipSession::connect_to_port(&addr, port, Some(Duration::from_secs(1)))
+                    .await
+                    .unwrap();
+            assert_eq!(session.session_id(), 0x42);
+            assert_eq!(session.query("*IDN?").await.unwrap(), "FISA,0,0,0");
+
+            session.write_all(b"SYSTEM:ERROR:NEXT?\n").await.unwrap();
+            let err = session.read_line().await.unwrap_err();
+            assert_eq!(err.to_string(), "HiSLIP error 1: Unrecognized command");
+        });
+
+        let received = server.join().unwrap();
+        let ids: Vec<_> = received.iter().map(|message| message.parameter).collect();
+        assert_eq!(ids, [0xFFFF_FF00, 0xFFFF_FF02, 0xFFFF_FF04]);
+        assert_eq!(received[1].payload, b"SYSTEM:ERROR:NEX");
+    }
+
+    #[test]
+    fn async_hislip_message_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut header = *b"HS\x07\0\0\0\0\0";
+            stream.write_all(&header).unwrap();
+            header = (MAX_MESSAGE_SIZE + 1).to_be_bytes();
+            stream.write_all(&header).unwrap();
+        });
+
+        // The length is refused before the payload is allocated.
+        let mut stream = Blocking(TcpStream::connect(("127.0.0.1", port)).unwrap());
+        let err = block_on(receive_message(&mut stream)).unwrap_err();
+        assert!(matches!(err, SessionError::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+        server.join().unwrap();
+    }
+}
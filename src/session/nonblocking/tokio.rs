@@ -0,0 +1,118 @@
+//! Module for running async sessions on tokio.
+use std::{future::Future, io, time::Duration};
+
+use ::tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::AsyncStream;
+
+/// A tokio TCP stream, with tokio's timer.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{parse::tcpip::TcpipAddress, session::nonblocking::{AsyncSession, AsyncHislipSession, TokioStream}};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let addr = TcpipAddress::try_new("TCPIP::10.0.0.5::hislip0::INSTR")?;
+/// let mut session: AsyncHislipSession<TokioStream> =
+///     AsyncHislipSession::connect(&addr, Some(Duration::from_secs(2))).await?;
+/// println!("{}", session.query("*IDN?").await?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TokioStream(TcpStream);
+
+impl TokioStream {
+    /// Takes the stream back out.
+    pub fn into_inner(self) -> TcpStream {
+        self.0
+    }
+}
+
+impl AsyncStream for TokioStream {
+    async fn connect(host: &str, port: u16) -> io::Result<Self> {
+        let stream = TcpStream::connect((host, port)).await?;
+        stream.set_nodelay(true)?;
+        Ok(TokioStream(stream))
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).await
+    }
+
+    async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0.write_all(data).await
+    }
+
+    async fn timeout<F: Future + Send>(duration: Duration, future: F) -> Option<F::Output> {
+        ::tokio::time::timeout(duration, future).await.ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Async sessions on tokio, against the blocking fake servers.
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+    use crate::{
+        parse::tcpip::{TcpipAddress, TcpipSocketAddress},
+        session::{
+            hislip,
+            nonblocking::{AsyncHislipSession, AsyncSession, AsyncSocketSession},
+            SessionError,
+        },
+    };
+
+    #[::tokio::test]
+    async fn tokio_socket_query() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "*IDN?\n");
+            (&stream).write_all(b"FISA,0,0,0\n").unwrap();
+            // Never answers the second query, and waits for the client to hang up.
+            line.clear();
+            while reader.read_line(&mut line).unwrap() > 0 {}
+        });
+
+        let addr = TcpipSocketAddress::try_new(&format!("TCPIP::127.0.0.1::{port}::SOCKET"));
+        let timeout = Some(Duration::from_millis(50));
+        let mut session: AsyncSocketSession<TokioStream> =
+            AsyncSocketSession::connect(&addr.unwrap(), timeout)
+                .await
+                .unwrap();
+        assert_eq!(session.query("*IDN?").await.unwrap(), "FISA,0,0,0");
+        assert!(matches!(
+            session.query("*IDN?").await,
+            Err(SessionError::Timeout(waited)) if Some(waited) == timeout
+        ));
+        drop(session);
+        server.join().unwrap();
+    }
+
+    #[::tokio::test]
+    async fn tokio_hislip_query() {
+        let (port, server) = hislip::test::fake_server();
+        let addr = TcpipAddress::try_new("TCPIP::127.0.0.1::hislip0::INSTR").unwrap();
+        let mut session: AsyncHislipSession<TokioStream> =
+            AsyncHislipSession::connect_to_port(&addr, port, Some(Duration::from_secs(1)))
+                .await
+                .unwrap();
+        assert_eq!(session.query("*IDN?").await.unwrap(), "FISA,0,0,0");
+        drop(session);
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,457 @@
+//! Module for serial ports, that `ASRL` addresses are opened on.
+//!
+//! Opening and configuring a port is left to a serial library. [`SerialportPorts`] does so
+//! with serialport, and any other library can be used through [`SerialPort`] and
+//! [`SerialPorts`]. This module frames messages, and carries the settings over to the library.
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+mod serialport;
+pub use self::serialport::SerialportPorts;
+
+use super::{
+    io_error, read_pending, read_terminated, stop_at_termination, Session, SessionError,
+    Termination,
+};
+use crate::{
+    parse::{asrl::AsrlAddress, VisaAddress},
+    rm::Backend,
+};
+
+/// Parity checking.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Default)]
+pub enum Parity {
+    /// No parity bit.
+    #[default]
+    None,
+    /// The parity bit makes the number of ones odd.
+    Odd,
+    /// The parity bit makes the number of ones even.
+    Even,
+    /// The parity bit is always one.
+    Mark,
+    /// The parity bit is always zero.
+    Space,
+}
+
+/// Stop bits after each character.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Default)]
+pub enum StopBits {
+    /// One stop bit.
+    #[default]
+    One,
+    /// One and a half stop bits, only for five data bits.
+    OnePointFive,
+    /// Two stop bits.
+    Two,
+}
+
+/// Flow control.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Default)]
+pub enum FlowControl {
+    /// No flow control.
+    #[default]
+    None,
+    /// Software flow control with XON and XOFF characters.
+    XonXoff,
+    /// Hardware flow control with the RTS and CTS lines.
+    RtsCts,
+    /// Hardware flow control with the DTR and DSR lines.
+    DtrDsr,
+}
+
+/// How a serial port is configured. The default is VISA's, 9600 baud 8N1 without flow control.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::session::serial::{Parity, SerialSettings};
+/// let settings = SerialSettings {
+///     baud_rate: 115_200,
+///     parity: Parity::Even,
+///     ..SerialSettings::default()
+/// };
+/// assert_eq!(settings.data_bits, 8);
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct SerialSettings {
+    /// Bits per second.
+    pub baud_rate: u32,
+    /// Bits in each character, from 5 to 8.
+    pub data_bits: u8,
+    /// Parity checking.
+    pub parity: Parity,
+    /// Stop bits after each character.
+    pub stop_bits: StopBits,
+    /// Flow control.
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        SerialSettings {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+/// An open serial port, from whichever serial library.
+pub trait SerialPort: Read + Write + Debug {
+    /// Changes how long reads and writes may take. `None` waits forever.
+    ///
+    /// # Errors
+    ///
+    /// If the port does not allow the timeout.
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+/// Lists and opens the serial ports attached, with whichever serial library.
+pub trait SerialPorts: Debug {
+    /// An opened port.
+    type Port: SerialPort + 'static;
+
+    /// The serial ports attached right now.
+    ///
+    /// # Errors
+    ///
+    /// If the serial library failed to list ports.
+    fn list(&mut self) -> io::Result<Vec<AsrlAddress>>;
+
+    /// Opens the port, configured with the settings.
+    /// How board numbers map to devices is up to the library, such as ASRL3 to COM3.
+    ///
+    /// # Errors
+    ///
+    /// If the port does not exist, is in use, or does not allow the settings.
+    fn open(&mut self, addr: &AsrlAddress, settings: &SerialSettings) -> io::Result<Self::Port>;
+}
+
+/// A session to a serial port.
+///
+/// Serial ports have no message framing, so commands are sent with the write termination
+/// added, and responses are read up to the read termination. Both are newlines by default.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use fisa::{parse::asrl::AsrlAddress, session::{Session, serial::{SerialPorts, SerialportPorts, SerialSession, SerialSettings}}};
+/// let addr = AsrlAddress::try_new("ASRL/dev/ttyUSB0::INSTR")?;
+/// let port = SerialportPorts.open(&addr, &SerialSettings::default())?;
+/// let mut session = SerialSession::new(port, Some(std::time::Duration::from_secs(2)))?
+///     .with_read_termination(b'\r');
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct SerialSession<P> {
+    /// The serial library's port.
+    port: P,
+    /// Kept so it can be reported when it runs out.
+    timeout: Option<Duration>,
+    /// How messages are framed.
+    termination: Termination,
+    /// Data that was read past the end of a response.
+    pending: Vec<u8>,
+}
+
+impl<P: SerialPort> SerialSession<P> {
+    /// Frames messages on the port.
+    ///
+    /// # Errors
+    ///
+    /// If the port does not allow the timeout.
+    pub fn new(mut port: P, timeout: Option<Duration>) -> Result<Self, SessionError> {
+        port.set_timeout(timeout).map_err(SessionError::Io)?;
+        Ok(SerialSession {
+            port,
+            timeout,
+            termination: Termination::default(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// The same session, but with responses ending at the given ASCII character.
+    #[must_use]
+    pub fn with_read_termination(mut self, termination: u8) -> Self {
+        self.termination = self.termination.with_read(termination);
+        self
+    }
+
+    /// The same session, but adding the given text to the end of commands.
+    /// Can be empty, for commands that are sent with their termination already.
+    #[must_use]
+    pub fn with_write_termination(mut self, termination: impl Into<String>) -> Self {
+        self.termination = self.termination.with_write(termination);
+        self
+    }
+
+    /// The same session, but with messages framed by the termination.
+    #[must_use]
+    pub fn with_termination(mut self, termination: Termination) -> Self {
+        self.termination = termination;
+        self
+    }
+
+    /// The character that responses end with.
+    pub fn read_termination(&self) -> u8 {
+        self.termination.read
+    }
+
+    /// What is added to the end of commands.
+    pub fn write_termination(&self) -> &str {
+        &self.termination.write
+    }
+
+    /// Takes the port back out. Any data that was read but not returned is lost.
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+}
+
+impl<P: SerialPort> Session for SerialSession<P> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        self.port
+            .write(data)
+            .map_err(|err| io_error(err, self.timeout))
+    }
+
+    /// Reads some data, stopping after the read termination if it is enabled.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        let read = if self.pending.is_empty() {
+            self.port
+                .read(buf)
+                .map_err(|err| io_error(err, self.timeout))?
+        } else {
+            read_pending(&mut self.pending, buf)
+        };
+        Ok(stop_at_termination(
+            &mut self.pending,
+            buf,
+            read,
+            &self.termination,
+        ))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        self.port.set_timeout(timeout).map_err(SessionError::Io)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn termination(&self) -> Option<&Termination> {
+        Some(&self.termination)
+    }
+
+    fn set_termination(&mut self, termination: Termination) -> Result<(), SessionError> {
+        self.termination = termination;
+        Ok(())
+    }
+
+    /// Sends a command, adding the write termination if it is enabled and the command
+    /// does not end with it already.
+    fn send(&mut self, command: &str) -> Result<(), SessionError> {
+        let message = self.termination.terminate(command);
+        self.write_all(message.as_bytes())?;
+        self.port.flush().map_err(|err| io_error(err, self.timeout))
+    }
+
+    /// Reads a response up to and including the read termination.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let (port, timeout) = (&mut self.port, self.timeout);
+        read_terminated(&mut self.pending, self.termination.read, |chunk| {
+            port.read(chunk).map_err(|err| io_error(err, timeout))
+        })
+    }
+}
+
+/// Opens `ASRL` addresses with a serial library.
+#[derive(Debug)]
+pub struct SerialBackend<S> {
+    /// The serial library.
+    ports: S,
+    /// How every port is configured.
+    settings: SerialSettings,
+    /// The timeout given to each session.
+    timeout: Option<Duration>,
+}
+
+impl<S: SerialPorts> SerialBackend<S> {
+    /// A backend that finds and opens ports with the serial library.
+    pub fn new(ports: S, settings: SerialSettings, timeout: Option<Duration>) -> Self {
+        SerialBackend {
+            ports,
+            settings,
+            timeout,
+        }
+    }
+}
+
+impl<S: SerialPorts> Backend for SerialBackend<S> {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::Asrl(_))
+    }
+
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        Ok(self
+            .ports
+            .list()?
+            .into_iter()
+            .map(VisaAddress::from)
+            .collect())
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::Asrl(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a serial address"),
+            )));
+        };
+        let port = self
+            .ports
+            .open(addr, &self.settings)
+            .map_err(SessionError::Io)?;
+        Ok(Box::new(SerialSession::new(port, self.timeout)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Serial sessions against a fake port.
+    use super::*;
+    use crate::rm::ResourceManager;
+
+    /// A port with canned input, that times out when it runs out.
+    #[derive(Debug, Default)]
+    struct FakePort {
+        /// What was written.
+        written: Vec<u8>,
+        /// What is left to read.
+        input: io::Cursor<Vec<u8>>,
+        /// The last timeout set.
+        timeout: Option<Duration>,
+        /// The settings it was opened with.
+        settings: SerialSettings,
+    }
+
+    impl Read for FakePort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // A byte at a time, as a slow port would.
+            match self.input.read(&mut buf[..1])? {
+                0 => Err(io::ErrorKind::TimedOut.into()),
+                read => Ok(read),
+            }
+        }
+    }
+
+    impl Write for FakePort {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.written.write(data)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialPort for FakePort {
+        fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+            self.timeout = timeout;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serial_query() {
+        let port = FakePort {
+            input: io::Cursor::new(b"FISA,0,0,0\r\n1.5\r".to_vec()),
+            ..FakePort::default()
+        };
+        let timeout = Duration::from_millis(10);
+        let mut session = SerialSession::new(port, Some(timeout)).unwrap();
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+
+        let mut session = session
+            .with_read_termination(b'\r')
+            .with_write_termination("\r");
+        assert_eq!(session.query("MEAS?").unwrap(), "1.5");
+        assert!(matches!(
+            session.read_line(),
+            Err(SessionError::Timeout(waited)) if waited == timeout
+        ));
+
+        let port = session.into_inner();
+        assert_eq!(port.written, b"*IDN?\nMEAS?\r");
+        assert_eq!(port.timeout, Some(timeout));
+    }
+
+    /// One fake port, given by its device path.
+    #[derive(Debug)]
+    struct FakePorts;
+
+    impl SerialPorts for FakePorts {
+        type Port = FakePort;
+
+        fn list(&mut self) -> io::Result<Vec<AsrlAddress>> {
+            Ok(vec![
+                AsrlAddress::try_new("ASRL/dev/ttyUSB0::INSTR").unwrap()
+            ])
+        }
+
+        fn open(&mut self, addr: &AsrlAddress, settings: &SerialSettings) -> io::Result<FakePort> {
+            if addr.path() != Some("/dev/ttyUSB0") {
+                return Err(io::ErrorKind::NotFound.into());
+            }
+            Ok(FakePort {
+                input: io::Cursor::new(b"FISA,0,0,0\n".to_vec()),
+                settings: *settings,
+                ..FakePort::default()
+            })
+        }
+    }
+
+    #[test]
+    fn serial_backend() {
+        let settings = SerialSettings {
+            baud_rate: 115_200,
+            flow_control: FlowControl::RtsCts,
+            ..SerialSettings::default()
+        };
+        let mut rm = ResourceManager::new();
+        rm.add_backend(SerialBackend::new(FakePorts, settings, None));
+
+        let found = rm.find_resources().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            rm.open(&found[0]).unwrap().query("*IDN?").unwrap(),
+            "FISA,0,0,0"
+        );
+
+        let missing = "ASRL3::INSTR".parse().unwrap();
+        assert!(matches!(
+            rm.open(&missing),
+            Err(crate::rm::RmError::Open {
+                backend: "serial",
+                ..
+            })
+        ));
+
+        let addr = AsrlAddress::try_new("ASRL/dev/ttyUSB0::INSTR").unwrap();
+        assert_eq!(FakePorts.open(&addr, &settings).unwrap().settings, settings);
+    }
+}
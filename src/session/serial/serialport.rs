@@ -0,0 +1,161 @@
+//! Module for finding and opening serial ports with serialport.
+use std::{io, time::Duration};
+
+use ::serialport::{DataBits, SerialPortBuilder};
+
+use super::{FlowControl, Parity, SerialPort, SerialPorts, SerialSettings, StopBits};
+use crate::parse::asrl::{AsrlAddress, AsrlPort};
+
+/// How long reads and writes may take when the session has no timeout, as serialport
+/// always needs one.
+const NO_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+impl SerialPort for Box<dyn ::serialport::SerialPort> {
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        ::serialport::SerialPort::set_timeout(self.as_mut(), timeout.unwrap_or(NO_TIMEOUT))
+            .map_err(io::Error::from)
+    }
+}
+
+/// Finds and opens serial ports with serialport.
+///
+/// Ports are found by their device names, such as `ASRL/dev/ttyUSB0::INSTR` or
+/// `ASRLCOM3::INSTR`. Board numbers count from one, as in NI-VISA, so `ASRL1::INSTR`
+/// is COM1 on Windows and `/dev/ttyS0` elsewhere.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{rm::ResourceManager, session::serial::{SerialBackend, SerialportPorts, SerialSettings}};
+/// let mut rm = ResourceManager::new();
+/// let settings = SerialSettings {
+///     baud_rate: 115_200,
+///     ..SerialSettings::default()
+/// };
+/// rm.add_backend(SerialBackend::new(SerialportPorts, settings, Some(Duration::from_secs(2))));
+/// let mut session = rm.open(&"ASRL/dev/ttyUSB0::INSTR".parse()?)?;
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SerialportPorts;
+
+impl SerialPorts for SerialportPorts {
+    type Port = Box<dyn ::serialport::SerialPort>;
+
+    fn list(&mut self) -> io::Result<Vec<AsrlAddress>> {
+        Ok(::serialport::available_ports()?
+            .into_iter()
+            .filter_map(|port| AsrlAddress::try_new(&format!("ASRL{}::INSTR", port.port_name)).ok())
+            .collect())
+    }
+
+    fn open(&mut self, addr: &AsrlAddress, settings: &SerialSettings) -> io::Result<Self::Port> {
+        Ok(builder(&device_path(addr)?, settings)?.open()?)
+    }
+}
+
+/// The device that the address names.
+fn device_path(addr: &AsrlAddress) -> io::Result<String> {
+    match addr.port() {
+        Some(AsrlPort::Path(path)) => Ok(path.clone()),
+        Some(AsrlPort::Board(board)) if cfg!(windows) && *board > 0 => Ok(format!("COM{board}")),
+        Some(AsrlPort::Board(board)) if *board > 0 => Ok(format!("/dev/ttyS{}", board - 1)),
+        Some(AsrlPort::Board(_)) => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{addr} is not a port, as boards count from 1"),
+        )),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{addr} does not name a port"),
+        )),
+    }
+}
+
+/// A builder for the port with the settings, some of which serialport does not support.
+fn builder(path: &str, settings: &SerialSettings) -> io::Result<SerialPortBuilder> {
+    let unsupported = |setting: String| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{setting} on {path}"))
+    };
+
+    let data_bits = DataBits::try_from(settings.data_bits)
+        .map_err(|()| unsupported(format!("{} data bits are not allowed", settings.data_bits)))?;
+    let parity = match settings.parity {
+        Parity::None => ::serialport::Parity::None,
+        Parity::Odd => ::serialport::Parity::Odd,
+        Parity::Even => ::serialport::Parity::Even,
+        parity @ (Parity::Mark | Parity::Space) => {
+            return Err(unsupported(format!("{parity:?} parity is not supported")))
+        }
+    };
+    let stop_bits = match settings.stop_bits {
+        StopBits::One => ::serialport::StopBits::One,
+        StopBits::Two => ::serialport::StopBits::Two,
+        StopBits::OnePointFive => {
+            return Err(unsupported("1.5 stop bits are not supported".to_string()))
+        }
+    };
+    let flow_control = match settings.flow_control {
+        FlowControl::None => ::serialport::FlowControl::None,
+        FlowControl::XonXoff => ::serialport::FlowControl::Software,
+        FlowControl::RtsCts => ::serialport::FlowControl::Hardware,
+        FlowControl::DtrDsr => {
+            return Err(unsupported(
+                "DTR/DSR flow control is not supported".to_string(),
+            ))
+        }
+    };
+
+    Ok(::serialport::new(path, settings.baud_rate)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .flow_control(flow_control)
+        .timeout(NO_TIMEOUT))
+}
+
+#[cfg(test)]
+mod test {
+    //! Addresses and settings carried over to serialport, without opening a port.
+    use super::*;
+
+    #[test]
+    fn serialport_paths() {
+        let path = |addr| device_path(&AsrlAddress::try_new(addr).unwrap());
+        assert_eq!(path("ASRL/dev/ttyUSB0::INSTR").unwrap(), "/dev/ttyUSB0");
+        assert_eq!(path("ASRLCOM3::INSTR").unwrap(), "COM3");
+        let first = if cfg!(windows) { "COM1" } else { "/dev/ttyS0" };
+        assert_eq!(path("ASRL1::INSTR").unwrap(), first);
+        assert_eq!(
+            path("ASRL0::INSTR").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn serialport_settings() {
+        assert!(builder("COM1", &SerialSettings::default()).is_ok());
+        for settings in [
+            SerialSettings {
+                data_bits: 9,
+                ..SerialSettings::default()
+            },
+            SerialSettings {
+                parity: Parity::Mark,
+                ..SerialSettings::default()
+            },
+            SerialSettings {
+                stop_bits: StopBits::OnePointFive,
+                ..SerialSettings::default()
+            },
+            SerialSettings {
+                flow_control: FlowControl::DtrDsr,
+                ..SerialSettings::default()
+            },
+        ] {
+            let err = builder("COM1", &settings).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{settings:?}");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Module for raw TCP sockets, that many instruments expose SCPI on, usually on port 5025.
+//!
+//! Sockets have no message framing, so messages end at a termination character.
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use super::{
+    io_error, read_pending, read_terminated, stop_at_termination, unbracket, Session, SessionError,
+    Termination,
+};
+use crate::parse::tcpip::TcpipSocketAddress;
+
+/// A raw TCP socket to a `TCPIP::host::port::SOCKET` address.
+///
+/// Commands are sent with the write termination added, which is a newline by default.
+/// Responses are read up to the read termination, which is also a newline by default.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{parse::tcpip::TcpipSocketAddress, session::{Session, socket::SocketSession}};
+/// let addr = TcpipSocketAddress::try_new("TCPIP::10.0.0.5::5025::SOCKET")?;
+/// let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(2)))?
+///     .with_read_termination(b'\r')
+///     .with_write_termination("\r\n");
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct SocketSession {
+    /// The connection.
+    stream: TcpStream,
+    /// Kept so it can be reported when it runs out.
+    timeout: Option<Duration>,
+    /// How messages are framed.
+    termination: Termination,
+    /// Data that was read past the end of a response.
+    pending: Vec<u8>,
+}
+
+impl SocketSession {
+    /// Connects to the address. The timeout is used for connecting as well.
+    ///
+    /// # Errors
+    ///
+    /// If the host does not resolve or no connection could be made.
+    pub fn connect(
+        addr: &TcpipSocketAddress,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let mut last_err = None;
+        let sockets = (unbracket(addr.host()), addr.port())
+            .to_socket_addrs()
+            .map_err(SessionError::Io)?;
+        for socket in sockets {
+            let stream = match timeout {
+                Some(timeout) => TcpStream::connect_timeout(&socket, timeout),
+                None => TcpStream::connect(socket),
+            };
+            match stream {
+                Ok(stream) => {
+                    let mut session = SocketSession {
+                        stream,
+                        timeout,
+                        termination: Termination::default(),
+                        pending: Vec::new(),
+                    };
+                    session.set_timeout(timeout)?;
+                    return Ok(session);
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.map_or_else(
+            || {
+                SessionError::Io(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} did not resolve to any address", addr.host()),
+                ))
+            },
+            |err| io_error(err, timeout),
+        ))
+    }
+
+    /// The same session, but with responses ending at the given ASCII character.
+    #[must_use]
+    pub fn with_read_termination(mut self, termination: u8) -> Self {
+        self.termination = self.termination.with_read(termination);
+        self
+    }
+
+    /// The same session, but adding the given text to the end of commands.
+    /// Can be empty, for commands that are sent with their termination already.
+    #[must_use]
+    pub fn with_write_termination(mut self, termination: impl Into<String>) -> Self {
+        self.termination = self.termination.with_write(termination);
+        self
+    }
+
+    /// The same session, but with messages framed by the termination.
+    #[must_use]
+    pub fn with_termination(mut self, termination: Termination) -> Self {
+        self.termination = termination;
+        self
+    }
+
+    /// The character that responses end with.
+    pub fn read_termination(&self) -> u8 {
+        self.termination.read
+    }
+
+    /// What is added to the end of commands.
+    pub fn write_termination(&self) -> &str {
+        &self.termination.write
+    }
+
+    /// Takes the stream back out. Any data that was read but not returned is lost.
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+impl Session for SocketSession {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        self.stream
+            .write(data)
+            .map_err(|err| io_error(err, self.timeout))
+    }
+
+    /// Reads some data, stopping after the read termination if it is enabled.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        let read = if self.pending.is_empty() {
+            self.stream
+                .read(buf)
+                .map_err(|err| io_error(err, self.timeout))?
+        } else {
+            read_pending(&mut self.pending, buf)
+        };
+        Ok(stop_at_termination(
+            &mut self.pending,
+            buf,
+            read,
+            &self.termination,
+        ))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        self.stream
+            .set_read_timeout(timeout)
+            .and_then(|()| self.stream.set_write_timeout(timeout))
+            .map_err(SessionError::Io)?;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn termination(&self) -> Option<&Termination> {
+        Some(&self.termination)
+    }
+
+    fn set_termination(&mut self, termination: Termination) -> Result<(), SessionError> {
+        self.termination = termination;
+        Ok(())
+    }
+
+    /// Sends a command, adding the write termination if it is enabled and the command
+    /// does not end with it already.
+    fn send(&mut self, command: &str) -> Result<(), SessionError> {
+        let message = self.termination.terminate(command);
+        self.write_all(message.as_bytes())
+    }
+
+    /// Reads a response up to and including the read termination.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let (stream, timeout) = (&mut self.stream, self.timeout);
+        read_terminated(&mut self.pending, self.termination.read, |chunk| {
+            stream.read(chunk).map_err(|err| io_error(err, timeout))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Sockets against a local listener.
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    /// Accepts one connection and hands it to the server.
+    fn serve(
+        server: impl FnOnce(TcpStream) + Send + 'static,
+    ) -> (TcpipSocketAddress, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || server(listener.accept().unwrap().0));
+        let addr =
+            TcpipSocketAddress::try_new(&format!("TCPIP::127.0.0.1::{port}::SOCKET")).unwrap();
+        (addr, server)
+    }
+
+    #[test]
+    fn socket_query() {
+        let (addr, server) = serve(|stream| {
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            assert_eq!(line, "*IDN?\n");
+            (&stream).write_all(b"FISA,0,0,0\n").unwrap();
+            // Hold the connection open until the client gives up.
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+        });
+
+        let timeout = Duration::from_millis(50);
+        let mut session = SocketSession::connect(&addr, Some(timeout)).unwrap();
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+        assert!(matches!(
+            session.read_line(),
+            Err(SessionError::Timeout(waited)) if waited == timeout
+        ));
+        drop(session);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn socket_termination() {
+        let (addr, server) = serve(|stream| {
+            let mut command = [0; 7];
+            (&stream).read_exact(&mut command).unwrap();
+            assert_eq!(&command, b"*IDN?\r\n");
+            // Two responses at once, split mid-response, with the CR that was asked for.
+            (&stream).write_all(b"FISA,0,0,0\r1.5").unwrap();
+            (&stream).flush().unwrap();
+            thread::sleep(Duration::from_millis(10));
+            (&stream).write_all(b"\rextra").unwrap();
+        });
+
+        let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(1)))
+            .unwrap()
+            .with_read_termination(b'\r')
+            .with_write_termination("\r\n");
+        assert_eq!(session.read_termination(), b'\r');
+        assert_eq!(session.write_termination(), "\r\n");
+
+        session.send("*IDN?\r\n").unwrap();
+        // Reads stop at the read termination, even with more data in the buffer.
+        let mut buf = [0; 32];
+        assert_eq!(session.read(&mut buf).unwrap(), 11);
+        assert_eq!(&buf[..11], b"FISA,0,0,0\r");
+        assert_eq!(session.read_line().unwrap(), "1.5\r");
+
+        let termination = session.termination().unwrap().clone().without_read();
+        session.set_termination(termination).unwrap();
+        assert_eq!(session.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"extra");
+        assert!(matches!(session.read_line(), Err(SessionError::Closed)));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn socket_binary_block() {
+        let (addr, server) = serve(|stream| {
+            (&stream)
+                .write_all(b"#15a\nb\nc\n#13xyz#0\x01\x02\n#9100000000ab")
+                .unwrap();
+        });
+
+        let mut session = SocketSession::connect(&addr, Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(session.read_binary_block().unwrap(), b"a\nb\nc");
+        // Without a read termination, nothing after the block is read.
+        let termination = session.termination().unwrap().clone().without_read();
+        session.set_termination(termination).unwrap();
+        assert_eq!(session.read_binary_block().unwrap(), b"xyz");
+        assert_eq!(session.read_binary_block().unwrap(), b"\x01\x02");
+        // A block far longer than what arrives is read as it comes, not allocated up front.
+        assert!(matches!(
+            session.read_binary_block(),
+            Err(SessionError::Closed)
+        ));
+        assert!(matches!(session.read_line(), Err(SessionError::Closed)));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,599 @@
+//! Module for the USBTMC protocol, that USB instruments are spoken to with.
+//!
+//! See the USBTMC 1.0 specification from the USB-IF. Only the bulk message protocol is
+//! implemented. [`NusbDevices`] finds and opens devices with nusb, and any other USB library
+//! can be used through [`BulkEndpoints`] and [`UsbDevices`].
+use std::{fmt::Debug, io, time::Duration};
+
+mod nusb;
+pub use self::nusb::{NusbDevices, NusbEndpoints};
+
+use super::{io_error, protocol_error, Session, SessionError};
+use crate::{
+    parse::{usb::UsbAddress, VisaAddress},
+    rm::Backend,
+};
+
+/// Size of the header before every bulk message.
+const HEADER_LEN: usize = 12;
+
+/// MsgID of a message from the host to the device.
+const DEV_DEP_MSG_OUT: u8 = 1;
+
+/// MsgID of a request for the device to send a message, and of the message it sends.
+const REQUEST_DEV_DEP_MSG_IN: u8 = 2;
+
+/// Set in bmTransferAttributes on the last transfer of a message.
+const EOM: u8 = 0b1;
+
+/// The most bytes a transfer holds, for endpoints that do not say.
+const DEFAULT_MAX_TRANSFER: usize = 1024 * 1024;
+
+/// The bulk endpoints of an opened USBTMC interface, from whichever USB library.
+pub trait BulkEndpoints: Debug {
+    /// Writes to the Bulk-OUT endpoint, returning how many bytes were sent.
+    ///
+    /// # Errors
+    ///
+    /// If the transfer failed, or took longer than the timeout, as [`io::ErrorKind::TimedOut`].
+    fn bulk_out(&mut self, data: &[u8], timeout: Option<Duration>) -> io::Result<usize>;
+
+    /// Reads from the Bulk-IN endpoint, returning how many bytes were received.
+    ///
+    /// # Errors
+    ///
+    /// If the transfer failed, or took longer than the timeout, as [`io::ErrorKind::TimedOut`].
+    fn bulk_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize>;
+
+    /// The most bytes one Bulk-IN transfer may hold, header included, which bounds how much
+    /// is asked of the device, and buffered, at once. A megabyte unless overridden.
+    fn max_transfer_size(&self) -> usize {
+        DEFAULT_MAX_TRANSFER
+    }
+
+    /// Aborts the Bulk-IN transfer with the bTag, after a request for it timed out, with
+    /// the INITIATE_ABORT_BULK_IN control request.
+    ///
+    /// The default does nothing. Either way, the session drops a late response to a request
+    /// that timed out if it does arrive.
+    ///
+    /// # Errors
+    ///
+    /// If the control requests failed.
+    fn abort_bulk_in(&mut self, _tag: u8, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A session to a USBTMC device.
+///
+/// Each [`write`](Session::write) is sent as one whole message. Reads request as much as
+/// the read buffer holds, and keep what does not fit for the next read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use fisa::{parse::usb::UsbAddress, session::{Session, usbtmc::{NusbEndpoints, UsbtmcSession}}};
+/// let addr = UsbAddress::try_new("USB::0x1A34::0x5678::A22-5::INSTR")?;
+/// let endpoints = NusbEndpoints::open(&addr)?;
+/// let mut session = UsbtmcSession::new(endpoints, Some(std::time::Duration::from_secs(2)));
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct UsbtmcSession<E> {
+    /// The USB library's endpoints.
+    endpoints: E,
+    /// How long each transfer may take.
+    timeout: Option<Duration>,
+    /// The bTag of the last transfer, which is never zero.
+    tag: u8,
+    /// Response data that has not been read yet.
+    pending: Vec<u8>,
+    /// Whether the device ended the message in `pending`.
+    end_of_message: bool,
+    /// The bTags of requests that timed out, whose responses may still arrive.
+    abandoned: Vec<u8>,
+}
+
+impl<E: BulkEndpoints> UsbtmcSession<E> {
+    /// Speaks USBTMC over the endpoints.
+    pub fn new(endpoints: E, timeout: Option<Duration>) -> Self {
+        UsbtmcSession {
+            endpoints,
+            timeout,
+            tag: 0,
+            pending: Vec::new(),
+            end_of_message: true,
+            abandoned: Vec::new(),
+        }
+    }
+
+    /// Takes the endpoints back out.
+    pub fn into_inner(self) -> E {
+        self.endpoints
+    }
+
+    /// The bTag for the next transfer, counting 1 to 255 and around again.
+    fn next_tag(&mut self) -> u8 {
+        self.tag = self.tag.checked_add(1).unwrap_or(1);
+        self.tag
+    }
+
+    /// The header shared by both of the messages sent to the device.
+    fn header(&mut self, msg_id: u8, transfer_size: u32, attributes: u8) -> [u8; HEADER_LEN] {
+        let tag = self.next_tag();
+        let size = transfer_size.to_le_bytes();
+        [
+            msg_id, tag, !tag, 0, size[0], size[1], size[2], size[3], attributes, 0, 0, 0,
+        ]
+    }
+
+    /// Sends a whole transfer, which may take more than one bulk write.
+    fn send(&mut self, mut transfer: &[u8]) -> Result<(), SessionError> {
+        while !transfer.is_empty() {
+            match self.endpoints.bulk_out(transfer, self.timeout) {
+                Ok(0) => return Err(SessionError::Closed),
+                Ok(sent) => transfer = &transfer[sent..],
+                Err(err) => return Err(io_error(err, self.timeout)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks the device for up to `max` bytes, and stores what it sends in `pending`.
+    fn request(&mut self, max: u32) -> Result<(), SessionError> {
+        // Asking for more than a transfer holds would just grow the buffer.
+        let limit = self
+            .endpoints
+            .max_transfer_size()
+            .saturating_sub(HEADER_LEN + 3)
+            .max(1);
+        let max = max.min(u32::try_from(limit).unwrap_or(u32::MAX));
+        let header = self.header(REQUEST_DEV_DEP_MSG_IN, max, 0);
+        let tag = header[1];
+        self.send(&header)?;
+
+        match self.receive(tag, max) {
+            Err(SessionError::Timeout(timeout)) => {
+                // The device may still answer, which must not be taken for the next response.
+                // Aborting is best effort, as a late response is dropped when it arrives anyway.
+                let _ = self.endpoints.abort_bulk_in(tag, self.timeout);
+                if !self.abandoned.contains(&tag) {
+                    self.abandoned.push(tag);
+                }
+                Err(SessionError::Timeout(timeout))
+            }
+            result => result,
+        }
+    }
+
+    /// Receives the response to the request with the bTag, dropping any late responses to
+    /// requests that timed out.
+    fn receive(&mut self, tag: u8, max: u32) -> Result<(), SessionError> {
+        // The response may be split across bulk reads, so read until the header's size is here.
+        let mut response = vec![0; HEADER_LEN + max as usize + 3];
+        let mut received = 0;
+        let mut expected = HEADER_LEN;
+        while received < expected {
+            received += self.bulk_in(&mut response[received..])?;
+            if received < HEADER_LEN {
+                continue;
+            }
+
+            let size = u32::from_le_bytes([response[4], response[5], response[6], response[7]]);
+            let late = response[1];
+            if response[0] == REQUEST_DEV_DEP_MSG_IN
+                && response[2] == !late
+                && self.abandoned.contains(&late)
+            {
+                let late_len = HEADER_LEN + size as usize;
+                // Devices pad each transfer to four bytes, and the padding is read along with it.
+                let padded_len = HEADER_LEN + (size as usize).next_multiple_of(4);
+                if received > late_len {
+                    // The read went past the late response, into the next one.
+                    let skip = padded_len.min(received);
+                    response.copy_within(skip..received, 0);
+                    received -= skip;
+                } else {
+                    while received < late_len {
+                        let len = (padded_len - received).min(response.len());
+                        received += self.bulk_in(&mut response[..len])?;
+                    }
+                    received = 0;
+                }
+                self.abandoned.retain(|abandoned| *abandoned != late);
+                expected = HEADER_LEN;
+                continue;
+            }
+
+            if response[0] != REQUEST_DEV_DEP_MSG_IN || response[1] != tag || response[2] != !tag {
+                return Err(protocol_error(format!(
+                    "Expected a response to bTag {tag}, found {:02X?}",
+                    &response[..HEADER_LEN]
+                )));
+            }
+            if size > max {
+                return Err(protocol_error(format!(
+                    "Asked for at most {max} bytes, but the device is sending {size}"
+                )));
+            }
+            expected = HEADER_LEN + size as usize;
+        }
+
+        // Responses come in order, so any earlier ones are not coming anymore.
+        self.abandoned.clear();
+        self.end_of_message = response[8] & EOM != 0;
+        self.pending
+            .extend_from_slice(&response[HEADER_LEN..expected]);
+        Ok(())
+    }
+
+    /// Reads once from the Bulk-IN endpoint.
+    fn bulk_in(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        match self.endpoints.bulk_in(buf, self.timeout) {
+            Ok(0) => Err(SessionError::Closed),
+            Ok(read) => Ok(read),
+            Err(err) => Err(io_error(err, self.timeout)),
+        }
+    }
+}
+
+impl<E: BulkEndpoints> Session for UsbtmcSession<E> {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        let size = u32::try_from(data.len()).map_err(|_| {
+            SessionError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "USBTMC messages are at most 4 GiB",
+            ))
+        })?;
+
+        let mut transfer = Vec::with_capacity(HEADER_LEN + data.len() + 3);
+        transfer.extend_from_slice(&self.header(DEV_DEP_MSG_OUT, size, EOM));
+        transfer.extend_from_slice(data);
+        // Transfers are padded to four bytes.
+        transfer.resize(transfer.len().next_multiple_of(4), 0);
+        self.send(&transfer)?;
+        Ok(data.len())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            let max = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+            self.request(max)?;
+        }
+        let read = buf.len().min(self.pending.len());
+        buf[..read].copy_from_slice(&self.pending[..read]);
+        self.pending.drain(..read);
+        Ok(read)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Reads a whole message, ending when the device sets EOM rather than at a newline.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = std::mem::take(&mut self.pending);
+        while message.is_empty() || !self.end_of_message {
+            self.request(1024 * 1024)?;
+            message.append(&mut self.pending);
+        }
+        Ok(message)
+    }
+}
+
+/// Lists and opens the USB devices attached, with whichever USB library.
+pub trait UsbDevices: Debug {
+    /// The bulk endpoints of an opened device.
+    type Endpoints: BulkEndpoints + 'static;
+
+    /// The USBTMC devices attached right now.
+    ///
+    /// # Errors
+    ///
+    /// If the USB library failed to list devices.
+    fn list(&mut self) -> io::Result<Vec<UsbAddress>>;
+
+    /// Opens the USBTMC interface of the device.
+    ///
+    /// # Errors
+    ///
+    /// If the device is not attached, or could not be claimed.
+    fn open(&mut self, addr: &UsbAddress) -> io::Result<Self::Endpoints>;
+}
+
+/// Opens `USB::...::INSTR` addresses with USBTMC.
+#[derive(Debug)]
+pub struct UsbtmcBackend<D> {
+    /// The USB library.
+    devices: D,
+    /// The timeout given to each session.
+    timeout: Option<Duration>,
+}
+
+impl<D: UsbDevices> UsbtmcBackend<D> {
+    /// A backend that finds and opens devices with the USB library.
+    pub fn new(devices: D, timeout: Option<Duration>) -> Self {
+        UsbtmcBackend { devices, timeout }
+    }
+}
+
+impl<D: UsbDevices> Backend for UsbtmcBackend<D> {
+    fn name(&self) -> &'static str {
+        "usbtmc"
+    }
+
+    fn supports(&self, addr: &VisaAddress) -> bool {
+        matches!(addr, VisaAddress::Usb(usb) if usb.is_instr())
+    }
+
+    fn find_resources(&mut self) -> io::Result<Vec<VisaAddress>> {
+        Ok(self
+            .devices
+            .list()?
+            .into_iter()
+            .map(VisaAddress::from)
+            .collect())
+    }
+
+    fn open(&mut self, addr: &VisaAddress) -> Result<Box<dyn Session>, SessionError> {
+        let VisaAddress::Usb(addr) = addr else {
+            return Err(SessionError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{addr} is not a USB address"),
+            )));
+        };
+        let endpoints = self.devices.open(addr).map_err(SessionError::Io)?;
+        Ok(Box::new(UsbtmcSession::new(endpoints, self.timeout)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! The bulk message protocol against a fake device.
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::rm::ResourceManager;
+
+    /// A device that answers each request from a queue of responses.
+    #[derive(Debug, Default)]
+    struct FakeDevice {
+        /// Every transfer the host sent.
+        sent: Vec<Vec<u8>>,
+        /// Messages to answer with, each split into transfers by what the host asks for.
+        responses: VecDeque<Vec<u8>>,
+        /// Bulk-IN reads waiting to be read, at most 8 bytes at a time.
+        bulk_in: VecDeque<u8>,
+        /// Holds back the next response until the host sends something else.
+        hold: bool,
+        /// A response held back, that the device is late with.
+        held: Vec<u8>,
+        /// The bTags of the Bulk-IN transfers the host aborted.
+        aborted: Vec<u8>,
+        /// The most a transfer may hold, if not the default.
+        max_transfer: Option<usize>,
+    }
+
+    impl BulkEndpoints for FakeDevice {
+        fn bulk_out(&mut self, data: &[u8], _timeout: Option<Duration>) -> io::Result<usize> {
+            self.sent.push(data.to_vec());
+            self.bulk_in.extend(self.held.drain(..));
+            if data[0] == REQUEST_DEV_DEP_MSG_IN {
+                let max = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+                let Some(message) = self.responses.front_mut() else {
+                    return Ok(data.len());
+                };
+                let chunk: Vec<u8> = message.drain(..max.min(message.len())).collect();
+                let eom = message.is_empty();
+                if eom {
+                    self.responses.pop_front();
+                }
+                let size = u32::try_from(chunk.len()).unwrap().to_le_bytes();
+                let mut response = vec![2, data[1], data[2], 0];
+                response.extend(size);
+                response.extend([u8::from(eom), 0, 0, 0]);
+                response.extend(chunk);
+                // Transfers are padded to four bytes.
+                response.resize(response.len().next_multiple_of(4), 0);
+                if std::mem::take(&mut self.hold) {
+                    self.held = response;
+                } else {
+                    self.bulk_in.extend(response);
+                }
+            }
+            Ok(data.len())
+        }
+
+        fn bulk_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+            if self.bulk_in.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{timeout:?}"),
+                ));
+            }
+            let read = buf.len().min(self.bulk_in.len()).min(8);
+            for (byte, received) in buf.iter_mut().zip(self.bulk_in.drain(..read)) {
+                *byte = received;
+            }
+            Ok(read)
+        }
+
+        fn max_transfer_size(&self) -> usize {
+            self.max_transfer.unwrap_or(DEFAULT_MAX_TRANSFER)
+        }
+
+        fn abort_bulk_in(&mut self, tag: u8, _timeout: Option<Duration>) -> io::Result<()> {
+            self.aborted.push(tag);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn usbtmc_query() {
+        let device = FakeDevice {
+            responses: [b"FISA,0,0,0\n".to_vec()].into(),
+            ..FakeDevice::default()
+        };
+        let mut session = UsbtmcSession::new(device, None);
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+
+        let device = session.into_inner();
+        assert_eq!(
+            device.sent[0],
+            [1, 1, 0xFE, 0, 6, 0, 0, 0, 1, 0, 0, 0, b'*', b'I', b'D', b'N', b'?', b'\n', 0, 0]
+        );
+        assert_eq!(device.sent[1][..4], [2, 2, 0xFD, 0]);
+        assert_eq!(device.sent[1][8], 0);
+    }
+
+    #[test]
+    fn usbtmc_split_read() {
+        let device = FakeDevice {
+            responses: [b"0123456789".to_vec(), b"ABC".to_vec()].into(),
+            ..FakeDevice::default()
+        };
+        let mut session = UsbtmcSession::new(device, Some(Duration::from_millis(1)));
+
+        let mut buf = [0; 4];
+        assert_eq!(session.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"0123");
+        assert!(!session.end_of_message);
+        assert_eq!(session.read_line().unwrap(), "456789");
+        assert_eq!(session.read_line().unwrap(), "ABC");
+        assert!(matches!(session.read_line(), Err(SessionError::Timeout(_))));
+    }
+
+    #[test]
+    fn usbtmc_tags_wrap() {
+        let mut session = UsbtmcSession::new(FakeDevice::default(), None);
+        for _ in 0..300 {
+            session.write(b"*CLS\n").unwrap();
+        }
+        let tags: Vec<u8> = session
+            .into_inner()
+            .sent
+            .iter()
+            .map(|sent| sent[1])
+            .collect();
+        assert!(!tags.contains(&0));
+        assert_eq!(tags[254..257], [255, 1, 2]);
+    }
+
+    #[test]
+    fn usbtmc_bad_tag() {
+        let mut device = FakeDevice::default();
+        device.bulk_in.extend([2, 9, !9, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+        let mut session = UsbtmcSession::new(device, None);
+        assert!(matches!(
+            session.read(&mut [0; 4]),
+            Err(SessionError::Io(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn usbtmc_late_response() {
+        // Late answers that end before and after the first reads, both padded.
+        for late in [&b"LATE\n"[..], b"L\n"] {
+            let device = FakeDevice {
+                responses: [late.to_vec(), b"FISA,0,0,0\n".to_vec()].into(),
+                hold: true,
+                ..FakeDevice::default()
+            };
+            let mut session = UsbtmcSession::new(device, Some(Duration::from_millis(1)));
+            assert!(matches!(
+                session.query("*IDN?"),
+                Err(SessionError::Timeout(_))
+            ));
+            // The late answer to the first query arrives ahead of the answer to the second.
+            assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+            assert!(session.abandoned.is_empty());
+            assert_eq!(session.into_inner().aborted, [2]);
+        }
+    }
+
+    #[test]
+    fn usbtmc_max_transfer() {
+        let device = FakeDevice {
+            responses: [vec![b'x'; 100]].into(),
+            max_transfer: Some(64),
+            ..FakeDevice::default()
+        };
+        let mut session = UsbtmcSession::new(device, None);
+        let mut buf = [0; 1000];
+        assert_eq!(session.read(&mut buf).unwrap(), 49);
+
+        let device = session.into_inner();
+        assert_eq!(device.sent[0][4..8], 49u32.to_le_bytes());
+    }
+
+    #[test]
+    fn usbtmc_binary_block() {
+        let device = FakeDevice {
+            responses: [
+                b"#0a\nb\nc\n".to_vec(),
+                b"#15d\ne\nf".to_vec(),
+                b"#13ghi\n".to_vec(),
+            ]
+            .into(),
+            ..FakeDevice::default()
+        };
+        let mut session = UsbtmcSession::new(device, None);
+        // Indefinite blocks end with the message, not at the first newline.
+        assert_eq!(session.read_binary_block().unwrap(), b"a\nb\nc");
+        assert_eq!(session.read_binary_block().unwrap(), b"d\ne\nf");
+        assert_eq!(session.read_binary_block().unwrap(), b"ghi");
+        // Each block is one request, rather than one for each byte.
+        assert_eq!(session.into_inner().sent.len(), 3);
+    }
+
+    /// A single fake device on the bus.
+    #[derive(Debug)]
+    struct FakeBus;
+
+    impl UsbDevices for FakeBus {
+        type Endpoints = FakeDevice;
+
+        fn list(&mut self) -> io::Result<Vec<UsbAddress>> {
+            Ok(vec![UsbAddress::try_new(
+                "USB::0x1A34::0x5678::A22-5::INSTR",
+            )
+            .unwrap()])
+        }
+
+        fn open(&mut self, _addr: &UsbAddress) -> io::Result<FakeDevice> {
+            Ok(FakeDevice {
+                responses: [b"FISA,0,0,0\n".to_vec()].into(),
+                ..FakeDevice::default()
+            })
+        }
+    }
+
+    #[test]
+    fn usbtmc_backend() {
+        let mut rm = ResourceManager::new();
+        rm.add_backend(UsbtmcBackend::new(FakeBus, None));
+
+        let found = rm.find_resources().unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            rm.open(&found[0]).unwrap().query("*IDN?").unwrap(),
+            "FISA,0,0,0"
+        );
+
+        let raw = "USB::0x1A34::0x5678::A22-5::RAW".parse().unwrap();
+        assert!(matches!(
+            rm.open(&raw),
+            Err(crate::rm::RmError::NoBackend(_))
+        ));
+    }
+}
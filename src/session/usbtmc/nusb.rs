@@ -0,0 +1,266 @@
+//! Module for finding and opening USBTMC devices with nusb.
+use std::{io, thread, time::Duration};
+
+use ::nusb::{
+    descriptors::TransferType,
+    transfer::{
+        Buffer, Bulk, ControlIn, ControlType, Direction, In, Out, Recipient, TransferError,
+    },
+    DeviceInfo, Endpoint, Interface, MaybeFuture,
+};
+
+use super::{BulkEndpoints, UsbDevices};
+use crate::parse::usb::{UsbAddress, UsbClass};
+
+/// The interface class of USBTMC, which is application specific.
+const USBTMC_CLASS: u8 = 0xFE;
+
+/// The interface subclass of USBTMC.
+const USBTMC_SUBCLASS: u8 = 0x03;
+
+/// bRequest of INITIATE_ABORT_BULK_IN.
+const INITIATE_ABORT_BULK_IN: u8 = 3;
+
+/// bRequest of CHECK_ABORT_BULK_IN_STATUS.
+const CHECK_ABORT_BULK_IN_STATUS: u8 = 4;
+
+/// USBTMC_status of a control request that succeeded.
+const STATUS_SUCCESS: u8 = 0x01;
+
+/// USBTMC_status of a control request that is still in progress.
+const STATUS_PENDING: u8 = 0x02;
+
+/// How long transfers may take when the session has no timeout, as nusb always needs one.
+const NO_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The bulk endpoints of a claimed USBTMC interface, through nusb.
+#[derive(Debug)]
+pub struct NusbEndpoints {
+    /// The claimed interface, for control requests.
+    interface: Interface,
+    /// The Bulk-OUT endpoint.
+    bulk_out: Endpoint<Bulk, Out>,
+    /// The Bulk-IN endpoint.
+    bulk_in: Endpoint<Bulk, In>,
+    /// What the last Bulk-IN transfer received that has not been read yet.
+    received: Vec<u8>,
+}
+
+impl NusbEndpoints {
+    /// Opens the USBTMC interface of the device at the address, detaching any kernel driver
+    /// from it, such as Linux's usbtmc. The lowest USBTMC interface is opened if the address
+    /// does not have an interface number.
+    ///
+    /// # Errors
+    ///
+    /// If the device is not attached, has no such USBTMC interface, or could not be claimed.
+    pub fn open(addr: &UsbAddress) -> io::Result<Self> {
+        let device = ::nusb::list_devices()
+            .wait()?
+            .find(|device| {
+                device.vendor_id() == addr.manufacturer_id()
+                    && device.product_id() == addr.model_code()
+                    && device.serial_number() == Some(addr.serial_number())
+            })
+            .ok_or_else(|| not_found(format!("{addr} is not attached")))?;
+        let number = usbtmc_interfaces(&device)
+            .into_iter()
+            .find(|number| {
+                addr.interface_number()
+                    .is_none_or(|wanted| u16::from(*number) == wanted)
+            })
+            .ok_or_else(|| not_found(format!("{addr} has no such USBTMC interface")))?;
+
+        let interface = device
+            .open()
+            .wait()?
+            .detach_and_claim_interface(number)
+            .wait()?;
+        let (out_address, in_address) = {
+            let descriptor = interface
+                .descriptor()
+                .ok_or_else(|| not_found(format!("{addr} has no interface descriptor")))?;
+            let bulk = |direction| {
+                descriptor
+                    .endpoints()
+                    .find(|endpoint| {
+                        endpoint.transfer_type() == TransferType::Bulk
+                            && endpoint.direction() == direction
+                    })
+                    .map(|endpoint| endpoint.address())
+                    .ok_or_else(|| not_found(format!("{addr} is missing a bulk endpoint")))
+            };
+            (bulk(Direction::Out)?, bulk(Direction::In)?)
+        };
+
+        Ok(NusbEndpoints {
+            bulk_out: interface.endpoint(out_address)?,
+            bulk_in: interface.endpoint(in_address)?,
+            interface,
+            received: Vec::new(),
+        })
+    }
+
+    /// Sends a USBTMC class request for the Bulk-IN endpoint, returning its response.
+    fn control_in(
+        &self,
+        request: u8,
+        value: u16,
+        length: u16,
+        timeout: Duration,
+    ) -> io::Result<Vec<u8>> {
+        self.interface
+            .control_in(
+                ControlIn {
+                    control_type: ControlType::Class,
+                    recipient: Recipient::Endpoint,
+                    request,
+                    value,
+                    index: u16::from(self.bulk_in.endpoint_address()),
+                    length,
+                },
+                timeout,
+            )
+            .wait()
+            .map_err(transfer_error)
+    }
+
+    /// Reads and drops Bulk-IN packets until a short one ends the transfer.
+    fn drain_bulk_in(&mut self, timeout: Duration) -> io::Result<()> {
+        self.received.clear();
+        let packet = self.bulk_in.max_packet_size();
+        loop {
+            let completion = self.bulk_in.transfer_blocking(Buffer::new(packet), timeout);
+            completion.status.map_err(transfer_error)?;
+            if completion.actual_len < packet {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl BulkEndpoints for NusbEndpoints {
+    fn bulk_out(&mut self, data: &[u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let completion = self
+            .bulk_out
+            .transfer_blocking(Buffer::from(data), timeout.unwrap_or(NO_TIMEOUT));
+        completion.status.map_err(transfer_error)?;
+        Ok(completion.actual_len)
+    }
+
+    fn bulk_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        // IN transfers are whole packets, so what does not fit is kept for the next read.
+        let packet = self.bulk_in.max_packet_size();
+        while self.received.is_empty() {
+            let len = buf.len().div_ceil(packet).max(1) * packet;
+            let completion = self
+                .bulk_in
+                .transfer_blocking(Buffer::new(len), timeout.unwrap_or(NO_TIMEOUT));
+            completion.status.map_err(transfer_error)?;
+            let received = completion.actual_len;
+            self.received = completion.buffer.into_vec();
+            self.received.truncate(received);
+        }
+
+        let read = buf.len().min(self.received.len());
+        buf[..read].copy_from_slice(&self.received[..read]);
+        self.received.drain(..read);
+        Ok(read)
+    }
+
+    fn abort_bulk_in(&mut self, tag: u8, timeout: Option<Duration>) -> io::Result<()> {
+        let timeout = timeout.unwrap_or(NO_TIMEOUT);
+        let initiated = self.control_in(INITIATE_ABORT_BULK_IN, u16::from(tag), 2, timeout)?;
+        if initiated.first() != Some(&STATUS_SUCCESS) {
+            // No transfer in progress, the device may have sent it already.
+            return Ok(());
+        }
+
+        self.drain_bulk_in(timeout)?;
+        loop {
+            let status = self.control_in(CHECK_ABORT_BULK_IN_STATUS, 0, 8, timeout)?;
+            match status.as_slice() {
+                // bmAbortBulkIn D0 says the device still has data queued to be read.
+                [STATUS_PENDING, queued, ..] if queued & 1 != 0 => self.drain_bulk_in(timeout)?,
+                [STATUS_PENDING, ..] => thread::sleep(Duration::from_millis(1)),
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Finds and opens USBTMC devices with nusb.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{rm::ResourceManager, session::usbtmc::{NusbDevices, UsbtmcBackend}};
+/// let mut rm = ResourceManager::new();
+/// rm.add_backend(UsbtmcBackend::new(NusbDevices, Some(Duration::from_secs(2))));
+/// for addr in rm.find_resources()? {
+///     println!("{addr}");
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NusbDevices;
+
+impl UsbDevices for NusbDevices {
+    type Endpoints = NusbEndpoints;
+
+    fn list(&mut self) -> io::Result<Vec<UsbAddress>> {
+        let mut found = Vec::new();
+        for device in ::nusb::list_devices().wait()? {
+            // Devices without a serial number can not be written as an address.
+            let Some(serial) = device.serial_number() else {
+                continue;
+            };
+            let interfaces = usbtmc_interfaces(&device);
+            for number in &interfaces {
+                let mut builder = UsbAddress::builder()
+                    .manufacturer_id(device.vendor_id())
+                    .model_code(device.product_id())
+                    .serial_number(serial)
+                    .class(UsbClass::Instr);
+                // The lowest interface is used when there is no number, as with most devices.
+                if interfaces.len() > 1 {
+                    builder = builder.interface_number(u16::from(*number));
+                }
+                found.extend(builder.build());
+            }
+        }
+        Ok(found)
+    }
+
+    fn open(&mut self, addr: &UsbAddress) -> io::Result<NusbEndpoints> {
+        NusbEndpoints::open(addr)
+    }
+}
+
+/// The interface numbers of the device's USBTMC interfaces, lowest first.
+fn usbtmc_interfaces(device: &DeviceInfo) -> Vec<u8> {
+    let mut interfaces: Vec<u8> = device
+        .interfaces()
+        .filter(|interface| {
+            interface.class() == USBTMC_CLASS && interface.subclass() == USBTMC_SUBCLASS
+        })
+        .map(|interface| interface.interface_number())
+        .collect();
+    interfaces.sort_unstable();
+    interfaces.dedup();
+    interfaces
+}
+
+/// An error for a device or interface that is not there.
+fn not_found(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+/// Turns a transfer that nusb cancelled at its timeout into [`io::ErrorKind::TimedOut`].
+fn transfer_error(err: TransferError) -> io::Error {
+    match err {
+        TransferError::Cancelled => io::Error::new(io::ErrorKind::TimedOut, err),
+        err => err.into(),
+    }
+}
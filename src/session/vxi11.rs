@@ -0,0 +1,724 @@
+//! Module for VXI-11, the ONC RPC protocol that most LAN instruments speak.
+//!
+//! Only the core channel is implemented: create_link, device_write, device_read,
+//! and destroy_link. See the VXI-11 specification from the VXIbus Consortium,
+//! and RFC 5531 for ONC RPC.
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use super::{io_error, protocol_error, unbracket, Session, SessionError};
+use crate::parse::tcpip::TcpipAddress;
+
+/// The port that the portmapper listens on.
+pub(super) const PORTMAPPER_PORT: u16 = 111;
+
+/// The portmapper's RPC program number and version.
+pub(super) const PORTMAPPER: (u32, u32) = (100_000, 2);
+
+/// The portmapper procedure that looks up the port of a program.
+pub(super) const PMAPPROC_GETPORT: u32 = 3;
+
+/// The VXI-11 core channel's RPC program number and version.
+pub(super) const DEVICE_CORE: (u32, u32) = (0x0006_07AF, 1);
+
+/// The core channel procedures.
+pub(super) const CREATE_LINK: u32 = 10;
+/// Writes to a link.
+pub(super) const DEVICE_WRITE: u32 = 11;
+/// Reads from a link.
+pub(super) const DEVICE_READ: u32 = 12;
+/// Closes a link.
+pub(super) const DESTROY_LINK: u32 = 23;
+
+/// Set in the flags of the last write of a message.
+const FLAG_END: u32 = 0x08;
+
+/// Set in the reason of a read that ended at a termination character.
+const REASON_CHR: u32 = 0x02;
+/// Set in the reason of a read that ended at the end of the message.
+const REASON_END: u32 = 0x04;
+
+/// The device error code for a timed out operation.
+const ERR_IO_TIMEOUT: u32 = 15;
+
+/// How much is asked for when reading a whole message, and the most asked for by any read.
+pub(super) const READ_CHUNK: u32 = 1024 * 1024;
+
+/// The longest record accepted, which is a whole read with room for the reply around it.
+const MAX_RECORD_LEN: usize = READ_CHUNK as usize + 1024;
+
+/// Appends XDR encoded values to a buffer.
+#[derive(Debug, Default)]
+pub(super) struct XdrWriter(Vec<u8>);
+
+impl XdrWriter {
+    /// Appends an unsigned integer, which is also how XDR encodes bools and enums.
+    pub(super) fn u32(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Appends variable length data, padded to four bytes.
+    fn opaque(self, data: &[u8]) -> Result<Self, SessionError> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| protocol_error("Data is too long for XDR".to_string()))?;
+        let mut writer = self.u32(len);
+        writer.0.extend_from_slice(data);
+        writer.0.resize(writer.0.len().next_multiple_of(4), 0);
+        Ok(writer)
+    }
+}
+
+/// Reads XDR encoded values from a reply.
+#[derive(Debug)]
+struct XdrReader<'a>(&'a [u8]);
+
+impl XdrReader<'_> {
+    /// Reads an unsigned integer.
+    fn u32(&mut self) -> Result<u32, SessionError> {
+        let Some((value, rest)) = self.0.split_first_chunk() else {
+            return Err(protocol_error("The reply ended early".to_string()));
+        };
+        self.0 = rest;
+        Ok(u32::from_be_bytes(*value))
+    }
+
+    /// Reads variable length data.
+    fn opaque(&mut self) -> Result<Vec<u8>, SessionError> {
+        let len = self.u32()? as usize;
+        let padded = len.next_multiple_of(4);
+        if self.0.len() < padded {
+            return Err(protocol_error("The reply ended early".to_string()));
+        }
+        let data = self.0[..len].to_vec();
+        self.0 = &self.0[padded..];
+        Ok(data)
+    }
+}
+
+/// Marks a record as one fragment, the last.
+pub(super) fn mark_record(record: &[u8]) -> io::Result<Vec<u8>> {
+    let len = u32::try_from(record.len())
+        .ok()
+        .filter(|len| len & 0x8000_0000 == 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "RPC record is too long"))?;
+    let mut message = (len | 0x8000_0000).to_be_bytes().to_vec();
+    message.extend_from_slice(record);
+    Ok(message)
+}
+
+/// The length of a fragment from its mark, and whether it is the last of the record.
+///
+/// # Errors
+///
+/// If the fragment would make the record, `received` bytes so far, longer than any reply.
+pub(super) fn fragment_mark(mark: [u8; 4], received: usize) -> io::Result<(usize, bool)> {
+    let mark = u32::from_be_bytes(mark);
+    let len = (mark & 0x7FFF_FFFF) as usize;
+    if received + len > MAX_RECORD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("An RPC record of over {MAX_RECORD_LEN} bytes"),
+        ));
+    }
+    Ok((len, mark & 0x8000_0000 != 0))
+}
+
+/// Sends a record, marked as the last fragment.
+fn write_record(stream: &mut TcpStream, record: &[u8]) -> io::Result<()> {
+    stream.write_all(&mark_record(record)?)
+}
+
+/// Reads a record, joining all of its fragments.
+fn read_record(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut record = Vec::new();
+    loop {
+        let mut mark = [0; 4];
+        stream.read_exact(&mut mark)?;
+        let (len, last) = fragment_mark(mark, record.len())?;
+        let start = record.len();
+        record.resize(start + len, 0);
+        stream.read_exact(&mut record[start..])?;
+        if last {
+            return Ok(record);
+        }
+    }
+}
+
+/// Encodes a call, without its record mark.
+pub(super) fn encode_call(
+    xid: u32,
+    program: (u32, u32),
+    procedure: u32,
+    args: XdrWriter,
+) -> Vec<u8> {
+    let (program, version) = program;
+    let mut call = XdrWriter::default()
+        .u32(xid)
+        .u32(0) // CALL
+        .u32(2) // RPC version
+        .u32(program)
+        .u32(version)
+        .u32(procedure)
+        .u32(0) // AUTH_NONE credentials
+        .u32(0)
+        .u32(0) // AUTH_NONE verifier
+        .u32(0);
+    call.0.extend_from_slice(&args.0);
+    call.0
+}
+
+/// Checks that a reply is to the call, and was accepted, returning its encoded results.
+pub(super) fn decode_reply(xid: u32, reply: &[u8]) -> Result<Vec<u8>, SessionError> {
+    let mut reader = XdrReader(reply);
+    let found = reader.u32()?;
+    if found != xid || reader.u32()? != 1 {
+        return Err(protocol_error(format!(
+            "Expected the reply to call {xid}, found {found}"
+        )));
+    }
+    if reader.u32()? != 0 {
+        return Err(protocol_error(format!("Call {xid} was denied")));
+    }
+    let _flavor = reader.u32()?;
+    let _verifier = reader.opaque()?;
+    match reader.u32()? {
+        0 => Ok(reader.0.to_vec()),
+        status => Err(protocol_error(format!(
+            "Call {xid} was not accepted, with status {status}"
+        ))),
+    }
+}
+
+/// An ONC RPC client over one TCP connection.
+#[derive(Debug)]
+struct RpcClient {
+    /// The connection.
+    stream: TcpStream,
+    /// The program number and version that is called.
+    program: (u32, u32),
+    /// The transaction ID of the last call.
+    xid: u32,
+    /// The socket timeout, for reporting when it runs out.
+    timeout: Option<Duration>,
+}
+
+impl RpcClient {
+    /// Connects to a program.
+    fn connect(
+        host: &str,
+        port: u16,
+        program: (u32, u32),
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        let stream =
+            TcpStream::connect((unbracket(host), port)).map_err(|err| io_error(err, timeout))?;
+        stream
+            .set_read_timeout(timeout)
+            .and_then(|()| stream.set_write_timeout(timeout))
+            .map_err(SessionError::Io)?;
+        Ok(RpcClient {
+            stream,
+            program,
+            xid: 0,
+            timeout,
+        })
+    }
+
+    /// Calls a procedure, returning its encoded results.
+    fn call(&mut self, procedure: u32, args: XdrWriter) -> Result<Vec<u8>, SessionError> {
+        self.xid = self.xid.wrapping_add(1);
+        let call = encode_call(self.xid, self.program, procedure, args);
+        write_record(&mut self.stream, &call).map_err(|err| io_error(err, self.timeout))?;
+        let reply = read_record(&mut self.stream).map_err(|err| io_error(err, self.timeout))?;
+        decode_reply(self.xid, &reply)
+    }
+}
+
+/// Asks a host's portmapper which TCP port a program is on.
+fn get_port(
+    host: &str,
+    portmapper_port: u16,
+    program: (u32, u32),
+    timeout: Option<Duration>,
+) -> Result<u16, SessionError> {
+    let mut portmapper = RpcClient::connect(host, portmapper_port, PORTMAPPER, timeout)?;
+    let reply = portmapper.call(PMAPPROC_GETPORT, getport_args(program))?;
+    decode_port(&reply, host)
+}
+
+/// The arguments to the portmapper's GETPORT, for a program over TCP.
+pub(super) fn getport_args(program: (u32, u32)) -> XdrWriter {
+    XdrWriter::default()
+        .u32(program.0)
+        .u32(program.1)
+        .u32(6) // TCP
+        .u32(0)
+}
+
+/// The port from the portmapper's GETPORT.
+pub(super) fn decode_port(reply: &[u8], host: &str) -> Result<u16, SessionError> {
+    match XdrReader(reply).u32()? {
+        0 => Err(SessionError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{host} does not have a VXI-11 core channel"),
+        ))),
+        port => u16::try_from(port)
+            .map_err(|_| protocol_error(format!("The portmapper gave port {port}"))),
+    }
+}
+
+/// Describes a VXI-11 device error code.
+fn device_error(code: u32, timeout: Option<Duration>) -> SessionError {
+    let description = match code {
+        ERR_IO_TIMEOUT => {
+            if let Some(timeout) = timeout {
+                return SessionError::Timeout(timeout);
+            }
+            "I/O timeout"
+        }
+        1 => "syntax error",
+        3 => "device not accessible",
+        4 => "invalid link identifier",
+        5 => "parameter error",
+        6 => "channel not established",
+        8 => "operation not supported",
+        9 => "out of resources",
+        11 => "device locked by another link",
+        12 => "no lock held by this link",
+        17 => "I/O error",
+        21 => "invalid address",
+        23 => "abort",
+        29 => "channel already established",
+        _ => "unknown error",
+    };
+    SessionError::Io(io::Error::other(format!(
+        "VXI-11 error {code}: {description}"
+    )))
+}
+
+/// The timeout as VXI-11 milliseconds, where waiting forever is the longest timeout.
+fn timeout_ms(timeout: Option<Duration>) -> u32 {
+    timeout.map_or(u32::MAX, |timeout| {
+        u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX)
+    })
+}
+
+/// The arguments to create_link, for the address's LAN device name.
+pub(super) fn create_link_args(addr: &TcpipAddress) -> Result<XdrWriter, SessionError> {
+    XdrWriter::default()
+        .u32(std::process::id())
+        .u32(0) // Do not lock the device
+        .u32(0)
+        .opaque(addr.device_name().unwrap_or("inst0").as_bytes())
+}
+
+/// The link ID and the most the device accepts in one write, from create_link.
+pub(super) fn decode_link(
+    reply: &[u8],
+    timeout: Option<Duration>,
+) -> Result<(u32, usize), SessionError> {
+    let mut reader = XdrReader(reply);
+    match reader.u32()? {
+        0 => {}
+        code => return Err(device_error(code, timeout)),
+    }
+    let link = reader.u32()?;
+    let _abort_port = reader.u32()?;
+    let max_write = reader.u32()? as usize;
+    Ok((link, max_write.max(1)))
+}
+
+/// The arguments to device_write, with END set on the last part of a message.
+pub(super) fn write_args(
+    link: u32,
+    data: &[u8],
+    end: bool,
+    timeout: Option<Duration>,
+) -> Result<XdrWriter, SessionError> {
+    XdrWriter::default()
+        .u32(link)
+        .u32(timeout_ms(timeout))
+        .u32(0) // Lock timeout
+        .u32(if end { FLAG_END } else { 0 })
+        .opaque(data)
+}
+
+/// How many bytes device_write accepted.
+pub(super) fn decode_write(reply: &[u8], timeout: Option<Duration>) -> Result<usize, SessionError> {
+    decode_status(reply, timeout)?;
+    let mut reader = XdrReader(reply);
+    reader.u32()?;
+    Ok(reader.u32()? as usize)
+}
+
+/// The part of `data` that the next device_write sends, and whether it ends the message.
+///
+/// # Errors
+///
+/// If the device accepted none of the last write, which would never finish.
+pub(super) fn next_write(
+    data: &[u8],
+    max_write: usize,
+    accepted: Option<usize>,
+) -> Result<(&[u8], bool), SessionError> {
+    if accepted == Some(0) {
+        return Err(protocol_error(
+            "The device accepted none of a write".to_string(),
+        ));
+    }
+    let chunk = &data[..data.len().min(max_write)];
+    Ok((chunk, chunk.len() == data.len()))
+}
+
+/// The arguments to device_read, for up to `max` bytes.
+pub(super) fn read_args(link: u32, max: u32, timeout: Option<Duration>) -> XdrWriter {
+    XdrWriter::default()
+        .u32(link)
+        .u32(max)
+        .u32(timeout_ms(timeout))
+        .u32(0) // Lock timeout
+        .u32(0) // No termination character
+        .u32(0)
+}
+
+/// The data from device_read, and whether it ended the message.
+pub(super) fn decode_read(
+    reply: &[u8],
+    timeout: Option<Duration>,
+) -> Result<(Vec<u8>, bool), SessionError> {
+    let mut reader = XdrReader(reply);
+    decode_status(reply, timeout)?;
+    reader.u32()?;
+    let reason = reader.u32()?;
+    Ok((reader.opaque()?, reason & (REASON_END | REASON_CHR) != 0))
+}
+
+/// Checks the error code that every core channel reply starts with.
+pub(super) fn decode_status(reply: &[u8], timeout: Option<Duration>) -> Result<(), SessionError> {
+    match XdrReader(reply).u32()? {
+        0 => Ok(()),
+        code => Err(device_error(code, timeout)),
+    }
+}
+
+/// A session to a `TCPIP::host::inst0::INSTR` address, over a VXI-11 core channel link.
+///
+/// The link is destroyed when the session is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fisa::{parse::tcpip::TcpipAddress, session::{Session, vxi11::Vxi11Session}};
+/// let addr = TcpipAddress::try_new("TCPIP::10.0.0.5::inst0::INSTR")?;
+/// let mut session = Vxi11Session::connect(&addr, Some(Duration::from_secs(2)))?;
+/// println!("{}", session.query("*IDN?")?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Vxi11Session {
+    /// The core channel.
+    core: RpcClient,
+    /// The link ID from create_link.
+    link: u32,
+    /// The most that the device accepts in one write.
+    max_write: usize,
+    /// How long the device may take for each operation.
+    timeout: Option<Duration>,
+    /// Response data that has not been read yet.
+    pending: Vec<u8>,
+    /// Whether the device ended the message in `pending`.
+    end_of_message: bool,
+    /// Whether [`close`](Vxi11Session::close) destroyed the link already.
+    closed: bool,
+}
+
+impl Vxi11Session {
+    /// Looks up the core channel with the host's portmapper, and links to the device.
+    /// The timeout is used for each RPC call as well.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the device refused the link.
+    pub fn connect(addr: &TcpipAddress, timeout: Option<Duration>) -> Result<Self, SessionError> {
+        let port = get_port(addr.host(), PORTMAPPER_PORT, DEVICE_CORE, timeout)?;
+        Vxi11Session::connect_to_port(addr, port, timeout)
+    }
+
+    /// Links to the device, with the core channel on a known port rather than asking
+    /// the portmapper.
+    ///
+    /// # Errors
+    ///
+    /// If the host could not be reached, or the device refused the link.
+    pub fn connect_to_port(
+        addr: &TcpipAddress,
+        port: u16,
+        timeout: Option<Duration>,
+    ) -> Result<Self, SessionError> {
+        // Give the device its whole timeout before the socket gives up on it.
+        let socket_timeout = timeout.map(|timeout| timeout + Duration::from_secs(1));
+        let mut core = RpcClient::connect(addr.host(), port, DEVICE_CORE, socket_timeout)?;
+
+        let reply = core.call(CREATE_LINK, create_link_args(addr)?)?;
+        let (link, max_write) = decode_link(&reply, timeout)?;
+
+        Ok(Vxi11Session {
+            core,
+            link,
+            max_write,
+            timeout,
+            pending: Vec::new(),
+            end_of_message: true,
+            closed: false,
+        })
+    }
+
+    /// Asks the device for up to `max` bytes, and stores what it sends in `pending`.
+    fn request(&mut self, max: u32) -> Result<(), SessionError> {
+        let reply = self
+            .core
+            .call(DEVICE_READ, read_args(self.link, max, self.timeout))?;
+        let (data, end_of_message) = decode_read(&reply, self.timeout)?;
+        self.pending.extend(data);
+        self.end_of_message = end_of_message;
+        Ok(())
+    }
+
+    /// Closes the link. Dropping the session does this too, but ignores errors.
+    ///
+    /// # Errors
+    ///
+    /// If the device did not close the link.
+    pub fn close(mut self) -> Result<(), SessionError> {
+        self.closed = true;
+        self.destroy_link()
+    }
+
+    /// Tells the device that the link is no longer used.
+    fn destroy_link(&mut self) -> Result<(), SessionError> {
+        let reply = self
+            .core
+            .call(DESTROY_LINK, XdrWriter::default().u32(self.link))?;
+        decode_status(&reply, self.timeout)
+    }
+}
+
+impl Drop for Vxi11Session {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.destroy_link();
+        }
+    }
+}
+
+impl Session for Vxi11Session {
+    fn write(&mut self, data: &[u8]) -> Result<usize, SessionError> {
+        // The device may accept less than it was sent, so the rest is sent again.
+        let mut rest = data;
+        let mut accepted = None;
+        while !rest.is_empty() {
+            let (chunk, end) = next_write(rest, self.max_write, accepted)?;
+            let args = write_args(self.link, chunk, end, self.timeout)?;
+            let reply = self.core.call(DEVICE_WRITE, args)?;
+            let sent = decode_write(&reply, self.timeout)?.min(chunk.len());
+            rest = &rest[sent..];
+            accepted = Some(sent);
+        }
+        Ok(data.len())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, SessionError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            self.request(u32::try_from(buf.len()).unwrap_or(u32::MAX).min(READ_CHUNK))?;
+        }
+        let read = buf.len().min(self.pending.len());
+        buf[..read].copy_from_slice(&self.pending[..read]);
+        self.pending.drain(..read);
+        Ok(read)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), SessionError> {
+        let socket_timeout = timeout.map(|timeout| timeout + Duration::from_secs(1));
+        self.core
+            .stream
+            .set_read_timeout(socket_timeout)
+            .and_then(|()| self.core.stream.set_write_timeout(socket_timeout))
+            .map_err(SessionError::Io)?;
+        self.core.timeout = socket_timeout;
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    /// Reads a whole message, ending when the device says it has ended.
+    fn read_message(&mut self) -> Result<Vec<u8>, SessionError> {
+        let mut message = std::mem::take(&mut self.pending);
+        while message.is_empty() || !self.end_of_message {
+            self.request(READ_CHUNK)?;
+            message.append(&mut self.pending);
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    //! The core channel against a fake instrument.
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    /// Serves the portmapper and a core channel on one port, answering `*IDN?`.
+    /// Returns the port, and the thread that ends when the client hangs up.
+    pub(in crate::session) fn fake_instrument(
+        max_write: u32,
+    ) -> (u16, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut written = Vec::new();
+            let mut response = Vec::new();
+            let mut linked = false;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                while let Ok(call) = read_record(&mut stream) {
+                    let mut args = XdrReader(&call);
+                    let xid = args.u32().unwrap();
+                    for _ in 0..4 {
+                        args.u32().unwrap();
+                    }
+                    let procedure = args.u32().unwrap();
+                    for _ in 0..4 {
+                        args.u32().unwrap();
+                    }
+
+                    let reply = XdrWriter::default()
+                        .u32(xid)
+                        .u32(1)
+                        .u32(0)
+                        .u32(0)
+                        .u32(0)
+                        .u32(0);
+                    let reply = match procedure {
+                        PMAPPROC_GETPORT => reply.u32(u32::from(port)),
+                        CREATE_LINK => {
+                            linked = true;
+                            reply.u32(0).u32(7).u32(0).u32(max_write)
+                        }
+                        DEVICE_WRITE => {
+                            let (_link, _io, _lock) = (args.u32(), args.u32(), args.u32());
+                            let flags = args.u32().unwrap();
+                            // Accepts a byte less than it may be sent, so writes need resending.
+                            let data = args.opaque().unwrap();
+                            let accepted = data.len().min(max_write as usize - 1).max(1);
+                            written.extend(&data[..accepted]);
+                            if flags & FLAG_END != 0
+                                && accepted == data.len()
+                                && written.ends_with(b"*IDN?\n")
+                            {
+                                response = b"FISA,0,0,0\n".to_vec();
+                            }
+                            reply.u32(0).u32(u32::try_from(accepted).unwrap())
+                        }
+                        DEVICE_READ => {
+                            assert_eq!(args.u32().unwrap(), 7);
+                            let max = args.u32().unwrap() as usize;
+                            if response.is_empty() {
+                                reply.u32(ERR_IO_TIMEOUT).u32(0).u32(0)
+                            } else {
+                                let chunk: Vec<u8> =
+                                    response.drain(..max.min(response.len())).collect();
+                                let reason = if response.is_empty() { REASON_END } else { 0 };
+                                reply.u32(0).u32(reason).opaque(&chunk).unwrap()
+                            }
+                        }
+                        DESTROY_LINK => {
+                            assert!(linked, "The link was destroyed twice");
+                            linked = false;
+                            reply.u32(0)
+                        }
+                        _ => reply.u32(8),
+                    };
+                    write_record(&mut stream, &reply.0).unwrap();
+                }
+                if !written.is_empty() {
+                    return written;
+                }
+            }
+            written
+        });
+        (port, server)
+    }
+
+    #[test]
+    fn vxi11_query() {
+        let (port, server) = fake_instrument(4);
+        let addr = TcpipAddress::try_new("TCPIP::127.0.0.1::inst0::INSTR").unwrap();
+
+        let timeout = Some(Duration::from_millis(100));
+        let core = get_port("127.0.0.1", port, DEVICE_CORE, timeout).unwrap();
+        assert_eq!(core, port);
+
+        let mut session = Vxi11Session::connect_to_port(&addr, core, timeout).unwrap();
+        assert_eq!(session.max_write, 4);
+        assert_eq!(session.query("*IDN?").unwrap(), "FISA,0,0,0");
+
+        let mut buf = [0; 4];
+        session.write_all(b"*IDN?\n").unwrap();
+        assert_eq!(session.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"FISA");
+        assert!(!session.end_of_message);
+        assert_eq!(session.read_line().unwrap(), ",0,0,0\n");
+
+        assert!(matches!(
+            session.read_line(),
+            Err(SessionError::Timeout(waited)) if Some(waited) == timeout
+        ));
+        session.close().unwrap();
+        assert_eq!(server.join().unwrap(), b"*IDN?\n*IDN?\n");
+    }
+
+    #[test]
+    fn vxi11_xdr() {
+        let writer = XdrWriter::default().u32(1).opaque(b"inst0").unwrap();
+        assert_eq!(writer.0, b"\0\0\0\x01\0\0\0\x05inst0\0\0\0");
+
+        let mut reader = XdrReader(&writer.0);
+        assert_eq!(reader.u32().unwrap(), 1);
+        assert_eq!(reader.opaque().unwrap(), b"inst0");
+        assert!(reader.u32().is_err());
+        assert!(XdrReader(b"\0\0\0\x09abc").opaque().is_err());
+    }
+
+    #[test]
+    fn vxi11_records() {
+        assert_eq!(fragment_mark([0x80, 0, 0, 8], 0).unwrap(), (8, true));
+        assert_eq!(fragment_mark([0, 0, 1, 0], 16).unwrap(), (256, false));
+        assert_eq!(
+            fragment_mark([0xFF, 0xFF, 0xFF, 0xFF], 0)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(fragment_mark([0, 0x10, 0, 0], MAX_RECORD_LEN - 0x10_0000).is_ok());
+        assert!(fragment_mark([0, 0x10, 0, 1], MAX_RECORD_LEN - 0x10_0000).is_err());
+
+        assert!(next_write(b"*IDN?", 4, Some(0)).is_err());
+        assert_eq!(
+            next_write(b"*IDN?", 4, None).unwrap(),
+            (&b"*IDN"[..], false)
+        );
+        assert_eq!(next_write(b"N?", 4, Some(3)).unwrap(), (&b"N?"[..], true));
+    }
+}
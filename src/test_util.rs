@@ -0,0 +1,68 @@
+//! Helpers for crates that test their use of addresses.
+use crate::parse::usb::UsbAddress;
+
+/// Asserts that an address parses and displays as the expected canonical form.
+///
+/// # Panics
+///
+/// If the input does not parse, or the canonical form differs. The message points at
+/// the first character that differs.
+///
+/// # Examples
+///
+/// ```
+/// # use fisa::test_util::assert_canonical;
+/// assert_canonical("USB::0x1a34::0x5678::A22-5::instr", "USB::0x1A34::0x5678::A22-5::INSTR");
+/// ```
+#[track_caller]
+pub fn assert_canonical(input: &str, expected: &str) {
+    let canonical = match UsbAddress::try_new(input) {
+        Ok(addr) => addr.to_string(),
+        Err(err) => panic!("{input:?} does not parse: {err}"),
+    };
+
+    if canonical != expected {
+        // Counted in characters, so the caret lines up for non-ASCII serials.
+        let diverges = canonical
+            .chars()
+            .zip(expected.chars())
+            .take_while(|(found, wanted)| found == wanted)
+            .count();
+
+        panic!(
+            "Canonical form of {input:?} differs at character {diverges}\n\
+             expected: {expected}\n\
+             \x20  found: {canonical}\n\
+             \x20         {caret:>width$}",
+            caret = "^",
+            width = diverges + 1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    //! Self-tests for the helpers.
+    use super::*;
+
+    #[test]
+    fn assert_canonical_passes() {
+        assert_canonical(
+            "USB0::0x1a34::0X5678::A22-5::Instr",
+            "USB0::0x1A34::0x5678::A22-5::INSTR",
+        );
+        assert_canonical("USB::0x1A34::0x5678::A22-5", "USB::0x1A34::0x5678::A22-5");
+    }
+
+    #[test]
+    #[should_panic(expected = "differs at character 18")]
+    fn assert_canonical_mismatch() {
+        assert_canonical("USB::0x1A34::0x5678::A22-5", "USB::0x1A34::0x5679::A22-5");
+    }
+
+    #[test]
+    #[should_panic(expected = "does not parse")]
+    fn assert_canonical_invalid() {
+        assert_canonical("USB::0x1A34", "USB::0x1A34::0x5678::A22-5");
+    }
+}
@@ -0,0 +1,349 @@
+//! Helpers shared by the unit tests.
+//!
+//! There is no property testing crate in the dependency tree, so this is a tiny
+//! deterministic generator. Seeds are fixed so any failure is reproducible.
+
+use std::ops::Range;
+
+use crate::parse::usb::UsbAddress;
+
+/// The seed of every randomized test. Any other value works as well, it only has to stay
+/// the same so failures can be reproduced.
+pub const SEED: u64 = 0x5EED_F15A;
+
+/// Parses a USB address that the test knows is valid.
+pub fn usb(addr: &str) -> UsbAddress {
+    UsbAddress::try_new(addr).unwrap()
+}
+
+/// Xorshift64* pseudo-random generator. Not for anything but tests.
+#[derive(Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator from a seed. A seed of zero is bumped as xorshift gets stuck on it.
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    /// Next raw value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform-ish value in `0..bound`. `bound` must not be zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        // Truncation is the point here.
+        #[allow(clippy::cast_possible_truncation)]
+        let ret = (self.next_u64() % bound as u64) as usize;
+        ret
+    }
+
+    /// Picks an element of a non-empty slice.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Characters that show up in USB addresses, plus some that really should not.
+pub const ADDRESS_CHARS: &[char] = &[
+    'U', 'S', 'B', 'u', 's', 'b', '0', '1', '2', '5', '9', 'A', 'F', 'a', 'f', 'x', 'X', 'Z', ':',
+    ':', ':', 'I', 'N', 'S', 'T', 'R', 'i', 'n', '-', '_', ' ', '\t', '?', '*', 'é', '：', '💾',
+];
+
+/// Takes a string and randomly inserts, deletes, replaces characters, or truncates it.
+pub fn mutate(rng: &mut Rng, input: &str) -> String {
+    let mut chars: Vec<char> = input.chars().collect();
+
+    for _ in 0..=rng.below(4) {
+        let i = rng.below(chars.len() + 1);
+        match rng.below(4) {
+            0 => chars.insert(i, *rng.pick(ADDRESS_CHARS)),
+            1 if i < chars.len() => {
+                chars.remove(i);
+            }
+            2 if i < chars.len() => chars[i] = *rng.pick(ADDRESS_CHARS),
+            _ => chars.truncate(i),
+        }
+    }
+
+    chars.into_iter().collect()
+}
+
+/// A completely random string built from [`ADDRESS_CHARS`].
+pub fn random_string(rng: &mut Rng, max_len: usize) -> String {
+    (0..rng.below(max_len + 1))
+        .map(|_| *rng.pick(ADDRESS_CHARS))
+        .collect()
+}
+
+/// Completely random bytes, which are very unlikely to be UTF-8.
+pub fn random_bytes(rng: &mut Rng, max_len: usize) -> Vec<u8> {
+    // Truncation is the point here.
+    #[allow(clippy::cast_possible_truncation)]
+    (0..rng.below(max_len + 1))
+        .map(|_| rng.next_u64() as u8)
+        .collect()
+}
+
+/// Feeds a parser random inputs: arbitrary bytes, the prefix followed by address characters,
+/// and mutations of the seed addresses. Nothing may panic, and the span of every error, which
+/// `parse` returns, must be within the input.
+pub fn check_never_panics(
+    prefix: &str,
+    seeds: &[&str],
+    mut parse: impl FnMut(&str) -> Option<Range<usize>>,
+) {
+    let mut rng = Rng::new(SEED);
+
+    for i in 0..20_000 {
+        let input = match i % 3 {
+            0 => String::from_utf8_lossy(&random_bytes(&mut rng, 60)).into_owned(),
+            1 => format!("{prefix}{}", random_string(&mut rng, 40)),
+            _ => {
+                let seed = *rng.pick(seeds);
+                mutate(&mut rng, seed)
+            }
+        };
+
+        if let Some(span) = parse(&input) {
+            assert!(
+                span.start <= span.end && span.end <= input.len(),
+                "Span {span:?} out of bounds for {input:?}"
+            );
+        }
+    }
+}
+
+/// Characters that show up in serial numbers, including ones that look like other fields.
+pub const SERIAL_CHARS: &[char] = &[
+    'A', 'B', 'Z', 'a', 'z', '0', '1', '9', '-', '_', '.', 'I', 'N', 'S', 'T', 'R', 'i', 'x', '/',
+];
+
+/// A random USB address that is valid, but written in any of the non-canonical ways accepted.
+pub fn random_usb_address(rng: &mut Rng) -> String {
+    /// Writes a hex code with any prefix case, digit case, and zero padding.
+    fn hex(rng: &mut Rng) -> String {
+        // Truncation is the point here.
+        #[allow(clippy::cast_possible_truncation)]
+        let code = rng.next_u64() as u16;
+        let prefix = *rng.pick(&["0x", "0X"]);
+        let padding = "0".repeat(rng.below(3));
+        match rng.below(2) {
+            0 => format!("{prefix}{padding}{code:X}"),
+            _ => format!("{prefix}{padding}{code:x}"),
+        }
+    }
+
+    let mut addr = rng.pick(&["USB", "usb", "Usb"]).to_string();
+    match rng.below(3) {
+        0 => {}
+        1 => addr.push_str(&rng.below(100).to_string()),
+        _ => addr.push_str(&format!("0{}", rng.below(10))),
+    }
+    addr.push_str(&format!("::{}::{}::", hex(rng), hex(rng)));
+    for _ in 0..=rng.below(8) {
+        addr.push(*rng.pick(SERIAL_CHARS));
+    }
+    if rng.below(2) == 0 {
+        addr.push_str(&format!("::{}", rng.below(1000)));
+    }
+    if rng.below(2) == 0 {
+        addr.push_str(&format!(
+            "::{}",
+            rng.pick(&["INSTR", "instr", "Instr", "RAW", "raw"])
+        ));
+    }
+
+    addr
+}
+
+/// A tiny regular expression matcher, as there is no regex crate in the dependency tree.
+///
+/// Only handles the subset that [`crate::parse::usb::address_regex`] uses: literals, `\` escapes,
+/// `[...]` classes with ranges and negation, `(?:...)` groups, `|`, and the `*`, `+`, and `?`
+/// quantifiers. The pattern must be anchored with `^` and `$`. This is deliberately written
+/// without looking at the address parser, so the two can be checked against each other.
+#[derive(Debug)]
+pub struct Regex(Node);
+
+/// A node of a parsed [`Regex`].
+#[derive(Debug)]
+enum Node {
+    /// A single character.
+    Char(char),
+    /// A character class, with inclusive ranges.
+    Class {
+        /// Ranges of characters in the class.
+        ranges: Vec<(char, char)>,
+        /// Whether this matches characters not in the ranges instead.
+        negated: bool,
+    },
+    /// Nodes one after another.
+    Seq(Vec<Node>),
+    /// Any one of the nodes.
+    Alt(Vec<Node>),
+    /// The node repeated, at least `min` times and at most once if not `many`.
+    Repeat {
+        /// The repeated node
+        node: Box<Node>,
+        /// Fewest repetitions
+        min: usize,
+        /// Whether more than one repetition is allowed
+        many: bool,
+    },
+}
+
+impl Regex {
+    /// Parses an anchored pattern. Panics on anything outside the supported subset.
+    pub fn new(pattern: &str) -> Self {
+        let inner = pattern
+            .strip_prefix('^')
+            .and_then(|pattern| pattern.strip_suffix('$'))
+            .expect("Pattern must be anchored");
+        let mut chars = inner.chars().peekable();
+        let node = Regex::parse_alt(&mut chars);
+        assert!(chars.next().is_none(), "Unbalanced ')' in {pattern:?}");
+        Regex(node)
+    }
+
+    /// Whether the whole input matches.
+    pub fn is_match(&self, input: &str) -> bool {
+        let chars: Vec<char> = input.chars().collect();
+        self.0.ends(&chars, 0).contains(&chars.len())
+    }
+
+    /// Parses alternatives up to the end of the group.
+    fn parse_alt(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Node {
+        let mut alts = vec![Regex::parse_seq(chars)];
+        while chars.next_if_eq(&'|').is_some() {
+            alts.push(Regex::parse_seq(chars));
+        }
+        Node::Alt(alts)
+    }
+
+    /// Parses a sequence up to a `|` or the end of the group.
+    fn parse_seq(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Node {
+        let mut seq = Vec::new();
+        while let Some(&char) = chars.peek() {
+            if char == '|' || char == ')' {
+                break;
+            }
+            chars.next();
+
+            let atom = match char {
+                '(' => {
+                    assert!(
+                        chars.next() == Some('?') && chars.next() == Some(':'),
+                        "Only non-capturing groups are supported"
+                    );
+                    let group = Regex::parse_alt(chars);
+                    assert_eq!(chars.next(), Some(')'), "Unclosed group");
+                    group
+                }
+                '[' => {
+                    let negated = chars.next_if_eq(&'^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        match chars.next().expect("Unclosed class") {
+                            ']' => break,
+                            low => match chars.next_if_eq(&'-') {
+                                Some(_) => ranges.push((low, chars.next().expect("Open range"))),
+                                None => ranges.push((low, low)),
+                            },
+                        }
+                    }
+                    Node::Class { ranges, negated }
+                }
+                '\\' => Node::Char(chars.next().expect("Dangling escape")),
+                '*' | '+' | '?' | '^' | '$' | '.' | '{' => {
+                    panic!("Unsupported or misplaced {char:?}")
+                }
+                char => Node::Char(char),
+            };
+
+            let (min, many) = match chars.peek() {
+                Some('*') => (0, true),
+                Some('+') => (1, true),
+                Some('?') => (0, false),
+                _ => {
+                    seq.push(atom);
+                    continue;
+                }
+            };
+            chars.next();
+            seq.push(Node::Repeat {
+                node: Box::new(atom),
+                min,
+                many,
+            });
+        }
+        Node::Seq(seq)
+    }
+}
+
+impl Node {
+    /// Every position a match of this node starting at `start` could end at.
+    fn ends(&self, input: &[char], start: usize) -> Vec<usize> {
+        match self {
+            Node::Char(expected) => match input.get(start) {
+                Some(found) if found == expected => vec![start + 1],
+                _ => Vec::new(),
+            },
+            Node::Class { ranges, negated } => match input.get(start) {
+                Some(found)
+                    if ranges
+                        .iter()
+                        .any(|(low, high)| (low..=high).contains(&found))
+                        != *negated =>
+                {
+                    vec![start + 1]
+                }
+                _ => Vec::new(),
+            },
+            Node::Seq(nodes) => nodes.iter().fold(vec![start], |positions, node| {
+                let mut next: Vec<usize> = positions
+                    .into_iter()
+                    .flat_map(|position| node.ends(input, position))
+                    .collect();
+                next.sort_unstable();
+                next.dedup();
+                next
+            }),
+            Node::Alt(nodes) => {
+                let mut ends: Vec<usize> = nodes
+                    .iter()
+                    .flat_map(|node| node.ends(input, start))
+                    .collect();
+                ends.sort_unstable();
+                ends.dedup();
+                ends
+            }
+            Node::Repeat { node, min, many } => {
+                let mut ends = if *min == 0 { vec![start] } else { Vec::new() };
+                let mut frontier = vec![start];
+                let mut count = 0;
+                // Every repetition consumes at least one character or adds nothing new,
+                // so this always terminates.
+                while !frontier.is_empty() && (*many || count < 1) {
+                    count += 1;
+                    let mut next: Vec<usize> = frontier
+                        .iter()
+                        .flat_map(|&position| node.ends(input, position))
+                        .filter(|end| count < *min || !ends.contains(end))
+                        .collect();
+                    next.sort_unstable();
+                    next.dedup();
+                    if count >= *min {
+                        ends.extend(&next);
+                    }
+                    frontier = next;
+                }
+                ends
+            }
+        }
+    }
+}